@@ -0,0 +1,104 @@
+//! Throughput of the lexer and the parser on the large generated source.
+//!
+//! `cargo bench --bench lexer` prints the best time of several runs for every stage and the number of the allocations,
+//! which is stable unlike the time, set `AVO_BENCH_SIZE` to the size of the source in megabytes, 4 by default.
+//! The `baseline` stage is the lexer which copies the text of every token, to compare the other stages with.
+use avoc::internals::{ Token, TokenReader, TreeBuilder };
+use std::alloc::{ GlobalAlloc, Layout, System };
+use std::sync::atomic::{ AtomicUsize, Ordering };
+use std::time::{ Duration, Instant };
+
+/// System allocator which counts the allocations.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+const RUNS: usize = 10;
+const PROGRAM: &str = r#"// shapes and their areas
+struct Point { x, y }
+
+enum Shape { Circle(radius), Rect(width, height) }
+
+/* the area of the shape,
+   the unknown shapes have no area */
+fn area(shape) -> Float {
+  match shape {
+    Shape.Circle(radius) -> 3.14159 * radius ^ 2
+    Shape.Rect(width, height) if width >= 0 and height >= 0 -> width * height
+    _ -> 0.0
+  }
+}
+
+mut total = 0.0
+for i in 1..=100 {
+  if i / 3 * 3 == i {
+    total += area(Shape.Circle(i / 10))
+  } else {
+    total -= 1.5 * 50%
+  }
+}
+names = ["first", "second", "third"]
+scores = {"first": 1, "second": 2}
+scale = { @ value -> value * 2 }
+println("total:", total, names[0], scale(scores["first"]))
+"#;
+
+fn main() {
+    let megabytes = std::env::var("AVO_BENCH_SIZE").ok().and_then(|size| size.parse().ok()).unwrap_or(4);
+    let source = PROGRAM.repeat(megabytes * 1024 * 1024 / PROGRAM.len() + 1);
+    let reader = TokenReader::new();
+    let tokens = reader.parse(&source).unwrap();
+    println!("source: {} bytes, {} tokens", source.len(), tokens.len());
+    bench("baseline", source.len(), || reader.tokens(&source).filter_map(|token| copied_text(token.unwrap())).map(|text| text.len()).sum());
+    bench("parse_with_comments", source.len(), || reader.parse_with_comments(&source).unwrap().len());
+    bench("tokens", source.len(), || reader.tokens(&source).count());
+    bench("read_tokens", source.len(), || reader.read_tokens(source.as_bytes()).count());
+    bench("build_tree", source.len(), || TreeBuilder::new().build_tree(&tokens).unwrap().children.len());
+}
+
+/// Copies the text of the token into its own string like the lexer did before the operators were read into the enum
+/// and the names, the strings and the comments were interned, so the baseline allocates for every such token.
+fn copied_text(token: Token) -> Option<String> {
+    match token {
+        Token::Operator { payload, .. } => Some(String::from(payload.as_str())),
+        Token::Identifier { name: text, .. }
+        | Token::Function { name: text, .. }
+        | Token::StringConstant { value: text, .. }
+        | Token::Comment { text, .. } => Some(String::from(text.as_str())),
+        _ => None,
+    }
+}
+
+/// Runs the stage several times and prints the best time, the throughput and the allocations of one run.
+fn bench(name: &str, bytes: usize, mut stage: impl FnMut() -> usize) {
+    let mut best = Duration::MAX;
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        std::hint::black_box(stage());
+        best = best.min(start.elapsed());
+    }
+    let allocations = (ALLOCATIONS.load(Ordering::Relaxed) - allocations) / RUNS;
+    let megabytes_per_second = bytes as f64 / best.as_secs_f64() / (1024.0 * 1024.0);
+    println!("{:<20} {:>10.2?} {:>10.1} MB/s {:>10} allocations", name, best, megabytes_per_second, allocations);
+}
@@ -0,0 +1,10 @@
+//! `cargo fuzz run parser`: the parser returns the syntax tree or the error for any source, but never panics.
+#![no_main]
+use avoc::internals::{ TokenReader, TreeBuilder };
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|source: &str| {
+    if let Ok(tokens) = TokenReader::new().parse(source) {
+        let _ = TreeBuilder::new().build_tree(&tokens);
+    }
+});
@@ -0,0 +1,24 @@
+//! `cargo fuzz run rpntreebuilder`: the expression builder returns the tree or the error for any tokens, but never panics.
+#![no_main]
+use avoc::internals::{ Token, RpnTreeBuilder, TokenReader };
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|source: &str| {
+    let tokens = match TokenReader::new().parse(source) {
+        Ok(tokens) => tokens,
+        Err(_) => return,
+    };
+    let mut builder = RpnTreeBuilder::new();
+    for token in tokens {
+        let result = match token {
+            Token::NewLine { pos } => builder.notify_met_separator(pos),
+            token => builder.push_token(token),
+        };
+        if result.is_err() {
+            return
+        }
+    }
+    if builder.notify_met_separator(source.len()).is_ok() {
+        let _ = builder.build_tree(source.len());
+    }
+});
@@ -0,0 +1,16 @@
+//! `cargo fuzz run tokenreader`: the lexer returns the tokens or the error for any source, but never panics.
+#![no_main]
+use avoc::internals::TokenReader;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|source: &str| {
+    let reader = TokenReader::new();
+    if let Ok(tokens) = reader.parse_with_comments(source) {
+        for token in &tokens {
+            assert!(source.is_char_boundary(token.pos()), "{:?} is not at the char boundary", token);
+        }
+        assert!(tokens.windows(2).all(|pair| pair[0].pos() < pair[1].pos()));
+    }
+    // the reader is reused after the error
+    let _ = reader.parse(source);
+});
@@ -0,0 +1,130 @@
+use crate::dto::{ Node, NodeType, Operator, Token };
+use std::collections::HashSet;
+#[cfg(test)]
+use crate::parser::parse;
+
+/// Finds the names which are used inside of the lambda or the function but declared outside of it.
+///
+/// Parameters, loop variables and variables declared with `mut` inside of the lambda are local.
+/// The plain assignment `name = value` may either declare a new local variable or assign the
+/// captured one, so such names are reported too and the caller decides by the enclosing scope.
+///
+/// Names are returned in the order of their first usage.
+pub fn free_variables(lambda: &Node) -> Vec<String> {
+    let mut collector = Collector { scopes: vec!(HashSet::new()), free: vec!() };
+    collector.visit_lambda(lambda);
+    collector.free
+}
+
+struct Collector {
+    scopes: Vec<HashSet<String>>,
+    free: Vec<String>,
+}
+
+impl Collector {
+
+    fn is_bound(&self, name: &str) -> bool {
+        self.scopes.iter().any(|scope| scope.contains(name))
+    }
+
+    fn bind(&mut self, name: &str) {
+        self.scopes.last_mut().unwrap().insert(name.to_string());
+    }
+
+    fn use_name(&mut self, name: &str) {
+        if !self.is_bound(name) && !self.free.iter().any(|free| free == name) {
+            self.free.push(name.to_string())
+        }
+    }
+
+    /// Names in the pattern of the match arm are bound to the parts of the matched value.
+    fn bind_pattern(&mut self, pattern: &Node) {
+        match (pattern.node_type, &pattern.data) {
+            (NodeType::Token, Some(Token::Identifier { name, .. })) => self.bind(name),
+            _ => pattern.children.iter().for_each(|child| self.bind_pattern(child)),
+        }
+    }
+
+    fn visit_lambda(&mut self, node: &Node) {
+        self.scopes.push(HashSet::new());
+        for parameter in &node.condition {
+            parameter.children.iter().for_each(|default| self.visit(default));
+            if let Some(Token::Identifier { name, .. }) = &parameter.data {
+                self.bind(name)
+            }
+        }
+        node.children.iter().for_each(|child| self.visit(child));
+        self.scopes.pop();
+    }
+
+    fn visit(&mut self, node: &Node) {
+        match (node.node_type, &node.data) {
+            (NodeType::Token, Some(Token::Identifier { name, .. })) => self.use_name(name),
+            (NodeType::Call, Some(Token::Function { name, .. })) => {
+                self.use_name(name);
+                node.children.iter().for_each(|child| self.visit(child))
+            },
+            (NodeType::Expression, Some(Token::Operator { payload: Operator::Assign, .. })) => {
+                self.visit(&node.children[1]);
+                match (node.children[0].operator(), &node.children[0].children.first()) {
+                    (Some(Operator::Mut), Some(Node { data: Some(Token::Identifier { name, .. }), .. })) => self.bind(name),
+                    _ => self.visit(&node.children[0]),
+                }
+            },
+            // names of fields, methods and types are not variables
+            (NodeType::Expression, Some(Token::Operator { payload: Operator::Dot | Operator::Is, .. })) => {
+                self.visit(&node.children[0]);
+                if node.children[1].node_type == NodeType::Call {
+                    node.children[1].children.iter().for_each(|child| self.visit(child))
+                }
+            },
+            (NodeType::Struct, Some(Token::Identifier { name, .. })) => self.bind(name),
+            (NodeType::Enum, Some(Token::Identifier { name, .. })) => {
+                self.bind(name);
+                for variant in &node.children {
+                    if let Some(Token::Identifier { name, .. }) = &variant.data {
+                        self.bind(name)
+                    }
+                }
+            },
+            (NodeType::Lambda, _) => self.visit_lambda(node),
+            (NodeType::Function, Some(Token::Identifier { name, .. })) => {
+                self.bind(name);
+                self.visit_lambda(node)
+            },
+            (NodeType::For, _) => {
+                node.condition.iter().for_each(|child| self.visit(child));
+                self.scopes.push(HashSet::new());
+                if let Some(Token::Identifier { name, .. }) = &node.children[0].data {
+                    self.bind(name)
+                }
+                self.visit(&node.children[1]);
+                self.scopes.pop();
+            },
+            (NodeType::Arm, _) => {
+                self.scopes.push(HashSet::new());
+                self.bind_pattern(&node.condition[0]);
+                node.condition[1..].iter().chain(node.children.iter()).for_each(|child| self.visit(child));
+                self.scopes.pop();
+            },
+            (NodeType::Block, _) => {
+                self.scopes.push(HashSet::new());
+                node.children.iter().for_each(|child| self.visit(child));
+                self.scopes.pop();
+            },
+            _ => {
+                node.condition.iter().for_each(|child| self.visit(child));
+                node.children.iter().for_each(|child| self.visit(child));
+            },
+        }
+    }
+}
+
+#[test]
+fn test_free_variables() {
+    let source = "{ @ x, y = x + offset ->\n  mut total = x + y + scale\n  for i in 0..n { total += i * step }\n  inner = { @ z -> z + total + outer }\n  fn twice(v) { v + v + twice(v) + x }\n  apply(inner, total.field)\n  match total { (a, [b, ..c]) -> a + b + c + d, Some(e) -> e }\n  total is Int or E.make(f)\n}";
+    let tree = parse(source);
+    let expected: Vec<String> = ["offset", "scale", "n", "step", "outer", "inner", "apply", "d", "E", "f"]
+        .iter().map(|name| name.to_string()).collect();
+    assert_eq!(expected, free_variables(&tree.children[0]));
+}
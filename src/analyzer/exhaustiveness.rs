@@ -0,0 +1,423 @@
+use crate::dto::{ Node, NodeType, Operator, Token, SyntaxError };
+use crate::runtime::Value;
+use crate::runtime::patterns::{ literal, matches };
+use std::collections::HashMap;
+#[cfg(test)]
+use crate::parser::parse;
+
+/// Checks every `match` of the tree: reports the arms which can never be reached
+/// and the matches which don't cover all possible values, with the example of the missing value.
+///
+/// The language is dynamically typed, so the type of the matched value is inferred from the patterns:
+/// `match x { true -> 1, false -> 2 }` is exhaustive, while `match x { 0 -> 1, 1.. -> 2 }` misses the negative numbers.
+/// Arms with the guard may fail, so they never cover the values for the following arms.
+/// The variant pattern tells the enum of the value, so the match must cover all variants of the enum.
+///
+/// The check is the usefulness algorithm by Luc Maranget, "Warnings for pattern matching".
+pub fn check_exhaustiveness(root: &Node) -> Vec<SyntaxError> {
+    // the tree may be built by hand, while the check walks it recursively
+    if let Err(error) = root.depth() {
+        return vec!(error)
+    }
+    let mut errors = vec!();
+    let mut definitions = HashMap::new();
+    collect_definitions(root, &mut definitions);
+    visit(root, &definitions, &mut errors);
+    errors
+}
+
+/// Constructors of the type by the name of each struct and variant, with the number of their fields.
+/// Struct is the only constructor of its type, variants are constructors of their enum.
+type Definitions<'a> = HashMap<&'a str, Vec<(&'a str, usize)>>;
+
+fn collect_definitions<'a>(node: &'a Node, definitions: &mut Definitions<'a>) {
+    match (node.node_type, &node.data) {
+        (NodeType::Struct, Some(Token::Identifier { name, .. })) => {
+            definitions.insert(name, vec!((name, node.children.len())));
+        },
+        (NodeType::Enum, _) => {
+            let variants: Vec<(&str, usize)> = node.children.iter().filter_map(|variant| match &variant.data {
+                Some(Token::Identifier { name, .. }) => Some((name.as_str(), variant.children.len())),
+                _ => None,
+            }).collect();
+            variants.iter().for_each(|(name, _)| { definitions.insert(name, variants.clone()); });
+        },
+        _ => (),
+    }
+    node.condition.iter().chain(node.children.iter()).for_each(|child| collect_definitions(child, definitions));
+}
+
+fn visit(node: &Node, definitions: &Definitions, errors: &mut Vec<SyntaxError>) {
+    if node.node_type == NodeType::Match {
+        check_match(node, definitions, errors)
+    }
+    node.condition.iter().chain(node.children.iter()).for_each(|child| visit(child, definitions, errors));
+}
+
+fn check_match(node: &Node, definitions: &Definitions, errors: &mut Vec<SyntaxError>) {
+    let mut rows: Vec<Vec<Pattern>> = vec!();
+    for arm in &node.children {
+        let pattern = Pattern::from(&arm.condition[0], definitions);
+        if useful(&rows, &[pattern.clone(), Pattern::Wild]).is_none() {
+            errors.push(SyntaxError::new(arm.condition[0].pos(), String::from("This match arm is unreachable")))
+        }
+        // guarded arm covers nothing, but its pattern still tells the type of the value
+        let guard = match arm.condition.len() {
+            1 => Pattern::Wild,
+            _ => Pattern::Never,
+        };
+        rows.push(vec!(pattern, guard))
+    }
+    if let Some(witness) = useful(&rows, &[Pattern::Wild, Pattern::Wild]) {
+        errors.push(SyntaxError::new(node.pos(), format!("Match is not exhaustive, missing case: {}", witness[0])))
+    }
+}
+
+/// Simplified pattern: bindings are wildcards, integers are inclusive intervals.
+///
+/// Float and String values have no finite set of constructors, so their patterns are kept as is.
+#[derive(Debug, Clone)]
+enum Pattern<'a> {
+    Wild,
+    Bool(bool),
+    None,
+    Int(i64, i64),
+    Other { is_float: bool, key: String, node: &'a Node },
+    Tuple(Vec<Pattern<'a>>),
+    List { items: Vec<Pattern<'a>>, has_rest: bool },
+    /// Struct or variant, the pattern without the fields matches any value of the variant.
+    Variant { name: &'a str, fields: Vec<Pattern<'a>>, constructors: Vec<(&'a str, usize)> },
+    /// Matches nothing, used for the failed guard.
+    Never,
+}
+
+/// Constructor of the values which are matched by the same patterns of the column.
+#[derive(Debug, Clone, PartialEq)]
+enum Constructor {
+    Bool(bool),
+    None,
+    Int(i64, i64),
+    Tuple(usize),
+    List(usize),
+    ListAtLeast(usize),
+    Variant(String, usize),
+}
+
+impl<'a> Pattern<'a> {
+
+    fn from(node: &'a Node, definitions: &Definitions<'a>) -> Pattern<'a> {
+        match node.node_type {
+            NodeType::Token => match &node.data {
+                Some(Token::IntConstant { value, .. }) => Pattern::Int(*value, *value),
+                Some(Token::BoolConstant { value, .. }) => Pattern::Bool(*value),
+                Some(Token::NoneConstant { .. }) => Pattern::None,
+                Some(token @ Token::FloatConstant { .. }) | Some(token @ Token::StringConstant { .. }) => Pattern::Other {
+                    is_float: matches!(token, Token::FloatConstant { .. }),
+                    key: literal(token).map(|value| value.repr()).unwrap_or_default(),
+                    node,
+                },
+                _ => Pattern::Wild,
+            },
+            NodeType::Range => {
+                let bound = |node: &Node| node.data.as_ref().and_then(literal).unwrap_or(Value::None);
+                match (bound(&node.children[0]), bound(&node.children[1])) {
+                    (start @ Value::Int(_), end @ Value::Int(_))
+                    | (start @ Value::None, end @ Value::Int(_))
+                    | (start @ Value::Int(_), end @ Value::None) => {
+                        let start = match start { Value::Int(start) => start, _ => i64::MIN };
+                        // exclusive `..i64::MIN` matches nothing, so the interval is empty
+                        let end = match (end, node.operator() == Some(Operator::InclusiveRange)) {
+                            (Value::Int(end), true) => Some(end),
+                            (Value::Int(end), false) => end.checked_sub(1),
+                            _ => Some(i64::MAX),
+                        };
+                        match end {
+                            Some(end) => Pattern::Int(start, end),
+                            None => Pattern::Int(1, 0),
+                        }
+                    },
+                    (start, end) => Pattern::Other {
+                        is_float: !matches!(start, Value::Str(_)) && !matches!(end, Value::Str(_)),
+                        key: format!("{}{}{}", bound_repr(&start), node.operator().map(Operator::as_str).unwrap_or_default(), bound_repr(&end)),
+                        node,
+                    },
+                }
+            },
+            NodeType::Tuple => Pattern::Tuple(node.children.iter().map(|item| Pattern::from(item, definitions)).collect()),
+            NodeType::List => Pattern::List {
+                items: node.children.iter()
+                    .filter(|item| item.node_type != NodeType::Rest)
+                    .map(|item| Pattern::from(item, definitions))
+                    .collect(),
+                has_rest: node.children.iter().any(|item| item.node_type == NodeType::Rest),
+            },
+            // unknown variants are reported by the resolver
+            NodeType::Variant => match &node.data {
+                Some(Token::Identifier { name, .. }) if definitions.contains_key(name.as_str()) => Pattern::Variant {
+                    name,
+                    fields: node.children.iter().map(|field| Pattern::from(field, definitions)).collect(),
+                    constructors: definitions[name.as_str()].clone(),
+                },
+                _ => Pattern::Wild,
+            },
+            _ => Pattern::Wild,
+        }
+    }
+
+    fn covers(&self, constructor: &Constructor) -> bool {
+        match (self, constructor) {
+            (Pattern::Wild, _) => true,
+            (Pattern::Bool(a), Constructor::Bool(b)) => a == b,
+            (Pattern::None, Constructor::None) => true,
+            (Pattern::Int(start, end), Constructor::Int(a, b)) => start <= a && b <= end,
+            (Pattern::Tuple(items), Constructor::Tuple(arity)) => items.len() == *arity,
+            (Pattern::List { items, has_rest: false }, Constructor::List(length)) => items.len() == *length,
+            (Pattern::List { items, has_rest: true }, Constructor::List(length))
+            | (Pattern::List { items, has_rest: true }, Constructor::ListAtLeast(length)) => items.len() <= *length,
+            (Pattern::Variant { name, .. }, Constructor::Variant(variant, _)) => name == variant,
+            _ => false,
+        }
+    }
+}
+
+impl Constructor {
+
+    fn arity(&self) -> usize {
+        match self {
+            Constructor::Tuple(arity) | Constructor::List(arity) | Constructor::ListAtLeast(arity) | Constructor::Variant(_, arity) => *arity,
+            _ => 0,
+        }
+    }
+
+    /// Example of the value built by this constructor from the examples of its fields.
+    fn example(&self, fields: &[String]) -> String {
+        match self {
+            Constructor::Bool(value) => value.to_string(),
+            Constructor::None => String::from("None"),
+            // the value closest to zero is the most readable one
+            Constructor::Int(start, end) => match (*start <= 0, *end >= 0) {
+                (true, true) => String::from("0"),
+                (false, _) => start.to_string(),
+                (_, false) => end.to_string(),
+            },
+            Constructor::Tuple(1) => format!("({},)", fields[0]),
+            Constructor::Tuple(_) => format!("({})", fields.join(", ")),
+            Constructor::List(_) => format!("[{}]", fields.join(", ")),
+            Constructor::ListAtLeast(0) => String::from("[..]"),
+            Constructor::ListAtLeast(_) => format!("[{}, ..]", fields.join(", ")),
+            Constructor::Variant(name, 0) => name.clone(),
+            Constructor::Variant(name, _) => format!("{}({})", name, fields.join(", ")),
+        }
+    }
+}
+
+/// Checks whether there is a value which matches the row `pattern` but none of the `rows`.
+///
+/// Returns the examples of such value for every column of the row.
+fn useful(rows: &[Vec<Pattern>], pattern: &[Pattern]) -> Option<Vec<String>> {
+    let head = match pattern.first() {
+        Some(head) => head,
+        None => return match rows.is_empty() {
+            true => Some(vec!()),
+            false => None,
+        },
+    };
+    if let Pattern::Other { key, .. } = head {
+        let rows: Vec<Vec<Pattern>> = rows.iter().filter_map(|row| match &row[0] {
+            Pattern::Wild => Some(row[1..].to_vec()),
+            Pattern::Other { key: row_key, .. } if row_key == key => Some(row[1..].to_vec()),
+            _ => None,
+        }).collect();
+        return useful(&rows, &pattern[1..]).map(|witness| prepend(key.clone(), witness))
+    }
+    let mut heads: Vec<&Pattern> = rows.iter().map(|row| &row[0]).collect();
+    heads.push(head);
+    let constructors = split(&heads);
+    for constructor in constructors.iter().filter(|constructor| head.covers(constructor)) {
+        if let Some(witness) = useful_constructor(rows, pattern, constructor) {
+            return Some(witness)
+        }
+    }
+    let others: Vec<&Pattern> = heads.iter().copied().filter(|head| matches!(head, Pattern::Other { .. })).collect();
+    match head {
+        // values of the other types or Float and String values which are not matched by the literals,
+        // `None` alone means the optional value of the unknown type
+        Pattern::Wild if constructors.is_empty() || constructors == [Constructor::None] || !others.is_empty() => {
+            let rows: Vec<Vec<Pattern>> = rows.iter().filter(|row| matches!(row[0], Pattern::Wild)).map(|row| row[1..].to_vec()).collect();
+            let example = match others.first() {
+                Some(Pattern::Other { is_float, .. }) => fresh_value(*is_float, &others),
+                _ => String::from("_"),
+            };
+            useful(&rows, &pattern[1..]).map(|witness| prepend(example, witness))
+        },
+        _ => None,
+    }
+}
+
+fn useful_constructor(rows: &[Vec<Pattern>], pattern: &[Pattern], constructor: &Constructor) -> Option<Vec<String>> {
+    let rows: Vec<Vec<Pattern>> = rows.iter().filter_map(|row| specialize(row, constructor)).collect();
+    let pattern = specialize(pattern, constructor)?;
+    let mut witness = useful(&rows, &pattern)?;
+    let fields: Vec<String> = witness.drain(..constructor.arity()).collect();
+    Some(prepend(constructor.example(&fields), witness))
+}
+
+/// Replaces the head of the row by the fields of the constructor, or returns `None` if the head does not cover it.
+fn specialize<'a>(row: &[Pattern<'a>], constructor: &Constructor) -> Option<Vec<Pattern<'a>>> {
+    let head = &row[0];
+    if !head.covers(constructor) {
+        return None
+    }
+    let mut fields = match head {
+        Pattern::Tuple(items) | Pattern::List { items, .. } | Pattern::Variant { fields: items, .. } => items.clone(),
+        _ => vec!(),
+    };
+    // wildcards stand for the fields of the wildcard and variant patterns and for the items matched by the rest `..`
+    fields.resize(constructor.arity(), Pattern::Wild);
+    fields.extend_from_slice(&row[1..]);
+    Some(fields)
+}
+
+/// Splits the values matched by the patterns of the column into the constructors,
+/// so each constructor is either completely covered by the pattern or not at all.
+fn split(heads: &[&Pattern]) -> Vec<Constructor> {
+    let mut constructors = vec!();
+    if heads.iter().any(|head| matches!(head, Pattern::Bool(_))) {
+        constructors.push(Constructor::Bool(false));
+        constructors.push(Constructor::Bool(true));
+    }
+    if heads.iter().any(|head| matches!(head, Pattern::None)) {
+        constructors.push(Constructor::None)
+    }
+    let mut boundaries: Vec<i64> = vec!();
+    for head in heads {
+        if let Pattern::Int(start, end) = head {
+            boundaries.push(*start);
+            if let Some(next) = end.checked_add(1) {
+                boundaries.push(next)
+            }
+        }
+    }
+    if !boundaries.is_empty() {
+        boundaries.push(i64::MIN);
+        boundaries.sort_unstable();
+        boundaries.dedup();
+        for (index, start) in boundaries.iter().enumerate() {
+            let end = boundaries.get(index + 1).map(|next| next - 1).unwrap_or(i64::MAX);
+            constructors.push(Constructor::Int(*start, end))
+        }
+    }
+    let mut arities: Vec<usize> = heads.iter().filter_map(|head| match head {
+        Pattern::Tuple(items) => Some(items.len()),
+        _ => None,
+    }).collect();
+    arities.sort_unstable();
+    arities.dedup();
+    constructors.extend(arities.into_iter().map(Constructor::Tuple));
+    let lists: Vec<(usize, bool)> = heads.iter().filter_map(|head| match head {
+        Pattern::List { items, has_rest } => Some((items.len(), *has_rest)),
+        _ => None,
+    }).collect();
+    if !lists.is_empty() {
+        // lists longer than any fixed length pattern are matched by the same patterns
+        let max_prefix = lists.iter().filter(|(_, has_rest)| *has_rest).map(|(length, _)| *length).max().unwrap_or(0);
+        let max_fixed = lists.iter().filter(|(_, has_rest)| !*has_rest).map(|(length, _)| length + 1).max().unwrap_or(0);
+        let at_least = max_prefix.max(max_fixed);
+        constructors.extend((0..at_least).map(Constructor::List));
+        constructors.push(Constructor::ListAtLeast(at_least))
+    }
+    for head in heads {
+        if let Pattern::Variant { constructors: variants, .. } = head {
+            for (name, arity) in variants {
+                let constructor = Constructor::Variant(name.to_string(), *arity);
+                if !constructors.contains(&constructor) {
+                    constructors.push(constructor)
+                }
+            }
+        }
+    }
+    constructors
+}
+
+/// Finds the Float or String value which is not matched by the literal and range patterns.
+fn fresh_value(is_float: bool, others: &[&Pattern]) -> String {
+    let candidates = match is_float {
+        true => vec!(Value::Float(0.5), Value::Float(1.5), Value::Float(-0.5), Value::Float(1e300), Value::Float(-1e300)),
+        false => ["a", "b", "z", "", "A", "0"].iter().map(|value| Value::Str(value.to_string())).collect(),
+    };
+    let is_matched = |value: &Value| others.iter().any(|other| match other {
+        Pattern::Other { node, .. } => matches(node, value, &mut vec!()),
+        _ => false,
+    });
+    match candidates.into_iter().find(|value| !is_matched(value)) {
+        Some(value) => value.repr(),
+        // NaN is not equal to any number and is out of any range
+        None if is_float => String::from("NaN"),
+        None => String::from("_"),
+    }
+}
+
+#[inline]
+fn bound_repr(value: &Value) -> String {
+    match value {
+        Value::None => String::new(),
+        _ => value.repr(),
+    }
+}
+
+#[inline]
+fn prepend(example: String, mut witness: Vec<String>) -> Vec<String> {
+    witness.insert(0, example);
+    witness
+}
+
+#[cfg(test)]
+fn check(arms: &str) -> Vec<String> {
+    // declarations go before the match
+    let (declarations, arms) = match arms.rfind("}\n") {
+        Some(index) => arms.split_at(index + 2),
+        None => ("", arms),
+    };
+    let source = format!("{}match x {{\n{}\n}}", declarations, arms);
+    let tree = parse(&source);
+    check_exhaustiveness(&tree).into_iter().map(|error| error.message).collect()
+}
+
+/// Testing the missing cases of the literal, range and tuple patterns.
+#[test]
+fn test_missing_cases() {
+    assert!(check("true -> 1\nfalse -> 2").is_empty());
+    assert!(check("..0 -> 1\n0 -> 2\n1.. -> 3").is_empty());
+    assert!(check("(true, _) -> 1\n(_, None) -> 2\n(false, n) -> n").is_empty());
+    assert!(check("(_, [], true) -> 1\n(None, _, _) -> 2\n(_, _, _) -> 3").is_empty());
+    assert_eq!(vec!("Match is not exhaustive, missing case: -1"), check("0 -> 1\n1..=10 -> 2\n10.. -> 3"));
+    assert_eq!(vec!("Match is not exhaustive, missing case: (false, true)"), check("(true, _) -> 1\n(_, false) -> 2"));
+    assert_eq!(vec!("Match is not exhaustive, missing case: None"), check("true -> 1\nfalse -> 2\nNone if y -> 3"));
+    assert_eq!(vec!("Match is not exhaustive, missing case: \"b\""), check("\"a\" -> 1"));
+    assert_eq!(vec!("Match is not exhaustive, missing case: 1.5"), check("..1.0 -> 1\n0.5 -> 2"));
+    assert_eq!(vec!("Match is not exhaustive, missing case: _"), check("n if n > 0 -> 1"));
+}
+
+/// Testing lists with the rest patterns and the unreachable arms.
+#[test]
+fn test_lists_and_unreachable_arms() {
+    assert!(check("[] -> 0\n[x] -> 1\n[_, _, ..] -> 2").is_empty());
+    assert_eq!(vec!("Match is not exhaustive, missing case: [_, _, ..]"), check("[] -> 0\n[_] -> 1"));
+    assert_eq!(vec!("Match is not exhaustive, missing case: [false, ..]"), check("[] -> 0\n[true, ..] -> 1"));
+    assert_eq!(vec!("This match arm is unreachable"), check("[..] -> 0\n[a, b] -> 1"));
+    assert_eq!(vec!("This match arm is unreachable"), check("1..10 -> 0\n5 if y -> 1\n_ -> 2"));
+    assert_eq!(vec!("This match arm is unreachable"), check("_ -> 0\n\"a\" -> 1"));
+    assert_eq!(vec!("This match arm is unreachable", "Match is not exhaustive, missing case: 0"), check("1.. -> 0\n5..=3 -> 1"));
+}
+
+/// Testing the variants of the enums and the structs.
+#[test]
+fn test_variants() {
+    let shape = "enum Shape { Circle(r), Rect(w, h), Empty }\nstruct P { x, y }\n";
+    assert!(check(&format!("{}Circle -> 1\nRect(_, _) -> 2\nShape.Empty -> 3", shape)).is_empty());
+    assert!(check(&format!("{}P(0, y) -> y\nP(x, _) -> x", shape)).is_empty());
+    assert_eq!(vec!("Match is not exhaustive, missing case: Rect(_, _)"), check(&format!("{}Circle(r) -> r\nEmpty -> 0", shape)));
+    assert_eq!(vec!("Match is not exhaustive, missing case: Circle(false)"), check(&format!("{}Circle(true) -> 1\nRect(_, _) -> 2\nEmpty -> 3", shape)));
+    assert_eq!(vec!("This match arm is unreachable"), check(&format!("{}Circle -> 1\nCircle(0) -> 2\n_ -> 3", shape)));
+    assert_eq!(vec!("Match is not exhaustive, missing case: P(1, _)"), check(&format!("{}P(0, y) -> y\nP(..0, _) -> 1\nP(2.., _) -> 2", shape)));
+}
@@ -0,0 +1,6 @@
+pub mod captures;
+pub mod exhaustiveness;
+pub mod resolver;
+pub use captures::free_variables;
+pub use exhaustiveness::check_exhaustiveness;
+pub use resolver::{ declared_name, resolve, resolve_assignments, resolve_with_globals, Assignment };
@@ -0,0 +1,608 @@
+use crate::dto::{ Node, NodeType, Operator, Token, SyntaxError };
+#[cfg(test)]
+use crate::dto::Symbol;
+use crate::runtime::builtins::{ Builtin, find_builtin };
+use crate::runtime::types::TYPE_NAMES;
+use std::collections::{ HashMap, HashSet };
+#[cfg(test)]
+use crate::parser::parse;
+
+/// Checks the names of the program before its execution.
+///
+/// # Errors
+/// - variables and functions which are not declared at the point of usage
+/// - functions, structs and enums declared twice in the same scope
+/// - unknown types in the annotations and `is` tests, duplicate fields and variants
+/// - unknown variants in the patterns and patterns with the wrong number of fields
+/// - calls of the builtin functions with the wrong number of arguments or literal arguments of the wrong type
+/// - `break` and `continue` outside of the loop, `return` outside of the function
+/// - tests with the same name
+///
+/// Imports, functions, structs and enums of the root block are hoisted like in the [`Interpreter`].
+/// Names imported with `from` may be the types and variants of the other module, so they are not checked. Functions and lambdas
+/// look up the global variables at the moment of the call, so inside of them all variables
+/// declared in the root block are visible, even those declared after the function.
+///
+/// [`Interpreter`]: crate::runtime::Interpreter
+pub fn resolve(root: &Node) -> Vec<SyntaxError> {
+    resolve_with_globals(root, &[])
+}
+
+/// Same as [`resolve`], but the global variables defined outside of the program, e.g. by the host of the [`Engine`],
+/// are treated as declared. They may be declared again by the program.
+///
+/// [`Engine`]: crate::Engine
+pub fn resolve_with_globals(root: &Node, predeclared: &[String]) -> Vec<SyntaxError> {
+    // the tree may be built by hand, while the resolver walks it recursively
+    if let Err(error) = root.depth() {
+        return vec!(error)
+    }
+    run(root, predeclared).errors
+}
+
+/// What the assignments to the names and `mut` declarations of the checked program do, by the positions of their operators.
+///
+/// The backends which compile the program declare and assign their variables by it, so they follow the same scopes as the resolver.
+pub fn resolve_assignments(root: &Node) -> HashMap<usize, Assignment> {
+    run(root, &[]).assignments
+}
+
+/// Assignment `name = value`, `name += value` or `mut name = value`.
+#[derive(Debug, Clone)]
+pub enum Assignment {
+    /// Declares the new variable in the current scope.
+    Declare { is_mutable: bool },
+    /// Changes the mutable variable which is already declared.
+    Assign,
+    /// The assignment is rejected by the compiled program, the interpreter reports the same error at runtime.
+    Invalid(SyntaxError),
+}
+
+fn run(root: &Node, predeclared: &[String]) -> Resolver {
+    let mut resolver = Resolver {
+        predeclared: predeclared.iter().cloned().collect(),
+        globals: HashMap::new(),
+        types: HashSet::new(),
+        definitions: HashMap::new(),
+        imported: HashSet::new(),
+        scopes: vec!(HashMap::new()),
+        function_depth: 0,
+        loop_depth: 0,
+        assignments: HashMap::new(),
+        errors: vec!(),
+    };
+    collect_types(root, &mut resolver);
+    let mut tests = HashSet::new();
+    for statement in &root.children {
+        for (name, pos) in imported_names(statement) {
+            if resolver.scopes[0].contains_key(name) {
+                resolver.error(pos, format!("'{}' is already declared in this scope", name))
+            }
+            resolver.declare(name);
+            if !statement.condition.is_empty() {
+                resolver.types.insert(name.to_string());
+                resolver.imported.insert(name.to_string());
+            }
+        }
+        let statement = statement.declaration();
+        match (statement.node_type, &statement.data) {
+            (NodeType::Function, Some(Token::Identifier { name, pos }))
+            | (NodeType::Struct, Some(Token::Identifier { name, pos }))
+            | (NodeType::Enum, Some(Token::Identifier { name, pos })) => {
+                if resolver.scopes[0].contains_key(name.as_str()) {
+                    resolver.error(*pos, format!("'{}' is already declared in this scope", name))
+                }
+                resolver.declare(name);
+                if statement.node_type == NodeType::Enum {
+                    statement.children.iter().filter_map(|variant| identifier(variant)).for_each(|name| resolver.declare(name))
+                }
+            },
+            (NodeType::Expression, _) => {
+                if let Some((name, is_mutable)) = declared_name(statement) {
+                    *resolver.globals.entry(name.to_string()).or_default() |= is_mutable;
+                }
+            },
+            (NodeType::Test, Some(Token::StringConstant { value, pos })) if !tests.insert(value) =>
+                resolver.error(*pos, format!("Test '{}' is already declared", value)),
+            _ => (),
+        }
+    }
+    for name in resolver.scopes[0].keys() {
+        resolver.globals.entry(name.clone()).or_default();
+    }
+    root.children.iter().for_each(|statement| resolver.visit(statement));
+    resolver
+}
+
+struct Resolver {
+    predeclared: HashSet<String>,
+    /// Names declared in the root block and whether they are mutable.
+    globals: HashMap<String, bool>,
+    /// Names of all structs and enums, they may be used in the annotations before the declaration.
+    types: HashSet<String>,
+    /// Structs and variants by name with the name of their enum and the number of fields.
+    definitions: HashMap<String, (Option<String>, usize)>,
+    /// Names imported from the other modules with `from`.
+    imported: HashSet<String>,
+    /// Declared names and whether they are mutable.
+    scopes: Vec<HashMap<String, bool>>,
+    function_depth: usize,
+    loop_depth: usize,
+    assignments: HashMap<usize, Assignment>,
+    errors: Vec<SyntaxError>,
+}
+
+impl Resolver {
+
+    #[inline]
+    fn error(&mut self, pos: usize, message: String) {
+        self.errors.push(SyntaxError::new(pos, message))
+    }
+
+    fn is_declared(&self, name: &str) -> bool {
+        self.binding(name).is_some()
+    }
+
+    /// Whether the variable is mutable and whether it is the global variable seen by the function.
+    fn binding(&self, name: &str) -> Option<(bool, bool)> {
+        self.scopes.iter().enumerate().rev().find_map(|(index, scope)| scope.get(name).map(|is_mutable| (*is_mutable, index == 0)))
+            .or_else(|| self.globals.get(name).filter(|_| self.function_depth > 0).map(|is_mutable| (*is_mutable, true)))
+            .or_else(|| self.predeclared.get(name).map(|_| (false, true)))
+    }
+
+    fn declare(&mut self, name: &str) {
+        self.declare_variable(name, false)
+    }
+
+    fn declare_variable(&mut self, name: &str, is_mutable: bool) {
+        self.scopes.last_mut().unwrap().insert(name.to_string(), is_mutable);
+    }
+
+    fn declare_pattern(&mut self, pattern: &Node, is_mutable: bool) {
+        match (pattern.node_type, &pattern.data) {
+            (NodeType::Token, Some(Token::Identifier { name, .. })) => self.declare_variable(name, is_mutable),
+            _ => pattern.children.iter().for_each(|child| self.declare_pattern(child, is_mutable)),
+        }
+    }
+
+    /// Decides what the assignment to the name does, only variables declared with `mut` can be assigned again.
+    fn assignment(&self, name: &str, node: &Node) -> Assignment {
+        let is_plain = node.operator() == Some(Operator::Assign);
+        match self.binding(name) {
+            Some((true, _)) => Assignment::Assign,
+            // the function may be called before the global variable is declared, then the name is local
+            Some((false, true)) if is_plain && self.function_depth > 0 => Assignment::Declare { is_mutable: false },
+            Some(_) => invalid(node.pos(), format!("Cannot assign twice to immutable variable '{}', declare it with 'mut'", name)),
+            None if is_plain => Assignment::Declare { is_mutable: false },
+            None => invalid(node.children[0].pos(), format!("Undefined variable '{}'", name)),
+        }
+    }
+
+    /// Decides whether `mut name = value` declares the variable, the name can't be declared twice in the same scope.
+    fn mutable_declaration(&self, name: &str, node: &Node) -> Assignment {
+        match (node.operator() == Some(Operator::Assign), self.scopes.last().unwrap().contains_key(name)) {
+            (false, _) => invalid(node.pos(), format!("Variable '{}' must be declared with '='", name)),
+            (true, true) => invalid(node.children[0].pos(), format!("Variable '{}' is already declared in this scope", name)),
+            (true, false) => Assignment::Declare { is_mutable: true },
+        }
+    }
+
+    fn visit_scoped(&mut self, visit: impl FnOnce(&mut Resolver)) {
+        self.scopes.push(HashMap::new());
+        visit(self);
+        self.scopes.pop();
+    }
+
+    fn visit(&mut self, node: &Node) {
+        match (node.node_type, &node.data) {
+            (NodeType::Token, Some(Token::Identifier { name, pos })) => {
+                if !self.is_declared(name) {
+                    self.error(*pos, format!("Undefined variable '{}'", name))
+                }
+            },
+            (NodeType::Call, Some(Token::Function { name, pos })) => {
+                if !self.is_declared(name) {
+                    match find_builtin(name) {
+                        Some(builtin) => self.check_builtin_call(builtin, node),
+                        None => self.error(*pos, format!("Unknown function '{}'", name)),
+                    }
+                }
+                node.children.iter().for_each(|child| self.visit(child))
+            },
+            (NodeType::Expression, Some(Token::Operator { payload, .. })) if payload.is_assignment() => {
+                self.visit(&node.children[1]);
+                let target = &node.children[0];
+                match (target.operator(), &target.data) {
+                    (Some(Operator::Mut), _) => {
+                        let assignment = match identifier(&target.children[0]) {
+                            Some(name) => self.mutable_declaration(name, node),
+                            None => invalid(target.pos(), String::from("Invalid assignment target")),
+                        };
+                        self.assignments.insert(node.pos(), assignment);
+                        self.declare_pattern(&target.children[0], true)
+                    },
+                    (_, Some(Token::Identifier { name, .. })) => {
+                        let assignment = self.assignment(name, node);
+                        match assignment {
+                            Assignment::Declare { .. } => self.declare(name),
+                            _ => self.visit(target),
+                        }
+                        self.assignments.insert(node.pos(), assignment);
+                    },
+                    _ => self.visit(target),
+                }
+            },
+            (NodeType::Block, _) => self.visit_scoped(|resolver| node.children.iter().for_each(|child| resolver.visit(child))),
+            (NodeType::For, _) => {
+                node.condition.iter().for_each(|child| self.visit(child));
+                self.loop_depth += 1;
+                self.visit_scoped(|resolver| {
+                    resolver.declare_pattern(&node.children[0], false);
+                    resolver.visit(&node.children[1])
+                });
+                self.loop_depth -= 1;
+            },
+            (NodeType::Loop, _) => {
+                self.loop_depth += 1;
+                node.children.iter().for_each(|child| self.visit(child));
+                self.loop_depth -= 1;
+            },
+            (NodeType::Break, _) | (NodeType::Continue, _) if self.loop_depth == 0 =>
+                self.error(node.pos(), format!("'{}' outside of the loop", node.operator().map(Operator::as_str).unwrap_or_default())),
+            (NodeType::Return, _) => {
+                if self.function_depth == 0 {
+                    self.error(node.pos(), String::from("'return' outside of the function"))
+                }
+                node.children.iter().for_each(|child| self.visit(child))
+            },
+            (NodeType::Arm, _) => self.visit_scoped(|resolver| {
+                resolver.check_pattern(&node.condition[0]);
+                resolver.declare_pattern(&node.condition[0], false);
+                node.condition[1..].iter().chain(node.children.iter()).for_each(|child| resolver.visit(child))
+            }),
+            // tests run after the module, so like the functions they see all its global variables
+            (NodeType::Lambda, _) | (NodeType::Test, _) => self.visit_function(node),
+            (NodeType::Import, _) => (),
+            // names of the fields and methods are not variables
+            (NodeType::Expression, Some(Token::Operator { payload: Operator::Dot, .. })) => {
+                self.visit(&node.children[0]);
+                if node.children[1].node_type == NodeType::Call {
+                    node.children[1].children.iter().for_each(|child| self.visit(child))
+                }
+            },
+            (NodeType::Expression, Some(Token::Operator { payload: Operator::Is, .. })) => {
+                self.visit(&node.children[0]);
+                self.check_type_test(&node.children[1])
+            },
+            (NodeType::Function, Some(Token::Identifier { name, pos })) => {
+                self.declare_local(name, *pos);
+                self.visit_function(node)
+            },
+            (NodeType::Struct, Some(Token::Identifier { name, pos })) => {
+                self.declare_local(name, *pos);
+                let mut fields = HashSet::new();
+                for field in &node.children {
+                    let field = match field.operator() {
+                        Some(Operator::Mut) => &field.children[0],
+                        _ => field,
+                    };
+                    field.condition.iter().for_each(|annotation| self.visit(annotation));
+                    if let Some(Token::Identifier { name: field_name, pos }) = &field.data {
+                        if !fields.insert(field_name) {
+                            self.error(*pos, format!("Field '{}' is already declared in {}", field_name, name))
+                        }
+                    }
+                }
+            },
+            (NodeType::Enum, Some(Token::Identifier { name, pos })) => {
+                self.declare_local(name, *pos);
+                let mut variants = HashSet::new();
+                for variant in &node.children {
+                    if let Some(Token::Identifier { name: variant_name, pos }) = &variant.data {
+                        if !variants.insert(variant_name) {
+                            self.error(*pos, format!("Variant '{}' is already declared in {}", variant_name, name))
+                        }
+                        self.declare_local(variant_name, *pos);
+                    }
+                    variant.children.iter().flat_map(|field| field.condition.iter()).for_each(|annotation| self.visit(annotation));
+                }
+            },
+            (NodeType::Type, Some(Token::Identifier { name, pos })) => {
+                if !TYPE_NAMES.contains(&name.as_str()) && !self.types.contains(name.as_str()) {
+                    self.error(*pos, format!("Unknown type '{}'", name))
+                }
+            },
+            _ => {
+                node.condition.iter().for_each(|child| self.visit(child));
+                node.children.iter().for_each(|child| self.visit(child));
+            },
+        }
+    }
+
+    /// Checks the right side of `value is Type`, it is a type, a struct, an enum or a qualified variant.
+    fn check_type_test(&mut self, node: &Node) {
+        match (node.operator(), &node.data) {
+            (Some(Operator::Dot), _) => match (&node.children[0].data, &node.children[1].data) {
+                (Some(Token::Identifier { name: enum_name, .. }), Some(Token::Identifier { name, pos })) => {
+                    if !self.types.contains(enum_name.as_str()) {
+                        self.error(node.children[0].pos(), format!("Unknown type '{}'", enum_name))
+                    } else if self.definitions.get(name.as_str()).map(|(owner, _)| owner.as_deref() != Some(enum_name.as_str())).unwrap_or(true) {
+                        self.error(*pos, format!("{} has no variant '{}'", enum_name, name))
+                    }
+                },
+                _ => self.error(node.pos(), String::from("Expected type")),
+            },
+            (_, Some(Token::Identifier { name, pos })) => {
+                if !TYPE_NAMES.contains(&name.as_str()) && !self.types.contains(name.as_str()) && !self.definitions.contains_key(name.as_str()) {
+                    self.error(*pos, format!("Unknown type '{}'", name))
+                }
+            },
+            (_, Some(Token::NoneConstant { .. })) => (),
+            _ => self.error(node.pos(), String::from("Expected type")),
+        }
+    }
+
+    /// Variant patterns must refer to the known structs or variants and list all their fields.
+    fn check_pattern(&mut self, pattern: &Node) {
+        if let (NodeType::Variant, Some(Token::Identifier { name, pos })) = (pattern.node_type, &pattern.data) {
+            let qualifier = pattern.condition.first().and_then(identifier);
+            match self.definitions.get(name.as_str()) {
+                None if self.imported.contains(name.as_str()) => (),
+                None => self.error(*pos, format!("Unknown variant '{}'", name)),
+                Some((owner, _)) if qualifier.is_some() && owner.as_deref() != qualifier =>
+                    self.error(*pos, format!("{} has no variant '{}'", qualifier.unwrap_or_default(), name)),
+                Some((_, arity)) if !pattern.children.is_empty() && pattern.children.len() != *arity => {
+                    let message = format!("{} has {} fields, found {} in the pattern", name, arity, pattern.children.len());
+                    self.error(*pos, message)
+                },
+                _ => (),
+            }
+        }
+        pattern.children.iter().for_each(|child| self.check_pattern(child))
+    }
+
+    /// Checks the call against the signature of the builtin. Types of the arguments are known only for the literals
+    /// and the results of the other builtins, the rest is checked at runtime.
+    fn check_builtin_call(&mut self, builtin: &Builtin, node: &Node) {
+        if let Err(message) = builtin.check_count(node.children.len()) {
+            return self.error(node.pos(), message)
+        }
+        for (index, argument) in node.children.iter().enumerate() {
+            let expected = builtin.parameter_type(index).unwrap_or("Any");
+            match self.static_type(argument) {
+                Some(found) if !is_subtype(found, expected) => {
+                    let message = format!("Argument {} of '{}' must be {}, found {}", index + 1, builtin.name, expected, found);
+                    self.error(argument.pos(), message)
+                },
+                _ => (),
+            }
+        }
+    }
+
+    fn static_type(&self, node: &Node) -> Option<&'static str> {
+        match (node.node_type, &node.data) {
+            (NodeType::Token, Some(Token::IntConstant { .. })) => Some("Int"),
+            (NodeType::Token, Some(Token::FloatConstant { .. })) => Some("Float"),
+            (NodeType::Token, Some(Token::StringConstant { .. })) => Some("String"),
+            (NodeType::Token, Some(Token::BoolConstant { .. })) => Some("Bool"),
+            (NodeType::Token, Some(Token::NoneConstant { .. })) => Some("None"),
+            (NodeType::List, _) => Some("List"),
+            (NodeType::Map, _) => Some("Map"),
+            (NodeType::Lambda, _) => Some("Function"),
+            (NodeType::Call, Some(Token::Function { name, .. })) if !self.is_declared(name) =>
+                find_builtin(name).map(|builtin| builtin.signature.return_type).filter(|type_name| !matches!(*type_name, "Any" | "Number")),
+            _ => None,
+        }
+    }
+
+    /// Declares the function or the struct, declarations of the root block are already hoisted.
+    fn declare_local(&mut self, name: &str, pos: usize) {
+        if self.scopes.len() > 1 {
+            if self.scopes.last().unwrap().contains_key(name) {
+                self.error(pos, format!("'{}' is already declared in this scope", name))
+            }
+            self.declare(name)
+        }
+    }
+
+    /// Parameters are declared one by one, so the default value may refer to the previous parameters.
+    fn visit_function(&mut self, node: &Node) {
+        let loop_depth = self.loop_depth;
+        self.loop_depth = 0;
+        self.function_depth += 1;
+        self.visit_scoped(|resolver| {
+            for parameter in &node.condition {
+                parameter.condition.iter().chain(parameter.children.iter()).for_each(|child| resolver.visit(child));
+                resolver.declare_pattern(parameter, false);
+            }
+            node.children.iter().for_each(|child| resolver.visit(child))
+        });
+        self.function_depth -= 1;
+        self.loop_depth = loop_depth;
+    }
+}
+
+fn collect_types(node: &Node, resolver: &mut Resolver) {
+    match (node.node_type, &node.data) {
+        (NodeType::Struct, Some(Token::Identifier { name, .. })) => {
+            resolver.types.insert(name.to_string());
+            resolver.definitions.insert(name.to_string(), (None, node.children.len()));
+        },
+        (NodeType::Enum, Some(Token::Identifier { name, .. })) => {
+            resolver.types.insert(name.to_string());
+            for variant in &node.children {
+                if let Some(variant_name) = identifier(variant) {
+                    resolver.definitions.insert(variant_name.to_string(), (Some(name.to_string()), variant.children.len()));
+                }
+            }
+            return
+        },
+        _ => (),
+    }
+    node.children.iter().for_each(|child| collect_types(child, resolver));
+}
+
+/// Names declared by `import a.b` or `from a.b import c, d` with their positions.
+fn imported_names(statement: &Node) -> Vec<(&str, usize)> {
+    if statement.node_type != NodeType::Import {
+        return vec!()
+    }
+    let names = match statement.condition.is_empty() {
+        true => &statement.children[statement.children.len() - 1..],
+        false => &statement.condition[..],
+    };
+    names.iter().filter_map(|name| identifier(name).map(|text| (text, name.pos()))).collect()
+}
+
+#[inline]
+fn identifier(node: &Node) -> Option<&str> {
+    match &node.data {
+        Some(Token::Identifier { name, .. }) => Some(name),
+        _ => None,
+    }
+}
+
+/// Checks that the values of the type are accepted by the parameter of the builtin, the same way as [`conform`] does.
+///
+/// [`conform`]: crate::runtime::types::conform
+#[inline]
+fn is_subtype(found: &str, expected: &str) -> bool {
+    match expected {
+        "Any" => true,
+        "Float" => matches!(found, "Int" | "Float"),
+        "Number" => matches!(found, "Int" | "Float"),
+        _ => found == expected,
+    }
+}
+
+#[inline]
+fn invalid(pos: usize, message: String) -> Assignment {
+    Assignment::Invalid(SyntaxError::new(pos, message))
+}
+
+/// Name and mutability of the variable declared by the statement `name = value` or `mut name = value`.
+pub fn declared_name(statement: &Node) -> Option<(&str, bool)> {
+    if statement.operator() != Some(Operator::Assign) {
+        return None
+    }
+    let target = &statement.children[0];
+    let (target, is_mutable) = match target.operator() {
+        Some(Operator::Mut) => (&target.children[0], true),
+        _ => (target, false),
+    };
+    match &target.data {
+        Some(Token::Identifier { name, .. }) => Some((name, is_mutable)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+fn check(source: &str) -> Vec<String> {
+    let tree = parse(source);
+    resolve(&tree).into_iter().map(|error| error.message).collect()
+}
+
+#[test]
+fn test_resolve_names() {
+    assert!(check("a = 1\nmut b = a\nb += 1\nmap(0..b, { @ x -> x + a })").is_empty());
+    assert!(check("f(1)\nfn f(n) { if n > 0 { f(n - 1) } }\nfn g() { late }\nlate = 1").is_empty());
+    assert!(check("fn outer() {\n  fn inner(n) { inner(n) }\n  for i in 0..3 { inner(i) }\n}").is_empty());
+    assert!(check("r = match (1, 2) { (a, _) if a > 0 -> a, _ -> 0 }").is_empty());
+    assert_eq!(vec!("Undefined variable 'b'"), check("a = b\nb = 1"));
+    assert_eq!(vec!("Undefined variable 'x'"), check("if true { x = 1 }\nx"));
+    assert_eq!(vec!("Unknown function 'nope'"), check("nope(1)"));
+    assert_eq!(vec!("Undefined variable 'c'"), check("c += 1"));
+    assert_eq!(vec!("Undefined variable 'inner'"), check("fn outer() {\n  fn inner() { }\n}\ninner"));
+}
+
+#[test]
+fn test_resolve_declarations_and_flow() {
+    assert_eq!(vec!("'f' is already declared in this scope"), check("fn f() {}\nfn f() {}"));
+    assert_eq!(vec!("Unknown type 'Integer'"), check("fn f(a: Integer) -> Int { a }"));
+    assert_eq!(vec!("'return' outside of the function"), check("return 1"));
+    assert!(check("fn f(p: Point) -> Point { p.x = p.y\n p }\nstruct Point { x: Int, mut y }").is_empty());
+    assert_eq!(vec!("'P' is already declared in this scope", "Field 'x' is already declared in P"), check("struct P { x, x }\nstruct P { y }"));
+    assert_eq!(vec!("'break' outside of the loop"), check("loop { f = { @ -> break } }"));
+    assert!(check("loop { if true { break } }\nfor i in 0..1 { continue }").is_empty());
+    assert!(check("test \"a\" {\n  assertEq(f(), late)\n  return\n}\nfn f() { 1 }\nlate = 1").is_empty());
+    assert_eq!(vec!("Test 'a' is already declared", "'break' outside of the loop"), check("test \"a\" {}\ntest \"a\" {\n  break\n}"));
+}
+
+#[test]
+fn test_resolve_assignments() {
+    let source = "mut a = 1\na += 1\nb = 2\nb = 3\nfn f() {\n  b = 4\n  a = 5\n}\nc += 1\nmut a = 6\nif true { mut a = 7 }\nmut d -= 1";
+    let tree = parse(source);
+    let mut assignments: Vec<(usize, Assignment)> = resolve_assignments(&tree).into_iter().collect();
+    assignments.sort_by_key(|(pos, _)| *pos);
+    let assignments: Vec<String> = assignments.into_iter().map(|(_, assignment)| match assignment {
+        Assignment::Declare { is_mutable } => format!("declare mutable: {}", is_mutable),
+        Assignment::Assign => String::from("assign"),
+        Assignment::Invalid(error) => error.message,
+    }).collect();
+    assert_eq!(vec!(
+        "declare mutable: true",
+        "assign",
+        "declare mutable: false",
+        "Cannot assign twice to immutable variable 'b', declare it with 'mut'",
+        "declare mutable: false",
+        "assign",
+        "Undefined variable 'c'",
+        "Variable 'a' is already declared in this scope",
+        "declare mutable: true",
+        "Variable 'd' must be declared with '='",
+    ), assignments);
+}
+
+#[test]
+fn test_resolve_enums() {
+    let source = "enum Shape { Circle(r: Float), Rect(w, h), Empty }\n\
+        fn area(s: Shape) -> Float {\n  match s { Circle(r) -> r * r, Shape.Rect(w, h) -> w * h, Empty -> 0.0 }\n}\n\
+        area(Circle(1.0)) + area(Shape.Rect(1, 2))\nEmpty is Shape and Empty is Shape.Empty";
+    assert!(check(source).is_empty());
+    assert_eq!(vec!("Variant 'A' is already declared in E"), check("enum E { A, A }"));
+    assert_eq!(vec!("Unknown variant 'Square'"), check("enum E { A }\nmatch E.A { Square -> 1 }"));
+    assert_eq!(vec!("A has 1 fields, found 2 in the pattern"), check("enum E { A(x) }\nmatch E.A(1) { A(x, y) -> x }"));
+    assert_eq!(vec!("E has no variant 'B'", "Unknown type 'Shape'"), check("enum E { A }\nE.A is E.B or E.A is Shape"));
+    assert_eq!(vec!("'E' is already declared in this scope"), check("enum E { A }\nstruct E { x }"));
+}
+
+#[test]
+fn test_resolve_builtins() {
+    assert!(check("println(\"a\", 1)\nsqrt(2)\nfn f(sqrt) { sqrt(\"x\") }\nx = format(\"{}\", floor(1.5))").is_empty());
+    assert_eq!(vec!("'sqrt' expects 1 argument, found 2"), check("sqrt(1, 2)"));
+    assert_eq!(vec!("'format' expects at least 1 argument, found 0"), check("format()"));
+    assert_eq!(vec!("Argument 1 of 'sqrt' must be Float, found String"), check("sqrt(\"4\")"));
+    assert_eq!(vec!("Argument 2 of 'split' must be String, found List"), check("split(\"a b\", split(\"a\", \" \"))"));
+}
+
+#[test]
+fn test_resolve_predeclared() {
+    let check = |source: &str| {
+        let tree = parse(source);
+        resolve_with_globals(&tree, &[String::from("host"), String::from("f")]).into_iter().map(|error| error.message).collect::<Vec<String>>()
+    };
+    assert_eq!(vec!("Undefined variable 'late'"), check("host(1) + late\nfn f() {}\nlate = 1"));
+    assert!(check("sqrt = host\nsqrt(1)").is_empty());
+}
+
+#[test]
+fn test_resolve_imports() {
+    assert!(check("fn f(s: Shape) { match s { Circle(r) -> utils.twice(r) } }\nimport lib.utils\nfrom geo import Shape, Circle").is_empty());
+    assert_eq!(vec!("Undefined variable 'lib'"), check("import lib.utils\nlib"));
+    assert_eq!(vec!("'f' is already declared in this scope"), check("from a import f\npub fn f() {}"));
+}
+
+/// Testing the tree which is built by hand, since the parser never builds such a deep tree.
+#[test]
+fn test_resolve_too_deep_tree() {
+    let tree = (1..=1000).fold(Node::from(Token::Identifier { name: Symbol::from("x"), pos: 0 }), |operand, pos| {
+        let mut node = Node::new(Some(Token::Operator { payload: Operator::UnaryMinus, pos }), NodeType::Expression);
+        node.add_child(operand);
+        node
+    });
+    let errors: Vec<(usize, String)> = resolve(&tree).into_iter().chain(crate::analyzer::check_exhaustiveness(&tree))
+        .map(|error| (error.pos, error.message))
+        .collect();
+    let error = SyntaxError::no_stack(1000 - crate::dto::stack::max_tree_depth());
+    assert_eq!(vec!((error.pos, error.message.clone()), (error.pos, error.message)), errors);
+}
@@ -0,0 +1,1207 @@
+/*
+ * Runtime of the Avo programs compiled to C with `avoc build --target c`.
+ *
+ * Values are tagged unions with the same semantics as in the interpreter: integers are checked
+ * for the overflow, `+` converts any value to the string when the other operand is the string,
+ * floats are printed in the shortest form which is read back to the same number.
+ *
+ * The memory is never freed, the compiled programs are expected to be short-lived.
+ * Runtime errors are printed as `error: message at offset` and stop the program with the exit code 1.
+ */
+#ifndef AVO_RUNTIME_H
+#define AVO_RUNTIME_H
+
+#include <inttypes.h>
+#include <math.h>
+#include <stdarg.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+typedef enum {
+    AVO_NONE,
+    AVO_BOOL,
+    AVO_INT,
+    AVO_FLOAT,
+    AVO_STRING,
+    AVO_LIST,
+    AVO_RANGE,
+    AVO_FUNCTION
+} AvoTag;
+
+typedef struct AvoValue AvoValue;
+typedef struct AvoClosure AvoClosure;
+
+typedef struct {
+    size_t length;
+    char data[];
+} AvoString;
+
+typedef struct {
+    size_t length;
+    size_t capacity;
+    AvoValue *items;
+} AvoList;
+
+typedef struct AvoRange AvoRange;
+
+/* Compiled function, the number of arguments is already checked by `avo_call`. */
+typedef AvoValue (*AvoFunction)(AvoClosure *self, int count, AvoValue *arguments, int pos);
+
+struct AvoValue {
+    AvoTag tag;
+    union {
+        int boolean;
+        int64_t integer;
+        double number;
+        AvoString *string;
+        AvoList *list;
+        AvoRange *range;
+        AvoClosure *closure;
+    } as;
+};
+
+/* Bounds are Int, Float or None for the omitted ones. */
+struct AvoRange {
+    AvoValue start;
+    AvoValue end;
+    AvoValue step;
+    int is_inclusive;
+    int is_step_range;
+};
+
+/* Function with the cells of the variables captured from the enclosing functions. */
+struct AvoClosure {
+    AvoFunction function;
+    /* NULL for the lambdas */
+    const char *name;
+    int required;
+    int total;
+    AvoValue **cells;
+};
+
+typedef struct {
+    char *data;
+    size_t length;
+    size_t capacity;
+} AvoBuffer;
+
+/* Iterator over the items of the range, the copy of the list, or the characters of the string. */
+typedef struct {
+    AvoValue source;
+    AvoValue *items;
+    size_t index;
+    size_t count;
+    int is_bounded;
+    int is_finished;
+    int64_t next;
+    int64_t step;
+    int is_float;
+    double float_start;
+    double float_step;
+} AvoIterator;
+
+static inline void avo_fail(int pos, const char *format, ...) {
+    va_list arguments;
+    fflush(stdout);
+    fputs("error: ", stderr);
+    va_start(arguments, format);
+    vfprintf(stderr, format, arguments);
+    va_end(arguments);
+    fprintf(stderr, " at %d\n", pos);
+    exit(1);
+}
+
+static inline void *avo_alloc(size_t size) {
+    void *memory = malloc(size > 0 ? size : 1);
+    if (memory == NULL) {
+        fputs("error: out of memory\n", stderr);
+        exit(1);
+    }
+    return memory;
+}
+
+static inline AvoValue avo_none(void) {
+    AvoValue value;
+    value.tag = AVO_NONE;
+    value.as.integer = 0;
+    return value;
+}
+
+static inline AvoValue avo_bool(int boolean) {
+    AvoValue value;
+    value.tag = AVO_BOOL;
+    value.as.boolean = boolean != 0;
+    return value;
+}
+
+static inline AvoValue avo_int(int64_t integer) {
+    AvoValue value;
+    value.tag = AVO_INT;
+    value.as.integer = integer;
+    return value;
+}
+
+static inline AvoValue avo_float(double number) {
+    AvoValue value;
+    value.tag = AVO_FLOAT;
+    value.as.number = number;
+    return value;
+}
+
+static inline AvoValue avo_string(const char *data, size_t length) {
+    AvoValue value;
+    value.tag = AVO_STRING;
+    value.as.string = avo_alloc(sizeof(AvoString) + length + 1);
+    value.as.string->length = length;
+    memcpy(value.as.string->data, data, length);
+    value.as.string->data[length] = '\0';
+    return value;
+}
+
+static inline AvoValue avo_list_of(size_t count, const AvoValue *items) {
+    AvoValue value;
+    value.tag = AVO_LIST;
+    value.as.list = avo_alloc(sizeof(AvoList));
+    value.as.list->length = count;
+    value.as.list->capacity = count;
+    value.as.list->items = avo_alloc(count * sizeof(AvoValue));
+    if (count > 0) {
+        memcpy(value.as.list->items, items, count * sizeof(AvoValue));
+    }
+    return value;
+}
+
+static inline AvoValue avo_function(AvoClosure *closure) {
+    AvoValue value;
+    value.tag = AVO_FUNCTION;
+    value.as.closure = closure;
+    return value;
+}
+
+/* Creates the lambda or the nested function, the array of the cells is copied. */
+static inline AvoValue avo_closure(AvoFunction function, const char *name, int required, int total, int count, AvoValue **cells) {
+    AvoClosure *closure = avo_alloc(sizeof(AvoClosure));
+    closure->function = function;
+    closure->name = name;
+    closure->required = required;
+    closure->total = total;
+    closure->cells = avo_alloc(count * sizeof(AvoValue *));
+    if (count > 0) {
+        memcpy(closure->cells, cells, count * sizeof(AvoValue *));
+    }
+    return avo_function(closure);
+}
+
+/* Heap slot of the variable captured by the lambdas. */
+static inline AvoValue *avo_cell(AvoValue value) {
+    AvoValue *cell = avo_alloc(sizeof(AvoValue));
+    *cell = value;
+    return cell;
+}
+
+static inline const char *avo_type_name(AvoValue value) {
+    switch (value.tag) {
+        case AVO_NONE: return "None";
+        case AVO_BOOL: return "Bool";
+        case AVO_INT: return "Int";
+        case AVO_FLOAT: return "Float";
+        case AVO_STRING: return "String";
+        case AVO_LIST: return "List";
+        case AVO_RANGE: return "Range";
+        default: return "Function";
+    }
+}
+
+/* Strings */
+
+static inline void avo_buffer_append(AvoBuffer *buffer, const char *data, size_t length) {
+    if (buffer->length + length + 1 > buffer->capacity) {
+        size_t capacity = buffer->capacity * 2 + length + 16;
+        char *grown = avo_alloc(capacity);
+        if (buffer->length > 0) {
+            memcpy(grown, buffer->data, buffer->length);
+        }
+        free(buffer->data);
+        buffer->data = grown;
+        buffer->capacity = capacity;
+    }
+    memcpy(buffer->data + buffer->length, data, length);
+    buffer->length += length;
+    buffer->data[buffer->length] = '\0';
+}
+
+static inline void avo_buffer_text(AvoBuffer *buffer, const char *text) {
+    avo_buffer_append(buffer, text, strlen(text));
+}
+
+/* Formats the float the same way as Rust's `{:?}`: the shortest digits which are read back to the same number,
+ * with `.0` for the round numbers and the exponent for the very small and very large ones. */
+static inline void avo_buffer_float(AvoBuffer *buffer, double number) {
+    char digits[32];
+    char text[64];
+    int precision;
+    int exponent;
+    size_t count = 0;
+    size_t length = 0;
+    char *mark;
+    size_t index;
+    if (isnan(number)) {
+        avo_buffer_text(buffer, "NaN");
+        return;
+    }
+    if (isinf(number)) {
+        avo_buffer_text(buffer, number > 0 ? "inf" : "-inf");
+        return;
+    }
+    if (number == 0) {
+        avo_buffer_text(buffer, signbit(number) ? "-0.0" : "0.0");
+        return;
+    }
+    for (precision = 1; precision <= 17; precision++) {
+        snprintf(text, sizeof(text), "%.*e", precision - 1, number);
+        if (strtod(text, NULL) == number) {
+            break;
+        }
+    }
+    mark = strchr(text, 'e');
+    exponent = atoi(mark + 1);
+    for (index = 0; text + index < mark; index++) {
+        if (text[index] >= '0' && text[index] <= '9') {
+            digits[count++] = text[index];
+        }
+    }
+    while (count > 1 && digits[count - 1] == '0') {
+        count--;
+    }
+    if (number < 0) {
+        text[length++] = '-';
+    }
+    if (exponent < -4 || exponent >= 16) {
+        text[length++] = digits[0];
+        if (count > 1) {
+            text[length++] = '.';
+            memcpy(text + length, digits + 1, count - 1);
+            length += count - 1;
+        }
+        length += (size_t) snprintf(text + length, sizeof(text) - length, "e%d", exponent);
+    } else if (exponent < 0) {
+        text[length++] = '0';
+        text[length++] = '.';
+        for (index = 1; index < (size_t) -exponent; index++) {
+            text[length++] = '0';
+        }
+        memcpy(text + length, digits, count);
+        length += count;
+    } else {
+        for (index = 0; index <= (size_t) exponent; index++) {
+            text[length++] = index < count ? digits[index] : '0';
+        }
+        text[length++] = '.';
+        if (count > (size_t) exponent + 1) {
+            memcpy(text + length, digits + exponent + 1, count - exponent - 1);
+            length += count - exponent - 1;
+        } else {
+            text[length++] = '0';
+        }
+    }
+    avo_buffer_append(buffer, text, length);
+}
+
+/* Quotes the string the same way as Rust's `{:?}`, used for the items of the lists. */
+static inline void avo_buffer_quoted(AvoBuffer *buffer, const AvoString *string) {
+    size_t index;
+    char escaped[16];
+    avo_buffer_text(buffer, "\"");
+    for (index = 0; index < string->length; index++) {
+        unsigned char symbol = (unsigned char) string->data[index];
+        switch (symbol) {
+            case '"': avo_buffer_text(buffer, "\\\""); break;
+            case '\\': avo_buffer_text(buffer, "\\\\"); break;
+            case '\n': avo_buffer_text(buffer, "\\n"); break;
+            case '\r': avo_buffer_text(buffer, "\\r"); break;
+            case '\t': avo_buffer_text(buffer, "\\t"); break;
+            case '\0': avo_buffer_text(buffer, "\\0"); break;
+            default:
+                if (symbol < 0x20 || symbol == 0x7f) {
+                    snprintf(escaped, sizeof(escaped), "\\u{%x}", symbol);
+                    avo_buffer_text(buffer, escaped);
+                } else {
+                    avo_buffer_append(buffer, (const char *) &symbol, 1);
+                }
+        }
+    }
+    avo_buffer_text(buffer, "\"");
+}
+
+/* List which is printed or compared at the moment, the printed one has no `rhs`. */
+typedef struct AvoVisit {
+    const AvoList *lhs;
+    const AvoList *rhs;
+    struct AvoVisit *parent;
+} AvoVisit;
+
+/* Lists visited up the stack, the list may contain itself after `xs.push(xs)`. */
+static AvoVisit *avo_visits = NULL;
+
+static inline int avo_is_visited(const AvoList *lhs, const AvoList *rhs) {
+    AvoVisit *visit;
+    for (visit = avo_visits; visit != NULL; visit = visit->parent) {
+        if (visit->lhs == lhs && visit->rhs == rhs) {
+            return 1;
+        }
+    }
+    return 0;
+}
+
+static inline void avo_buffer_value(AvoBuffer *buffer, AvoValue value, int is_item) {
+    char text[32];
+    size_t index;
+    AvoVisit visit;
+    switch (value.tag) {
+        case AVO_NONE:
+            avo_buffer_text(buffer, "None");
+            break;
+        case AVO_BOOL:
+            avo_buffer_text(buffer, value.as.boolean ? "true" : "false");
+            break;
+        case AVO_INT:
+            snprintf(text, sizeof(text), "%" PRId64, value.as.integer);
+            avo_buffer_text(buffer, text);
+            break;
+        case AVO_FLOAT:
+            avo_buffer_float(buffer, value.as.number);
+            break;
+        case AVO_STRING:
+            if (is_item) {
+                avo_buffer_quoted(buffer, value.as.string);
+            } else {
+                avo_buffer_append(buffer, value.as.string->data, value.as.string->length);
+            }
+            break;
+        case AVO_LIST:
+            /* the list which contains itself is printed as `[...]` inside of itself */
+            if (avo_is_visited(value.as.list, NULL)) {
+                avo_buffer_text(buffer, "[...]");
+                break;
+            }
+            visit.lhs = value.as.list;
+            visit.rhs = NULL;
+            visit.parent = avo_visits;
+            avo_visits = &visit;
+            avo_buffer_text(buffer, "[");
+            for (index = 0; index < value.as.list->length; index++) {
+                if (index > 0) {
+                    avo_buffer_text(buffer, ", ");
+                }
+                avo_buffer_value(buffer, value.as.list->items[index], 1);
+            }
+            avo_buffer_text(buffer, "]");
+            avo_visits = visit.parent;
+            break;
+        case AVO_RANGE:
+            if (value.as.range->is_step_range && value.as.range->step.tag != AVO_NONE) {
+                if (value.as.range->start.tag != AVO_NONE) {
+                    avo_buffer_value(buffer, value.as.range->start, 0);
+                }
+                avo_buffer_text(buffer, ":");
+                avo_buffer_value(buffer, value.as.range->step, 0);
+                avo_buffer_text(buffer, ":");
+            } else {
+                if (value.as.range->start.tag != AVO_NONE) {
+                    avo_buffer_value(buffer, value.as.range->start, 0);
+                }
+                avo_buffer_text(buffer, value.as.range->is_inclusive ? "..=" : "..");
+            }
+            if (value.as.range->end.tag != AVO_NONE) {
+                avo_buffer_value(buffer, value.as.range->end, 0);
+            }
+            break;
+        case AVO_FUNCTION:
+            if (value.as.closure->name == NULL) {
+                avo_buffer_text(buffer, "<lambda>");
+            } else {
+                avo_buffer_text(buffer, "<fn ");
+                avo_buffer_text(buffer, value.as.closure->name);
+                avo_buffer_text(buffer, ">");
+            }
+            break;
+    }
+}
+
+static inline AvoValue avo_to_string(AvoValue value) {
+    AvoBuffer buffer = { NULL, 0, 0 };
+    AvoValue result;
+    if (value.tag == AVO_STRING) {
+        return value;
+    }
+    avo_buffer_value(&buffer, value, 0);
+    result = avo_string(buffer.data, buffer.length);
+    free(buffer.data);
+    return result;
+}
+
+/* Number of the UTF-8 characters. */
+static inline size_t avo_char_count(const AvoString *string) {
+    size_t index;
+    size_t count = 0;
+    for (index = 0; index < string->length; index++) {
+        if (((unsigned char) string->data[index] & 0xC0) != 0x80) {
+            count++;
+        }
+    }
+    return count;
+}
+
+/* Byte offset of the character with the index, or the length of the string. */
+static inline size_t avo_char_offset(const AvoString *string, size_t position) {
+    size_t index;
+    size_t count = 0;
+    for (index = 0; index < string->length; index++) {
+        if (((unsigned char) string->data[index] & 0xC0) != 0x80) {
+            if (count == position) {
+                return index;
+            }
+            count++;
+        }
+    }
+    return string->length;
+}
+
+/* Operators */
+
+static inline int avo_condition(AvoValue value, int pos) {
+    if (value.tag != AVO_BOOL) {
+        avo_fail(pos, "Condition must be Bool, found %s", avo_type_name(value));
+    }
+    return value.as.boolean;
+}
+
+static inline int avo_is_number(AvoValue value) {
+    return value.tag == AVO_INT || value.tag == AVO_FLOAT;
+}
+
+static inline double avo_as_float(AvoValue value) {
+    return value.tag == AVO_INT ? (double) value.as.integer : value.as.number;
+}
+
+static inline void avo_fail_operator(const char *operator, AvoValue lhs, AvoValue rhs, int pos) {
+    avo_fail(pos, "Operator '%s' can't be applied to %s and %s", operator, avo_type_name(lhs), avo_type_name(rhs));
+}
+
+static inline int avo_add_overflows(int64_t x, int64_t y) {
+    return (y > 0 && x > INT64_MAX - y) || (y < 0 && x < INT64_MIN - y);
+}
+
+static inline int avo_sub_overflows(int64_t x, int64_t y) {
+    return (y < 0 && x > INT64_MAX + y) || (y > 0 && x < INT64_MIN + y);
+}
+
+static inline int avo_mul_overflows(int64_t x, int64_t y) {
+    if (x > 0) {
+        return y > 0 ? x > INT64_MAX / y : y < INT64_MIN / x;
+    }
+    if (y > 0) {
+        return x < INT64_MIN / y;
+    }
+    return x != 0 && y < INT64_MAX / x;
+}
+
+static inline void avo_check_overflow(int is_overflow, int pos) {
+    if (is_overflow) {
+        avo_fail(pos, "Integer overflow");
+    }
+}
+
+/* Same as `checked_pow`: the base is squared only while it is needed. */
+static inline int64_t avo_int_pow(int64_t base, int64_t exponent, int pos) {
+    int64_t result = 1;
+    while (exponent > 1) {
+        if (exponent & 1) {
+            avo_check_overflow(avo_mul_overflows(result, base), pos);
+            result *= base;
+        }
+        exponent /= 2;
+        avo_check_overflow(avo_mul_overflows(base, base), pos);
+        base *= base;
+    }
+    if (exponent == 1) {
+        avo_check_overflow(avo_mul_overflows(result, base), pos);
+        result *= base;
+    }
+    return result;
+}
+
+static inline AvoValue avo_arithmetic(char operator, AvoValue lhs, AvoValue rhs, int pos) {
+    char name[2];
+    double a;
+    double b;
+    if (lhs.tag == AVO_INT && rhs.tag == AVO_INT) {
+        int64_t x = lhs.as.integer;
+        int64_t y = rhs.as.integer;
+        switch (operator) {
+            case '+':
+                avo_check_overflow(avo_add_overflows(x, y), pos);
+                return avo_int(x + y);
+            case '-':
+                avo_check_overflow(avo_sub_overflows(x, y), pos);
+                return avo_int(x - y);
+            case '*':
+                avo_check_overflow(avo_mul_overflows(x, y), pos);
+                return avo_int(x * y);
+            case '/':
+                if (y == 0) {
+                    avo_fail(pos, "Division by zero");
+                }
+                avo_check_overflow(x == INT64_MIN && y == -1, pos);
+                return avo_int(x / y);
+            default:
+                /* negative powers of integers are fractions */
+                if (y < 0 || y > (int64_t) UINT32_MAX) {
+                    return avo_float(pow((double) x, (double) y));
+                }
+                return avo_int(avo_int_pow(x, y, pos));
+        }
+    }
+    if (!avo_is_number(lhs) || !avo_is_number(rhs)) {
+        name[0] = operator;
+        name[1] = '\0';
+        avo_fail_operator(name, lhs, rhs, pos);
+    }
+    a = avo_as_float(lhs);
+    b = avo_as_float(rhs);
+    switch (operator) {
+        case '+': return avo_float(a + b);
+        case '-': return avo_float(a - b);
+        case '*': return avo_float(a * b);
+        case '/': return avo_float(a / b);
+        default: return avo_float(pow(a, b));
+    }
+}
+
+static inline AvoValue avo_add(AvoValue lhs, AvoValue rhs, int pos) {
+    if (lhs.tag == AVO_STRING || rhs.tag == AVO_STRING) {
+        AvoBuffer buffer = { NULL, 0, 0 };
+        AvoValue result;
+        avo_buffer_value(&buffer, lhs, 0);
+        avo_buffer_value(&buffer, rhs, 0);
+        result = avo_string(buffer.data, buffer.length);
+        free(buffer.data);
+        return result;
+    }
+    return avo_arithmetic('+', lhs, rhs, pos);
+}
+
+static inline AvoValue avo_sub(AvoValue lhs, AvoValue rhs, int pos) {
+    return avo_arithmetic('-', lhs, rhs, pos);
+}
+
+static inline AvoValue avo_mul(AvoValue lhs, AvoValue rhs, int pos) {
+    return avo_arithmetic('*', lhs, rhs, pos);
+}
+
+static inline AvoValue avo_div(AvoValue lhs, AvoValue rhs, int pos) {
+    return avo_arithmetic('/', lhs, rhs, pos);
+}
+
+static inline AvoValue avo_pow(AvoValue lhs, AvoValue rhs, int pos) {
+    return avo_arithmetic('^', lhs, rhs, pos);
+}
+
+static inline AvoValue avo_neg(AvoValue operand, int pos) {
+    if (operand.tag == AVO_INT) {
+        avo_check_overflow(operand.as.integer == INT64_MIN, pos);
+        return avo_int(-operand.as.integer);
+    }
+    if (operand.tag == AVO_FLOAT) {
+        return avo_float(-operand.as.number);
+    }
+    avo_fail(pos, "Operator '-' can't be applied to %s", avo_type_name(operand));
+    return operand;
+}
+
+static inline AvoValue avo_not(AvoValue operand, int pos) {
+    if (operand.tag != AVO_BOOL) {
+        avo_fail(pos, "Operator 'not' can't be applied to %s", avo_type_name(operand));
+    }
+    return avo_bool(!operand.as.boolean);
+}
+
+static inline AvoValue avo_xor(AvoValue lhs, AvoValue rhs, int pos) {
+    if (lhs.tag != AVO_BOOL || rhs.tag != AVO_BOOL) {
+        avo_fail_operator("xor", lhs, rhs, pos);
+    }
+    return avo_bool(lhs.as.boolean != rhs.as.boolean);
+}
+
+/* Values of the different types are never equal, the items of the lists are compared without the Int to Float promotion. */
+static inline int avo_same(AvoValue lhs, AvoValue rhs) {
+    size_t index;
+    int is_same;
+    AvoVisit visit;
+    if (lhs.tag != rhs.tag) {
+        return 0;
+    }
+    switch (lhs.tag) {
+        case AVO_NONE: return 1;
+        case AVO_BOOL: return lhs.as.boolean == rhs.as.boolean;
+        case AVO_INT: return lhs.as.integer == rhs.as.integer;
+        case AVO_FLOAT: return lhs.as.number == rhs.as.number;
+        case AVO_STRING:
+            return lhs.as.string->length == rhs.as.string->length
+                && memcmp(lhs.as.string->data, rhs.as.string->data, lhs.as.string->length) == 0;
+        case AVO_LIST:
+            /* the lists which are already compared up the stack are equal there, unless the other items differ */
+            if (lhs.as.list == rhs.as.list || avo_is_visited(lhs.as.list, rhs.as.list)) {
+                return 1;
+            }
+            if (lhs.as.list->length != rhs.as.list->length) {
+                return 0;
+            }
+            visit.lhs = lhs.as.list;
+            visit.rhs = rhs.as.list;
+            visit.parent = avo_visits;
+            avo_visits = &visit;
+            is_same = 1;
+            for (index = 0; index < lhs.as.list->length && is_same; index++) {
+                is_same = avo_same(lhs.as.list->items[index], rhs.as.list->items[index]);
+            }
+            avo_visits = visit.parent;
+            return is_same;
+        case AVO_RANGE:
+            return avo_same(lhs.as.range->start, rhs.as.range->start) && avo_same(lhs.as.range->end, rhs.as.range->end)
+                && avo_same(lhs.as.range->step, rhs.as.range->step) && lhs.as.range->is_inclusive == rhs.as.range->is_inclusive
+                && lhs.as.range->is_step_range == rhs.as.range->is_step_range;
+        default:
+            return lhs.as.closure == rhs.as.closure;
+    }
+}
+
+static inline int avo_equal(AvoValue lhs, AvoValue rhs) {
+    if (avo_is_number(lhs) && avo_is_number(rhs) && lhs.tag != rhs.tag) {
+        return avo_as_float(lhs) == avo_as_float(rhs);
+    }
+    return avo_same(lhs, rhs);
+}
+
+static inline AvoValue avo_eq(AvoValue lhs, AvoValue rhs) {
+    return avo_bool(avo_equal(lhs, rhs));
+}
+
+static inline AvoValue avo_ne(AvoValue lhs, AvoValue rhs) {
+    return avo_bool(!avo_equal(lhs, rhs));
+}
+
+/* Returns -1, 0 or 1 like `Ordering`. */
+static inline int avo_compare(AvoValue lhs, AvoValue rhs, int pos) {
+    if (lhs.tag == AVO_INT && rhs.tag == AVO_INT) {
+        return (lhs.as.integer > rhs.as.integer) - (lhs.as.integer < rhs.as.integer);
+    }
+    if (lhs.tag == AVO_STRING && rhs.tag == AVO_STRING) {
+        size_t length = lhs.as.string->length < rhs.as.string->length ? lhs.as.string->length : rhs.as.string->length;
+        int result = memcmp(lhs.as.string->data, rhs.as.string->data, length);
+        if (result != 0) {
+            return result < 0 ? -1 : 1;
+        }
+        return (lhs.as.string->length > rhs.as.string->length) - (lhs.as.string->length < rhs.as.string->length);
+    }
+    if (lhs.tag == AVO_BOOL && rhs.tag == AVO_BOOL) {
+        return lhs.as.boolean - rhs.as.boolean;
+    }
+    if (avo_is_number(lhs) && avo_is_number(rhs)) {
+        double a = avo_as_float(lhs);
+        double b = avo_as_float(rhs);
+        if (isnan(a) || isnan(b)) {
+            avo_fail(pos, "Can't compare NaN with a number");
+        }
+        return (a > b) - (a < b);
+    }
+    avo_fail(pos, "Can't compare %s and %s", avo_type_name(lhs), avo_type_name(rhs));
+    return 0;
+}
+
+static inline AvoValue avo_lt(AvoValue lhs, AvoValue rhs, int pos) {
+    return avo_bool(avo_compare(lhs, rhs, pos) < 0);
+}
+
+static inline AvoValue avo_le(AvoValue lhs, AvoValue rhs, int pos) {
+    return avo_bool(avo_compare(lhs, rhs, pos) <= 0);
+}
+
+static inline AvoValue avo_gt(AvoValue lhs, AvoValue rhs, int pos) {
+    return avo_bool(avo_compare(lhs, rhs, pos) > 0);
+}
+
+static inline AvoValue avo_ge(AvoValue lhs, AvoValue rhs, int pos) {
+    return avo_bool(avo_compare(lhs, rhs, pos) >= 0);
+}
+
+/* Ranges */
+
+static inline AvoValue avo_range(AvoValue start, AvoValue end, AvoValue step, int is_inclusive, int is_step_range, int pos) {
+    AvoValue bounds[3];
+    AvoValue value;
+    int index;
+    bounds[0] = start;
+    bounds[1] = end;
+    bounds[2] = step;
+    for (index = 0; index < 3; index++) {
+        if (bounds[index].tag != AVO_NONE && !avo_is_number(bounds[index])) {
+            avo_fail(pos, "Range bounds must be numbers, found %s", avo_type_name(bounds[index]));
+        }
+    }
+    if ((step.tag == AVO_INT && step.as.integer == 0) || (step.tag == AVO_FLOAT && step.as.number == 0)) {
+        avo_fail(pos, "Range step can't be zero");
+    }
+    value.tag = AVO_RANGE;
+    value.as.range = avo_alloc(sizeof(AvoRange));
+    value.as.range->start = start;
+    value.as.range->end = end;
+    value.as.range->step = step;
+    value.as.range->is_inclusive = is_inclusive;
+    value.as.range->is_step_range = is_step_range;
+    return value;
+}
+
+static inline int avo_range_contains(const AvoRange *range, AvoValue item) {
+    double value;
+    double step;
+    int has_low;
+    int has_high;
+    double low;
+    double high;
+    if (!avo_is_number(item)) {
+        return 0;
+    }
+    value = avo_as_float(item);
+    step = range->step.tag == AVO_NONE ? 1 : avo_as_float(range->step);
+    if (step < 0) {
+        has_low = range->end.tag != AVO_NONE;
+        low = has_low ? avo_as_float(range->end) : 0;
+        has_high = range->start.tag != AVO_NONE;
+        high = has_high ? avo_as_float(range->start) : 0;
+    } else {
+        has_low = range->start.tag != AVO_NONE;
+        low = has_low ? avo_as_float(range->start) : 0;
+        has_high = range->end.tag != AVO_NONE;
+        high = has_high ? avo_as_float(range->end) : 0;
+    }
+    if (has_low && value < low) {
+        return 0;
+    }
+    if (has_high && (range->is_inclusive ? value > high : value >= high)) {
+        return 0;
+    }
+    if (range->is_step_range && range->start.tag != AVO_NONE && range->step.tag != AVO_NONE) {
+        double steps = (value - avo_as_float(range->start)) / step;
+        return fabs(steps - round(steps)) < 1e-9;
+    }
+    return 1;
+}
+
+/* Patterns */
+
+/* Range pattern matches the values comparable with its bounds, so `1..3` does not match `"a"`. */
+static inline int avo_compares(AvoValue lhs, AvoValue rhs, int *result) {
+    if ((lhs.tag == AVO_STRING && rhs.tag == AVO_STRING) || (lhs.tag == AVO_BOOL && rhs.tag == AVO_BOOL)
+        || (lhs.tag == AVO_INT && rhs.tag == AVO_INT)
+        || (avo_is_number(lhs) && avo_is_number(rhs) && !isnan(avo_as_float(lhs)) && !isnan(avo_as_float(rhs)))) {
+        *result = avo_compare(lhs, rhs, 0);
+        return 1;
+    }
+    return 0;
+}
+
+static inline int avo_pattern_range(AvoValue value, AvoValue start, AvoValue end, int is_inclusive) {
+    int result;
+    if (start.tag != AVO_NONE && (!avo_compares(value, start, &result) || result < 0)) {
+        return 0;
+    }
+    if (end.tag != AVO_NONE && (!avo_compares(value, end, &result) || result > 0 || (result == 0 && !is_inclusive))) {
+        return 0;
+    }
+    return 1;
+}
+
+static inline void avo_no_match(AvoValue value, int pos) {
+    AvoBuffer buffer = { NULL, 0, 0 };
+    avo_buffer_value(&buffer, value, 1);
+    avo_fail(pos, "No match arm for value %s", buffer.data);
+}
+
+/* Collections */
+
+static inline AvoValue avo_in(AvoValue item, AvoValue collection, int pos) {
+    size_t index;
+    switch (collection.tag) {
+        case AVO_LIST:
+            for (index = 0; index < collection.as.list->length; index++) {
+                if (avo_equal(collection.as.list->items[index], item)) {
+                    return avo_bool(1);
+                }
+            }
+            return avo_bool(0);
+        case AVO_STRING:
+            if (item.tag != AVO_STRING) {
+                avo_fail(pos, "String can contain only String, found %s", avo_type_name(item));
+            }
+            if (item.as.string->length == 0) {
+                return avo_bool(1);
+            }
+            for (index = 0; index + item.as.string->length <= collection.as.string->length; index++) {
+                if (memcmp(collection.as.string->data + index, item.as.string->data, item.as.string->length) == 0) {
+                    return avo_bool(1);
+                }
+            }
+            return avo_bool(0);
+        case AVO_RANGE:
+            return avo_bool(avo_range_contains(collection.as.range, item));
+        default:
+            avo_fail(pos, "%s is not a collection", avo_type_name(collection));
+            return avo_bool(0);
+    }
+}
+
+static inline size_t avo_position(AvoValue collection, AvoValue index, size_t length, int pos) {
+    if (index.tag == AVO_RANGE) {
+        avo_fail(pos, "Slices are not supported by the compiled programs");
+    }
+    if (index.tag != AVO_INT) {
+        avo_fail(pos, "%s index must be Int, found %s", avo_type_name(collection), avo_type_name(index));
+    }
+    if (index.as.integer < 0 || (uint64_t) index.as.integer >= length) {
+        avo_fail(pos, "Index %" PRId64 " is out of bounds for the %s of length %lu", index.as.integer, avo_type_name(collection), (unsigned long) length);
+    }
+    return (size_t) index.as.integer;
+}
+
+static inline AvoValue avo_index(AvoValue collection, AvoValue index, int pos) {
+    size_t position;
+    size_t start;
+    switch (collection.tag) {
+        case AVO_LIST:
+            return collection.as.list->items[avo_position(collection, index, collection.as.list->length, pos)];
+        case AVO_STRING:
+            position = avo_position(collection, index, avo_char_count(collection.as.string), pos);
+            start = avo_char_offset(collection.as.string, position);
+            return avo_string(collection.as.string->data + start, avo_char_offset(collection.as.string, position + 1) - start);
+        default:
+            avo_fail(pos, "%s can't be indexed", avo_type_name(collection));
+            return avo_none();
+    }
+}
+
+static inline AvoValue avo_set_index(AvoValue collection, AvoValue index, AvoValue value, int pos) {
+    switch (collection.tag) {
+        case AVO_LIST:
+            collection.as.list->items[avo_position(collection, index, collection.as.list->length, pos)] = value;
+            return value;
+        case AVO_STRING:
+            avo_fail(pos, "String is immutable");
+            return value;
+        default:
+            avo_fail(pos, "%s can't be indexed", avo_type_name(collection));
+            return value;
+    }
+}
+
+static inline void avo_list_push(AvoList *list, AvoValue value) {
+    if (list->length == list->capacity) {
+        size_t capacity = list->capacity * 2 + 4;
+        AvoValue *items = avo_alloc(capacity * sizeof(AvoValue));
+        if (list->length > 0) {
+            memcpy(items, list->items, list->length * sizeof(AvoValue));
+        }
+        list->items = items;
+        list->capacity = capacity;
+    }
+    list->items[list->length++] = value;
+}
+
+/* Iteration */
+
+static inline AvoIterator avo_iterate(AvoValue iterable, int pos) {
+    AvoIterator iterator;
+    memset(&iterator, 0, sizeof(iterator));
+    iterator.source = iterable;
+    switch (iterable.tag) {
+        case AVO_RANGE: {
+            const AvoRange *range = iterable.as.range;
+            if (range->start.tag == AVO_NONE) {
+                avo_fail(pos, "Range without start can't be iterated");
+            }
+            if (range->start.tag == AVO_INT && range->end.tag != AVO_FLOAT && range->step.tag != AVO_FLOAT) {
+                iterator.next = range->start.as.integer;
+                iterator.is_bounded = range->end.tag != AVO_NONE;
+                iterator.step = range->step.tag == AVO_NONE ? 1 : range->step.as.integer;
+                return iterator;
+            }
+            iterator.is_float = 1;
+            iterator.float_start = avo_as_float(range->start);
+            iterator.float_step = range->step.tag == AVO_NONE ? 1 : avo_as_float(range->step);
+            if (range->end.tag != AVO_NONE) {
+                /* small epsilon makes `0:0.1:1` include `1.0` despite the rounding errors */
+                double steps = (avo_as_float(range->end) - iterator.float_start) / iterator.float_step;
+                double count = range->is_inclusive ? floor(steps + 1e-9) + 1 : ceil(steps - 1e-9);
+                iterator.is_bounded = 1;
+                iterator.count = count > 0 ? (size_t) count : 0;
+            }
+            return iterator;
+        }
+        case AVO_LIST:
+            iterator.count = iterable.as.list->length;
+            iterator.items = avo_alloc(iterator.count * sizeof(AvoValue));
+            if (iterator.count > 0) {
+                memcpy(iterator.items, iterable.as.list->items, iterator.count * sizeof(AvoValue));
+            }
+            return iterator;
+        case AVO_STRING:
+            iterator.count = iterable.as.string->length;
+            return iterator;
+        default:
+            avo_fail(pos, "%s is not iterable", avo_type_name(iterable));
+            return iterator;
+    }
+}
+
+static inline int avo_next(AvoIterator *iterator, AvoValue *item) {
+    switch (iterator->source.tag) {
+        case AVO_RANGE:
+            if (iterator->is_float) {
+                if (iterator->is_bounded && iterator->index >= iterator->count) {
+                    return 0;
+                }
+                *item = avo_float(iterator->float_start + (double) iterator->index * iterator->float_step);
+                iterator->index++;
+                return 1;
+            } else {
+                const AvoRange *range = iterator->source.as.range;
+                int64_t value = iterator->next;
+                int64_t step = iterator->step;
+                if (iterator->is_finished) {
+                    return 0;
+                }
+                if (iterator->is_bounded) {
+                    int64_t end = range->end.as.integer;
+                    int is_in_range = step > 0
+                        ? (range->is_inclusive ? value <= end : value < end)
+                        : (range->is_inclusive ? value >= end : value > end);
+                    if (!is_in_range) {
+                        return 0;
+                    }
+                }
+                if ((step > 0 && value > INT64_MAX - step) || (step < 0 && value < INT64_MIN - step)) {
+                    iterator->is_finished = 1;
+                } else {
+                    iterator->next = value + step;
+                }
+                *item = avo_int(value);
+                return 1;
+            }
+        case AVO_LIST:
+            if (iterator->index >= iterator->count) {
+                return 0;
+            }
+            *item = iterator->items[iterator->index++];
+            return 1;
+        default: {
+            const AvoString *string = iterator->source.as.string;
+            size_t end = iterator->index + 1;
+            if (iterator->index >= string->length) {
+                return 0;
+            }
+            while (end < string->length && ((unsigned char) string->data[end] & 0xC0) == 0x80) {
+                end++;
+            }
+            *item = avo_string(string->data + iterator->index, end - iterator->index);
+            iterator->index = end;
+            return 1;
+        }
+    }
+}
+
+/* Functions */
+
+static inline AvoValue avo_call(AvoValue function, int count, AvoValue *arguments, int pos) {
+    AvoClosure *closure;
+    if (function.tag != AVO_FUNCTION) {
+        avo_fail(pos, "%s is not a function", avo_type_name(function));
+    }
+    closure = function.as.closure;
+    if (count < closure->required || count > closure->total) {
+        if (closure->required == closure->total) {
+            avo_fail(pos, "Expected %d argument%s, found %d", closure->total, closure->total == 1 ? "" : "s", count);
+        }
+        avo_fail(pos, "Expected %d to %d arguments, found %d", closure->required, closure->total, count);
+    }
+    return closure->function(closure, count, arguments, pos);
+}
+
+/* Calls the function from the variable with the name. */
+static inline AvoValue avo_call_named(AvoValue function, const char *name, int count, AvoValue *arguments, int pos) {
+    if (function.tag != AVO_FUNCTION) {
+        avo_fail(pos, "'%s' is %s, not a function", name, avo_type_name(function));
+    }
+    return avo_call(function, count, arguments, pos);
+}
+
+/* Checks the value against the type annotation, the only conversion is Int to Float. */
+static inline int avo_conform(const char *type_name, AvoValue *value) {
+    if (strcmp(type_name, "Float") == 0 && value->tag == AVO_INT) {
+        *value = avo_float((double) value->as.integer);
+        return 1;
+    }
+    return strcmp(type_name, "Any") == 0 || strcmp(type_name, avo_type_name(*value)) == 0
+        || (strcmp(type_name, "Number") == 0 && avo_is_number(*value));
+}
+
+static inline AvoValue avo_is(AvoValue value, const char *type_name) {
+    return avo_bool(strcmp(type_name, "Any") == 0 || strcmp(type_name, avo_type_name(value)) == 0
+        || (strcmp(type_name, "Number") == 0 && avo_is_number(value)));
+}
+
+static inline AvoValue avo_parameter(AvoValue value, const char *type_name, const char *name, int pos) {
+    if (!avo_conform(type_name, &value)) {
+        avo_fail(pos, "Parameter '%s' must be %s, found %s", name, type_name, avo_type_name(value));
+    }
+    return value;
+}
+
+static inline AvoValue avo_returned(AvoValue value, const char *type_name, const char *name, int pos) {
+    if (!avo_conform(type_name, &value)) {
+        avo_fail(pos, "<fn %s> must return %s, found %s", name, type_name, avo_type_name(value));
+    }
+    return value;
+}
+
+/* Builtins */
+
+static inline AvoValue avo_argument(AvoValue value, const char *type_name, const char *builtin, int index, int pos) {
+    if (!avo_conform(type_name, &value)) {
+        avo_fail(pos, "Argument %d of '%s' must be %s, found %s", index, builtin, type_name, avo_type_name(value));
+    }
+    return value;
+}
+
+static inline AvoValue avo_print(int count, AvoValue *arguments, const char *end) {
+    AvoBuffer buffer = { NULL, 0, 0 };
+    int index;
+    for (index = 0; index < count; index++) {
+        if (index > 0) {
+            avo_buffer_text(&buffer, " ");
+        }
+        avo_buffer_value(&buffer, arguments[index], 0);
+    }
+    avo_buffer_text(&buffer, end);
+    fwrite(buffer.data, 1, buffer.length, stdout);
+    fflush(stdout);
+    free(buffer.data);
+    return avo_none();
+}
+
+static inline AvoValue avo_len(AvoValue collection, int pos) {
+    switch (collection.tag) {
+        case AVO_LIST: return avo_int((int64_t) collection.as.list->length);
+        case AVO_STRING: return avo_int((int64_t) avo_char_count(collection.as.string));
+        default:
+            avo_fail(pos, "%s has no length", avo_type_name(collection));
+            return avo_none();
+    }
+}
+
+static inline AvoValue avo_push(AvoValue list, AvoValue item, int pos) {
+    avo_list_push(avo_argument(list, "List", "push", 1, pos).as.list, item);
+    return avo_none();
+}
+
+static inline AvoValue avo_to_int(double number, int pos) {
+    AvoBuffer buffer = { NULL, 0, 0 };
+    if (!isfinite(number) || fabs(number) >= (double) INT64_MAX) {
+        avo_buffer_float(&buffer, number);
+        avo_fail(pos, "%s can't be converted to Int", buffer.data);
+    }
+    return avo_int((int64_t) number);
+}
+
+static inline double avo_float_argument(AvoValue value, const char *builtin, int pos) {
+    return avo_argument(value, "Float", builtin, 1, pos).as.number;
+}
+
+static inline AvoValue avo_sqrt(AvoValue number, int pos) {
+    return avo_float(sqrt(avo_float_argument(number, "sqrt", pos)));
+}
+
+static inline AvoValue avo_sin(AvoValue number, int pos) {
+    return avo_float(sin(avo_float_argument(number, "sin", pos)));
+}
+
+static inline AvoValue avo_cos(AvoValue number, int pos) {
+    return avo_float(cos(avo_float_argument(number, "cos", pos)));
+}
+
+static inline AvoValue avo_tan(AvoValue number, int pos) {
+    return avo_float(tan(avo_float_argument(number, "tan", pos)));
+}
+
+static inline AvoValue avo_floor(AvoValue number, int pos) {
+    return avo_to_int(floor(avo_float_argument(number, "floor", pos)), pos);
+}
+
+static inline AvoValue avo_ceil(AvoValue number, int pos) {
+    return avo_to_int(ceil(avo_float_argument(number, "ceil", pos)), pos);
+}
+
+static inline AvoValue avo_round(AvoValue number, int pos) {
+    return avo_to_int(round(avo_float_argument(number, "round", pos)), pos);
+}
+
+static inline AvoValue avo_abs(AvoValue number, int pos) {
+    number = avo_argument(number, "Number", "abs", 1, pos);
+    if (number.tag == AVO_INT) {
+        avo_check_overflow(number.as.integer == INT64_MIN, pos);
+        return avo_int(number.as.integer < 0 ? -number.as.integer : number.as.integer);
+    }
+    return avo_float(fabs(number.as.number));
+}
+
+static inline AvoValue avo_power(AvoValue base, AvoValue exponent, int pos) {
+    return avo_pow(avo_argument(base, "Number", "pow", 1, pos), avo_argument(exponent, "Number", "pow", 2, pos), pos);
+}
+
+static inline AvoValue avo_map(AvoValue iterable, AvoValue transform, int pos) {
+    AvoValue result = avo_list_of(0, NULL);
+    AvoIterator iterator = avo_iterate(iterable, pos);
+    AvoValue item;
+    while (avo_next(&iterator, &item)) {
+        avo_list_push(result.as.list, avo_call(transform, 1, &item, pos));
+    }
+    return result;
+}
+
+static inline AvoValue avo_filter(AvoValue iterable, AvoValue predicate, int pos) {
+    AvoValue result = avo_list_of(0, NULL);
+    AvoIterator iterator = avo_iterate(iterable, pos);
+    AvoValue item;
+    while (avo_next(&iterator, &item)) {
+        AvoValue keep = avo_call(predicate, 1, &item, pos);
+        if (keep.tag != AVO_BOOL) {
+            avo_fail(pos, "Filter predicate must return Bool, found %s", avo_type_name(keep));
+        }
+        if (keep.as.boolean) {
+            avo_list_push(result.as.list, item);
+        }
+    }
+    return result;
+}
+
+static inline AvoValue avo_reduce(AvoValue iterable, AvoValue initial, AvoValue combine, int pos) {
+    AvoIterator iterator = avo_iterate(iterable, pos);
+    AvoValue arguments[2];
+    arguments[0] = initial;
+    while (avo_next(&iterator, &arguments[1])) {
+        arguments[0] = avo_call(combine, 2, arguments, pos);
+    }
+    return arguments[0];
+}
+
+#endif
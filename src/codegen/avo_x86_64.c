@@ -0,0 +1,285 @@
+/*
+ * Entry points for the programs compiled to x86-64 assembly with `avoc build --target x86_64`,
+ * this file is appended to `avo_runtime.h` and compiled together with the assembly of the program.
+ *
+ * The compiled code keeps Int, Float and Bool in the registers and the other values as the pointers
+ * to the boxed `AvoValue`. The functions here take and return the boxed values, floats are passed
+ * as their bits in the integer registers. The compiled code stores the offset of the instruction
+ * in the source to `avox_position` before it may fail, the runtime errors are reported at it.
+ */
+
+AvoValue avox_none_value = { AVO_NONE, { 0 } };
+
+int64_t avox_position = 0;
+
+#define AVOX_POS ((int) avox_position)
+
+/* Iterator of `for` with the item which is already taken by `avox_has_next`. */
+typedef struct {
+    AvoIterator iterator;
+    AvoValue item;
+} AvoxIterator;
+
+static AvoValue *avox_box(AvoValue value) {
+    AvoValue *box;
+    if (value.tag == AVO_NONE) {
+        return &avox_none_value;
+    }
+    box = avo_alloc(sizeof(AvoValue));
+    *box = value;
+    return box;
+}
+
+static void avox_expect(const AvoValue *value, AvoTag tag, const char *type_name) {
+    if (value->tag != tag) {
+        avo_fail(AVOX_POS, "Expected %s, found %s", type_name, avo_type_name(*value));
+    }
+}
+
+AvoValue *avox_box_int(int64_t integer) {
+    return avox_box(avo_int(integer));
+}
+
+AvoValue *avox_box_float(uint64_t bits) {
+    double number;
+    memcpy(&number, &bits, sizeof(number));
+    return avox_box(avo_float(number));
+}
+
+AvoValue *avox_box_bool(int64_t boolean) {
+    return avox_box(avo_bool(boolean != 0));
+}
+
+int64_t avox_unbox_int(AvoValue *value) {
+    avox_expect(value, AVO_INT, "Int");
+    return value->as.integer;
+}
+
+uint64_t avox_unbox_float(AvoValue *value) {
+    uint64_t bits;
+    avox_expect(value, AVO_FLOAT, "Float");
+    memcpy(&bits, &value->as.number, sizeof(bits));
+    return bits;
+}
+
+int64_t avox_unbox_bool(AvoValue *value) {
+    avox_expect(value, AVO_BOOL, "Bool");
+    return value->as.boolean;
+}
+
+AvoValue *avox_string(const char *data, int64_t length) {
+    return avox_box(avo_string(data, (size_t) length));
+}
+
+AvoValue *avox_list(int64_t count, AvoValue **items) {
+    AvoValue list = avo_list_of(0, NULL);
+    int64_t index;
+    for (index = 0; index < count; index++) {
+        avo_list_push(list.as.list, *items[index]);
+    }
+    return avox_box(list);
+}
+
+/* Only the ranges with `:` have the step, so the step tells how the range is printed. */
+AvoValue *avox_range(AvoValue *start, AvoValue *end, AvoValue *step, int64_t is_inclusive) {
+    return avox_box(avo_range(*start, *end, *step, (int) is_inclusive, step->tag != AVO_NONE, AVOX_POS));
+}
+
+/* Failures of the operators computed by the compiled code */
+
+void avox_overflow(void) {
+    avo_fail(AVOX_POS, "Integer overflow");
+}
+
+void avox_division_by_zero(void) {
+    avo_fail(AVOX_POS, "Division by zero");
+}
+
+void avox_nan_comparison(void) {
+    avo_fail(AVOX_POS, "Can't compare NaN with a number");
+}
+
+/* Operators */
+
+AvoValue *avox_add(AvoValue *lhs, AvoValue *rhs) {
+    return avox_box(avo_add(*lhs, *rhs, AVOX_POS));
+}
+
+AvoValue *avox_sub(AvoValue *lhs, AvoValue *rhs) {
+    return avox_box(avo_sub(*lhs, *rhs, AVOX_POS));
+}
+
+AvoValue *avox_mul(AvoValue *lhs, AvoValue *rhs) {
+    return avox_box(avo_mul(*lhs, *rhs, AVOX_POS));
+}
+
+AvoValue *avox_div(AvoValue *lhs, AvoValue *rhs) {
+    return avox_box(avo_div(*lhs, *rhs, AVOX_POS));
+}
+
+AvoValue *avox_pow(AvoValue *lhs, AvoValue *rhs) {
+    return avox_box(avo_pow(*lhs, *rhs, AVOX_POS));
+}
+
+AvoValue *avox_eq(AvoValue *lhs, AvoValue *rhs) {
+    return avox_box(avo_eq(*lhs, *rhs));
+}
+
+AvoValue *avox_ne(AvoValue *lhs, AvoValue *rhs) {
+    return avox_box(avo_ne(*lhs, *rhs));
+}
+
+AvoValue *avox_lt(AvoValue *lhs, AvoValue *rhs) {
+    return avox_box(avo_lt(*lhs, *rhs, AVOX_POS));
+}
+
+AvoValue *avox_le(AvoValue *lhs, AvoValue *rhs) {
+    return avox_box(avo_le(*lhs, *rhs, AVOX_POS));
+}
+
+AvoValue *avox_gt(AvoValue *lhs, AvoValue *rhs) {
+    return avox_box(avo_gt(*lhs, *rhs, AVOX_POS));
+}
+
+AvoValue *avox_ge(AvoValue *lhs, AvoValue *rhs) {
+    return avox_box(avo_ge(*lhs, *rhs, AVOX_POS));
+}
+
+AvoValue *avox_in(AvoValue *item, AvoValue *collection) {
+    return avox_box(avo_in(*item, *collection, AVOX_POS));
+}
+
+AvoValue *avox_xor(AvoValue *lhs, AvoValue *rhs) {
+    return avox_box(avo_xor(*lhs, *rhs, AVOX_POS));
+}
+
+AvoValue *avox_neg(AvoValue *operand) {
+    return avox_box(avo_neg(*operand, AVOX_POS));
+}
+
+AvoValue *avox_not(AvoValue *operand) {
+    return avox_box(avo_not(*operand, AVOX_POS));
+}
+
+AvoValue *avox_is(AvoValue *value, const char *type_name) {
+    return avox_box(avo_is(*value, type_name));
+}
+
+int64_t avox_condition(AvoValue *value) {
+    return avo_condition(*value, AVOX_POS);
+}
+
+AvoValue *avox_index(AvoValue *collection, AvoValue *index) {
+    return avox_box(avo_index(*collection, *index, AVOX_POS));
+}
+
+AvoValue *avox_set_index(AvoValue *collection, AvoValue *index, AvoValue *value) {
+    avo_set_index(*collection, *index, *value, AVOX_POS);
+    return &avox_none_value;
+}
+
+/* Global variable is `NULL` until it is assigned, the functions may be called before that. */
+AvoValue *avox_load_global(AvoValue *value, const char *name) {
+    if (value == NULL) {
+        avo_fail(AVOX_POS, "Undefined variable '%s'", name);
+    }
+    return value;
+}
+
+/* Functions */
+
+AvoValue *avox_parameter(AvoValue *value, const char *type_name, const char *name) {
+    return avox_box(avo_parameter(*value, type_name, name, AVOX_POS));
+}
+
+AvoValue *avox_returned(AvoValue *value, const char *type_name, const char *name) {
+    return avox_box(avo_returned(*value, type_name, name, AVOX_POS));
+}
+
+AvoxIterator *avox_iterate(AvoValue *iterable) {
+    AvoxIterator *iterator = avo_alloc(sizeof(AvoxIterator));
+    iterator->iterator = avo_iterate(*iterable, AVOX_POS);
+    iterator->item = avo_none();
+    return iterator;
+}
+
+int64_t avox_has_next(AvoxIterator *iterator) {
+    return avo_next(&iterator->iterator, &iterator->item);
+}
+
+AvoValue *avox_next(AvoxIterator *iterator) {
+    return avox_box(iterator->item);
+}
+
+/* Builtins */
+
+static AvoValue *avox_print_values(int64_t count, AvoValue **arguments, const char *end) {
+    AvoValue *values = avo_alloc((size_t) count * sizeof(AvoValue));
+    int64_t index;
+    for (index = 0; index < count; index++) {
+        values[index] = *arguments[index];
+    }
+    avo_print((int) count, values, end);
+    free(values);
+    return &avox_none_value;
+}
+
+AvoValue *avox_print(int64_t count, AvoValue **arguments) {
+    return avox_print_values(count, arguments, "");
+}
+
+AvoValue *avox_println(int64_t count, AvoValue **arguments) {
+    return avox_print_values(count, arguments, "\n");
+}
+
+AvoValue *avox_len(AvoValue *collection) {
+    return avox_box(avo_len(*collection, AVOX_POS));
+}
+
+AvoValue *avox_push(AvoValue *list, AvoValue *item) {
+    return avox_box(avo_push(*list, *item, AVOX_POS));
+}
+
+AvoValue *avox_sqrt(AvoValue *number) {
+    return avox_box(avo_sqrt(*number, AVOX_POS));
+}
+
+AvoValue *avox_sin(AvoValue *number) {
+    return avox_box(avo_sin(*number, AVOX_POS));
+}
+
+AvoValue *avox_cos(AvoValue *number) {
+    return avox_box(avo_cos(*number, AVOX_POS));
+}
+
+AvoValue *avox_tan(AvoValue *number) {
+    return avox_box(avo_tan(*number, AVOX_POS));
+}
+
+AvoValue *avox_floor(AvoValue *number) {
+    return avox_box(avo_floor(*number, AVOX_POS));
+}
+
+AvoValue *avox_ceil(AvoValue *number) {
+    return avox_box(avo_ceil(*number, AVOX_POS));
+}
+
+AvoValue *avox_round(AvoValue *number) {
+    return avox_box(avo_round(*number, AVOX_POS));
+}
+
+AvoValue *avox_abs(AvoValue *number) {
+    return avox_box(avo_abs(*number, AVOX_POS));
+}
+
+AvoValue *avox_power(AvoValue *base, AvoValue *exponent) {
+    return avox_box(avo_power(*base, *exponent, AVOX_POS));
+}
+
+/* The root block of the program */
+AvoValue *avo_fn_main(void);
+
+int main(void) {
+    avo_fn_main();
+    return 0;
+}
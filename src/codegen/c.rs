@@ -0,0 +1,858 @@
+use crate::analyzer::{ declared_name, free_variables, resolve_assignments, Assignment };
+use crate::dto::{ Node, NodeType, Operator, Token, SyntaxError };
+use crate::runtime::Function;
+use crate::runtime::builtins::is_builtin;
+use std::collections::{ HashMap, HashSet };
+#[cfg(test)]
+use crate::parser::parse;
+
+/// Runtime of the compiled programs, it is copied to the beginning of every program,
+/// so the output is the single file which is compiled with `cc program.c -lm`.
+const RUNTIME: &str = include_str!("avo_runtime.h");
+
+/// Translates the checked syntax tree of the program to the C99 source.
+///
+/// Values keep the dynamic types of the interpreter, so the compiled program prints the same output
+/// and stops with the same runtime errors. Variables are the C variables, mutable variables captured
+/// by the lambdas live on the heap, and global functions are called directly when the number of arguments is right.
+/// The backend walks the syntax tree instead of the [`lower`]ed program, because the IR has no lambdas and matches yet.
+///
+/// [`lower`]: crate::ir::lower
+///
+/// # Errors
+/// Constructs which are not supported by the runtime of the compiled programs: tuples, maps, structs, enums,
+/// modules, slices and the builtins besides the `io`, `math` and collection functions of the runtime.
+/// Assignments to the immutable variables are reported here instead of at runtime, the variables follow the scopes of the resolver.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// # use avoc::internals::{ generate_c, TokenReader, TreeBuilder };
+/// let tokens = TokenReader::new().parse("println(6 * 7)").unwrap();
+/// let tree = TreeBuilder::new().build_tree(&tokens).unwrap();
+///
+/// assert!(generate_c(&tree).unwrap().contains("int main(void)"));
+/// ```
+pub fn generate_c(root: &Node) -> Result<String, Vec<SyntaxError>> {
+    let mut generator = Generator {
+        globals: HashMap::new(),
+        global_names: vec!(),
+        declared: HashSet::new(),
+        prototypes: String::new(),
+        closures: String::new(),
+        definitions: String::new(),
+        contexts: vec!(),
+        assignments: resolve_assignments(root),
+        counter: 0,
+        errors: vec!(),
+    };
+    generator.collect_globals(root);
+    let functions: Vec<&Node> = root.children.iter().map(Node::declaration).filter(|node| node.node_type == NodeType::Function).collect();
+    for node in &functions {
+        let name = name_of(node);
+        if let Some(direct) = generator.globals[name].direct.clone() {
+            generator.function(&direct.function, node, Some(name), vec!());
+        }
+    }
+    generator.contexts.push(Context::new(captured_names(root)));
+    for node in &functions {
+        let global = &generator.globals[name_of(node)];
+        if let (Place::Value(target), Some(direct)) = (&global.place, &global.direct) {
+            let line = format!("{} = avo_function(&{});", target, direct.closure);
+            generator.line(line);
+            generator.declared.insert(name_of(node).to_string());
+        }
+    }
+    // global functions are already declared
+    for statement in root.children.iter().map(Node::declaration).filter(|node| node.node_type != NodeType::Function) {
+        generator.expression(statement);
+    }
+    let main = generator.contexts.pop().unwrap().code;
+    if !generator.errors.is_empty() {
+        return Err(generator.errors)
+    }
+    let mut program = String::from(RUNTIME);
+    program.push('\n');
+    program.push_str(&generator.prototypes);
+    program.push_str(&generator.closures);
+    for name in &generator.global_names {
+        if let Place::Value(target) = &generator.globals[name].place {
+            program.push_str(&format!("static AvoValue {};\n", target));
+        }
+    }
+    program.push('\n');
+    program.push_str(&generator.definitions);
+    program.push_str(&format!("int main(void) {{\n{}    return 0;\n}}\n", main));
+    Ok(program)
+}
+
+/// Builtins implemented by the runtime of the compiled programs.
+const BUILTINS: &[&str] = &["print", "println", "len", "push", "sqrt", "sin", "cos", "tan", "floor", "ceil", "round", "abs", "pow", "map", "filter", "reduce"];
+
+struct Generator {
+    globals: HashMap<String, Variable>,
+    /// Names of the global variables in the order of declaration, so the output is the same for the same program.
+    global_names: Vec<String>,
+    /// Globals which are already assigned by the statements of `main` compiled so far.
+    declared: HashSet<String>,
+    prototypes: String,
+    closures: String,
+    definitions: String,
+    /// Functions which are being compiled, the innermost one is the last, `main` is the first.
+    contexts: Vec<Context>,
+    assignments: HashMap<usize, Assignment>,
+    counter: usize,
+    errors: Vec<SyntaxError>,
+}
+
+/// Body of the C function which is being generated with the variables visible in it.
+struct Context {
+    code: String,
+    depth: usize,
+    /// The first scope of the lambda holds the captured variables.
+    scopes: Vec<HashMap<String, Variable>>,
+    /// Names used by the nested lambdas, the mutable variables with these names are allocated on the heap.
+    captured: HashSet<String>,
+    /// Return type and the name of the function for the message of the failed check.
+    return_type: Option<(String, String)>,
+}
+
+#[derive(Clone)]
+struct Variable {
+    place: Place,
+    is_mutable: bool,
+    /// Global function which may be called without the checks of `avo_call`.
+    direct: Option<Direct>,
+}
+
+/// C expression of the `AvoValue`, or of the pointer to the `AvoValue` which is shared with the lambdas.
+#[derive(Clone)]
+enum Place {
+    Value(String),
+    Cell(String),
+}
+
+#[derive(Clone)]
+struct Direct {
+    function: String,
+    closure: String,
+    required: usize,
+    total: usize,
+}
+
+impl Variable {
+
+    fn read(&self) -> String {
+        match &self.place {
+            Place::Value(expression) => expression.clone(),
+            Place::Cell(pointer) => format!("(*{})", pointer),
+        }
+    }
+}
+
+impl Context {
+
+    fn new(captured: HashSet<String>) -> Context {
+        Context { code: String::new(), depth: 1, scopes: vec!(HashMap::new()), captured, return_type: None }
+    }
+}
+
+impl Generator {
+
+    /// Global functions and the variables assigned in the root block, functions are visible before their declaration.
+    fn collect_globals(&mut self, root: &Node) {
+        for statement in root.children.iter().map(Node::declaration) {
+            match statement.node_type {
+                NodeType::Function => {
+                    let name = name_of(statement);
+                    let direct = Direct {
+                        function: self.unique("f", name),
+                        closure: self.unique("c", name),
+                        required: statement.condition.iter().filter(|parameter| parameter.children.is_empty()).count(),
+                        total: statement.condition.len(),
+                    };
+                    self.closures.push_str(&format!("static AvoClosure {} = {{ {}, {}, {}, {}, NULL }};\n",
+                        direct.closure, direct.function, c_string(name), direct.required, direct.total));
+                    self.add_global(name, false, Some(direct));
+                },
+                NodeType::Expression => if let Some((name, is_mutable)) = declared_name(statement) {
+                    self.add_global(name, is_mutable, None)
+                },
+                _ => (),
+            }
+        }
+    }
+
+    fn add_global(&mut self, name: &str, is_mutable: bool, direct: Option<Direct>) {
+        if self.globals.contains_key(name) {
+            return
+        }
+        let place = Place::Value(self.unique("g", name));
+        self.globals.insert(name.to_string(), Variable { place, is_mutable, direct });
+        self.global_names.push(name.to_string());
+    }
+
+    /// Creates the C name which is not used by the other variables and functions.
+    fn unique(&mut self, prefix: &str, name: &str) -> String {
+        self.counter += 1;
+        match name {
+            "" => format!("{}{}", prefix, self.counter),
+            _ => format!("{}{}_{}", prefix, self.counter, name.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect::<String>()),
+        }
+    }
+
+    fn context(&mut self) -> &mut Context {
+        self.contexts.last_mut().unwrap()
+    }
+
+    fn line(&mut self, text: String) {
+        let context = self.context();
+        for _ in 0..context.depth {
+            context.code.push_str("    ");
+        }
+        context.code.push_str(&text);
+        context.code.push('\n');
+    }
+
+    fn open(&mut self, text: String) {
+        self.line(text);
+        self.context().depth += 1;
+    }
+
+    fn close(&mut self, text: &str) {
+        self.context().depth -= 1;
+        self.line(text.to_string());
+    }
+
+    /// Closes the block and opens the next one, e.g. `} else {`.
+    fn reopen(&mut self, text: &str) {
+        self.close(text);
+        self.context().depth += 1;
+    }
+
+    /// Stores the value in the new variable, so it is evaluated exactly once and in the order of the source.
+    fn temporary(&mut self, value: String) -> String {
+        let name = self.unique("t", "");
+        self.line(format!("AvoValue {} = {};", name, value));
+        name
+    }
+
+    /// Declares the variable for the result of the statement which is assigned in its branches.
+    fn result(&mut self) -> String {
+        let name = self.unique("t", "");
+        self.line(format!("AvoValue {};", name));
+        name
+    }
+
+    fn error(&mut self, pos: usize, message: String) -> String {
+        self.errors.push(SyntaxError::new(pos, message));
+        String::from("avo_none()")
+    }
+
+    fn unsupported(&mut self, node: &Node, what: &str) -> String {
+        self.error(node.pos(), format!("{} are not supported by the C backend", what))
+    }
+
+    fn push_scope(&mut self) {
+        self.context().scopes.push(HashMap::new())
+    }
+
+    fn pop_scope(&mut self) {
+        self.context().scopes.pop();
+    }
+
+    /// Looks up the variable in the local scopes of the current function.
+    fn lookup_local(&self, name: &str) -> Option<Variable> {
+        let context = self.contexts.last().unwrap();
+        context.scopes.iter().rev().find_map(|scope| scope.get(name)).cloned()
+    }
+
+    /// Functions see all globals, `main` sees only the globals assigned before.
+    fn lookup(&self, name: &str) -> Option<Variable> {
+        self.lookup_local(name).or_else(|| match self.contexts.len() {
+            1 if !self.declared.contains(name) => None,
+            _ => self.globals.get(name).cloned(),
+        })
+    }
+
+    fn is_global_scope(&self) -> bool {
+        self.contexts.len() == 1 && self.contexts[0].scopes.len() == 1
+    }
+
+    /// Declares the variable in the current scope and assigns the value to it.
+    fn declare(&mut self, name: &str, value: String, is_mutable: bool) -> String {
+        if self.is_global_scope() {
+            self.add_global(name, is_mutable, None);
+            self.declared.insert(name.to_string());
+            let target = self.globals[name].read();
+            self.line(format!("{} = {};", target, value));
+            return value
+        }
+        let target = self.unique("v", name);
+        let place = match is_mutable && self.context().captured.contains(name) {
+            true => {
+                self.line(format!("AvoValue *{} = avo_cell({});", target, value));
+                Place::Cell(target)
+            },
+            false => {
+                self.line(format!("AvoValue {} = {};", target, value));
+                Place::Value(target)
+            },
+        };
+        let variable = Variable { place, is_mutable, direct: None };
+        self.context().scopes.last_mut().unwrap().insert(name.to_string(), variable);
+        value
+    }
+
+    /// Generates the code which evaluates the node and returns the C expression of its value.
+    ///
+    /// The expression is either the constant, the immutable variable or the temporary variable,
+    /// so it may be used several times and after the other expressions.
+    fn expression(&mut self, node: &Node) -> String {
+        match node.node_type {
+            NodeType::Token => self.token(node),
+            NodeType::Expression => self.operation(node),
+            NodeType::Block => {
+                let result = self.result();
+                self.open(String::from("{"));
+                self.push_scope();
+                let value = self.statements(&node.children);
+                self.line(format!("{} = {};", result, value));
+                self.pop_scope();
+                self.close("}");
+                result
+            },
+            NodeType::If => {
+                let condition = self.expression(&node.condition[0]);
+                let result = self.result();
+                self.open(format!("if (avo_condition({}, {})) {{", condition, node.condition[0].pos()));
+                let value = self.expression(&node.children[0]);
+                self.line(format!("{} = {};", result, value));
+                self.reopen("} else {");
+                let value = match node.children.get(1) {
+                    Some(otherwise) => self.expression(otherwise),
+                    None => String::from("avo_none()"),
+                };
+                self.line(format!("{} = {};", result, value));
+                self.close("}");
+                result
+            },
+            NodeType::Call => self.call(node),
+            NodeType::Lambda => self.closure(node, None),
+            NodeType::Range => {
+                let start = self.expression(&node.children[0]);
+                let end = self.expression(&node.children[1]);
+                let step = self.expression(&node.children[2]);
+                let is_step_range = node.operator() == Some(Operator::Colon);
+                let is_inclusive = is_step_range || node.operator() == Some(Operator::InclusiveRange);
+                self.temporary(format!("avo_range({}, {}, {}, {}, {}, {})", start, end, step, is_inclusive as u8, is_step_range as u8, node.pos()))
+            },
+            NodeType::For => {
+                let iterable = self.expression(&node.condition[0]);
+                let iterator = self.unique("i", "");
+                let item = self.unique("t", "");
+                self.line(format!("AvoIterator {} = avo_iterate({}, {});", iterator, iterable, node.condition[0].pos()));
+                self.line(format!("AvoValue {};", item));
+                self.open(format!("while (avo_next(&{}, &{})) {{", iterator, item));
+                self.push_scope();
+                self.declare(name_of(&node.children[0]), item, false);
+                self.expression(&node.children[1]);
+                self.pop_scope();
+                self.close("}");
+                String::from("avo_none()")
+            },
+            NodeType::Loop => {
+                self.open(String::from("while (1) {"));
+                self.expression(&node.children[0]);
+                self.close("}");
+                String::from("avo_none()")
+            },
+            NodeType::Break => {
+                self.line(String::from("break;"));
+                String::from("avo_none()")
+            },
+            NodeType::Continue => {
+                self.line(String::from("continue;"));
+                String::from("avo_none()")
+            },
+            NodeType::Return => {
+                let value = match node.children.first() {
+                    Some(value) => self.expression(value),
+                    None => String::from("avo_none()"),
+                };
+                let value = self.returned(value);
+                self.line(format!("return {};", value));
+                String::from("avo_none()")
+            },
+            NodeType::List => {
+                let items: Vec<String> = node.children.iter().map(|item| self.expression(item)).collect();
+                let items = self.array(&items);
+                self.temporary(format!("avo_list_of({}, {})", node.children.len(), items))
+            },
+            NodeType::Match => self.match_expression(node),
+            NodeType::Function => {
+                let function = self.closure(node, Some(name_of(node)));
+                self.declare(name_of(node), function, false);
+                String::from("avo_none()")
+            },
+            NodeType::Public => self.expression(&node.children[0]),
+            NodeType::Tuple => self.unsupported(node, "Tuples"),
+            NodeType::Map => self.unsupported(node, "Maps"),
+            NodeType::Struct => self.unsupported(node, "Structs"),
+            NodeType::Enum | NodeType::Variant => self.unsupported(node, "Enums"),
+            NodeType::Import => self.unsupported(node, "Modules"),
+            NodeType::Test => String::from("avo_none()"),
+            NodeType::Arm | NodeType::Rest => self.error(node.pos(), String::from("Pattern can be used only in the match arm")),
+            NodeType::Type => self.error(node.pos(), String::from("Type can be used only in the annotation")),
+        }
+    }
+
+    /// Statements of the block, the value is the value of the last one.
+    fn statements(&mut self, statements: &[Node]) -> String {
+        let mut value = String::from("avo_none()");
+        for statement in statements {
+            value = self.expression(statement);
+        }
+        value
+    }
+
+    fn token(&mut self, node: &Node) -> String {
+        match &node.data {
+            Some(Token::Identifier { name, .. }) => match self.lookup(name) {
+                // mutable variable may be changed by the next expressions, so its current value is copied
+                Some(variable) if variable.is_mutable => self.temporary(variable.read()),
+                Some(variable) => variable.read(),
+                None => self.error(node.pos(), format!("Undefined variable '{}'", name)),
+            },
+            Some(token) => match constant(token) {
+                Some(value) => value,
+                None => self.error(node.pos(), String::from("Unexpected token")),
+            },
+            None => self.error(node.pos(), String::from("Unexpected token")),
+        }
+    }
+
+    fn operation(&mut self, node: &Node) -> String {
+        let operator = match node.operator() {
+            Some(operator) => operator,
+            None => return self.error(node.pos(), String::from("Unexpected token")),
+        };
+        match operator {
+            _ if operator.is_assignment() => self.assignment(operator, node),
+            Operator::Mut => self.error(node.pos(), String::from("Mutable variable must be declared with assignment: mut name = value")),
+            Operator::Dot => match node.children[1].node_type {
+                NodeType::Call if BUILTINS.contains(&name_of(&node.children[1])) => {
+                    let method = &node.children[1];
+                    let mut arguments = vec!(self.expression(&node.children[0]));
+                    arguments.extend(method.children.iter().map(|argument| self.expression(argument)));
+                    self.builtin(name_of(method), &arguments, method.pos())
+                },
+                _ => self.unsupported(node, "Fields, methods of the modules and variants of the enums"),
+            },
+            Operator::LeftBracket if node.children[1].node_type == NodeType::Range => self.unsupported(node, "Slices"),
+            Operator::LeftBracket => {
+                let collection = self.expression(&node.children[0]);
+                let key = self.expression(&node.children[1]);
+                self.temporary(format!("avo_index({}, {}, {})", collection, key, node.pos()))
+            },
+            Operator::Is => {
+                let value = self.expression(&node.children[0]);
+                match &node.children[1].data {
+                    Some(Token::NoneConstant { .. }) => self.temporary(format!("avo_bool({}.tag == AVO_NONE)", value)),
+                    Some(Token::Identifier { name, .. }) => self.temporary(format!("avo_is({}, {})", value, c_string(name))),
+                    _ => self.unsupported(node, "Variants of the enums"),
+                }
+            },
+            // the right operand is evaluated only if the result is not known from the left one
+            Operator::And | Operator::Or => {
+                let lhs = self.expression(&node.children[0]);
+                let result = self.temporary(format!("avo_bool(avo_condition({}, {}))", lhs, node.children[0].pos()));
+                let negation = if operator == Operator::And { "" } else { "!" };
+                self.open(format!("if ({}{}.as.boolean) {{", negation, result));
+                let rhs = self.expression(&node.children[1]);
+                self.line(format!("{} = avo_bool(avo_condition({}, {}));", result, rhs, node.children[1].pos()));
+                self.close("}");
+                result
+            },
+            Operator::UnaryMinus | Operator::Not => {
+                let operand = self.expression(&node.children[0]);
+                let function = if operator == Operator::UnaryMinus { "avo_neg" } else { "avo_not" };
+                self.temporary(format!("{}({}, {})", function, operand, node.pos()))
+            },
+            _ => {
+                let lhs = self.expression(&node.children[0]);
+                let rhs = self.expression(&node.children[1]);
+                match operator {
+                    Operator::Equal => self.temporary(format!("avo_eq({}, {})", lhs, rhs)),
+                    Operator::NotEqual => self.temporary(format!("avo_ne({}, {})", lhs, rhs)),
+                    Operator::In => self.temporary(format!("avo_in({}, {}, {})", lhs, rhs, node.pos())),
+                    _ => match binary_function(operator) {
+                        Some(function) => self.temporary(format!("{}({}, {}, {})", function, lhs, rhs, node.pos())),
+                        None => self.error(node.pos(), format!("Unknown operator '{}'", operator)),
+                    },
+                }
+            },
+        }
+    }
+
+    /// Declares the C variable or stores the value to the existing one, as the resolver has decided for the assignment,
+    /// the item of the list is stored by the runtime. The compound assignment applies its operator to the current value first.
+    fn assignment(&mut self, operator: Operator, node: &Node) -> String {
+        let target = &node.children[0];
+        let value = self.expression(&node.children[1]);
+        match (target.operator(), &target.data) {
+            (Some(Operator::LeftBracket), _) => {
+                let collection = self.expression(&target.children[0]);
+                let key = self.expression(&target.children[1]);
+                let value = match operator.compound_base() {
+                    None => value,
+                    Some(base) => {
+                        let current = self.temporary(format!("avo_index({}, {}, {})", collection, key, node.pos()));
+                        self.temporary(format!("{}({}, {}, {})", binary_function(base).unwrap(), current, value, node.pos()))
+                    },
+                };
+                self.line(format!("avo_set_index({}, {}, {}, {});", collection, key, value, node.pos()));
+                value
+            },
+            (Some(Operator::Dot), _) => self.unsupported(target, "Fields"),
+            (Some(Operator::Mut), _) | (_, Some(Token::Identifier { .. })) => {
+                let name = name_of(target.children.first().unwrap_or(target));
+                match self.assignments[&node.pos()].clone() {
+                    Assignment::Declare { is_mutable } => self.declare(name, value, is_mutable),
+                    Assignment::Assign => match self.lookup(name) {
+                        Some(variable) => {
+                            let value = match operator.compound_base() {
+                                None => value,
+                                Some(base) => self.temporary(format!("{}({}, {}, {})", binary_function(base).unwrap(), variable.read(), value, node.pos())),
+                            };
+                            self.line(format!("{} = {};", variable.read(), value));
+                            value
+                        },
+                        None => self.error(target.pos(), format!("Undefined variable '{}'", name)),
+                    },
+                    Assignment::Invalid(error) => self.error(error.pos, error.message),
+                }
+            },
+            _ => self.error(target.pos(), String::from("Invalid assignment target")),
+        }
+    }
+
+    /// Calls the function from the variable or the builtin function with the same name.
+    fn call(&mut self, node: &Node) -> String {
+        let name = name_of(node);
+        let arguments: Vec<String> = node.children.iter().map(|argument| self.expression(argument)).collect();
+        let function = match self.lookup(name) {
+            Some(function) => function,
+            None => return self.builtin(name, &arguments, node.pos()),
+        };
+        let count = arguments.len();
+        let arguments = self.array(&arguments);
+        match function.direct {
+            Some(direct) if direct.required <= count && count <= direct.total =>
+                self.temporary(format!("{}(&{}, {}, {}, {})", direct.function, direct.closure, count, arguments, node.pos())),
+            _ => self.temporary(format!("avo_call_named({}, {}, {}, {}, {})", function.read(), c_string(name), count, arguments, node.pos())),
+        }
+    }
+
+    /// Calls the builtin of the runtime, the numbers of the arguments are already checked by the resolver.
+    fn builtin(&mut self, name: &str, arguments: &[String], pos: usize) -> String {
+        match (name, arguments) {
+            ("print", _) | ("println", _) => {
+                let end = if name == "println" { "\"\\n\"" } else { "\"\"" };
+                let array = self.array(arguments);
+                self.temporary(format!("avo_print({}, {}, {})", arguments.len(), array, end))
+            },
+            ("len", [value]) | ("sqrt", [value]) | ("sin", [value]) | ("cos", [value]) | ("tan", [value])
+            | ("floor", [value]) | ("ceil", [value]) | ("round", [value]) | ("abs", [value]) =>
+                self.temporary(format!("avo_{}({}, {})", name, value, pos)),
+            ("push", [list, item]) => self.temporary(format!("avo_push({}, {}, {})", list, item, pos)),
+            ("pow", [base, exponent]) => self.temporary(format!("avo_power({}, {}, {})", base, exponent, pos)),
+            ("map", [iterable, function]) | ("filter", [iterable, function]) =>
+                self.temporary(format!("avo_{}({}, {}, {})", name, iterable, function, pos)),
+            ("reduce", [iterable, initial, function]) => self.temporary(format!("avo_reduce({}, {}, {}, {})", iterable, initial, function, pos)),
+            _ if is_builtin(name) => self.error(pos, format!("Builtin '{}' is not supported by the C backend", name)),
+            _ => self.error(pos, format!("Unknown function '{}'", name)),
+        }
+    }
+
+    /// Declares the C array with the values, or returns `NULL` for the empty one.
+    fn array(&mut self, values: &[String]) -> String {
+        if values.is_empty() {
+            return String::from("NULL")
+        }
+        let name = self.unique("a", "");
+        self.line(format!("AvoValue {}[] = {{ {} }};", name, values.join(", ")));
+        name
+    }
+
+    /// Checks the returned value against the return type of the function.
+    fn returned(&mut self, value: String) -> String {
+        match &self.context().return_type {
+            Some((type_name, name)) => format!("avo_returned({}, {}, {}, pos)", value, c_string(type_name), c_string(name)),
+            None => value,
+        }
+    }
+
+    /// Creates the lambda or the nested function with the cells of the captured variables.
+    ///
+    /// Mutable variables share their cells with the lambda, immutable ones are copied to the new cells.
+    fn closure(&mut self, node: &Node, name: Option<&str>) -> String {
+        let captures: Vec<(String, Variable)> = free_variables(node).into_iter()
+            .filter(|captured| Some(captured.as_str()) != name)
+            .filter_map(|captured| self.lookup_local(&captured).map(|variable| (captured, variable)))
+            .collect();
+        let function = self.unique(if name.is_some() { "f" } else { "l" }, name.unwrap_or("lambda"));
+        let cells: Vec<String> = captures.iter().map(|(_, variable)| match &variable.place {
+            Place::Cell(pointer) => pointer.clone(),
+            Place::Value(value) => format!("avo_cell({})", value),
+        }).collect();
+        self.function(&function, node, name, captures);
+        let array = match cells.is_empty() {
+            true => String::from("NULL"),
+            false => {
+                let array = self.unique("k", "");
+                self.line(format!("AvoValue *{}[] = {{ {} }};", array, cells.join(", ")));
+                array
+            },
+        };
+        let name = name.map(c_string).unwrap_or_else(|| String::from("NULL"));
+        let required = node.condition.iter().filter(|parameter| parameter.children.is_empty()).count();
+        self.temporary(format!("avo_closure({}, {}, {}, {}, {}, {})", function, name, required, node.condition.len(), cells.len(), array))
+    }
+
+    /// Generates the C function of the lambda or the declared function.
+    ///
+    /// The function sees itself by its name, parameters without arguments get their default values or `None`.
+    fn function(&mut self, function: &str, node: &Node, name: Option<&str>, captures: Vec<(String, Variable)>) {
+        let signature = format!("static AvoValue {}(AvoClosure *self, int count, AvoValue *arguments, int pos)", function);
+        self.prototypes.push_str(&format!("{};\n", signature));
+        let mut context = Context::new(captured_names(node));
+        for (index, (captured, variable)) in captures.into_iter().enumerate() {
+            let place = Place::Cell(format!("self->cells[{}]", index));
+            context.scopes[0].insert(captured, Variable { place, is_mutable: variable.is_mutable, direct: None });
+        }
+        context.return_type = match (node.node_type, node.children.get(1), name) {
+            (NodeType::Function, Some(annotation), Some(name)) => Some((name_of(annotation).to_string(), name.to_string())),
+            _ => None,
+        };
+        context.scopes.push(HashMap::new());
+        if let Some(name) = name {
+            let itself = Variable { place: Place::Value(String::from("avo_function(self)")), is_mutable: false, direct: None };
+            context.scopes[1].insert(name.to_string(), itself);
+        }
+        self.contexts.push(context);
+        for (index, parameter) in node.condition.iter().enumerate() {
+            let parameter_name = name_of(parameter);
+            let target = self.unique("v", parameter_name);
+            match parameter.children.first() {
+                Some(default) => {
+                    self.line(format!("AvoValue {};", target));
+                    self.open(format!("if (count > {}) {{", index));
+                    self.line(format!("{} = arguments[{}];", target, index));
+                    self.reopen("} else {");
+                    let value = self.expression(default);
+                    self.line(format!("{} = {};", target, value));
+                    self.close("}");
+                },
+                None => self.line(format!("AvoValue {} = count > {} ? arguments[{}] : avo_none();", target, index, index)),
+            }
+            if let Some(type_name) = Function::parameter_type(parameter) {
+                self.line(format!("{} = avo_parameter({}, {}, {}, pos);", target, target, c_string(type_name), c_string(parameter_name)));
+            }
+            let variable = Variable { place: Place::Value(target), is_mutable: false, direct: None };
+            self.context().scopes.last_mut().unwrap().insert(parameter_name.to_string(), variable);
+        }
+        let value = self.expression(&node.children[0]);
+        let value = self.returned(value);
+        self.line(format!("return {};", value));
+        let context = self.contexts.pop().unwrap();
+        self.definitions.push_str(&format!("{} {{\n{}}}\n\n", signature, context.code));
+    }
+
+    /// Evaluates the first arm whose pattern matches the value and whose guard is `true`.
+    fn match_expression(&mut self, node: &Node) -> String {
+        let value = self.expression(&node.condition[0]);
+        let value = self.temporary(value);
+        let result = self.result();
+        let is_matched = self.unique("m", "");
+        self.line(format!("int {} = 0;", is_matched));
+        for arm in &node.children {
+            let mut bindings = vec!();
+            let condition = self.pattern(&arm.condition[0], &value, &mut bindings);
+            self.open(format!("if (!{} && {}) {{", is_matched, condition));
+            self.push_scope();
+            for (name, value) in bindings {
+                self.declare(&name, value, false);
+            }
+            let guard = match arm.condition.get(1) {
+                Some(guard) => {
+                    let value = self.expression(guard);
+                    format!("avo_condition({}, {})", value, guard.pos())
+                },
+                None => String::from("1"),
+            };
+            self.open(format!("if ({}) {{", guard));
+            let value = self.expression(&arm.children[0]);
+            self.line(format!("{} = {};", result, value));
+            self.line(format!("{} = 1;", is_matched));
+            self.close("}");
+            self.pop_scope();
+            self.close("}");
+        }
+        self.open(format!("if (!{}) {{", is_matched));
+        self.line(format!("avo_no_match({}, {});", value, node.pos()));
+        self.line(format!("{} = avo_none();", result));
+        self.close("}");
+        result
+    }
+
+    /// Returns the C condition which checks the value against the pattern, names bound by the pattern are appended to the `bindings`.
+    fn pattern(&mut self, pattern: &Node, value: &str, bindings: &mut Vec<(String, String)>) -> String {
+        match (pattern.node_type, &pattern.data) {
+            (NodeType::Token, Some(Token::Identifier { name, .. })) if name == "_" => String::from("1"),
+            (NodeType::Token, Some(Token::Identifier { name, .. })) => {
+                bindings.push((name.to_string(), value.to_string()));
+                String::from("1")
+            },
+            (NodeType::Token, Some(token)) => match constant(token) {
+                Some(literal) => format!("avo_equal({}, {})", value, literal),
+                None => self.error(pattern.pos(), String::from("Unexpected token")),
+            },
+            (NodeType::Range, _) => {
+                let bound = |node: &Node| node.data.as_ref().and_then(constant).unwrap_or_else(|| String::from("avo_none()"));
+                let is_inclusive = pattern.operator() == Some(Operator::InclusiveRange);
+                format!("avo_pattern_range({}, {}, {}, {})", value, bound(&pattern.children[0]), bound(&pattern.children[1]), is_inclusive as u8)
+            },
+            (NodeType::List, _) => {
+                let rest = pattern.children.last().filter(|rest| rest.node_type == NodeType::Rest);
+                let head = &pattern.children[..pattern.children.len() - rest.iter().count()];
+                let length = match rest {
+                    Some(_) => format!("{}.as.list->length >= {}", value, head.len()),
+                    None => format!("{}.as.list->length == {}", value, head.len()),
+                };
+                let mut conditions = vec!(format!("{}.tag == AVO_LIST", value), length);
+                for (index, item) in head.iter().enumerate() {
+                    conditions.push(self.pattern(item, &format!("{}.as.list->items[{}]", value, index), bindings));
+                }
+                if let Some(name) = rest.and_then(|rest| rest.children.first()) {
+                    let tail = format!("avo_list_of({0}.as.list->length - {1}, {0}.as.list->items + {1})", value, head.len());
+                    bindings.push((name_of(name).to_string(), tail));
+                }
+                format!("({})", conditions.join(" && "))
+            },
+            (NodeType::Tuple, _) => self.unsupported(pattern, "Tuples"),
+            _ => self.unsupported(pattern, "Enums"),
+        }
+    }
+}
+
+/// Names used by the lambdas and functions declared inside of the node.
+fn captured_names(node: &Node) -> HashSet<String> {
+    let mut names = HashSet::new();
+    for child in node.condition.iter().chain(node.children.iter()) {
+        match child.node_type {
+            NodeType::Lambda | NodeType::Function => names.extend(free_variables(child)),
+            _ => names.extend(captured_names(child)),
+        }
+    }
+    names
+}
+
+/// Runtime function of the arithmetic and comparison operators.
+fn binary_function(operator: Operator) -> Option<&'static str> {
+    match operator {
+        Operator::Plus => Some("avo_add"),
+        Operator::Minus => Some("avo_sub"),
+        Operator::Multiply => Some("avo_mul"),
+        Operator::Divide => Some("avo_div"),
+        Operator::Power => Some("avo_pow"),
+        Operator::Less => Some("avo_lt"),
+        Operator::LessOrEqual => Some("avo_le"),
+        Operator::Greater => Some("avo_gt"),
+        Operator::GreaterOrEqual => Some("avo_ge"),
+        Operator::Xor => Some("avo_xor"),
+        _ => None,
+    }
+}
+
+/// C expression of the literal.
+fn constant(token: &Token) -> Option<String> {
+    match token {
+        Token::IntConstant { value, .. } if *value == i64::MIN => Some(String::from("avo_int(INT64_MIN)")),
+        Token::IntConstant { value, .. } => Some(format!("avo_int(INT64_C({}))", value)),
+        Token::FloatConstant { value, .. } if value.is_nan() => Some(String::from("avo_float(NAN)")),
+        Token::FloatConstant { value, .. } if value.is_infinite() => Some(format!("avo_float({}INFINITY)", if *value < 0.0 { "-" } else { "" })),
+        // `{:e}` is the shortest form which is read back to the same number
+        Token::FloatConstant { value, .. } => Some(format!("avo_float({:e})", value)),
+        Token::StringConstant { value, .. } => Some(format!("avo_string({}, {})", c_string(value), value.len())),
+        Token::BoolConstant { value, .. } => Some(format!("avo_bool({})", *value as u8)),
+        Token::NoneConstant { .. } => Some(String::from("avo_none()")),
+        _ => None,
+    }
+}
+
+/// Quotes the string for C, the bytes besides the printable ASCII are written as the octal escapes.
+fn c_string(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    for byte in value.bytes() {
+        match byte {
+            b'"' | b'\\' => {
+                quoted.push('\\');
+                quoted.push(byte as char)
+            },
+            // `??` starts the trigraphs
+            b' '..=b'~' if byte != b'?' => quoted.push(byte as char),
+            _ => quoted.push_str(&format!("\\{:03o}", byte)),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[inline]
+fn name_of(node: &Node) -> &str {
+    match &node.data {
+        Some(Token::Identifier { name, .. }) | Some(Token::Function { name, .. }) => name,
+        _ => "",
+    }
+}
+
+#[cfg(test)]
+fn generate(source: &str) -> Result<String, Vec<SyntaxError>> {
+    let tree = parse(source);
+    generate_c(&tree)
+}
+
+#[test]
+fn test_compiled_output_is_same_as_interpreted() {
+    let programs = [
+        "fn fact(n: Int) -> Int {\n  if n <= 1 { return 1 }\n  n * fact(n - 1)\n}\nprintln(fact(20), 1.5, \"x\" + 0.1 + [1, \"a\"], 1 / 3.0, 0.1 + 0.2)",
+        "make = { @ ->\n  mut count = 0\n  step = 2\n  { @ -> count += step }\n}\ncounter = make()\ncounter()\nprintln(counter())\nfn outer(k) {\n  fn count(n) { if n == 0 { 0 } else { k + count(n - 1) } }\n  count(3)\n}\nprint(outer(2), \"\\n\")",
+        "mut sum = 0\nfor i in 0:0.5:2 { sum += i }\nmut n = 0\nloop {\n  n += 1\n  if n == 2 { continue }\n  if n >= 4 { break }\n}\nprintln(sum, n, map(1..=3, { @ x -> x * x }), filter(0..10, { @ x -> x - x / 2 * 2 == 0 }).reduce(0){ @ a, b -> a + b })",
+        "describe = { @ x -> match x {\n  0 -> \"zero\"\n  [a, ..rest] -> \"list \" + a + rest\n  n if n < 0 -> \"negative\"\n  1..=9 -> \"digit\"\n  _ -> \"big\"\n}}\nprintln(describe(-3), describe(0), describe(7), describe(10), describe([1, 2, 3]))",
+        "mut xs = [3, 1]\nxs.push(5)\nxs[0] += 10\nprintln(xs, len(xs), 2 in xs, \"ell\" in \"hello\", \"hello\"[1], 1 is Int, None is None, 1..=3, 0:2:6)\nprintln(sqrt(2), floor(2.7), pow(2, 10), abs(-3), \"quote \\\" ??\")",
+        "add = { @ a, b = a -> a + b }\nprintln(add(1), add(1, 2))\nzero = 0\nprintln(\"before\")\nprintln(1 / zero)",
+        "xs = [1]\nxs.push(xs)\nys = [1]\nys.push(ys)\nprintln(xs, [xs], xs == xs, xs == ys, xs == [1, ys])",
+    ];
+    super::assert_same_output_as_interpreted("c", &programs, |source, executable| {
+        let program = executable.with_extension("c");
+        std::fs::write(&program, generate(source).unwrap()).unwrap();
+        let status = std::process::Command::new("cc").arg("-std=c99").arg("-o").arg(executable).arg(&program).arg("-lm").status().ok()?;
+        assert!(status.success(), "{} is not compiled", program.to_string_lossy());
+        Some(())
+    });
+}
+
+#[test]
+fn test_unsupported_constructs() {
+    let message = |source: &str| generate(source).unwrap_err()[0].message.clone();
+    assert_eq!("Tuples are not supported by the C backend", message("a = (1, 2)"));
+    assert_eq!("Structs are not supported by the C backend", message("struct P { x }"));
+    assert_eq!("Builtin 'split' is not supported by the C backend", message("split(\"a b\", \" \")"));
+    assert_eq!("Slices are not supported by the C backend", message("[1, 2][0..1]"));
+    assert_eq!("Cannot assign twice to immutable variable 'a', declare it with 'mut'", message("a = 1\na = 2"));
+    // the function may be called before the global is declared, so the name is local there
+    assert!(generate("fn f() { x = 1\n x }\nx = f()").is_ok());
+}
@@ -0,0 +1,34 @@
+pub mod c;
+pub mod wat;
+pub mod x86_64;
+#[cfg(test)]
+mod watinterpreter;
+pub use c::generate_c;
+pub use wat::generate_wat;
+pub use x86_64::{ generate_x86_64, link_x86_64 };
+
+/// Builds every program with `build` into the temporary directory of the backend, runs it and checks that it prints
+/// the same output as the interpreter, followed by the runtime error. Does nothing if `build` returns `None`,
+/// e.g. there is no compiler here.
+#[cfg(test)]
+fn assert_same_output_as_interpreted(backend: &str, programs: &[&str], build: impl Fn(&str, &std::path::Path) -> Option<()>) {
+    let directory = std::env::temp_dir().join(format!("avoc-{}-{}", backend, std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    for (index, source) in programs.iter().enumerate() {
+        let mut engine = crate::Engine::new();
+        engine.capture_output();
+        let error = engine.eval(source).err().map(|e| match e {
+            crate::engine::Error::Runtime(e) => format!("error: {} at {}\n", e.message, e.pos),
+            e => panic!("{:?}", e),
+        });
+        let expected = engine.take_output() + &error.unwrap_or_default();
+        let executable = directory.join(format!("program{}", index));
+        if build(source, &executable).is_none() {
+            break
+        }
+        let output = std::process::Command::new(&executable).output().unwrap();
+        let output = String::from_utf8(output.stdout).unwrap() + &String::from_utf8(output.stderr).unwrap();
+        assert_eq!(expected, output, "{}", source);
+    }
+    std::fs::remove_dir_all(directory).unwrap();
+}
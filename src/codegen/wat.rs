@@ -0,0 +1,1293 @@
+use crate::analyzer::{ resolve_assignments, Assignment };
+use crate::dto::{ Node, NodeType, Operator, Token, SyntaxError };
+use crate::runtime::Function;
+use crate::runtime::builtins::{ self, is_builtin };
+use std::collections::{ BTreeSet, HashMap };
+use std::fmt;
+#[cfg(test)]
+use crate::parser::parse;
+
+/// Translates the checked syntax tree of the program to the WebAssembly text format.
+///
+/// WebAssembly values have the static types, so every variable and expression keeps the single type:
+/// `Int` is `i64`, `Float` is `f64`, `Bool` is `i32`, strings and lists are `i32` pointers to the objects
+/// in the linear memory. Parameters of the functions must have the type annotations, the return type
+/// is taken from the annotation or from the body.
+///
+/// The module exports the `memory` and the `main` function with the statements of the root block.
+/// It imports the output functions from the `host` module: `print` writes the string object,
+/// `print_int` and `print_float` write the numbers the same way as `println` does.
+/// `sin`, `cos`, `tan` and `pow` are imported from `host` as well, only if the program uses them.
+/// Runtime errors, e.g. the division by zero or the index out of bounds, are traps.
+///
+/// The backend walks the syntax tree instead of the [`lower`]ed program: the blocks and the branches of the IR
+/// must be turned back into the nested `block`s and `loop`s of WebAssembly first.
+///
+/// [`lower`]: crate::ir::lower
+///
+/// # Errors
+/// Expressions whose type is not known before the execution or changes at runtime, and constructs which are not
+/// supported by the backend: lambdas, tuples, maps, matches, structs, enums, modules and the most of the builtins.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// # use avoc::internals::{ generate_wat, TokenReader, TreeBuilder };
+/// let tokens = TokenReader::new().parse("fn square(x: Int) { x * x }\nprintln(square(7))").unwrap();
+/// let tree = TreeBuilder::new().build_tree(&tokens).unwrap();
+///
+/// assert!(generate_wat(&tree).unwrap().contains("(func $main (export \"main\")"));
+/// ```
+pub fn generate_wat(root: &Node) -> Result<String, Vec<SyntaxError>> {
+    let mut generator = Generator {
+        functions: HashMap::new(),
+        definitions: vec!(),
+        globals: HashMap::new(),
+        global_names: vec!(),
+        strings: HashMap::new(),
+        data: vec!(),
+        imports: BTreeSet::new(),
+        contexts: vec!(),
+        assignments: resolve_assignments(root),
+        counter: 0,
+        errors: vec!(),
+    };
+    // the constants of the runtime have the fixed offsets
+    for text in RUNTIME_STRINGS {
+        generator.string(text);
+    }
+    for statement in root.children.iter().map(Node::declaration) {
+        if statement.node_type == NodeType::Function {
+            generator.functions.insert(name_of(statement).to_string(), (statement, State::Pending));
+        }
+    }
+    generator.contexts.push(Context::new(None));
+    // global functions are compiled when they are called for the first time
+    let statements: Vec<&Node> = root.children.iter().map(Node::declaration).filter(|node| node.node_type != NodeType::Function).collect();
+    let mut body = vec!();
+    for statement in statements {
+        let code = generator.expression(statement);
+        body.push(code.dropped());
+    }
+    let main = generator.contexts.pop().unwrap();
+    let mut names: Vec<&String> = generator.functions.keys().collect();
+    names.sort();
+    let pending: Vec<String> = names.into_iter().filter(|name| matches!(generator.functions[*name].1, State::Pending)).cloned().collect();
+    for name in pending {
+        generator.signature(&name, 0);
+    }
+    if !generator.errors.is_empty() {
+        generator.errors.sort_by_key(|e| e.pos);
+        return Err(generator.errors)
+    }
+    Ok(generator.module(main, body))
+}
+
+/// Static type of the expression.
+#[derive(Debug, Clone, PartialEq)]
+enum Type {
+    None,
+    Bool,
+    Int,
+    Float,
+    Str,
+    List(Box<Type>),
+    /// Type of `return`, `break` and `continue`, the code after them is never executed.
+    Never,
+}
+
+impl Type {
+
+    /// Type of the WebAssembly value, `None` and `Never` have no value.
+    fn wasm(&self) -> Option<&'static str> {
+        match self {
+            Type::Bool | Type::Str | Type::List(_) => Some("i32"),
+            Type::Int => Some("i64"),
+            Type::Float => Some("f64"),
+            Type::None | Type::Never => None,
+        }
+    }
+
+    fn from_annotation(name: &str) -> Option<Type> {
+        match name {
+            "Bool" => Some(Type::Bool),
+            "Int" => Some(Type::Int),
+            "Float" => Some(Type::Float),
+            "String" => Some(Type::Str),
+            "None" => Some(Type::None),
+            _ => None,
+        }
+    }
+
+    /// Code of the list item which is stored as `i64`.
+    fn encode(&self, code: &str) -> String {
+        match self {
+            Type::Float => format!("(i64.reinterpret_f64 {})", code),
+            Type::Int => code.to_string(),
+            _ => format!("(i64.extend_i32_u {})", code),
+        }
+    }
+
+    fn decode(&self, code: &str) -> String {
+        match self {
+            Type::Float => format!("(f64.reinterpret_i64 {})", code),
+            Type::Int => code.to_string(),
+            _ => format!("(i32.wrap_i64 {})", code),
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::None | Type::Never => write!(f, "None"),
+            Type::Bool => write!(f, "Bool"),
+            Type::Int => write!(f, "Int"),
+            Type::Float => write!(f, "Float"),
+            Type::Str => write!(f, "String"),
+            Type::List(item) => write!(f, "List of {}", item),
+        }
+    }
+}
+
+/// Instructions which leave the value of the type on the stack.
+struct Code {
+    text: String,
+    value_type: Type,
+}
+
+impl Code {
+
+    fn new(text: String, value_type: Type) -> Code {
+        Code { text, value_type }
+    }
+
+    fn none(text: String) -> Code {
+        Code { text, value_type: Type::None }
+    }
+
+    /// Instructions of the statement whose value is not used.
+    fn dropped(self) -> String {
+        match self.value_type.wasm() {
+            Some(_) => format!("(drop {})", self.text),
+            None => self.text,
+        }
+    }
+}
+
+enum State {
+    Pending,
+    InProgress,
+    Compiled(Signature),
+}
+
+#[derive(Clone)]
+struct Signature {
+    name: String,
+    parameters: Vec<Type>,
+    result: Type,
+}
+
+#[derive(Clone)]
+struct Variable {
+    /// Name of the local or global, empty for the variables of the type `None` which have no value.
+    name: String,
+    value_type: Type,
+    is_global: bool,
+}
+
+impl Variable {
+
+    fn get(&self) -> Code {
+        match (self.name.is_empty(), self.is_global) {
+            (true, _) => Code::none(String::new()),
+            (false, true) => Code::new(format!("(global.get {})", self.name), self.value_type.clone()),
+            (false, false) => Code::new(format!("(local.get {})", self.name), self.value_type.clone()),
+        }
+    }
+
+    fn set(&self, value: String) -> String {
+        match (self.name.is_empty(), self.is_global) {
+            (true, _) => value,
+            (false, true) => format!("(global.set {} {})", self.name, value),
+            (false, false) => format!("(local.set {} {})", self.name, value),
+        }
+    }
+}
+
+/// Function which is being compiled.
+struct Context {
+    /// Locals besides the parameters.
+    locals: Vec<(String, &'static str)>,
+    scopes: Vec<HashMap<String, Variable>>,
+    /// Labels of `break` and `continue` of the enclosing loops.
+    loops: Vec<(String, String)>,
+    /// Declared return type, `None` for `main` and the functions without the annotation.
+    result: Option<Type>,
+    /// Types of the values of the `return` statements.
+    returns: Vec<(Type, usize)>,
+}
+
+impl Context {
+
+    fn new(result: Option<Type>) -> Context {
+        Context { locals: vec!(), scopes: vec!(HashMap::new()), loops: vec!(), result, returns: vec!() }
+    }
+}
+
+struct Generator<'a> {
+    functions: HashMap<String, (&'a Node, State)>,
+    definitions: Vec<String>,
+    globals: HashMap<String, Variable>,
+    global_names: Vec<String>,
+    /// Offsets of the string constants in the linear memory.
+    strings: HashMap<String, usize>,
+    data: Vec<u8>,
+    /// Math functions of the host used by the program.
+    imports: BTreeSet<&'static str>,
+    contexts: Vec<Context>,
+    assignments: HashMap<usize, Assignment>,
+    counter: usize,
+    errors: Vec<SyntaxError>,
+}
+
+/// Strings used by the runtime functions, their offsets are the constants of the runtime.
+const RUNTIME_STRINGS: [&str; 9] = [" ", "\n", "None", "true", "false", "[", ", ", "]", "-"];
+
+/// Functions of the module which implement the operations on the objects in the linear memory.
+///
+/// Strings are the length of the UTF-8 bytes followed by the bytes. Lists are the length, the capacity
+/// and the pointer to the items, every item takes 8 bytes.
+const RUNTIME: &str = r#"  (func $alloc (param $size i32) (result i32)
+    (local $pointer i32)
+    (local.set $pointer (global.get $heap))
+    (global.set $heap (i32.and (i32.add (i32.add (global.get $heap) (local.get $size)) (i32.const 7)) (i32.const -8)))
+    (if (i32.gt_u (global.get $heap) (i32.mul (memory.size) (i32.const 65536)))
+      (then (drop (memory.grow (i32.add (i32.shr_u (i32.sub (global.get $heap) (i32.mul (memory.size) (i32.const 65536))) (i32.const 16)) (i32.const 1))))))
+    (local.get $pointer))
+  (func $copy (param $to i32) (param $from i32) (param $count i32)
+    (block $done
+      (loop $next
+        (br_if $done (i32.eqz (local.get $count)))
+        (i32.store8 (local.get $to) (i32.load8_u (local.get $from)))
+        (local.set $to (i32.add (local.get $to) (i32.const 1)))
+        (local.set $from (i32.add (local.get $from) (i32.const 1)))
+        (local.set $count (i32.sub (local.get $count) (i32.const 1)))
+        (br $next))))
+  (func $concat (param $lhs i32) (param $rhs i32) (result i32)
+    (local $result i32)
+    (local.set $result (call $alloc (i32.add (i32.const 4) (i32.add (i32.load (local.get $lhs)) (i32.load (local.get $rhs))))))
+    (i32.store (local.get $result) (i32.add (i32.load (local.get $lhs)) (i32.load (local.get $rhs))))
+    (call $copy (i32.add (local.get $result) (i32.const 4)) (i32.add (local.get $lhs) (i32.const 4)) (i32.load (local.get $lhs)))
+    (call $copy (i32.add (i32.add (local.get $result) (i32.const 4)) (i32.load (local.get $lhs))) (i32.add (local.get $rhs) (i32.const 4)) (i32.load (local.get $rhs)))
+    (local.get $result))
+  (func $string_equal (param $lhs i32) (param $rhs i32) (result i32)
+    (local $index i32)
+    (if (i32.ne (i32.load (local.get $lhs)) (i32.load (local.get $rhs)))
+      (then (return (i32.const 0))))
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $index) (i32.load (local.get $lhs))))
+        (if (i32.ne (i32.load8_u offset=4 (i32.add (local.get $lhs) (local.get $index))) (i32.load8_u offset=4 (i32.add (local.get $rhs) (local.get $index))))
+          (then (return (i32.const 0))))
+        (local.set $index (i32.add (local.get $index) (i32.const 1)))
+        (br $next)))
+    (i32.const 1))
+  (func $string_length (param $string i32) (result i64)
+    (local $index i32)
+    (local $count i64)
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $index) (i32.load (local.get $string))))
+        (if (i32.ne (i32.and (i32.load8_u offset=4 (i32.add (local.get $string) (local.get $index))) (i32.const 192)) (i32.const 128))
+          (then (local.set $count (i64.add (local.get $count) (i64.const 1)))))
+        (local.set $index (i32.add (local.get $index) (i32.const 1)))
+        (br $next)))
+    (local.get $count))
+  (func $int_to_string (param $value i64) (result i32)
+    (local $digits i32)
+    (local $index i32)
+    (local $digit i64)
+    (local $result i32)
+    (local.set $digits (call $alloc (i32.const 20)))
+    (local.set $index (i32.const 20))
+    (loop $next
+      (local.set $digit (i64.rem_s (local.get $value) (i64.const 10)))
+      (if (i64.lt_s (local.get $digit) (i64.const 0))
+        (then (local.set $digit (i64.sub (i64.const 0) (local.get $digit)))))
+      (local.set $index (i32.sub (local.get $index) (i32.const 1)))
+      (i32.store8 (i32.add (local.get $digits) (local.get $index)) (i32.add (i32.const 48) (i32.wrap_i64 (local.get $digit))))
+      (local.set $value (i64.div_s (local.get $value) (i64.const 10)))
+      (br_if $next (i64.ne (local.get $value) (i64.const 0))))
+    (local.set $result (call $alloc (i32.sub (i32.const 24) (local.get $index))))
+    (i32.store (local.get $result) (i32.sub (i32.const 20) (local.get $index)))
+    (call $copy (i32.add (local.get $result) (i32.const 4)) (i32.add (local.get $digits) (local.get $index)) (i32.sub (i32.const 20) (local.get $index)))
+    (local.get $result))
+  (func $signed_int_to_string (param $value i64) (result i32)
+    (if (result i32) (i64.lt_s (local.get $value) (i64.const 0))
+      (then (call $concat (global.get $minus) (call $int_to_string (local.get $value))))
+      (else (call $int_to_string (local.get $value)))))
+  (func $add_int (param $lhs i64) (param $rhs i64) (result i64)
+    (local $result i64)
+    (local.set $result (i64.add (local.get $lhs) (local.get $rhs)))
+    (if (i64.lt_s (i64.and (i64.xor (local.get $lhs) (local.get $result)) (i64.xor (local.get $rhs) (local.get $result))) (i64.const 0))
+      (then (unreachable)))
+    (local.get $result))
+  (func $sub_int (param $lhs i64) (param $rhs i64) (result i64)
+    (local $result i64)
+    (local.set $result (i64.sub (local.get $lhs) (local.get $rhs)))
+    (if (i64.lt_s (i64.and (i64.xor (local.get $lhs) (local.get $rhs)) (i64.xor (local.get $lhs) (local.get $result))) (i64.const 0))
+      (then (unreachable)))
+    (local.get $result))
+  (func $mul_int (param $lhs i64) (param $rhs i64) (result i64)
+    (local $result i64)
+    (local.set $result (i64.mul (local.get $lhs) (local.get $rhs)))
+    (if (i32.and (i64.ne (local.get $lhs) (i64.const 0))
+        (i32.or (i64.ne (i64.div_s (local.get $result) (local.get $lhs)) (local.get $rhs))
+          (i32.and (i64.eq (local.get $lhs) (i64.const -1)) (i64.eq (local.get $rhs) (i64.const 0x8000000000000000)))))
+      (then (unreachable)))
+    (local.get $result))
+  (func $pow_int (param $base i64) (param $exponent i64) (result i64)
+    (local $result i64)
+    (if (i64.lt_s (local.get $exponent) (i64.const 0))
+      (then (unreachable)))
+    (local.set $result (i64.const 1))
+    (block $done
+      (loop $next
+        (br_if $done (i64.eqz (local.get $exponent)))
+        (local.set $result (call $mul_int (local.get $result) (local.get $base)))
+        (local.set $exponent (i64.sub (local.get $exponent) (i64.const 1)))
+        (br $next)))
+    (local.get $result))
+  (func $abs_int (param $value i64) (result i64)
+    (if (result i64) (i64.lt_s (local.get $value) (i64.const 0))
+      (then (call $sub_int (i64.const 0) (local.get $value)))
+      (else (local.get $value))))
+  (func $round (param $value f64) (result f64)
+    (local $whole f64)
+    (local.set $whole (f64.trunc (local.get $value)))
+    (if (result f64) (f64.ge (f64.abs (f64.sub (local.get $value) (local.get $whole))) (f64.const 0.5))
+      (then (f64.add (local.get $whole) (f64.copysign (f64.const 1) (local.get $value))))
+      (else (local.get $whole))))
+  (func $list_new (param $capacity i32) (result i32)
+    (local $list i32)
+    (local.set $list (call $alloc (i32.const 12)))
+    (i32.store (local.get $list) (i32.const 0))
+    (i32.store offset=4 (local.get $list) (local.get $capacity))
+    (i32.store offset=8 (local.get $list) (call $alloc (i32.mul (local.get $capacity) (i32.const 8))))
+    (local.get $list))
+  (func $list_push (param $list i32) (param $item i64)
+    (local $items i32)
+    (if (i32.eq (i32.load (local.get $list)) (i32.load offset=4 (local.get $list)))
+      (then
+        (local.set $items (call $alloc (i32.add (i32.mul (i32.load offset=4 (local.get $list)) (i32.const 16)) (i32.const 32))))
+        (call $copy (local.get $items) (i32.load offset=8 (local.get $list)) (i32.mul (i32.load (local.get $list)) (i32.const 8)))
+        (i32.store offset=4 (local.get $list) (i32.add (i32.mul (i32.load offset=4 (local.get $list)) (i32.const 2)) (i32.const 4)))
+        (i32.store offset=8 (local.get $list) (local.get $items))))
+    (i64.store (i32.add (i32.load offset=8 (local.get $list)) (i32.mul (i32.load (local.get $list)) (i32.const 8))) (local.get $item))
+    (i32.store (local.get $list) (i32.add (i32.load (local.get $list)) (i32.const 1))))
+  (func $list_item (param $list i32) (param $index i64) (result i32)
+    (if (i64.ge_u (local.get $index) (i64.extend_i32_u (i32.load (local.get $list))))
+      (then (unreachable)))
+    (i32.add (i32.load offset=8 (local.get $list)) (i32.mul (i32.wrap_i64 (local.get $index)) (i32.const 8))))
+  (func $list_get (param $list i32) (param $index i64) (result i64)
+    (i64.load (call $list_item (local.get $list) (local.get $index))))
+  (func $list_set (param $list i32) (param $index i64) (param $item i64)
+    (i64.store (call $list_item (local.get $list) (local.get $index)) (local.get $item)))
+  (func $print_bool (param $value i32)
+    (call $print (select (global.get $true) (global.get $false) (local.get $value))))
+  (func $print_list (param $list i32) (param $kind i32)
+    (local $index i32)
+    (local $item i64)
+    (call $print (global.get $open))
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $index) (i32.load (local.get $list))))
+        (if (local.get $index)
+          (then (call $print (global.get $comma))))
+        (local.set $item (i64.load (i32.add (i32.load offset=8 (local.get $list)) (i32.mul (local.get $index) (i32.const 8)))))
+        (if (i32.eqz (local.get $kind))
+          (then (call $print_int (local.get $item)))
+          (else (if (i32.eq (local.get $kind) (i32.const 1))
+            (then (call $print_float (f64.reinterpret_i64 (local.get $item))))
+            (else (call $print_bool (i32.wrap_i64 (local.get $item)))))))
+        (local.set $index (i32.add (local.get $index) (i32.const 1)))
+        (br $next)))
+    (call $print (global.get $close)))
+"#;
+
+impl<'a> Generator<'a> {
+
+    /// Assembles the module from the compiled functions and the data of the strings.
+    fn module(&self, main: Context, body: Vec<String>) -> String {
+        let mut module = String::from("(module\n");
+        module.push_str("  (import \"host\" \"print\" (func $print (param i32)))\n");
+        module.push_str("  (import \"host\" \"print_int\" (func $print_int (param i64)))\n");
+        module.push_str("  (import \"host\" \"print_float\" (func $print_float (param f64)))\n");
+        for name in &self.imports {
+            let parameters = if *name == "pow" { "(param f64 f64)" } else { "(param f64)" };
+            module.push_str(&format!("  (import \"host\" \"{0}\" (func ${0} {1} (result f64)))\n", name, parameters));
+        }
+        module.push_str("  (memory (export \"memory\") 1)\n");
+        let heap = self.data.len().div_ceil(8) * 8;
+        module.push_str(&format!("  (global $heap (mut i32) (i32.const {}))\n", heap));
+        let constants = ["space", "newline", "none", "true", "false", "open", "comma", "close", "minus"];
+        for (name, text) in constants.iter().zip(RUNTIME_STRINGS.iter()) {
+            module.push_str(&format!("  (global ${} i32 (i32.const {}))\n", name, self.strings[*text]));
+        }
+        for name in &self.global_names {
+            let global = &self.globals[name];
+            if let Some(wasm) = global.value_type.wasm() {
+                module.push_str(&format!("  (global {} (mut {}) ({}.const 0))\n", global.name, wasm, wasm));
+            }
+        }
+        module.push_str(&format!("  (data (i32.const 0) \"{}\")\n", escape(&self.data)));
+        module.push_str(RUNTIME);
+        for definition in &self.definitions {
+            module.push_str(definition);
+        }
+        module.push_str("  (func $main (export \"main\")\n");
+        for (name, wasm) in &main.locals {
+            module.push_str(&format!("    (local {} {})\n", name, wasm));
+        }
+        for statement in body {
+            module.push_str(&indent(&statement, 4));
+        }
+        module.push_str("  )\n)\n");
+        module
+    }
+
+    /// Returns the offset of the string object with the text, the same texts share the object.
+    fn string(&mut self, text: &str) -> usize {
+        if let Some(offset) = self.strings.get(text) {
+            return *offset
+        }
+        while !self.data.len().is_multiple_of(4) {
+            self.data.push(0);
+        }
+        let offset = self.data.len();
+        self.data.extend_from_slice(&(text.len() as u32).to_le_bytes());
+        self.data.extend_from_slice(text.as_bytes());
+        self.strings.insert(text.to_string(), offset);
+        offset
+    }
+
+    /// Creates the name which is not used by the other locals, globals and labels.
+    fn unique(&mut self, prefix: &str, name: &str) -> String {
+        self.counter += 1;
+        match name {
+            "" => format!("${}{}", prefix, self.counter),
+            _ => format!("${}{}_{}", prefix, self.counter, name.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect::<String>()),
+        }
+    }
+
+    fn context(&mut self) -> &mut Context {
+        self.contexts.last_mut().unwrap()
+    }
+
+    /// Declares the local of the current function.
+    fn local(&mut self, name: &str, value_type: &Type) -> String {
+        let local = self.unique("v", name);
+        if let Some(wasm) = value_type.wasm() {
+            self.context().locals.push((local.clone(), wasm));
+        }
+        local
+    }
+
+    fn error(&mut self, pos: usize, message: String) -> Code {
+        self.errors.push(SyntaxError::new(pos, message));
+        Code::none(String::new())
+    }
+
+    fn unsupported(&mut self, node: &Node, what: &str) -> Code {
+        self.error(node.pos(), format!("{} are not supported by the WebAssembly backend", what))
+    }
+
+    fn lookup(&self, name: &str) -> Option<Variable> {
+        let context = self.contexts.last().unwrap();
+        context.scopes.iter().rev().find_map(|scope| scope.get(name)).or_else(|| self.globals.get(name)).cloned()
+    }
+
+    fn is_global_scope(&self) -> bool {
+        self.contexts.len() == 1 && self.contexts[0].scopes.len() == 1
+    }
+
+    /// Declares the variable in the current scope, its type is the type of the value.
+    fn declare(&mut self, name: &str, value: Code) -> Code {
+        let is_global = self.is_global_scope();
+        let variable_name = match (value.value_type.wasm(), is_global) {
+            (None, _) => String::new(),
+            (Some(_), true) => {
+                self.global_names.push(name.to_string());
+                self.unique("g", name)
+            },
+            (Some(_), false) => self.local(name, &value.value_type),
+        };
+        let variable = Variable { name: variable_name, value_type: value.value_type.clone(), is_global };
+        match is_global {
+            true => self.globals.insert(name.to_string(), variable.clone()),
+            false => self.context().scopes.last_mut().unwrap().insert(name.to_string(), variable.clone()),
+        };
+        Code::none(variable.set(value.text))
+    }
+
+    fn expression(&mut self, node: &Node) -> Code {
+        match node.node_type {
+            NodeType::Token => self.token(node),
+            NodeType::Expression => self.operation(node),
+            NodeType::Block => {
+                self.context().scopes.push(HashMap::new());
+                let code = self.statements(&node.children);
+                self.context().scopes.pop();
+                code
+            },
+            NodeType::If => self.if_expression(node),
+            NodeType::Call => self.call(node),
+            NodeType::For => self.for_loop(node),
+            NodeType::Loop => {
+                let (exit, next) = (self.unique("b", ""), self.unique("l", ""));
+                self.context().loops.push((exit.clone(), next.clone()));
+                let body = self.expression(&node.children[0]).dropped();
+                self.context().loops.pop();
+                let code = format!("(block {}\n  (loop {}\n{}    (br {})))", exit, next, indent(&body, 4), next);
+                match has_break(&node.children[0]) {
+                    true => Code::none(code),
+                    false => Code::new(format!("{}\n(unreachable)", code), Type::Never),
+                }
+            },
+            NodeType::Break | NodeType::Continue => match self.context().loops.last().cloned() {
+                Some((exit, next)) => {
+                    let label = if node.node_type == NodeType::Break { exit } else { next };
+                    Code::new(format!("(br {})", label), Type::Never)
+                },
+                None => self.error(node.pos(), String::from("Loop control statement outside of the loop")),
+            },
+            NodeType::Return => {
+                let value = match node.children.first() {
+                    Some(value) => self.expression(value),
+                    None => Code::none(String::new()),
+                };
+                let value = match self.context().result.clone() {
+                    Some(result) => self.convert(value, &result, node.pos(), "Returned value"),
+                    None => {
+                        let value_type = value.value_type.clone();
+                        self.context().returns.push((value_type, node.pos()));
+                        value.text
+                    },
+                };
+                Code::new(format!("(return {})", value).replace(" )", ")"), Type::Never)
+            },
+            NodeType::List => self.list(node),
+            NodeType::Public => self.expression(&node.children[0]),
+            NodeType::Function => self.unsupported(node, "Nested functions"),
+            NodeType::Lambda => self.unsupported(node, "Lambdas"),
+            NodeType::Range => self.unsupported(node, "Ranges outside of the for loops"),
+            NodeType::Match => self.unsupported(node, "Match expressions"),
+            NodeType::Tuple => self.unsupported(node, "Tuples"),
+            NodeType::Map => self.unsupported(node, "Maps"),
+            NodeType::Struct => self.unsupported(node, "Structs"),
+            NodeType::Enum | NodeType::Variant => self.unsupported(node, "Enums"),
+            NodeType::Import => self.unsupported(node, "Modules"),
+            NodeType::Test => Code::none(String::new()),
+            NodeType::Arm | NodeType::Rest => self.error(node.pos(), String::from("Pattern can be used only in the match arm")),
+            NodeType::Type => self.error(node.pos(), String::from("Type can be used only in the annotation")),
+        }
+    }
+
+    /// Statements of the block, the value is the value of the last one.
+    fn statements(&mut self, statements: &[Node]) -> Code {
+        let mut lines = vec!();
+        let mut value_type = Type::None;
+        for (index, statement) in statements.iter().enumerate() {
+            let code = self.expression(statement);
+            if code.value_type == Type::Never {
+                value_type = Type::Never
+            } else if index + 1 == statements.len() {
+                value_type = code.value_type.clone()
+            }
+            match index + 1 == statements.len() {
+                true => lines.push(code.text),
+                false => lines.push(code.dropped()),
+            }
+        }
+        // the statements after `return` are never executed
+        if value_type == Type::Never && statements.last().map(|last| !matches!(last.node_type, NodeType::Return | NodeType::Break | NodeType::Continue)).unwrap_or(false) {
+            lines.push(String::from("(unreachable)"))
+        }
+        Code::new(lines.into_iter().filter(|line| !line.is_empty()).collect::<Vec<String>>().join("\n"), value_type)
+    }
+
+    fn token(&mut self, node: &Node) -> Code {
+        match &node.data {
+            Some(Token::IntConstant { value, .. }) => Code::new(format!("(i64.const {})", value), Type::Int),
+            Some(Token::FloatConstant { value, .. }) => Code::new(format!("(f64.const {})", float(*value)), Type::Float),
+            Some(Token::BoolConstant { value, .. }) => Code::new(format!("(i32.const {})", *value as u8), Type::Bool),
+            Some(Token::StringConstant { value, .. }) => Code::new(format!("(i32.const {})", self.string(value)), Type::Str),
+            Some(Token::NoneConstant { .. }) => Code::none(String::new()),
+            Some(Token::Identifier { name, .. }) => match self.lookup(name) {
+                Some(variable) => variable.get(),
+                None => self.error(node.pos(), format!("Undefined variable '{}'", name)),
+            },
+            _ => self.error(node.pos(), String::from("Unexpected token")),
+        }
+    }
+
+    /// Converts the value to the type, the only conversion is `Int` to `Float`.
+    fn convert(&mut self, value: Code, to: &Type, pos: usize, what: &str) -> String {
+        match (&value.value_type, to) {
+            (from, to) if from == to => value.text,
+            (Type::Never, _) => value.text,
+            (Type::Int, Type::Float) => format!("(f64.convert_i64_s {})", value.text),
+            (from, to) => {
+                self.error(pos, format!("{} must be {}, found {}", what, to, from));
+                String::new()
+            },
+        }
+    }
+
+    fn condition(&mut self, node: &Node) -> String {
+        let code = self.expression(node);
+        self.convert(code, &Type::Bool, node.pos(), "Condition")
+    }
+
+    fn if_expression(&mut self, node: &Node) -> Code {
+        let condition = self.condition(&node.condition[0]);
+        let then = self.expression(&node.children[0]);
+        let otherwise = match node.children.get(1) {
+            Some(otherwise) => self.expression(otherwise),
+            None => Code::none(String::new()),
+        };
+        let value_type = match (&then.value_type, &otherwise.value_type) {
+            (a, b) if a == b => a.clone(),
+            (Type::Never, other) | (other, Type::Never) => other.clone(),
+            (Type::Int, Type::Float) | (Type::Float, Type::Int) => Type::Float,
+            _ => Type::None,
+        };
+        let (then, otherwise) = match value_type {
+            Type::None | Type::Never => (then.dropped(), otherwise.dropped()),
+            _ => {
+                let pos = node.pos();
+                (self.convert(then, &value_type, pos, "Branch"), self.convert(otherwise, &value_type, pos, "Branch"))
+            },
+        };
+        let result = match value_type.wasm() {
+            Some(wasm) => format!(" (result {})", wasm),
+            None => String::new(),
+        };
+        let mut code = format!("(if{} {}\n  (then\n{}  )", result, condition, indent(&then, 4));
+        if !otherwise.is_empty() {
+            code.push_str(&format!("\n  (else\n{}  )", indent(&otherwise, 4)));
+        }
+        code.push(')');
+        if value_type == Type::Never {
+            code.push_str("\n(unreachable)")
+        }
+        Code::new(code, value_type)
+    }
+
+    /// `for` loops iterate over the ranges of integers with the constant step and over the lists.
+    fn for_loop(&mut self, node: &Node) -> Code {
+        let iterable = &node.condition[0];
+        let (exit, next, skip) = (self.unique("b", ""), self.unique("l", ""), self.unique("k", ""));
+        let name = name_of(&node.children[0]);
+        let mut setup = vec!();
+        let (check, item, advance, item_type) = match (iterable.node_type, iterable.operator()) {
+            (NodeType::Range, operator) => {
+                let counter = self.local("counter", &Type::Int);
+                let start = self.expression(&iterable.children[0]);
+                if start.value_type == Type::None {
+                    return self.error(iterable.pos(), String::from("Range without start can't be iterated"))
+                }
+                let start = self.convert(start, &Type::Int, iterable.pos(), "Range bound");
+                setup.push(format!("(local.set {} {})", counter, start));
+                let step = match (&iterable.children[2].data, &iterable.children[2].children.first().and_then(|operand| operand.data.as_ref())) {
+                    (Some(Token::IntConstant { value, .. }), _) => *value,
+                    (Some(Token::NoneConstant { .. }), _) => 1,
+                    (Some(Token::Operator { payload: Operator::UnaryMinus, .. }), Some(Token::IntConstant { value, .. })) => -*value,
+                    _ => return self.error(iterable.children[2].pos(), String::from("Range step must be the Int constant for the WebAssembly backend")),
+                };
+                if step == 0 {
+                    return self.error(iterable.pos(), String::from("Range step can't be zero"))
+                }
+                let end = self.expression(&iterable.children[1]);
+                let check = match end.value_type {
+                    Type::None => String::from("(i32.const 0)"),
+                    _ => {
+                        let end = self.convert(end, &Type::Int, iterable.pos(), "Range bound");
+                        let bound = self.local("end", &Type::Int);
+                        setup.push(format!("(local.set {} {})", bound, end));
+                        let is_inclusive = operator != Some(Operator::Range);
+                        let comparison = match (step > 0, is_inclusive) {
+                            (true, true) => "gt_s",
+                            (true, false) => "ge_s",
+                            (false, true) => "lt_s",
+                            (false, false) => "le_s",
+                        };
+                        format!("(i64.{} (local.get {}) (local.get {}))", comparison, counter, bound)
+                    },
+                };
+                let advance = format!("(local.set {0} (i64.add (local.get {0}) (i64.const {1})))", counter, step);
+                (check, format!("(local.get {})", counter), advance, Type::Int)
+            },
+            _ => {
+                let list = self.expression(iterable);
+                let item_type = match &list.value_type {
+                    Type::List(item_type) => item_type.as_ref().clone(),
+                    other => return self.error(iterable.pos(), format!("{} is not iterable by the WebAssembly backend", other)),
+                };
+                let (pointer, index, length) = (self.local("list", &Type::Str), self.local("index", &Type::Int), self.local("length", &Type::Int));
+                setup.push(format!("(local.set {} {})", pointer, list.text));
+                setup.push(format!("(local.set {} (i64.extend_i32_u (i32.load (local.get {}))))", length, pointer));
+                let check = format!("(i64.ge_u (local.get {}) (local.get {}))", index, length);
+                let item = item_type.decode(&format!("(call $list_get (local.get {}) (local.get {}))", pointer, index));
+                let advance = format!("(local.set {0} (i64.add (local.get {0}) (i64.const 1)))", index);
+                (check, item, advance, item_type)
+            },
+        };
+        self.context().scopes.push(HashMap::new());
+        let variable = self.local(name, &item_type);
+        self.context().scopes.last_mut().unwrap().insert(name.to_string(), Variable { name: variable.clone(), value_type: item_type, is_global: false });
+        self.context().loops.push((exit.clone(), skip.clone()));
+        let body = self.expression(&node.children[1]).dropped();
+        self.context().loops.pop();
+        self.context().scopes.pop();
+        let mut code = setup.join("\n");
+        code.push_str(&format!("\n(block {}\n  (loop {}\n    (br_if {} {})\n    (local.set {} {})\n", exit, next, exit, check, variable, item));
+        code.push_str(&format!("    (block {}\n{}    )\n    {}\n    (br {})))", skip, indent(&body, 6), advance, next));
+        Code::none(code)
+    }
+
+    fn list(&mut self, node: &Node) -> Code {
+        let items: Vec<Code> = node.children.iter().map(|item| self.expression(item)).collect();
+        let item_type = match items.first().map(|item| item.value_type.clone()) {
+            Some(item_type @ Type::Int) | Some(item_type @ Type::Float) | Some(item_type @ Type::Bool) => item_type,
+            Some(other) => return self.error(node.pos(), format!("Lists of {} are not supported by the WebAssembly backend", other)),
+            None => return self.error(node.pos(), String::from("Type of the empty list is not known, the WebAssembly backend needs at least one item")),
+        };
+        let list = self.local("list", &Type::Str);
+        let mut code = format!("(local.set {} (call $list_new (i32.const {})))", list, items.len());
+        for (item, node) in items.into_iter().zip(node.children.iter()) {
+            if item.value_type != item_type {
+                return self.error(node.pos(), format!("Items of the list must have the same type for the WebAssembly backend, found {} and {}", item_type, item.value_type))
+            }
+            code.push_str(&format!("\n(call $list_push (local.get {}) {})", list, item_type.encode(&item.text)));
+        }
+        code.push_str(&format!("\n(local.get {})", list));
+        Code::new(code, Type::List(Box::new(item_type)))
+    }
+
+    fn operation(&mut self, node: &Node) -> Code {
+        let operator = match node.operator() {
+            Some(operator) => operator,
+            None => return self.error(node.pos(), String::from("Unexpected token")),
+        };
+        match operator {
+            _ if operator.is_assignment() => self.assignment(operator, node),
+            Operator::Mut => self.error(node.pos(), String::from("Mutable variable must be declared with assignment: mut name = value")),
+            Operator::Dot => match node.children[1].node_type {
+                NodeType::Call => {
+                    let method = &node.children[1];
+                    let mut arguments = vec!(self.expression(&node.children[0]));
+                    arguments.extend(method.children.iter().map(|argument| self.expression(argument)));
+                    self.builtin(name_of(method), arguments, method.pos())
+                },
+                _ => self.unsupported(node, "Fields, methods of the modules and variants of the enums"),
+            },
+            Operator::LeftBracket if node.children[1].node_type == NodeType::Range => self.unsupported(node, "Slices"),
+            Operator::LeftBracket => {
+                let list = self.expression(&node.children[0]);
+                let index = self.expression(&node.children[1]);
+                let index = self.convert(index, &Type::Int, node.children[1].pos(), "List index");
+                match list.value_type {
+                    Type::List(item_type) => {
+                        let item = item_type.decode(&format!("(call $list_get {} {})", list.text, index));
+                        Code::new(item, *item_type)
+                    },
+                    other => self.error(node.pos(), format!("{} can't be indexed by the WebAssembly backend", other)),
+                }
+            },
+            // the type of the value is known before the execution
+            Operator::Is => {
+                let value = self.expression(&node.children[0]);
+                let is_instance = match (&node.children[1].data, &value.value_type) {
+                    (Some(Token::NoneConstant { .. }), value_type) => *value_type == Type::None,
+                    (Some(Token::Identifier { name, .. }), value_type) => match name.as_str() {
+                        "Any" => true,
+                        "Number" => matches!(value_type, Type::Int | Type::Float),
+                        "List" => matches!(value_type, Type::List(_)),
+                        name => Type::from_annotation(name).as_ref() == Some(value_type),
+                    },
+                    _ => return self.unsupported(node, "Variants of the enums"),
+                };
+                Code::new(format!("{}\n(i32.const {})", value.dropped(), is_instance as u8).trim_start().to_string(), Type::Bool)
+            },
+            // the right operand is evaluated only if the result is not known from the left one
+            Operator::And | Operator::Or => {
+                let lhs = self.condition(&node.children[0]);
+                let rhs = self.condition(&node.children[1]);
+                let code = match operator {
+                    Operator::And => format!("(if (result i32) {} (then {}) (else (i32.const 0)))", lhs, rhs),
+                    _ => format!("(if (result i32) {} (then (i32.const 1)) (else {}))", lhs, rhs),
+                };
+                Code::new(code, Type::Bool)
+            },
+            Operator::Not => {
+                let operand = self.condition(&node.children[0]);
+                Code::new(format!("(i32.eqz {})", operand), Type::Bool)
+            },
+            Operator::UnaryMinus => {
+                let operand = self.expression(&node.children[0]);
+                match operand.value_type {
+                    Type::Int => Code::new(format!("(call $sub_int (i64.const 0) {})", operand.text), Type::Int),
+                    Type::Float => Code::new(format!("(f64.neg {})", operand.text), Type::Float),
+                    other => self.error(node.pos(), format!("Operator '-' can't be applied to {}", other)),
+                }
+            },
+            _ => {
+                let lhs = self.expression(&node.children[0]);
+                let rhs = self.expression(&node.children[1]);
+                self.binary(operator, lhs, rhs, node.pos())
+            },
+        }
+    }
+
+    fn binary(&mut self, operator: Operator, lhs: Code, rhs: Code, pos: usize) -> Code {
+        let (lhs_type, rhs_type) = (lhs.value_type.clone(), rhs.value_type.clone());
+        let instruction = |operator: &str, value_type: &str| format!("({}.{} {} {})", value_type, operator, lhs.text, rhs.text);
+        match (operator, &lhs_type, &rhs_type) {
+            (Operator::Plus, Type::Str, _) | (Operator::Plus, _, Type::Str) => {
+                let lhs = self.stringify(lhs, pos);
+                let rhs = self.stringify(rhs, pos);
+                Code::new(format!("(call $concat {} {})", lhs, rhs), Type::Str)
+            },
+            (Operator::Plus, Type::Int, Type::Int) => Code::new(format!("(call $add_int {} {})", lhs.text, rhs.text), Type::Int),
+            (Operator::Minus, Type::Int, Type::Int) => Code::new(format!("(call $sub_int {} {})", lhs.text, rhs.text), Type::Int),
+            (Operator::Multiply, Type::Int, Type::Int) => Code::new(format!("(call $mul_int {} {})", lhs.text, rhs.text), Type::Int),
+            (Operator::Divide, Type::Int, Type::Int) => Code::new(instruction("div_s", "i64"), Type::Int),
+            (Operator::Power, Type::Int, Type::Int) => Code::new(format!("(call $pow_int {} {})", lhs.text, rhs.text), Type::Int),
+            (Operator::Equal, Type::Int, Type::Int) => Code::new(instruction("eq", "i64"), Type::Bool),
+            (Operator::NotEqual, Type::Int, Type::Int) => Code::new(instruction("ne", "i64"), Type::Bool),
+            (Operator::Less, Type::Int, Type::Int) => Code::new(instruction("lt_s", "i64"), Type::Bool),
+            (Operator::LessOrEqual, Type::Int, Type::Int) => Code::new(instruction("le_s", "i64"), Type::Bool),
+            (Operator::Greater, Type::Int, Type::Int) => Code::new(instruction("gt_s", "i64"), Type::Bool),
+            (Operator::GreaterOrEqual, Type::Int, Type::Int) => Code::new(instruction("ge_s", "i64"), Type::Bool),
+            (Operator::Equal, Type::Bool, Type::Bool) => Code::new(instruction("eq", "i32"), Type::Bool),
+            (Operator::NotEqual, Type::Bool, Type::Bool) | (Operator::Xor, Type::Bool, Type::Bool) => Code::new(instruction("ne", "i32"), Type::Bool),
+            (Operator::Less, Type::Bool, Type::Bool) => Code::new(instruction("lt_u", "i32"), Type::Bool),
+            (Operator::LessOrEqual, Type::Bool, Type::Bool) => Code::new(instruction("le_u", "i32"), Type::Bool),
+            (Operator::Greater, Type::Bool, Type::Bool) => Code::new(instruction("gt_u", "i32"), Type::Bool),
+            (Operator::GreaterOrEqual, Type::Bool, Type::Bool) => Code::new(instruction("ge_u", "i32"), Type::Bool),
+            (Operator::Equal, Type::Str, Type::Str) => Code::new(format!("(call $string_equal {} {})", lhs.text, rhs.text), Type::Bool),
+            (Operator::NotEqual, Type::Str, Type::Str) => Code::new(format!("(i32.eqz (call $string_equal {} {}))", lhs.text, rhs.text), Type::Bool),
+            (_, Type::Int, Type::Float) | (_, Type::Float, Type::Int) | (_, Type::Float, Type::Float) => {
+                let (lhs, rhs) = (self.convert(lhs, &Type::Float, pos, "Operand"), self.convert(rhs, &Type::Float, pos, "Operand"));
+                let instruction = |operator: &str| format!("(f64.{} {} {})", operator, lhs, rhs);
+                match operator {
+                    Operator::Plus => Code::new(instruction("add"), Type::Float),
+                    Operator::Minus => Code::new(instruction("sub"), Type::Float),
+                    Operator::Multiply => Code::new(instruction("mul"), Type::Float),
+                    Operator::Divide => Code::new(instruction("div"), Type::Float),
+                    Operator::Power => {
+                        self.imports.insert("pow");
+                        Code::new(format!("(call $pow {} {})", lhs, rhs), Type::Float)
+                    },
+                    Operator::Equal => Code::new(instruction("eq"), Type::Bool),
+                    Operator::NotEqual => Code::new(instruction("ne"), Type::Bool),
+                    Operator::Less => Code::new(instruction("lt"), Type::Bool),
+                    Operator::LessOrEqual => Code::new(instruction("le"), Type::Bool),
+                    Operator::Greater => Code::new(instruction("gt"), Type::Bool),
+                    Operator::GreaterOrEqual => Code::new(instruction("ge"), Type::Bool),
+                    _ => self.error(pos, format!("Operator '{}' can't be applied to {} and {}", operator, lhs_type, rhs_type)),
+                }
+            },
+            // values of the different types are never equal
+            (Operator::Equal, _, _) | (Operator::NotEqual, _, _) if lhs_type != rhs_type && !matches!(lhs_type, Type::List(_)) && !matches!(rhs_type, Type::List(_)) => {
+                let result = (operator == Operator::NotEqual) as u8;
+                let code = [lhs.dropped(), rhs.dropped(), format!("(i32.const {})", result)];
+                Code::new(code.iter().filter(|line| !line.is_empty()).cloned().collect::<Vec<String>>().join("\n"), Type::Bool)
+            },
+            (Operator::Equal, Type::None, Type::None) | (Operator::NotEqual, Type::None, Type::None) => {
+                let code = [lhs.text, rhs.text, format!("(i32.const {})", (operator == Operator::Equal) as u8)];
+                Code::new(code.iter().filter(|line| !line.is_empty()).cloned().collect::<Vec<String>>().join("\n"), Type::Bool)
+            },
+            (Operator::In, _, _) => self.error(pos, String::from("Operator 'in' is not supported by the WebAssembly backend")),
+            _ => self.error(pos, format!("Operator '{}' can't be applied to {} and {}", operator, lhs_type, rhs_type)),
+        }
+    }
+
+    /// Converts the operand of `+` to the string, like `+` does with the string and the other value.
+    fn stringify(&mut self, value: Code, pos: usize) -> String {
+        match value.value_type {
+            Type::Str => value.text,
+            Type::Int => format!("(call $signed_int_to_string {})", value.text),
+            Type::Bool => format!("(select (global.get $true) (global.get $false) {})", value.text),
+            Type::None => format!("{}\n(global.get $none)", value.text).trim_start().to_string(),
+            other => {
+                self.error(pos, format!("Conversion of {} to String is not supported by the WebAssembly backend", other));
+                String::new()
+            },
+        }
+    }
+
+    /// Declares the local or sets the variable the resolver has chosen for the assignment, or sets the item of the list.
+    /// The type of the variable doesn't change after the declaration, only the `Int` value may be converted to its `Float`.
+    fn assignment(&mut self, operator: Operator, node: &Node) -> Code {
+        let target = &node.children[0];
+        let value = self.expression(&node.children[1]);
+        match (target.operator(), &target.data) {
+            (Some(Operator::LeftBracket), _) => {
+                let list = self.expression(&target.children[0]);
+                let index = self.expression(&target.children[1]);
+                let index = self.convert(index, &Type::Int, target.children[1].pos(), "List index");
+                let item_type = match &list.value_type {
+                    Type::List(item_type) => item_type.as_ref().clone(),
+                    other => return self.error(target.pos(), format!("{} can't be indexed by the WebAssembly backend", other)),
+                };
+                let (pointer, position) = (self.local("list", &Type::Str), self.local("index", &Type::Int));
+                let mut code = format!("(local.set {} {})\n(local.set {} {})\n", pointer, list.text, position, index);
+                let value = match operator.compound_base() {
+                    None => value,
+                    Some(base) => {
+                        let current = Code::new(item_type.decode(&format!("(call $list_get (local.get {}) (local.get {}))", pointer, position)), item_type.clone());
+                        self.binary(base, current, value, node.pos())
+                    },
+                };
+                let value = self.convert(value, &item_type, node.pos(), "List item");
+                code.push_str(&format!("(call $list_set (local.get {}) (local.get {}) {})", pointer, position, item_type.encode(&value)));
+                Code::none(code)
+            },
+            (Some(Operator::Dot), _) => self.unsupported(target, "Fields"),
+            (Some(Operator::Mut), _) | (_, Some(Token::Identifier { .. })) => {
+                let name = name_of(target.children.first().unwrap_or(target));
+                match self.assignments[&node.pos()].clone() {
+                    Assignment::Declare { .. } => self.declare(name, value),
+                    Assignment::Assign => match self.lookup(name) {
+                        Some(variable) => {
+                            let value = match operator.compound_base() {
+                                None => value,
+                                Some(base) => self.binary(base, variable.get(), value, node.pos()),
+                            };
+                            let value = self.convert(value, &variable.value_type, node.pos(), &format!("Variable '{}'", name));
+                            Code::none(variable.set(value))
+                        },
+                        // the function is compiled at its first call, the global variable may be declared after it
+                        None => self.error(target.pos(), format!("Undefined variable '{}'", name)),
+                    },
+                    Assignment::Invalid(error) => self.error(error.pos, error.message),
+                }
+            },
+            _ => self.error(target.pos(), String::from("Invalid assignment target")),
+        }
+    }
+
+    /// Calls the global function or the builtin with the same name.
+    fn call(&mut self, node: &Node) -> Code {
+        let name = name_of(node);
+        let arguments: Vec<Code> = node.children.iter().map(|argument| self.expression(argument)).collect();
+        if !self.functions.contains_key(name) {
+            return self.builtin(name, arguments, node.pos())
+        }
+        let signature = match self.signature(name, node.pos()) {
+            Some(signature) => signature,
+            None => return Code::none(String::new()),
+        };
+        if signature.parameters.len() != arguments.len() {
+            return self.error(node.pos(), format!("Expected {}, found {}", builtins::arguments(signature.parameters.len()), arguments.len()))
+        }
+        let mut code = format!("(call {}", signature.name);
+        for (index, (argument, parameter)) in arguments.into_iter().zip(signature.parameters.iter()).enumerate() {
+            let argument = self.convert(argument, parameter, node.children[index].pos(), &format!("Argument {} of '{}'", index + 1, name));
+            code.push(' ');
+            code.push_str(&argument);
+        }
+        code.push(')');
+        Code::new(code, signature.result)
+    }
+
+    /// Calls the builtin, the numbers of the arguments are already checked by the resolver.
+    fn builtin(&mut self, name: &str, arguments: Vec<Code>, pos: usize) -> Code {
+        let float = |generator: &mut Generator, argument: Code| generator.convert(argument, &Type::Float, pos, &format!("Argument 1 of '{}'", name));
+        let mut arguments = arguments.into_iter();
+        match name {
+            "print" | "println" => {
+                let mut lines = vec!();
+                for (index, argument) in arguments.enumerate() {
+                    if index > 0 {
+                        lines.push(String::from("(call $print (global.get $space))"));
+                    }
+                    lines.push(match argument.value_type {
+                        Type::Int => format!("(call $print_int {})", argument.text),
+                        Type::Float => format!("(call $print_float {})", argument.text),
+                        Type::Bool => format!("(call $print_bool {})", argument.text),
+                        Type::Str => format!("(call $print {})", argument.text),
+                        Type::List(item_type) => {
+                            let kind = match *item_type {
+                                Type::Int => 0,
+                                Type::Float => 1,
+                                _ => 2,
+                            };
+                            format!("(call $print_list {} (i32.const {}))", argument.text, kind)
+                        },
+                        Type::None | Type::Never => format!("{}\n(call $print (global.get $none))", argument.text).trim_start().to_string(),
+                    });
+                }
+                if name == "println" {
+                    lines.push(String::from("(call $print (global.get $newline))"));
+                }
+                Code::none(lines.join("\n"))
+            },
+            "len" => {
+                let value = arguments.next().unwrap();
+                match value.value_type {
+                    Type::Str => Code::new(format!("(call $string_length {})", value.text), Type::Int),
+                    Type::List(_) => Code::new(format!("(i64.extend_i32_u (i32.load {}))", value.text), Type::Int),
+                    other => self.error(pos, format!("{} has no length", other)),
+                }
+            },
+            "push" => {
+                let (list, item) = (arguments.next().unwrap(), arguments.next().unwrap());
+                match list.value_type.clone() {
+                    Type::List(item_type) => {
+                        let item = self.convert(item, &item_type, pos, "Argument 2 of 'push'");
+                        Code::none(format!("(call $list_push {} {})", list.text, item_type.encode(&item)))
+                    },
+                    other => self.error(pos, format!("Argument 1 of 'push' must be List, found {}", other)),
+                }
+            },
+            "sqrt" => {
+                let value = float(self, arguments.next().unwrap());
+                Code::new(format!("(f64.sqrt {})", value), Type::Float)
+            },
+            "floor" | "ceil" => {
+                let value = float(self, arguments.next().unwrap());
+                Code::new(format!("(i64.trunc_f64_s (f64.{} {}))", name, value), Type::Int)
+            },
+            "round" => {
+                let value = float(self, arguments.next().unwrap());
+                Code::new(format!("(i64.trunc_f64_s (call $round {}))", value), Type::Int)
+            },
+            "sin" | "cos" | "tan" => {
+                let value = float(self, arguments.next().unwrap());
+                self.imports.insert(match name {
+                    "sin" => "sin",
+                    "cos" => "cos",
+                    _ => "tan",
+                });
+                Code::new(format!("(call ${} {})", name, value), Type::Float)
+            },
+            "abs" => {
+                let value = arguments.next().unwrap();
+                match value.value_type {
+                    Type::Int => Code::new(format!("(call $abs_int {})", value.text), Type::Int),
+                    Type::Float => Code::new(format!("(f64.abs {})", value.text), Type::Float),
+                    other => self.error(pos, format!("Argument 1 of 'abs' must be Number, found {}", other)),
+                }
+            },
+            "pow" => {
+                let (base, exponent) = (arguments.next().unwrap(), arguments.next().unwrap());
+                self.binary(Operator::Power, base, exponent, pos)
+            },
+            _ if is_builtin(name) => self.error(pos, format!("Builtin '{}' is not supported by the WebAssembly backend", name)),
+            _ => self.error(pos, format!("Unknown function '{}'", name)),
+        }
+    }
+
+    /// Returns the signature of the global function, the function is compiled if its return type is not annotated.
+    fn signature(&mut self, name: &str, pos: usize) -> Option<Signature> {
+        let node = self.functions[name].0;
+        match &self.functions[name].1 {
+            State::Compiled(signature) => return Some(signature.clone()),
+            State::InProgress => {
+                if let Some(signature) = self.annotated_signature(node) {
+                    return Some(signature)
+                }
+                self.error(pos, format!("Recursive function '{}' needs the return type annotation for the WebAssembly backend", name));
+                return None
+            },
+            State::Pending => (),
+        }
+        self.functions.get_mut(name).unwrap().1 = State::InProgress;
+        let signature = self.function(node);
+        self.functions.get_mut(name).unwrap().1 = State::Compiled(signature.clone());
+        Some(signature)
+    }
+
+    fn annotated_signature(&mut self, node: &Node) -> Option<Signature> {
+        let result = node.children.get(1).and_then(|annotation| Type::from_annotation(name_of(annotation)))?;
+        let parameters = node.condition.iter().map(|parameter| Function::parameter_type(parameter).and_then(Type::from_annotation)).collect::<Option<Vec<Type>>>()?;
+        Some(Signature { name: format!("$f_{}", wasm_name(name_of(node))), parameters, result })
+    }
+
+    /// Compiles the global function, parameters must have the type annotations.
+    fn function(&mut self, node: &Node) -> Signature {
+        let name = name_of(node);
+        let declared = match node.children.get(1) {
+            Some(annotation) => match Type::from_annotation(name_of(annotation)) {
+                Some(result) => Some(result),
+                None => {
+                    self.error(annotation.pos(), format!("Return type {} is not supported by the WebAssembly backend", name_of(annotation)));
+                    Some(Type::None)
+                },
+            },
+            None => None,
+        };
+        self.contexts.push(Context::new(declared.clone()));
+        let mut parameters = vec!();
+        let mut text = format!("  (func $f_{}", wasm_name(name));
+        for parameter in &node.condition {
+            let parameter_name = name_of(parameter);
+            let parameter_type = match Function::parameter_type(parameter).and_then(Type::from_annotation) {
+                Some(parameter_type) if parameter_type != Type::None => parameter_type,
+                _ => {
+                    self.error(parameter.pos(), format!("Parameter '{}' needs the type annotation Int, Float, Bool or String for the WebAssembly backend", parameter_name));
+                    Type::Int
+                },
+            };
+            if let Some(default) = parameter.children.first() {
+                self.unsupported(default, "Default values of the parameters");
+            }
+            let local = self.unique("v", parameter_name);
+            text.push_str(&format!(" (param {} {})", local, parameter_type.wasm().unwrap()));
+            let variable = Variable { name: local, value_type: parameter_type.clone(), is_global: false };
+            self.context().scopes[0].insert(parameter_name.to_string(), variable);
+            parameters.push(parameter_type);
+        }
+        let body = self.expression(&node.children[0]);
+        let context = self.contexts.pop().unwrap();
+        let result = match declared {
+            Some(result) => result,
+            None => match (&body.value_type, context.returns.first()) {
+                (Type::Never, Some((value_type, _))) => value_type.clone(),
+                (Type::Never, None) => Type::None,
+                (value_type, _) => value_type.clone(),
+            },
+        };
+        for (value_type, pos) in &context.returns {
+            if *value_type != result && *value_type != Type::Never {
+                self.error(*pos, format!("Function '{}' returns both {} and {}, annotate its return type", name, result, value_type));
+            }
+        }
+        let body = match (&result, &body.value_type) {
+            (Type::None, _) => body.dropped(),
+            (_, Type::None) => {
+                self.error(node.pos(), format!("<fn {}> must return {}, found None", name, result));
+                String::new()
+            },
+            _ => self.convert(body, &result, node.pos(), &format!("Result of <fn {}>", name)),
+        };
+        if let Some(wasm) = result.wasm() {
+            text.push_str(&format!(" (result {})", wasm));
+        }
+        text.push('\n');
+        for (local, wasm) in &context.locals {
+            text.push_str(&format!("    (local {} {})\n", local, wasm));
+        }
+        text.push_str(&indent(&body, 4));
+        text.push_str("  )\n");
+        self.definitions.push(text);
+        Signature { name: format!("$f_{}", wasm_name(name)), parameters, result }
+    }
+}
+
+/// Checks whether the loop body has the `break` of this loop.
+fn has_break(node: &Node) -> bool {
+    match node.node_type {
+        NodeType::Break => true,
+        NodeType::Loop | NodeType::For | NodeType::Lambda | NodeType::Function => false,
+        _ => node.condition.iter().chain(node.children.iter()).any(has_break),
+    }
+}
+
+/// Float constant which is read back to the same number, `{:?}` keeps the fractional part and the exponent.
+fn float(value: f64) -> String {
+    match value {
+        _ if value.is_nan() => String::from("nan"),
+        _ if value.is_infinite() => String::from(if value > 0.0 { "inf" } else { "-inf" }),
+        _ => format!("{:?}", value),
+    }
+}
+
+/// Escapes the bytes of the data segment, the printable ASCII is written as is.
+fn escape(bytes: &[u8]) -> String {
+    let mut escaped = String::new();
+    for byte in bytes {
+        match byte {
+            b'"' | b'\\' => escaped.push_str(&format!("\\{:02x}", byte)),
+            b' '..=b'~' => escaped.push(*byte as char),
+            _ => escaped.push_str(&format!("\\{:02x}", byte)),
+        }
+    }
+    escaped
+}
+
+fn indent(text: &str, width: usize) -> String {
+    let mut indented = String::new();
+    for line in text.lines().filter(|line| !line.is_empty()) {
+        indented.push_str(&" ".repeat(width));
+        indented.push_str(line);
+        indented.push('\n');
+    }
+    indented
+}
+
+fn wasm_name(name: &str) -> String {
+    name.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect()
+}
+
+#[inline]
+fn name_of(node: &Node) -> &str {
+    match &node.data {
+        Some(Token::Identifier { name, .. }) | Some(Token::Function { name, .. }) => name,
+        _ => "",
+    }
+}
+
+#[cfg(test)]
+fn generate(source: &str) -> Result<String, Vec<SyntaxError>> {
+    let tree = parse(source);
+    generate_wat(&tree)
+}
+
+#[test]
+fn test_compiled_output_is_same_as_interpreted() {
+    let programs = [
+        "fn fact(n: Int) -> Int {\n  if n <= 1 { return 1 }\n  n * fact(n - 1)\n}\nprintln(fact(20), 1.5, 1 / 3.0, 0.1 + 0.2, 7 / 2, -7 / 2)",
+        "fn half(x: Float) { x / 2 }\nfn greet(name: String) { \"hi \" + name + \" \" + len(name) }\nprintln(half(3), greet(\"avo\"), \"a\" == \"a\", \"a\" != \"b\", 1 == 1.0, true and not false)",
+        "mut sum = 0\nfor i in 0..10 { sum += i }\nfor i in 10:-3:1 { print(i, \"\") }\nmut n = 0\nloop {\n  n += 1\n  if n == 2 { continue }\n  if n >= 4 { break }\n}\nprintln(sum, n, -42 + \"!\", None)",
+        "mut xs = [3, 1]\nxs.push(5)\nxs[0] += 10\nmut total = 0\nfor x in xs { total += x }\nprintln(xs, len(xs), total, xs[2], [0.5, 2.0], [true])",
+        "println(sqrt(2), floor(2.7), ceil(2.1), round(-2.5), pow(2, 10), abs(-3), abs(-1.5), 2 ^ 0.5, sin(0))\nx = if 1 > 2 { 1 } else { 2.5 }\nprintln(x, 1 is Int, 1.5 is Number, \"s\" is Int)",
+    ];
+    for source in programs {
+        let mut engine = crate::Engine::new();
+        engine.capture_output();
+        engine.eval(source).unwrap();
+        let (output, result) = super::watinterpreter::run(&generate(source).unwrap());
+        assert_eq!(Ok(()), result, "{}", source);
+        assert_eq!(engine.take_output(), output, "{}", source);
+    }
+}
+
+#[test]
+fn test_runtime_errors_are_traps() {
+    let (output, result) = super::watinterpreter::run(&generate("zero = 0\nprintln(\"before\")\nprintln(1 / zero)").unwrap());
+    assert_eq!("before\n", output);
+    assert_eq!(Err(String::from("integer divide by zero")), result);
+    let (_, result) = super::watinterpreter::run(&generate("println([1, 2][2])").unwrap());
+    assert_eq!(Err(String::from("unreachable")), result);
+    let (_, result) = super::watinterpreter::run(&generate("mut x = 9223372036854775807\nx += 1").unwrap());
+    assert_eq!(Err(String::from("unreachable")), result);
+}
+
+#[test]
+fn test_unsupported_constructs() {
+    let message = |source: &str| generate(source).unwrap_err()[0].message.clone();
+    assert_eq!("Lambdas are not supported by the WebAssembly backend", message("f = { @ x -> x }"));
+    assert_eq!("Builtin 'split' is not supported by the WebAssembly backend", message("split(\"a b\", \" \")"));
+    assert_eq!("Parameter 'x' needs the type annotation Int, Float, Bool or String for the WebAssembly backend", message("fn f(x) { x }\nf(1)"));
+    assert_eq!("Recursive function 'f' needs the return type annotation for the WebAssembly backend", message("fn f(n: Int) { f(n) }"));
+    assert_eq!("Variable 'x' must be Int, found String", message("mut x = 1\nx = \"a\""));
+    assert_eq!("Cannot assign twice to immutable variable 'a', declare it with 'mut'", message("a = 1\na = 2"));
+    assert_eq!("Items of the list must have the same type for the WebAssembly backend, found Float and Int", message("[0.5, 2]"));
+}
@@ -0,0 +1,554 @@
+//! Interpreter of the WebAssembly text modules for the tests of the backend.
+//!
+//! It runs the subset of the text format produced by [`generate_wat`](super::generate_wat):
+//! folded instructions, named labels, locals and globals, the linear memory with data segments
+//! and the functions imported from the `host` module. The module is not validated, the operands
+//! of the wrong types fail the execution instead.
+
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::rc::Rc;
+
+/// Runs the exported `main` function of the module and returns the output written by the host functions
+/// and the trap message if the execution fails.
+pub fn run(wat: &str) -> (String, Result<(), String>) {
+    let mut machine = Machine::default();
+    let result = parse(wat).and_then(|module| machine.load(&module)).and_then(|_| machine.call("$main", &[]).map(|_| ()));
+    (machine.output, result)
+}
+
+#[derive(Debug)]
+enum Expression {
+    Atom(String),
+    Text(Vec<u8>),
+    List(Vec<Expression>),
+}
+
+impl Expression {
+
+    fn atom(&self) -> Option<&str> {
+        match self {
+            Expression::Atom(atom) => Some(atom),
+            _ => None,
+        }
+    }
+
+    /// Keyword of the list, e.g. `func` of `(func $main ...)`.
+    fn head(&self) -> Option<&str> {
+        match self {
+            Expression::List(items) => items.first().and_then(Expression::atom),
+            _ => None,
+        }
+    }
+}
+
+fn parse(source: &str) -> Result<Expression, String> {
+    let bytes = source.as_bytes();
+    let mut stack: Vec<Vec<Expression>> = vec!(vec!());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'(' => stack.push(vec!()),
+            b')' => {
+                let list = stack.pop().ok_or("unexpected ')'")?;
+                stack.last_mut().ok_or("unexpected ')'")?.push(Expression::List(list));
+            },
+            b';' if bytes.get(i + 1) == Some(&b';') => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+            },
+            b'"' => {
+                let mut text = vec!();
+                i += 1;
+                while bytes[i] != b'"' {
+                    match bytes[i] {
+                        b'\\' => {
+                            let digits = std::str::from_utf8(&bytes[i + 1..i + 3]).map_err(|e| e.to_string())?;
+                            text.push(u8::from_str_radix(digits, 16).map_err(|e| e.to_string())?);
+                            i += 3;
+                        },
+                        byte => {
+                            text.push(byte);
+                            i += 1;
+                        },
+                    }
+                }
+                stack.last_mut().unwrap().push(Expression::Text(text));
+            },
+            byte if byte.is_ascii_whitespace() => (),
+            _ => {
+                let start = i;
+                while i + 1 < bytes.len() && !bytes[i + 1].is_ascii_whitespace() && !b"()\"".contains(&bytes[i + 1]) {
+                    i += 1;
+                }
+                stack.last_mut().unwrap().push(Expression::Atom(source[start..=i].to_string()));
+            },
+        }
+        i += 1;
+    }
+    match (stack.pop(), stack.is_empty()) {
+        (Some(mut top), true) if top.len() == 1 => Ok(top.remove(0)),
+        _ => Err(String::from("expected the single module")),
+    }
+}
+
+#[derive(Debug)]
+enum Instruction {
+    Plain(String, Vec<String>),
+    Block(String, Vec<Instruction>),
+    Loop(String, Vec<Instruction>),
+    If(Vec<Instruction>, Vec<Instruction>),
+}
+
+/// Flattens the folded instruction, its operands are evaluated before it.
+fn instructions(expression: &Expression, into: &mut Vec<Instruction>) -> Result<(), String> {
+    let items = match expression {
+        Expression::List(items) => items,
+        other => return Err(format!("expected the folded instruction, found {:?}", other)),
+    };
+    let operator = items.first().and_then(Expression::atom).ok_or("expected the instruction")?;
+    let mut rest = items[1..].iter().peekable();
+    let label = match rest.peek().and_then(|item| item.atom()) {
+        Some(label) if label.starts_with('$') && matches!(operator, "block" | "loop" | "if") => {
+            rest.next();
+            label.to_string()
+        },
+        _ => String::new(),
+    };
+    if rest.peek().and_then(|item| item.head()) == Some("result") {
+        rest.next();
+    }
+    match operator {
+        "block" | "loop" => {
+            let mut body = vec!();
+            for item in rest {
+                instructions(item, &mut body)?;
+            }
+            into.push(if operator == "block" { Instruction::Block(label, body) } else { Instruction::Loop(label, body) });
+        },
+        "if" => {
+            let (mut then, mut otherwise) = (vec!(), vec!());
+            for item in rest {
+                match item.head() {
+                    Some("then") | Some("else") => {
+                        let branch = if item.head() == Some("then") { &mut then } else { &mut otherwise };
+                        if let Expression::List(items) = item {
+                            for instruction in &items[1..] {
+                                instructions(instruction, branch)?;
+                            }
+                        }
+                    },
+                    _ => instructions(item, into)?,
+                }
+            }
+            into.push(Instruction::If(then, otherwise));
+        },
+        _ => {
+            let mut immediates = vec!();
+            for item in rest {
+                match item {
+                    Expression::Atom(atom) => immediates.push(atom.clone()),
+                    operand => instructions(operand, into)?,
+                }
+            }
+            into.push(Instruction::Plain(operator.to_string(), immediates));
+        },
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Value {
+    I32(i32),
+    I64(i64),
+    F64(f64),
+}
+
+impl Value {
+
+    fn zero(value_type: &str) -> Value {
+        match value_type {
+            "i64" => Value::I64(0),
+            "f64" => Value::F64(0.0),
+            _ => Value::I32(0),
+        }
+    }
+}
+
+struct Function {
+    parameters: Vec<String>,
+    locals: Vec<(String, String)>,
+    has_result: bool,
+    body: Vec<Instruction>,
+}
+
+enum Flow {
+    Next,
+    Branch(String),
+    Return,
+}
+
+#[derive(Default)]
+struct Machine {
+    functions: HashMap<String, Rc<Function>>,
+    /// Imported functions by their names in the module.
+    imports: HashMap<String, String>,
+    globals: HashMap<String, Value>,
+    memory: Vec<u8>,
+    stack: Vec<Value>,
+    output: String,
+}
+
+const PAGE: usize = 65536;
+
+impl Machine {
+
+    fn load(&mut self, module: &Expression) -> Result<(), String> {
+        let fields = match module {
+            Expression::List(items) if module.head() == Some("module") => &items[1..],
+            _ => return Err(String::from("expected the module")),
+        };
+        for field in fields {
+            let items = match field {
+                Expression::List(items) => items,
+                _ => return Err(String::from("expected the module field")),
+            };
+            match field.head() {
+                Some("import") => match (&items[2], &items[3]) {
+                    (Expression::Text(name), Expression::List(function)) => {
+                        let local = function[1].atom().ok_or("expected the name of the import")?;
+                        self.imports.insert(local.to_string(), String::from_utf8_lossy(name).to_string());
+                    },
+                    _ => return Err(String::from("expected the imported function")),
+                },
+                Some("memory") => {
+                    let pages: usize = items.last().and_then(Expression::atom).ok_or("expected the memory size")?.parse().map_err(|_| "invalid memory size")?;
+                    self.memory = vec!(0; pages * PAGE);
+                },
+                Some("global") => {
+                    let name = items[1].atom().ok_or("expected the name of the global")?.to_string();
+                    let mut code = vec!();
+                    instructions(items.last().unwrap(), &mut code)?;
+                    self.execute(&code, &mut HashMap::new())?;
+                    let value = self.stack.pop().ok_or("expected the initial value")?;
+                    self.globals.insert(name, value);
+                },
+                Some("data") => {
+                    let mut code = vec!();
+                    instructions(&items[1], &mut code)?;
+                    self.execute(&code, &mut HashMap::new())?;
+                    let offset = self.pop_i32()? as usize;
+                    if let Expression::Text(bytes) = &items[2] {
+                        self.memory[offset..offset + bytes.len()].copy_from_slice(bytes);
+                    }
+                },
+                Some("func") => {
+                    let name = items[1].atom().ok_or("expected the name of the function")?.to_string();
+                    let mut function = Function { parameters: vec!(), locals: vec!(), has_result: false, body: vec!() };
+                    for item in &items[2..] {
+                        match (item.head(), item) {
+                            (Some("export"), _) => (),
+                            (Some("param"), Expression::List(parts)) => function.parameters.push(parts[1].atom().unwrap().to_string()),
+                            (Some("result"), _) => function.has_result = true,
+                            (Some("local"), Expression::List(parts)) => function.locals.push((parts[1].atom().unwrap().to_string(), parts[2].atom().unwrap().to_string())),
+                            _ => instructions(item, &mut function.body)?,
+                        }
+                    }
+                    self.functions.insert(name, Rc::new(function));
+                },
+                other => return Err(format!("unexpected module field {:?}", other)),
+            }
+        }
+        Ok(())
+    }
+
+    fn call(&mut self, name: &str, arguments: &[Value]) -> Result<Option<Value>, String> {
+        if let Some(import) = self.imports.get(name).cloned() {
+            return self.host(&import, arguments)
+        }
+        let function = self.functions.get(name).cloned().ok_or(format!("unknown function {}", name))?;
+        let mut locals: HashMap<String, Value> = function.parameters.iter().cloned().zip(arguments.iter().copied()).collect();
+        for (local, value_type) in &function.locals {
+            locals.insert(local.clone(), Value::zero(value_type));
+        }
+        let height = self.stack.len();
+        self.execute(&function.body, &mut locals)?;
+        let result = match function.has_result {
+            true => Some(self.stack.pop().ok_or("expected the result")?),
+            false => None,
+        };
+        self.stack.truncate(height);
+        Ok(result)
+    }
+
+    fn host(&mut self, name: &str, arguments: &[Value]) -> Result<Option<Value>, String> {
+        match (name, arguments) {
+            ("print", [Value::I32(pointer)]) => {
+                let pointer = *pointer as usize;
+                let length = u32::from_le_bytes(self.memory[pointer..pointer + 4].try_into().unwrap()) as usize;
+                self.output.push_str(&String::from_utf8_lossy(&self.memory[pointer + 4..pointer + 4 + length]));
+                Ok(None)
+            },
+            ("print_int", [Value::I64(value)]) => {
+                self.output.push_str(&value.to_string());
+                Ok(None)
+            },
+            ("print_float", [Value::F64(value)]) => {
+                self.output.push_str(&format!("{:?}", value));
+                Ok(None)
+            },
+            ("sin", [Value::F64(value)]) => Ok(Some(Value::F64(value.sin()))),
+            ("cos", [Value::F64(value)]) => Ok(Some(Value::F64(value.cos()))),
+            ("tan", [Value::F64(value)]) => Ok(Some(Value::F64(value.tan()))),
+            ("pow", [Value::F64(base), Value::F64(exponent)]) => Ok(Some(Value::F64(base.powf(*exponent)))),
+            _ => Err(format!("invalid call of the host function {}", name)),
+        }
+    }
+
+    fn execute(&mut self, code: &[Instruction], locals: &mut HashMap<String, Value>) -> Result<Flow, String> {
+        for instruction in code {
+            let flow = match instruction {
+                Instruction::Block(label, body) => {
+                    let height = self.stack.len();
+                    match self.execute(body, locals)? {
+                        Flow::Branch(target) if target == *label => {
+                            self.stack.truncate(height);
+                            Flow::Next
+                        },
+                        flow => flow,
+                    }
+                },
+                Instruction::Loop(label, body) => {
+                    let height = self.stack.len();
+                    loop {
+                        match self.execute(body, locals)? {
+                            Flow::Branch(target) if target == *label => self.stack.truncate(height),
+                            flow => break flow,
+                        }
+                    }
+                },
+                Instruction::If(then, otherwise) => match self.pop_i32()? {
+                    0 => self.execute(otherwise, locals)?,
+                    _ => self.execute(then, locals)?,
+                },
+                Instruction::Plain(operator, immediates) => self.plain(operator, immediates, locals)?,
+            };
+            if !matches!(flow, Flow::Next) {
+                return Ok(flow)
+            }
+        }
+        Ok(Flow::Next)
+    }
+
+    fn plain(&mut self, operator: &str, immediates: &[String], locals: &mut HashMap<String, Value>) -> Result<Flow, String> {
+        let immediate = || immediates.first().cloned().ok_or(format!("{} expects the immediate", operator));
+        match operator {
+            "i32.const" => self.stack.push(Value::I32(parse_int(&immediate()?)? as i32)),
+            "i64.const" => self.stack.push(Value::I64(parse_int(&immediate()?)?)),
+            "f64.const" => self.stack.push(Value::F64(immediate()?.parse().map_err(|_| "invalid f64 constant")?)),
+            "local.get" => self.stack.push(*locals.get(&immediate()?).ok_or("unknown local")?),
+            "local.set" => {
+                let value = self.pop()?;
+                locals.insert(immediate()?, value);
+            },
+            "local.tee" => {
+                let value = *self.stack.last().ok_or("stack is empty")?;
+                locals.insert(immediate()?, value);
+            },
+            "global.get" => self.stack.push(*self.globals.get(&immediate()?).ok_or("unknown global")?),
+            "global.set" => {
+                let value = self.pop()?;
+                self.globals.insert(immediate()?, value);
+            },
+            "call" => {
+                let name = immediate()?;
+                let count = match self.functions.get(&name) {
+                    Some(function) => function.parameters.len(),
+                    None => match self.imports.get(&name).map(String::as_str) {
+                        Some("pow") => 2,
+                        Some(_) => 1,
+                        None => return Err(format!("unknown function {}", name)),
+                    },
+                };
+                let arguments = self.stack.split_off(self.stack.len().checked_sub(count).ok_or("stack is empty")?);
+                if let Some(result) = self.call(&name, &arguments)? {
+                    self.stack.push(result);
+                }
+            },
+            "br" => return Ok(Flow::Branch(immediate()?)),
+            "br_if" => if self.pop_i32()? != 0 {
+                return Ok(Flow::Branch(immediate()?))
+            },
+            "return" => return Ok(Flow::Return),
+            "unreachable" => return Err(String::from("unreachable")),
+            "nop" => (),
+            "drop" => {
+                self.pop()?;
+            },
+            "select" => {
+                let condition = self.pop_i32()?;
+                let (first, second) = (self.pop()?, self.pop()?);
+                self.stack.push(if condition != 0 { second } else { first });
+            },
+            "memory.size" => self.stack.push(Value::I32((self.memory.len() / PAGE) as i32)),
+            "memory.grow" => {
+                let pages = self.pop_i32()? as usize;
+                self.stack.push(Value::I32((self.memory.len() / PAGE) as i32));
+                self.memory.resize(self.memory.len() + pages * PAGE, 0);
+            },
+            _ if operator.contains("load") || operator.contains("store") => self.memory_access(operator, immediates)?,
+            _ => self.numeric(operator)?,
+        }
+        Ok(Flow::Next)
+    }
+
+    fn memory_access(&mut self, operator: &str, immediates: &[String]) -> Result<(), String> {
+        let offset = immediates.iter().find_map(|immediate| immediate.strip_prefix("offset=")).map(|offset| offset.parse::<usize>().unwrap()).unwrap_or(0);
+        let size = match operator {
+            "i32.load8_u" | "i32.store8" => 1,
+            "i32.load" | "i32.store" => 4,
+            _ => 8,
+        };
+        let value = match operator.contains("store") {
+            true => Some(self.pop()?),
+            false => None,
+        };
+        let address = self.pop_i32()? as u32 as usize + offset;
+        if address + size > self.memory.len() {
+            return Err(String::from("out of bounds memory access"))
+        }
+        let bytes = &mut self.memory[address..address + size];
+        match (operator, value) {
+            ("i32.load8_u", _) => self.stack.push(Value::I32(bytes[0] as i32)),
+            ("i32.load", _) => self.stack.push(Value::I32(i32::from_le_bytes(bytes.try_into().unwrap()))),
+            ("i64.load", _) => self.stack.push(Value::I64(i64::from_le_bytes(bytes.try_into().unwrap()))),
+            ("f64.load", _) => self.stack.push(Value::F64(f64::from_le_bytes(bytes.try_into().unwrap()))),
+            ("i32.store8", Some(Value::I32(value))) => bytes[0] = value as u8,
+            ("i32.store", Some(Value::I32(value))) => bytes.copy_from_slice(&value.to_le_bytes()),
+            ("i64.store", Some(Value::I64(value))) => bytes.copy_from_slice(&value.to_le_bytes()),
+            ("f64.store", Some(Value::F64(value))) => bytes.copy_from_slice(&value.to_le_bytes()),
+            _ => return Err(format!("invalid operands of {}", operator)),
+        }
+        Ok(())
+    }
+
+    fn numeric(&mut self, operator: &str) -> Result<(), String> {
+        let trap = |message: &str| Err(String::from(message));
+        let result = match operator {
+            "i32.eqz" => Value::I32((self.pop_i32()? == 0) as i32),
+            "i64.eqz" => Value::I32((self.pop_i64()? == 0) as i32),
+            "i32.wrap_i64" => Value::I32(self.pop_i64()? as i32),
+            "i64.extend_i32_u" => Value::I64(self.pop_i32()? as u32 as i64),
+            "i64.reinterpret_f64" => Value::I64(self.pop_f64()?.to_bits() as i64),
+            "f64.reinterpret_i64" => Value::F64(f64::from_bits(self.pop_i64()? as u64)),
+            "f64.convert_i64_s" => Value::F64(self.pop_i64()? as f64),
+            "i64.trunc_f64_s" => {
+                let value = self.pop_f64()?;
+                if value.is_nan() || value.trunc() < i64::MIN as f64 || value.trunc() >= i64::MAX as f64 {
+                    return trap("integer overflow")
+                }
+                Value::I64(value.trunc() as i64)
+            },
+            "f64.neg" => Value::F64(-self.pop_f64()?),
+            "f64.abs" => Value::F64(self.pop_f64()?.abs()),
+            "f64.sqrt" => Value::F64(self.pop_f64()?.sqrt()),
+            "f64.floor" => Value::F64(self.pop_f64()?.floor()),
+            "f64.ceil" => Value::F64(self.pop_f64()?.ceil()),
+            "f64.trunc" => Value::F64(self.pop_f64()?.trunc()),
+            _ => {
+                let (rhs, lhs) = (self.pop()?, self.pop()?);
+                let operation = operator.split('.').nth(1).unwrap_or_default();
+                match (lhs, rhs) {
+                    (Value::I32(a), Value::I32(b)) => match operation {
+                        "add" => Value::I32(a.wrapping_add(b)),
+                        "sub" => Value::I32(a.wrapping_sub(b)),
+                        "mul" => Value::I32(a.wrapping_mul(b)),
+                        "and" => Value::I32(a & b),
+                        "or" => Value::I32(a | b),
+                        "xor" => Value::I32(a ^ b),
+                        "shr_u" => Value::I32(((a as u32) >> (b as u32 % 32)) as i32),
+                        _ => Value::I32(compare(operation, a as i64, b as i64, a as u32 as u64, b as u32 as u64)?),
+                    },
+                    (Value::I64(a), Value::I64(b)) => match operation {
+                        "add" => Value::I64(a.wrapping_add(b)),
+                        "sub" => Value::I64(a.wrapping_sub(b)),
+                        "mul" => Value::I64(a.wrapping_mul(b)),
+                        "and" => Value::I64(a & b),
+                        "xor" => Value::I64(a ^ b),
+                        "div_s" | "rem_s" if b == 0 => return trap("integer divide by zero"),
+                        "div_s" => Value::I64(a.checked_div(b).ok_or("integer overflow")?),
+                        "rem_s" => Value::I64(a.wrapping_rem(b)),
+                        _ => Value::I32(compare(operation, a, b, a as u64, b as u64)?),
+                    },
+                    (Value::F64(a), Value::F64(b)) => match operation {
+                        "add" => Value::F64(a + b),
+                        "sub" => Value::F64(a - b),
+                        "mul" => Value::F64(a * b),
+                        "div" => Value::F64(a / b),
+                        "copysign" => Value::F64(a.copysign(b)),
+                        "eq" => Value::I32((a == b) as i32),
+                        "ne" => Value::I32((a != b) as i32),
+                        "lt" => Value::I32((a < b) as i32),
+                        "le" => Value::I32((a <= b) as i32),
+                        "gt" => Value::I32((a > b) as i32),
+                        "ge" => Value::I32((a >= b) as i32),
+                        _ => return Err(format!("unknown instruction {}", operator)),
+                    },
+                    _ => return Err(format!("invalid operands of {}", operator)),
+                }
+            },
+        };
+        self.stack.push(result);
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Result<Value, String> {
+        self.stack.pop().ok_or_else(|| String::from("stack is empty"))
+    }
+
+    fn pop_i32(&mut self) -> Result<i32, String> {
+        match self.pop()? {
+            Value::I32(value) => Ok(value),
+            other => Err(format!("expected i32, found {:?}", other)),
+        }
+    }
+
+    fn pop_i64(&mut self) -> Result<i64, String> {
+        match self.pop()? {
+            Value::I64(value) => Ok(value),
+            other => Err(format!("expected i64, found {:?}", other)),
+        }
+    }
+
+    fn pop_f64(&mut self) -> Result<f64, String> {
+        match self.pop()? {
+            Value::F64(value) => Ok(value),
+            other => Err(format!("expected f64, found {:?}", other)),
+        }
+    }
+}
+
+fn compare(operation: &str, a: i64, b: i64, unsigned_a: u64, unsigned_b: u64) -> Result<i32, String> {
+    let result = match operation {
+        "eq" => a == b,
+        "ne" => a != b,
+        "lt_s" => a < b,
+        "le_s" => a <= b,
+        "gt_s" => a > b,
+        "ge_s" => a >= b,
+        "lt_u" => unsigned_a < unsigned_b,
+        "le_u" => unsigned_a <= unsigned_b,
+        "gt_u" => unsigned_a > unsigned_b,
+        "ge_u" => unsigned_a >= unsigned_b,
+        _ => return Err(format!("unknown comparison {}", operation)),
+    };
+    Ok(result as i32)
+}
+
+fn parse_int(text: &str) -> Result<i64, String> {
+    match text.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).map(|value| value as i64).map_err(|e| e.to_string()),
+        None => text.parse().map_err(|_| format!("invalid integer {}", text)),
+    }
+}
@@ -0,0 +1,914 @@
+use crate::dto::{ Node, NodeType, Operator, Token, SyntaxError };
+use crate::ir::{ lower, optimize, Block, BlockId, Constant, Function, Instruction, Operation, Program, Terminator, Type, ValueId };
+use crate::runtime::builtins::is_builtin;
+use std::collections::{ BTreeSet, HashMap, HashSet };
+use std::convert::TryFrom;
+use std::fmt;
+use std::io::Write;
+use std::path::Path;
+use std::process::{ Command, Stdio };
+#[cfg(test)]
+use crate::parser::parse;
+
+/// Runtime of the compiled programs, `link_x86_64` compiles it together with the assembly of the program.
+pub const X86_64_RUNTIME: &str = concat!(include_str!("avo_runtime.h"), include_str!("avo_x86_64.c"));
+
+/// Builtins implemented by the runtime, `print` and `println` take the array of the arguments.
+const BUILTINS: &[&str] = &["print", "println", "len", "push", "sqrt", "sin", "cos", "tan", "floor", "ceil", "round", "abs", "pow"];
+
+/// Registers which keep the values, they are preserved by the calls into the runtime.
+const REGISTERS: [&str; 5] = ["%rbx", "%r12", "%r13", "%r14", "%r15"];
+
+/// Registers of the first arguments in the System V calling convention.
+const ARGUMENTS: [&str; 6] = ["%rdi", "%rsi", "%rdx", "%rcx", "%r8", "%r9"];
+
+/// Translates the checked syntax tree of the program to the GNU assembly for x86-64 Linux.
+///
+/// The program is lowered to the intermediate representation and optimized first. Values whose types are
+/// known to be Int, Float or Bool live unboxed in the registers and are computed by the inline instructions,
+/// the other values are the pointers to the values of the runtime. The registers are assigned by the linear
+/// scan over the live intervals, the values which don't fit are kept on the stack. The functions of the program
+/// and the runtime are called by the System V calling convention.
+///
+/// # Errors
+/// Constructs which have no intermediate representation and the builtins which are not implemented by the runtime.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// # use avoc::internals::{ generate_x86_64, TokenReader, TreeBuilder };
+/// let tokens = TokenReader::new().parse("println(6 * 7)").unwrap();
+/// let tree = TreeBuilder::new().build_tree(&tokens).unwrap();
+///
+/// assert!(generate_x86_64(&tree).unwrap().contains("avo_fn_main:"));
+/// ```
+pub fn generate_x86_64(root: &Node) -> Result<String, Vec<SyntaxError>> {
+    let functions: HashSet<&str> = root.children.iter().map(Node::declaration).filter(|node| node.node_type == NodeType::Function).map(name_of).collect();
+    let mut errors = vec!();
+    check_builtins(root, &functions, &mut errors);
+    let mut program = match lower(root) {
+        Ok(program) => program,
+        Err(lowering_errors) => {
+            errors.extend(lowering_errors);
+            errors.sort_by_key(|e| e.pos);
+            return Err(errors)
+        },
+    };
+    if !errors.is_empty() {
+        return Err(errors)
+    }
+    optimize(&mut program);
+    let mut generator = Generator::new(&program);
+    for (index, function) in program.functions.iter().enumerate() {
+        generator.function(index, function);
+    }
+    Ok(generator.finish())
+}
+
+/// Assembles the program and links it with the runtime by the system `cc`, the runtime is passed through the standard input.
+pub fn link_x86_64(assembly: &Path, executable: &Path) -> Result<(), String> {
+    let mut compiler = Command::new("cc")
+        .arg("-O2").arg("-o").arg(executable)
+        .arg("-x").arg("assembler").arg(assembly)
+        .arg("-x").arg("c").arg("-").arg("-lm")
+        .stdin(Stdio::piped())
+        .spawn().map_err(|e| format!("cc: {}", e))?;
+    compiler.stdin.take().unwrap().write_all(X86_64_RUNTIME.as_bytes()).map_err(|e| format!("cc: {}", e))?;
+    match compiler.wait() {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(format!("cc: {} is not linked, {}", assembly.to_string_lossy(), status)),
+        Err(e) => Err(format!("cc: {}", e)),
+    }
+}
+
+/// Reports the calls of the builtins which are not in the runtime, the syntax tree has their positions unlike the IR.
+/// Tests are not compiled, so they may call any builtin.
+fn check_builtins(node: &Node, functions: &HashSet<&str>, errors: &mut Vec<SyntaxError>) {
+    if node.node_type == NodeType::Test {
+        return
+    }
+    let name = name_of(node);
+    if node.node_type == NodeType::Call && is_builtin(name) && !BUILTINS.contains(&name) && !functions.contains(name) {
+        errors.push(SyntaxError::new(node.pos(), format!("Builtin '{}' is not supported by the x86-64 backend", name)));
+    }
+    node.condition.iter().chain(node.children.iter()).for_each(|child| check_builtins(child, functions, errors));
+}
+
+/// Place of the value: the register or the slot of the stack frame below `%rbp`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Location {
+    Register(&'static str),
+    Stack(usize),
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Location::Register(register) => write!(f, "{}", register),
+            Location::Stack(slot) => write!(f, "-{}(%rbp)", 8 * (slot + 1)),
+        }
+    }
+}
+
+/// Int, Float and Bool are kept in the registers as the numbers, floats as their bits.
+#[inline]
+fn is_unboxed(value_type: Type) -> bool {
+    matches!(value_type, Type::Int | Type::Float | Type::Bool)
+}
+
+struct Generator<'a> {
+    program: &'a Program,
+    text: String,
+    /// String constants and names, the label of the string is its index.
+    strings: Vec<String>,
+    globals: BTreeSet<String>,
+    /// Index of the function which is being generated, it makes its labels unique.
+    index: usize,
+    locations: HashMap<ValueId, Location>,
+    types: HashMap<ValueId, Type>,
+    /// Registers saved by the prologue with their slots.
+    saved: Vec<(&'static str, usize)>,
+    /// Number of the slots which keep the arguments passed in the registers.
+    parameters: usize,
+    /// Slot of the position of the call, the type of the returned value is checked at it.
+    call_position: Option<Location>,
+    edges: usize,
+}
+
+impl<'a> Generator<'a> {
+
+    fn new(program: &'a Program) -> Generator<'a> {
+        Generator {
+            program,
+            text: String::from("    .text\n    .globl avo_fn_main\n"),
+            strings: vec!(),
+            globals: BTreeSet::new(),
+            index: 0,
+            locations: HashMap::new(),
+            types: HashMap::new(),
+            saved: vec!(),
+            parameters: 0,
+            call_position: None,
+            edges: 0,
+        }
+    }
+
+    /// Appends the string constants and the global variables.
+    fn finish(mut self) -> String {
+        if !self.strings.is_empty() {
+            self.text.push_str("\n    .section .rodata\n");
+        }
+        for (index, string) in self.strings.iter().enumerate() {
+            self.text.push_str(&format!(".Ls{}:\n    .asciz \"{}\"\n", index, escape(string)));
+        }
+        if !self.globals.is_empty() {
+            self.text.push_str("\n    .bss\n    .balign 8\n");
+        }
+        for name in &self.globals {
+            self.text.push_str(&format!("avo_global_{}:\n    .zero 8\n", name));
+        }
+        self.text.push_str("\n    .section .note.GNU-stack,\"\",@progbits\n");
+        self.text
+    }
+
+    fn line(&mut self, text: &str) {
+        self.text.push_str("    ");
+        self.text.push_str(text);
+        self.text.push('\n');
+    }
+
+    fn label(&mut self, label: &str) {
+        self.text.push_str(label);
+        self.text.push_str(":\n");
+    }
+
+    fn block_label(&self, block: BlockId) -> String {
+        format!(".L{}_{}", self.index, block.0)
+    }
+
+    /// Label of the string constant, the same strings share the label.
+    fn string(&mut self, text: &str) -> String {
+        let index = match self.strings.iter().position(|string| string == text) {
+            Some(index) => index,
+            None => {
+                self.strings.push(text.to_string());
+                self.strings.len() - 1
+            },
+        };
+        format!(".Ls{}", index)
+    }
+
+    fn value_type(&self, value: ValueId) -> Type {
+        self.types.get(&value).copied().unwrap_or(Type::Any)
+    }
+
+    fn load(&mut self, value: ValueId, register: &'static str) {
+        let location = self.locations[&value];
+        if location != Location::Register(register) {
+            self.line(&format!("movq {}, {}", location, register));
+        }
+    }
+
+    fn store(&mut self, value: ValueId) {
+        let location = self.locations[&value];
+        self.line(&format!("movq %rax, {}", location));
+    }
+
+    fn call(&mut self, function: &str) {
+        self.line(&format!("call {}", function));
+    }
+
+    /// Leaves the pointer to the value in `%rax`, the unboxed values are boxed by the runtime.
+    fn boxed(&mut self, value: ValueId) {
+        let function = match self.value_type(value) {
+            Type::Int => "avox_box_int",
+            Type::Float => "avox_box_float",
+            Type::Bool => "avox_box_bool",
+            _ => return self.load(value, "%rax"),
+        };
+        self.load(value, "%rdi");
+        self.call(function);
+    }
+
+    /// Converts the pointer to the value in `%rax` to the representation of the type.
+    fn unboxed(&mut self, value_type: Type) {
+        let function = match value_type {
+            Type::Int => "avox_unbox_int",
+            Type::Float => "avox_unbox_float",
+            Type::Bool => "avox_unbox_bool",
+            _ => return,
+        };
+        self.line("movq %rax, %rdi");
+        self.call(function);
+    }
+
+    /// Leaves the value in `%rax` in the representation of the type.
+    fn converted(&mut self, value: ValueId, value_type: Type) {
+        let from = self.value_type(value);
+        if from == value_type || !is_unboxed(from) && !is_unboxed(value_type) {
+            return self.load(value, "%rax")
+        }
+        self.boxed(value);
+        self.unboxed(value_type);
+    }
+
+    /// Boxes the values to the memory reserved on the stack, the first one is at `(%rsp)`.
+    /// Returns the number of the reserved bytes, the stack stays aligned to 16 bytes for the calls.
+    fn stage(&mut self, values: &[ValueId]) -> usize {
+        let size = (8 * values.len()).next_multiple_of(16);
+        if size > 0 {
+            self.line(&format!("subq ${}, %rsp", size));
+        }
+        for (index, value) in values.iter().enumerate() {
+            self.boxed(*value);
+            self.line(&format!("movq %rax, {}(%rsp)", 8 * index));
+        }
+        size
+    }
+
+    fn release(&mut self, size: usize) {
+        if size > 0 {
+            self.line(&format!("addq ${}, %rsp", size));
+        }
+    }
+
+    /// Sets the position at which the runtime reports the errors.
+    fn position(&mut self, pos: usize) {
+        self.line(&format!("movq ${}, avox_position(%rip)", pos));
+    }
+
+    /// Calls the function of the runtime with the boxed values in the registers of the arguments.
+    fn call_runtime(&mut self, function: &str, arguments: &[ValueId]) {
+        let size = self.stage(arguments);
+        for (index, register) in ARGUMENTS.iter().enumerate().take(arguments.len()) {
+            self.line(&format!("movq {}(%rsp), {}", 8 * index, register));
+        }
+        self.call(function);
+        self.release(size);
+    }
+
+    fn function(&mut self, index: usize, function: &'a Function) {
+        let order = function.reverse_postorder();
+        let intervals = live_intervals(function, &order);
+        let registers = allocate(&intervals);
+        self.index = index;
+        self.types = function.types();
+        self.parameters = function.parameters.len().min(ARGUMENTS.len());
+        self.saved = REGISTERS.iter().copied().filter(|register| registers.values().any(|used| used == register))
+            .enumerate().map(|(slot, register)| (register, slot)).collect();
+        let mut slots = self.saved.len() + self.parameters;
+        let mut values: Vec<ValueId> = intervals.keys().copied().collect();
+        values.sort();
+        self.locations = values.into_iter().map(|value| match registers.get(&value) {
+            Some(register) => (value, Location::Register(register)),
+            None => {
+                slots += 1;
+                (value, Location::Stack(slots - 1))
+            },
+        }).collect();
+        self.call_position = match function.return_type {
+            Type::Any => None,
+            _ => {
+                slots += 1;
+                Some(Location::Stack(slots - 1))
+            },
+        };
+        self.text.push('\n');
+        self.label(&format!("avo_fn_{}", function.name));
+        self.line("pushq %rbp");
+        self.line("movq %rsp, %rbp");
+        let size = (8 * slots).next_multiple_of(16);
+        if size > 0 {
+            self.line(&format!("subq ${}, %rsp", size));
+        }
+        for (register, slot) in self.saved.clone() {
+            self.line(&format!("movq {}, {}", register, Location::Stack(slot)));
+        }
+        for (position, register) in ARGUMENTS.iter().enumerate().take(self.parameters) {
+            let slot = Location::Stack(self.saved.len() + position);
+            self.line(&format!("movq {}, {}", register, slot));
+        }
+        if let Some(slot) = self.call_position {
+            self.line("movq avox_position(%rip), %rax");
+            self.line(&format!("movq %rax, {}", slot));
+        }
+        for (position, id) in order.iter().enumerate() {
+            let block = function.block(*id);
+            let label = self.block_label(block.id);
+            self.label(&label);
+            for instruction in &block.instructions {
+                self.instruction(function, instruction);
+            }
+            self.terminator(function, block, order.get(position + 1).copied());
+        }
+    }
+
+    /// The instructions which may fail set the position, the parameters are checked at the position of the call.
+    fn instruction(&mut self, function: &Function, instruction: &Instruction) {
+        let value_type = instruction.value_type;
+        if !instruction.operation.is_pure(&self.types) {
+            self.position(instruction.pos);
+        }
+        match &instruction.operation {
+            // phis are assigned by the predecessors
+            Operation::Phi(_) => return,
+            Operation::Constant(constant) => self.constant(constant),
+            Operation::Parameter(position) => {
+                let location = match *position < ARGUMENTS.len() {
+                    true => Location::Stack(self.saved.len() + position).to_string(),
+                    // the arguments after the sixth one are on the stack of the caller above the return address
+                    false => format!("{}(%rbp)", 16 + 8 * (position - ARGUMENTS.len())),
+                };
+                self.line(&format!("movq {}, %rax", location));
+                if value_type != Type::Any {
+                    let (name, parameter) = (self.string(&value_type.to_string()), self.string(&function.parameters[*position].0));
+                    self.line("movq %rax, %rdi");
+                    self.line(&format!("leaq {}(%rip), %rsi", name));
+                    self.line(&format!("leaq {}(%rip), %rdx", parameter));
+                    self.call("avox_parameter");
+                    self.unboxed(value_type);
+                }
+            },
+            Operation::Unary(operator, operand) => self.unary(*operator, *operand, value_type),
+            Operation::Binary(operator, lhs, rhs) => self.binary(*operator, *lhs, *rhs, value_type),
+            Operation::Is(value, name) => {
+                let name = self.string(name);
+                self.boxed(*value);
+                self.line("movq %rax, %rdi");
+                self.line(&format!("leaq {}(%rip), %rsi", name));
+                self.call("avox_is");
+                self.unboxed(value_type);
+            },
+            Operation::Call(name, arguments) => {
+                self.function_call(name, arguments);
+                self.unboxed(value_type);
+            },
+            Operation::List(items) => {
+                let size = self.stage(items);
+                self.line(&format!("movq ${}, %rdi", items.len()));
+                self.line("movq %rsp, %rsi");
+                self.call("avox_list");
+                self.release(size);
+            },
+            Operation::Range(start, end, step, is_inclusive) => {
+                let size = self.stage(&[*start, *end, *step]);
+                for (index, register) in ARGUMENTS.iter().enumerate().take(3) {
+                    self.line(&format!("movq {}(%rsp), {}", 8 * index, register));
+                }
+                self.line(&format!("movq ${}, %rcx", *is_inclusive as u8));
+                self.call("avox_range");
+                self.release(size);
+            },
+            Operation::Index(collection, key) => {
+                self.call_runtime("avox_index", &[*collection, *key]);
+                self.unboxed(value_type);
+            },
+            Operation::SetIndex(collection, key, value) => self.call_runtime("avox_set_index", &[*collection, *key, *value]),
+            Operation::LoadGlobal(name) => {
+                self.globals.insert(name.clone());
+                let label = self.string(name);
+                self.line(&format!("movq avo_global_{}(%rip), %rdi", name));
+                self.line(&format!("leaq {}(%rip), %rsi", label));
+                self.call("avox_load_global");
+                self.unboxed(value_type);
+            },
+            Operation::StoreGlobal(name, value) => {
+                self.globals.insert(name.clone());
+                self.boxed(*value);
+                self.line(&format!("movq %rax, avo_global_{}(%rip)", name));
+                self.line("leaq avox_none_value(%rip), %rax");
+            },
+            Operation::Iterate(iterable) => self.call_runtime("avox_iterate", &[*iterable]),
+            Operation::HasNext(iterator) => {
+                self.load(*iterator, "%rdi");
+                self.call("avox_has_next");
+            },
+            Operation::Next(iterator) => {
+                self.load(*iterator, "%rdi");
+                self.call("avox_next");
+                self.unboxed(value_type);
+            },
+        }
+        self.store(instruction.result);
+    }
+
+    fn constant(&mut self, constant: &Constant) {
+        match constant {
+            Constant::None => self.line("leaq avox_none_value(%rip), %rax"),
+            Constant::Bool(value) => self.line(&format!("movq ${}, %rax", *value as u8)),
+            Constant::Int(value) if i32::try_from(*value).is_ok() => self.line(&format!("movq ${}, %rax", value)),
+            Constant::Int(value) => self.line(&format!("movabsq ${}, %rax", value)),
+            Constant::Float(value) => self.line(&format!("movabsq $0x{:x}, %rax", value.to_bits())),
+            Constant::String(value) => {
+                let label = self.string(value);
+                self.line(&format!("leaq {}(%rip), %rdi", label));
+                self.line(&format!("movq ${}, %rsi", value.len()));
+                self.call("avox_string");
+            },
+        }
+    }
+
+    /// Stops the program if the last instruction overflowed.
+    fn check_overflow(&mut self) {
+        self.line("jno 1f");
+        self.call("avox_overflow");
+        self.label("1");
+    }
+
+    fn unary(&mut self, operator: Operator, operand: ValueId, value_type: Type) {
+        match (operator, self.value_type(operand)) {
+            (Operator::Not, Type::Bool) => {
+                self.load(operand, "%rax");
+                self.line("xorq $1, %rax");
+            },
+            (Operator::UnaryMinus, Type::Int) => {
+                self.load(operand, "%rax");
+                self.line("negq %rax");
+                self.check_overflow();
+            },
+            (Operator::UnaryMinus, Type::Float) => {
+                self.load(operand, "%rax");
+                self.line("btcq $63, %rax");
+            },
+            _ => {
+                self.call_runtime(if operator == Operator::Not { "avox_not" } else { "avox_neg" }, &[operand]);
+                self.unboxed(value_type);
+            },
+        }
+    }
+
+    /// Integers, floats and booleans are computed inline, the other operands are passed to the runtime.
+    fn binary(&mut self, operator: Operator, lhs: ValueId, rhs: ValueId, value_type: Type) {
+        match (operator, self.value_type(lhs), self.value_type(rhs)) {
+            (Operator::Plus | Operator::Minus | Operator::Multiply, Type::Int, Type::Int) => {
+                self.load(lhs, "%rax");
+                self.load(rhs, "%rcx");
+                let instruction = match operator {
+                    Operator::Plus => "addq",
+                    Operator::Minus => "subq",
+                    _ => "imulq",
+                };
+                self.line(&format!("{} %rcx, %rax", instruction));
+                self.check_overflow();
+            },
+            (Operator::Divide, Type::Int, Type::Int) => {
+                self.load(lhs, "%rax");
+                self.load(rhs, "%rcx");
+                self.line("testq %rcx, %rcx");
+                self.line("jnz 1f");
+                self.call("avox_division_by_zero");
+                self.label("1");
+                // `idivq` traps on the overflow of the minimal integer divided by -1
+                self.line("cmpq $-1, %rcx");
+                self.line("jne 2f");
+                self.line("negq %rax");
+                self.line("jno 3f");
+                self.call("avox_overflow");
+                self.label("2");
+                self.line("cqo");
+                self.line("idivq %rcx");
+                self.label("3");
+            },
+            (Operator::Equal | Operator::NotEqual | Operator::Less | Operator::LessOrEqual | Operator::Greater | Operator::GreaterOrEqual, Type::Int, Type::Int)
+                | (Operator::Equal | Operator::NotEqual, Type::Bool, Type::Bool) => {
+                self.load(lhs, "%rax");
+                self.load(rhs, "%rcx");
+                self.line("cmpq %rcx, %rax");
+                let condition = match operator {
+                    Operator::Equal => "e",
+                    Operator::NotEqual => "ne",
+                    Operator::Less => "l",
+                    Operator::LessOrEqual => "le",
+                    Operator::Greater => "g",
+                    _ => "ge",
+                };
+                self.line(&format!("set{} %al", condition));
+                self.line("movzbq %al, %rax");
+            },
+            (Operator::Xor, Type::Bool, Type::Bool) => {
+                self.load(lhs, "%rax");
+                self.load(rhs, "%rcx");
+                self.line("xorq %rcx, %rax");
+            },
+            (Operator::Plus | Operator::Minus | Operator::Multiply | Operator::Divide, a, b)
+                | (Operator::Equal | Operator::NotEqual | Operator::Less | Operator::LessOrEqual | Operator::Greater | Operator::GreaterOrEqual, a, b)
+                if a.is_number() && b.is_number() => {
+                self.float_operand(lhs, "%xmm0");
+                self.float_operand(rhs, "%xmm1");
+                self.float_operation(operator);
+            },
+            _ => {
+                let function = match operator {
+                    Operator::Plus => "avox_add",
+                    Operator::Minus => "avox_sub",
+                    Operator::Multiply => "avox_mul",
+                    Operator::Divide => "avox_div",
+                    Operator::Power => "avox_pow",
+                    Operator::Equal => "avox_eq",
+                    Operator::NotEqual => "avox_ne",
+                    Operator::Less => "avox_lt",
+                    Operator::LessOrEqual => "avox_le",
+                    Operator::Greater => "avox_gt",
+                    Operator::GreaterOrEqual => "avox_ge",
+                    Operator::In => "avox_in",
+                    Operator::Xor => "avox_xor",
+                    operator => unreachable!("operator '{}' is lowered to the branches", operator),
+                };
+                self.call_runtime(function, &[lhs, rhs]);
+                self.unboxed(value_type);
+            },
+        }
+    }
+
+    /// Loads the number to the SSE register, integers are converted to floats.
+    fn float_operand(&mut self, value: ValueId, register: &'static str) {
+        let location = self.locations[&value];
+        match self.value_type(value) {
+            Type::Int => self.line(&format!("cvtsi2sdq {}, {}", location, register)),
+            _ => self.line(&format!("movq {}, {}", location, register)),
+        }
+    }
+
+    /// Computes the operator for the floats in `%xmm0` and `%xmm1`, comparisons of NaN fail like in the interpreter.
+    fn float_operation(&mut self, operator: Operator) {
+        let arithmetic = match operator {
+            Operator::Plus => Some("addsd"),
+            Operator::Minus => Some("subsd"),
+            Operator::Multiply => Some("mulsd"),
+            Operator::Divide => Some("divsd"),
+            _ => None,
+        };
+        if let Some(instruction) = arithmetic {
+            self.line(&format!("{} %xmm1, %xmm0", instruction));
+            return self.line("movq %xmm0, %rax")
+        }
+        self.line("ucomisd %xmm1, %xmm0");
+        match operator {
+            // NaN is unordered, it is equal to nothing
+            Operator::Equal => {
+                self.line("sete %al");
+                self.line("setnp %cl");
+                self.line("andb %cl, %al");
+            },
+            Operator::NotEqual => {
+                self.line("setne %al");
+                self.line("setp %cl");
+                self.line("orb %cl, %al");
+            },
+            _ => {
+                self.line("jnp 1f");
+                self.call("avox_nan_comparison");
+                self.label("1");
+                let condition = match operator {
+                    Operator::Less => "b",
+                    Operator::LessOrEqual => "be",
+                    Operator::Greater => "a",
+                    _ => "ae",
+                };
+                self.line(&format!("set{} %al", condition));
+            },
+        }
+        self.line("movzbq %al, %rax");
+    }
+
+    /// Calls the function of the program or the builtin, the result is the pointer to the value in `%rax`.
+    fn function_call(&mut self, name: &str, arguments: &[ValueId]) {
+        if self.program.functions.iter().any(|function| function.name == name) {
+            // the arguments after the sixth one go first, so they are at the top of the stack for the callee
+            let in_registers = arguments.len().min(ARGUMENTS.len());
+            let on_stack = arguments.len() - in_registers;
+            let staged: Vec<ValueId> = arguments[in_registers..].iter().chain(arguments[..in_registers].iter()).copied().collect();
+            let size = self.stage(&staged);
+            for (index, register) in ARGUMENTS.iter().enumerate().take(in_registers) {
+                self.line(&format!("movq {}(%rsp), {}", 8 * (on_stack + index), register));
+            }
+            self.call(&format!("avo_fn_{}", name));
+            return self.release(size)
+        }
+        match name {
+            "print" | "println" => {
+                let size = self.stage(arguments);
+                self.line(&format!("movq ${}, %rdi", arguments.len()));
+                self.line("movq %rsp, %rsi");
+                self.call(&format!("avox_{}", name));
+                self.release(size);
+            },
+            "pow" => self.call_runtime("avox_power", arguments),
+            _ => self.call_runtime(&format!("avox_{}", name), arguments),
+        }
+    }
+
+    fn terminator(&mut self, function: &Function, block: &Block, next: Option<BlockId>) {
+        match &block.terminator {
+            Terminator::Jump(target) => {
+                self.edge(function, block.id, *target);
+                if next != Some(*target) {
+                    let label = self.block_label(*target);
+                    self.line(&format!("jmp {}", label));
+                }
+            },
+            Terminator::Branch(condition, then, otherwise) => {
+                match self.value_type(*condition) {
+                    Type::Bool => self.load(*condition, "%rax"),
+                    _ => {
+                        self.position(block.terminator_pos);
+                        self.load(*condition, "%rdi");
+                        self.call("avox_condition");
+                    },
+                }
+                self.line("testq %rax, %rax");
+                if next == Some(*otherwise) && !has_phis(function.block(*then)) && !has_phis(function.block(*otherwise)) {
+                    let label = self.block_label(*then);
+                    return self.line(&format!("jnz {}", label))
+                }
+                // the phis of the other block are assigned on the separate path
+                let stub = match has_phis(function.block(*otherwise)) {
+                    true => {
+                        self.edges += 1;
+                        Some(format!(".L{}_e{}", self.index, self.edges))
+                    },
+                    false => None,
+                };
+                let target = stub.clone().unwrap_or_else(|| self.block_label(*otherwise));
+                self.line(&format!("jz {}", target));
+                self.edge(function, block.id, *then);
+                if stub.is_some() || next != Some(*then) {
+                    let label = self.block_label(*then);
+                    self.line(&format!("jmp {}", label));
+                }
+                if let Some(stub) = stub {
+                    self.label(&stub);
+                    self.edge(function, block.id, *otherwise);
+                    if next != Some(*otherwise) {
+                        let label = self.block_label(*otherwise);
+                        self.line(&format!("jmp {}", label));
+                    }
+                }
+            },
+            Terminator::Return(value) => {
+                self.boxed(*value);
+                if function.return_type != Type::Any {
+                    let (name, function_name) = (self.string(&function.return_type.to_string()), self.string(&function.name));
+                    if let Some(slot) = self.call_position {
+                        self.line(&format!("movq {}, %rcx", slot));
+                        self.line("movq %rcx, avox_position(%rip)");
+                    }
+                    self.line("movq %rax, %rdi");
+                    self.line(&format!("leaq {}(%rip), %rsi", name));
+                    self.line(&format!("leaq {}(%rip), %rdx", function_name));
+                    self.call("avox_returned");
+                }
+                for (register, slot) in self.saved.clone() {
+                    self.line(&format!("movq {}, {}", Location::Stack(slot), register));
+                }
+                self.line("leave");
+                self.line("ret");
+            },
+        }
+    }
+
+    /// Assigns the phis of the target block on the edge from the block, all incoming values are read before the first phi is written.
+    fn edge(&mut self, function: &Function, from: BlockId, to: BlockId) {
+        let moves: Vec<(ValueId, ValueId, Type)> = function.block(to).instructions.iter().filter_map(|instruction| match &instruction.operation {
+            Operation::Phi(incoming) => incoming.iter().find(|(source, _)| *source == from).map(|(_, value)| (instruction.result, *value, instruction.value_type)),
+            _ => None,
+        }).collect();
+        if let [(phi, value, value_type)] = moves.as_slice() {
+            self.converted(*value, *value_type);
+            return self.store(*phi)
+        }
+        let size = (8 * moves.len()).next_multiple_of(16);
+        if size > 0 {
+            self.line(&format!("subq ${}, %rsp", size));
+        }
+        for (index, (_, value, value_type)) in moves.iter().enumerate() {
+            self.converted(*value, *value_type);
+            self.line(&format!("movq %rax, {}(%rsp)", 8 * index));
+        }
+        for (index, (phi, _, _)) in moves.iter().enumerate() {
+            self.line(&format!("movq {}(%rsp), %rax", 8 * index));
+            self.store(*phi);
+        }
+        self.release(size);
+    }
+}
+
+#[inline]
+fn has_phis(block: &Block) -> bool {
+    matches!(block.instructions.first(), Some(Instruction { operation: Operation::Phi(_), .. }))
+}
+
+/// First and last positions where every value is live, the blocks are numbered in the order of the layout.
+///
+/// The liveness of the blocks is found by the backward data flow: the incoming value of the phi is live at the end
+/// of the predecessor, not at the start of the block of the phi. The interval of the value covers all blocks between
+/// its definition and its last use, including the whole loops where the value is live at the back edge.
+fn live_intervals(function: &Function, order: &[BlockId]) -> HashMap<ValueId, (usize, usize)> {
+    let mut live_in: HashMap<BlockId, HashSet<ValueId>> = HashMap::new();
+    let mut live_out: HashMap<BlockId, HashSet<ValueId>> = HashMap::new();
+    let mut is_changed = true;
+    while is_changed {
+        is_changed = false;
+        for id in order.iter().rev() {
+            let block = function.block(*id);
+            let mut out = HashSet::new();
+            for successor in block.terminator.successors() {
+                out.extend(live_in.get(&successor).into_iter().flatten().copied());
+                for instruction in &function.block(successor).instructions {
+                    if let Operation::Phi(incoming) = &instruction.operation {
+                        out.extend(incoming.iter().filter(|(source, _)| source == id).map(|(_, value)| *value));
+                    }
+                }
+            }
+            let defined: HashSet<ValueId> = block.instructions.iter().map(|instruction| instruction.result).collect();
+            let mut live: HashSet<ValueId> = out.iter().copied().filter(|value| !defined.contains(value)).collect();
+            for instruction in block.instructions.iter().filter(|instruction| !matches!(instruction.operation, Operation::Phi(_))) {
+                live.extend(instruction.operation.operands().into_iter().filter(|value| !defined.contains(value)));
+            }
+            live.extend(block.terminator.operands().into_iter().filter(|value| !defined.contains(value)));
+            if live_in.get(id) != Some(&live) || live_out.get(id) != Some(&out) {
+                live_in.insert(*id, live);
+                live_out.insert(*id, out);
+                is_changed = true;
+            }
+        }
+    }
+    let mut intervals = HashMap::new();
+    let mut position = 0;
+    for id in order {
+        let block = function.block(*id);
+        let start = position;
+        live_in[id].iter().for_each(|value| extend(&mut intervals, *value, start));
+        for instruction in &block.instructions {
+            if let Operation::Phi(_) = instruction.operation {
+                extend(&mut intervals, instruction.result, start);
+                continue
+            }
+            position += 1;
+            extend(&mut intervals, instruction.result, position);
+            instruction.operation.operands().into_iter().for_each(|value| extend(&mut intervals, value, position));
+        }
+        position += 1;
+        block.terminator.operands().into_iter().chain(live_out[id].iter().copied()).for_each(|value| extend(&mut intervals, value, position));
+        position += 1;
+    }
+    intervals
+}
+
+#[inline]
+fn extend(intervals: &mut HashMap<ValueId, (usize, usize)>, value: ValueId, position: usize) {
+    let interval = intervals.entry(value).or_insert((position, position));
+    interval.0 = interval.0.min(position);
+    interval.1 = interval.1.max(position);
+}
+
+/// Linear scan: the intervals get the free registers in the order of their starts. If there is no free register,
+/// the interval which ends last goes to the stack. Returns the registers of the values, the others are on the stack.
+fn allocate(intervals: &HashMap<ValueId, (usize, usize)>) -> HashMap<ValueId, &'static str> {
+    let mut sorted: Vec<(usize, usize, ValueId)> = intervals.iter().map(|(value, (start, end))| (*start, *end, *value)).collect();
+    sorted.sort();
+    let mut registers = HashMap::new();
+    // intervals in the registers with their ends
+    let mut active: Vec<(usize, ValueId)> = vec!();
+    let mut free: Vec<&'static str> = REGISTERS.iter().rev().copied().collect();
+    for (start, end, value) in sorted {
+        active.retain(|(active_end, active_value)| match *active_end < start {
+            true => {
+                free.push(registers[active_value]);
+                false
+            },
+            false => true,
+        });
+        if let Some(register) = free.pop() {
+            registers.insert(value, register);
+            active.push((end, value));
+            continue
+        }
+        let (index, (last_end, last)) = active.iter().copied().enumerate().max_by_key(|(_, interval)| *interval).unwrap();
+        if last_end > end {
+            let register = registers.remove(&last).unwrap();
+            registers.insert(value, register);
+            active[index] = (end, value);
+        }
+    }
+    registers
+}
+
+/// Escapes the string for `.asciz`, the bytes besides the printable ASCII are written in octal.
+fn escape(text: &str) -> String {
+    text.bytes().map(|byte| match byte {
+        b'"' | b'\\' => format!("\\{}", byte as char),
+        b' '..=b'~' => (byte as char).to_string(),
+        _ => format!("\\{:03o}", byte),
+    }).collect()
+}
+
+#[inline]
+fn name_of(node: &Node) -> &str {
+    match &node.data {
+        Some(Token::Identifier { name, .. }) | Some(Token::Function { name, .. }) => name,
+        _ => "",
+    }
+}
+
+#[cfg(test)]
+fn generate(source: &str) -> Result<String, Vec<SyntaxError>> {
+    let tree = parse(source);
+    generate_x86_64(&tree)
+}
+
+#[test]
+fn test_compiled_output_is_same_as_interpreted() {
+    let programs = [
+        "fn fact(n: Int) -> Int {\n  if n <= 1 { return 1 }\n  n * fact(n - 1)\n}\nprintln(fact(20), 1.5, \"x\" + 0.1 + [1, \"a\"], 1 / 3.0, 0.1 + 0.2)",
+        "mut sum = 0\nfor i in 0:0.5:2 { sum += i }\nmut n = 0\nloop {\n  n += 1\n  if n == 2 { continue }\n  if n >= 4 { break }\n}\nprintln(sum, n, -n, 7 / -2, n > 2.5, 2.5 != n)",
+        "mut total = 0\nfn add(x: Int) { total += x }\nfor i in 1..=10 { add(i) }\nprintln(total)",
+        "mut xs = [3, 1]\nxs.push(5)\nxs[0] += 10\nprintln(xs, len(xs), 2 in xs, \"ell\" in \"hello\", \"hello\"[1], 1 is Int, 1..=3, 0:2:6)\nprintln(sqrt(2), floor(2.7), pow(2, 10), abs(-3), 2 ^ 0.5)",
+        "fn f(a: Int, b: Int, c, d: Int, e, f: Float, g: Int, h: Bool) {\n  mut s = 0\n  for i in 0..3 { s += a * i + b - c + d * e - g }\n  if h { s + f } else { s }\n}\nprintln(f(1, 2, 3, 4, 5, 6, 7, true))",
+        "mut x = 1\nfor i in 0..4 {\n  x = if i == 2 { \"s\" + x } else { x + i }\n}\nfor c in \"ab\" { print(c, x, \"\\n\") }\nprintln(not (x is String) xor true)",
+        "fn big(n: Int) -> Int { n * n }\nprintln(big(3037000499))\nprintln(big(3037000500))",
+        "zero = 0\nprintln(\"before\")\nprintln(1 / zero)",
+        "fn half(x: Float) -> Int { x / 2 }\nprintln(half(3))",
+        "xs = [1]\nxs.push(xs)\nys = [1]\nys.push(ys)\nprintln(xs, [xs], xs == xs, xs == ys, xs == [1, ys])",
+        "fn at(xs, i) { xs[i] }\nprintln(at([1, 2], 1))\nprintln(at([1, 2], 5))",
+        "fn twice(x: Int) { x * 2 }\nprintln(twice(2))\nprintln(twice(\"a\"))",
+        "fn f(x) { if x { 1 } else { 2 } }\nprintln(f(true))\nprintln(f(1))",
+        "fn root(x) { 1 + x.sqrt() }\nmut xs = [1]\nxs[0] += 1\nprintln(xs, root(4))\nprintln(root(\"x\"))",
+    ];
+    super::assert_same_output_as_interpreted("x86-64", &programs, |source, executable| {
+        if !cfg!(all(target_arch = "x86_64", target_os = "linux")) || Command::new("cc").arg("--version").output().is_err() {
+            return None
+        }
+        let assembly = executable.with_extension("s");
+        std::fs::write(&assembly, generate(source).unwrap()).unwrap();
+        link_x86_64(&assembly, executable).unwrap();
+        Some(())
+    });
+}
+
+#[test]
+fn test_registers_are_reused_and_spilled() {
+    // six values are live at once, the one which ends last goes to the stack
+    let mut intervals: HashMap<ValueId, (usize, usize)> = (0..6).map(|value| (ValueId(value), (value, 10 + value))).collect();
+    intervals.insert(ValueId(6), (20, 30));
+    let registers = allocate(&intervals);
+    let used: HashSet<&str> = (0..5).map(|value| registers[&ValueId(value)]).collect();
+    assert_eq!(5, used.len());
+    assert_eq!(None, registers.get(&ValueId(5)));
+    assert!(used.contains(registers[&ValueId(6)]));
+    // the value which ends before the others takes the register of the longest one
+    intervals.insert(ValueId(5), (5, 11));
+    let registers = allocate(&intervals);
+    assert_eq!(None, registers.get(&ValueId(4)));
+    assert_eq!(Some(&"%r15"), registers.get(&ValueId(5)));
+}
+
+#[test]
+fn test_unsupported_constructs() {
+    let message = |source: &str| generate(source).unwrap_err()[0].message.clone();
+    assert_eq!("Builtin 'split' is not supported by the x86-64 backend", message("split(\"a b\", \" \")"));
+    assert_eq!("Lambdas are not supported by the IR", message("f = { @ x -> x }"));
+    assert!(generate("fn split(a) { a }\nsplit(1)").is_ok());
+}
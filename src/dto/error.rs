@@ -1,3 +1,36 @@
+use crate::dto::node::MAX_DEPTH;
+use crate::dto::stack::stack_size;
+use crate::runtime::limits::Limit;
+
+#[derive(Debug, Clone)]
+pub struct SyntaxError {
+    pub pos: usize,
+    pub message: String,
+    /// The limit exceeded by the source, e.g. by the nesting deeper than [`MAX_DEPTH`], `None` for the other errors.
+    pub limit: Option<Limit>,
+}
+
+impl SyntaxError {
+
+    pub fn new(pos: usize, message: String) -> SyntaxError {
+        SyntaxError { pos, message, limit: None }
+    }
+
+    /// Error of the source which exceeds the limit, the message is the description of the limit.
+    pub fn exceeded(pos: usize, limit: Limit) -> SyntaxError {
+        SyntaxError { pos, message: limit.to_string(), limit: Some(limit) }
+    }
+
+    /// Error of the construction nested deeper than [`MAX_DEPTH`].
+    pub fn too_deep(pos: usize) -> SyntaxError {
+        SyntaxError::exceeded(pos, Limit::Nesting(MAX_DEPTH))
+    }
+
+    /// Error of the source which is too deep for the stack, e.g. the syntax tree deeper than `max_tree_depth()`.
+    pub fn no_stack(pos: usize) -> SyntaxError {
+        SyntaxError::exceeded(pos, Limit::Stack(stack_size()))
+    }
+}
 
 #[derive(Debug)]
-pub struct SyntaxError { pub pos: usize, pub message: String }
+pub struct RuntimeError { pub pos: usize, pub message: String }
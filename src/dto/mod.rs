@@ -1,7 +1,13 @@
 pub mod node;
 pub mod token;
 pub mod error;
+pub mod stack;
 pub use node::Node;
 pub use node::NodeType;
+pub use node::MAX_DEPTH;
 pub use token::Token;
-pub use error::SyntaxError;
\ No newline at end of file
+pub use token::Operator;
+pub use token::Symbol;
+pub use error::SyntaxError;
+pub use error::RuntimeError;
+pub use stack::StackGuard;
@@ -1,44 +1,124 @@
+use crate::dto::{ Operator, Token, SyntaxError };
+use crate::dto::stack::max_tree_depth;
 
-use crate::dto::Token;
+/// Maximal depth of the nested constructions in the source: brackets, calls, collections, blocks, lambdas and patterns.
+///
+/// The parser reads them recursively. The operators are not the nesting, so the long chain `1 + 1 + ...`
+/// is limited only by the depth of its tree, see `max_tree_depth()`.
+pub const MAX_DEPTH: usize = 256;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct Node {
     pub data: Option<Token>,
     pub node_type: NodeType,
     pub condition: Vec<Node>,
     pub children: Vec<Node>,
+    /// Offsets of the first and the last token of the node, including its keywords and closing brackets.
+    /// The parser records them, the nodes built by hand may have no extent.
+    pub extent: Option<(usize, usize)>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum NodeType {
     Token,
-    // Expression,
-    // If,
+    Expression,
+    Block,
+    If,
+    Call,
+    Range,
+    For,
+    Loop,
+    Break,
+    Continue,
+    Lambda,
+    Tuple,
+    List,
+    Map,
+    Match,
+    Arm,
+    Rest,
+    Function,
+    Return,
+    Type,
+    Struct,
+    Enum,
+    Variant,
+    Import,
+    Public,
+    Test,
     // Else,
-    // Match,
     // Repeat,
-    // For,
-    // Loop,
-    // Lambda,
 }
 
 impl Node {
 
     pub fn from(token: Token) -> Node {
+        Node::new(Some(token), NodeType::Token)
+    }
+
+    pub fn new(data: Option<Token>, node_type: NodeType) -> Node {
         Node {
-            data: Some(token),
-            node_type: NodeType::Token,
+            data,
+            node_type,
             condition: vec!(),
             children: vec!(),
+            extent: None,
+        }
+    }
+
+    /// Returns the offset of the token this node was created from,
+    /// or `0` for the synthetic nodes without token (e.g. the root block).
+    pub fn pos(&self) -> usize {
+        match &self.data {
+            Some(token) => token.pos(),
+            None => 0,
+        }
+    }
+
+    /// Returns the offsets of the first and the last token of the node, which are the offset of its token without the extent.
+    pub fn bounds(&self) -> (usize, usize) {
+        self.extent.unwrap_or((self.pos(), self.pos()))
+    }
+
+    /// Returns the declaration marked with `pub`, or the node itself if it is not public.
+    pub fn declaration(&self) -> &Node {
+        match self.node_type {
+            NodeType::Public => &self.children[0],
+            _ => self,
+        }
+    }
+
+    /// Returns the operator payload if the node is an operator application.
+    pub fn operator(&self) -> Option<Operator> {
+        match &self.data {
+            Some(Token::Operator { payload, .. }) => Some(*payload),
+            _ => None,
         }
     }
     
-    fn add_condition_child(&mut self, condition_child: Node) {
+    pub fn add_condition_child(&mut self, condition_child: Node) {
         self.condition.push(condition_child)
     }
 
-    fn add_child(&mut self, child: Node) {
-        self.condition.push(child)
+    pub fn add_child(&mut self, child: Node) {
+        self.children.push(child)
     }
-}
 
+    /// Returns the number of nodes on the longest path from this node down to a leaf.
+    ///
+    /// The walk stops with the error at the first node deeper than [`max_tree_depth`], so the tree
+    /// which is measured can be walked recursively. The walk itself is not recursive, so any tree can be measured.
+    pub fn depth(&self) -> Result<usize, SyntaxError> {
+        let max_depth = max_tree_depth();
+        let mut depth = 0;
+        let mut nodes = vec!((self, 1));
+        while let Some((node, level)) = nodes.pop() {
+            if level > max_depth {
+                return Err(SyntaxError::no_stack(node.pos()))
+            }
+            depth = depth.max(level);
+            nodes.extend(node.condition.iter().chain(&node.children).rev().map(|child| (child, level + 1)))
+        }
+        Ok(depth)
+    }
+}
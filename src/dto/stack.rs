@@ -0,0 +1,96 @@
+use std::cell::Cell;
+
+/// Stack in bytes which the parser, the analyzer, the optimizer and the interpreter may use for one script.
+///
+/// It is the half of the 2 MiB stack of the threads spawned by `std::thread`, the rest is left for the frames of the host
+/// and for the frames between the checks, so the deep recursion stops with the error instead of the stack overflow.
+pub const DEFAULT_STACK_SIZE: usize = 1024 * 1024;
+
+thread_local! {
+    /// Address of the stack where the outermost [`StackGuard`] of the thread is created and its size, the address is `0` without the guard.
+    static STACK: Cell<(usize, usize)> = const { Cell::new((0, 0)) };
+}
+
+/// Counts the stack used by the recursive walks of the syntax tree on the current thread from the place where it is created.
+///
+/// Only the outermost guard counts, so the walk started by the other walk, e.g. the interpreter called by the engine,
+/// shares the stack of the outer one. The walks check it with `has_stack`.
+pub struct StackGuard {
+    is_outermost: bool,
+}
+
+impl StackGuard {
+
+    pub fn new(size: usize) -> StackGuard {
+        let is_outermost = STACK.with(|stack| match stack.get() {
+            (0, _) => {
+                stack.set((stack_address(), size));
+                true
+            },
+            _ => false,
+        });
+        StackGuard { is_outermost }
+    }
+}
+
+impl Drop for StackGuard {
+    fn drop(&mut self) {
+        if self.is_outermost {
+            STACK.with(|stack| stack.set((0, 0)))
+        }
+    }
+}
+
+/// Returns `false` if the walk has used the stack of its [`StackGuard`], then it must stop instead of going deeper.
+pub fn has_stack() -> bool {
+    match STACK.with(Cell::get) {
+        (0, _) => true,
+        (base, size) => base.abs_diff(stack_address()) < size,
+    }
+}
+
+/// Returns the size of the stack counted by the outermost [`StackGuard`], or the default one without the guard.
+pub fn stack_size() -> usize {
+    match STACK.with(Cell::get) {
+        (0, _) => DEFAULT_STACK_SIZE,
+        (_, size) => size,
+    }
+}
+
+/// Returns the depth of the syntax tree which may be walked recursively with the stack of the outermost [`StackGuard`].
+///
+/// The deeper tree is the error of the parser, so the walks which don't check [`has_stack`], e.g. the drop of the tree,
+/// don't overflow the stack. The walks which need more than [`TREE_LEVEL_SIZE`] per level must check it.
+pub fn max_tree_depth() -> usize {
+    stack_size() / TREE_LEVEL_SIZE
+}
+
+/// Stack in bytes used by the recursive walks per level of the syntax tree in the debug build.
+const TREE_LEVEL_SIZE: usize = 4096;
+
+/// Address of the frame of this function, which is right below the frame of the caller.
+#[inline(never)]
+fn stack_address() -> usize {
+    let marker = 0u8;
+    std::hint::black_box(&marker) as *const u8 as usize
+}
+
+#[test]
+fn test_stack_guard() {
+    fn depth(level: usize) -> usize {
+        match has_stack() {
+            true => depth(std::hint::black_box(level + 1)),
+            false => level,
+        }
+    }
+    let outer = StackGuard::new(64 * 1024);
+    let inner = StackGuard::new(DEFAULT_STACK_SIZE);
+    assert_eq!(64 * 1024, stack_size());
+    let levels = depth(0);
+    assert!(levels > 0 && levels < 64 * 1024, "{}", levels);
+    drop(inner);
+    assert!(!has_stack() || depth(0) == levels);
+    drop(outer);
+    assert_eq!(DEFAULT_STACK_SIZE, stack_size());
+    assert!(has_stack());
+}
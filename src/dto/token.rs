@@ -1,11 +1,338 @@
 
+use std::borrow::Borrow;
+use std::fmt;
+use std::ops::Deref;
+use std::sync::Arc;
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum Token {
-    Operator { payload: String, pos: usize },
-    Identifier { name: String, pos: usize },
-    Function { name: String, pos: usize },
-    IntConstant { value: i32, pos: usize },
-    FloatConstant { value: f32, pos: usize },
-    StringConstant { value: String, pos: usize },
+    Operator { payload: Operator, pos: usize },
+    Identifier { name: Symbol, pos: usize },
+    Function { name: Symbol, pos: usize },
+    IntConstant { value: i64, pos: usize },
+    FloatConstant { value: f64, pos: usize },
+    StringConstant { value: Symbol, pos: usize },
+    BoolConstant { value: bool, pos: usize },
+    NoneConstant { pos: usize },
     NewLine { pos: usize },
-}
\ No newline at end of file
+    /// Line or block comment with its delimiters, it is returned only by [`TokenReader::parse_with_comments`].
+    ///
+    /// [`TokenReader::parse_with_comments`]: crate::parser::TokenReader::parse_with_comments
+    Comment { text: Symbol, pos: usize },
+}
+
+impl Token {
+
+    /// Returns the offset of the first symbol of the token in the source code.
+    pub fn pos(&self) -> usize {
+        match self {
+            Token::Operator { pos, .. } => *pos,
+            Token::Identifier { pos, .. } => *pos,
+            Token::Function { pos, .. } => *pos,
+            Token::IntConstant { pos, .. } => *pos,
+            Token::FloatConstant { pos, .. } => *pos,
+            Token::StringConstant { pos, .. } => *pos,
+            Token::BoolConstant { pos, .. } => *pos,
+            Token::NoneConstant { pos } => *pos,
+            Token::NewLine { pos } => *pos,
+            Token::Comment { pos, .. } => *pos,
+        }
+    }
+
+    /// Returns the same token at the other offset, e.g. after the edit of the source before it.
+    pub fn with_pos(&self, new_pos: usize) -> Token {
+        let mut token = self.clone();
+        match &mut token {
+            Token::Operator { pos, .. } => *pos = new_pos,
+            Token::Identifier { pos, .. } => *pos = new_pos,
+            Token::Function { pos, .. } => *pos = new_pos,
+            Token::IntConstant { pos, .. } => *pos = new_pos,
+            Token::FloatConstant { pos, .. } => *pos = new_pos,
+            Token::StringConstant { pos, .. } => *pos = new_pos,
+            Token::BoolConstant { pos, .. } => *pos = new_pos,
+            Token::NoneConstant { pos } => *pos = new_pos,
+            Token::NewLine { pos } => *pos = new_pos,
+            Token::Comment { pos, .. } => *pos = new_pos,
+        }
+        token
+    }
+
+    /// Returns `true` if the token is an operator or a keyword with the given payload.
+    pub fn is_operator(&self, expected: Operator) -> bool {
+        matches!(self, Token::Operator { payload, .. } if *payload == expected)
+    }
+}
+
+/// Operators and keywords, except for the literals `true`, `false` and `None`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Operator {
+    // brackets
+    LeftParen,
+    RightParen,
+    LeftBracket,
+    RightBracket,
+    LeftBrace,
+    RightBrace,
+    // arithmetic
+    Plus,
+    Minus,
+    /// Prefix `-`, it is not read from the source, but the parser puts it instead of the binary one.
+    UnaryMinus,
+    Multiply,
+    Divide,
+    Power,
+    // assignment
+    Assign,
+    AddAssign,
+    SubtractAssign,
+    MultiplyAssign,
+    DivideAssign,
+    // comparison
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    // ranges
+    Range,
+    InclusiveRange,
+    Colon,
+    // others
+    Dot,
+    Comma,
+    Arrow,
+    At,
+    // flow
+    If,
+    Else,
+    Loop,
+    For,
+    In,
+    Break,
+    Continue,
+    Match,
+    // functions and types
+    Fn,
+    Return,
+    Struct,
+    Enum,
+    Is,
+    // variables
+    Mut,
+    // modules
+    Import,
+    From,
+    Pub,
+    // logical and bits
+    And,
+    Or,
+    Xor,
+    Not,
+}
+
+impl Operator {
+
+    /// Returns the operator or the keyword written as the text, e.g. `Operator::AddAssign` for `+=`.
+    pub fn parse(text: &str) -> Option<Operator> {
+        let operator = match text {
+            "(" => Operator::LeftParen,
+            ")" => Operator::RightParen,
+            "[" => Operator::LeftBracket,
+            "]" => Operator::RightBracket,
+            "{" => Operator::LeftBrace,
+            "}" => Operator::RightBrace,
+            "+" => Operator::Plus,
+            "-" => Operator::Minus,
+            "*" => Operator::Multiply,
+            "/" => Operator::Divide,
+            "^" => Operator::Power,
+            "=" => Operator::Assign,
+            "+=" => Operator::AddAssign,
+            "-=" => Operator::SubtractAssign,
+            "*=" => Operator::MultiplyAssign,
+            "/=" => Operator::DivideAssign,
+            "==" => Operator::Equal,
+            "!=" => Operator::NotEqual,
+            "<" => Operator::Less,
+            "<=" => Operator::LessOrEqual,
+            ">" => Operator::Greater,
+            ">=" => Operator::GreaterOrEqual,
+            ".." => Operator::Range,
+            "..=" => Operator::InclusiveRange,
+            ":" => Operator::Colon,
+            "." => Operator::Dot,
+            "," => Operator::Comma,
+            "->" => Operator::Arrow,
+            "@" => Operator::At,
+            "if" => Operator::If,
+            "else" => Operator::Else,
+            "loop" => Operator::Loop,
+            "for" => Operator::For,
+            "in" => Operator::In,
+            "break" => Operator::Break,
+            "continue" => Operator::Continue,
+            "match" => Operator::Match,
+            "fn" => Operator::Fn,
+            "return" => Operator::Return,
+            "struct" => Operator::Struct,
+            "enum" => Operator::Enum,
+            "is" => Operator::Is,
+            "mut" => Operator::Mut,
+            "import" => Operator::Import,
+            "from" => Operator::From,
+            "pub" => Operator::Pub,
+            "and" => Operator::And,
+            "or" => Operator::Or,
+            "xor" => Operator::Xor,
+            "not" => Operator::Not,
+            _ => return None,
+        };
+        Some(operator)
+    }
+
+    /// Returns the binary operator applied by the compound assignment, e.g. `Operator::Plus` for `+=`.
+    pub fn compound_base(self) -> Option<Operator> {
+        match self {
+            Operator::AddAssign => Some(Operator::Plus),
+            Operator::SubtractAssign => Some(Operator::Minus),
+            Operator::MultiplyAssign => Some(Operator::Multiply),
+            Operator::DivideAssign => Some(Operator::Divide),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` for the plain and the compound assignments.
+    pub fn is_assignment(self) -> bool {
+        self == Operator::Assign || self.compound_base().is_some()
+    }
+
+    /// Returns the operator as it is written in the source, the unary minus is `u-`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Operator::LeftParen => "(",
+            Operator::RightParen => ")",
+            Operator::LeftBracket => "[",
+            Operator::RightBracket => "]",
+            Operator::LeftBrace => "{",
+            Operator::RightBrace => "}",
+            Operator::Plus => "+",
+            Operator::Minus => "-",
+            Operator::UnaryMinus => "u-",
+            Operator::Multiply => "*",
+            Operator::Divide => "/",
+            Operator::Power => "^",
+            Operator::Assign => "=",
+            Operator::AddAssign => "+=",
+            Operator::SubtractAssign => "-=",
+            Operator::MultiplyAssign => "*=",
+            Operator::DivideAssign => "/=",
+            Operator::Equal => "==",
+            Operator::NotEqual => "!=",
+            Operator::Less => "<",
+            Operator::LessOrEqual => "<=",
+            Operator::Greater => ">",
+            Operator::GreaterOrEqual => ">=",
+            Operator::Range => "..",
+            Operator::InclusiveRange => "..=",
+            Operator::Colon => ":",
+            Operator::Dot => ".",
+            Operator::Comma => ",",
+            Operator::Arrow => "->",
+            Operator::At => "@",
+            Operator::If => "if",
+            Operator::Else => "else",
+            Operator::Loop => "loop",
+            Operator::For => "for",
+            Operator::In => "in",
+            Operator::Break => "break",
+            Operator::Continue => "continue",
+            Operator::Match => "match",
+            Operator::Fn => "fn",
+            Operator::Return => "return",
+            Operator::Struct => "struct",
+            Operator::Enum => "enum",
+            Operator::Is => "is",
+            Operator::Mut => "mut",
+            Operator::Import => "import",
+            Operator::From => "from",
+            Operator::Pub => "pub",
+            Operator::And => "and",
+            Operator::Or => "or",
+            Operator::Xor => "xor",
+            Operator::Not => "not",
+        }
+    }
+}
+
+impl fmt::Display for Operator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Text of the name, the string or the comment read from the source.
+///
+/// The [`TokenReader`] reads every distinct text once, the tokens with the same text share it,
+/// so the repeated names don't allocate. The text is shared atomically, so the tokens and the trees may be sent to the other thread.
+///
+/// [`TokenReader`]: crate::parser::TokenReader
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol(Arc<str>);
+
+impl Symbol {
+
+    /// Returns the text of the symbol, e.g. to compare it with the `String` or to look it up in the map.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Deref for Symbol {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for Symbol {
+
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for Symbol {
+
+    fn from(text: &str) -> Symbol {
+        Symbol(Arc::from(text))
+    }
+}
+
+impl From<String> for Symbol {
+
+    fn from(text: String) -> Symbol {
+        Symbol(Arc::from(text))
+    }
+}
+
+impl PartialEq<str> for Symbol {
+
+    fn eq(&self, other: &str) -> bool {
+        *self.0 == *other
+    }
+}
+
+impl PartialEq<&str> for Symbol {
+
+    fn eq(&self, other: &&str) -> bool {
+        *self.0 == **other
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
@@ -0,0 +1,302 @@
+use crate::analyzer::{ resolve_with_globals, check_exhaustiveness };
+use crate::dto::{ SyntaxError, RuntimeError, StackGuard };
+use crate::dto::stack::DEFAULT_STACK_SIZE;
+#[cfg(test)]
+use crate::dto::MAX_DEPTH;
+#[cfg(test)]
+use crate::dto::stack::max_tree_depth;
+use crate::optimizer::fold_constants;
+use crate::parser::{ TokenReader, TreeBuilder };
+use crate::runtime::{ Interpreter, Value };
+use crate::runtime::function::NativeFunction;
+use crate::runtime::limits::{ Limit, Limits };
+use std::cell::RefCell;
+use std::fmt;
+use std::io::{ self, BufRead, Write };
+use std::rc::Rc;
+
+/// Avo interpreter embedded into the Rust program.
+///
+/// Every call of [`eval`] runs in the same global scope, so the functions and variables
+/// defined by one script are visible to the next ones and to [`call`].
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// use avoc::{ Engine, Value };
+///
+/// let mut engine = Engine::new();
+/// engine.register_fn("double", |arguments| match arguments.as_slice() {
+///     [Value::Int(value)] => Ok(Value::Int(value * 2)),
+///     _ => Err(String::from("'double' expects Int")),
+/// });
+/// engine.set_value("base", 20);
+/// engine.eval("fn answer(extra) { double(base) + extra }").unwrap();
+///
+/// assert_eq!(Value::Int(42), engine.call("answer", vec!(Value::Int(2))).unwrap());
+/// ```
+///
+/// [`eval`]: Engine::eval
+/// [`call`]: Engine::call
+pub struct Engine {
+    interpreter: Interpreter,
+    /// Buffer of the output when it is captured with [`capture_output`].
+    ///
+    /// [`capture_output`]: Engine::capture_output
+    output: Option<Rc<RefCell<Vec<u8>>>>,
+}
+
+/// Error of the script: the syntax and name errors found before the execution, the runtime error,
+/// the exceeded limit, or the failed conversion of the result to the Rust type.
+#[derive(Debug)]
+pub enum Error {
+    Syntax(Vec<SyntaxError>),
+    Runtime(RuntimeError),
+    Limit { limit: Limit, pos: usize },
+    Conversion(String),
+}
+
+/// Shared buffer which collects the output of the scripts.
+struct Capture(Rc<RefCell<Vec<u8>>>);
+
+impl Default for Engine {
+
+    fn default() -> Engine {
+        Engine::new()
+    }
+}
+
+impl Engine {
+
+    pub fn new() -> Engine {
+        Engine {
+            interpreter: Interpreter::new(),
+            output: None,
+        }
+    }
+
+    /// Runs the script and returns the value of its last statement.
+    pub fn eval(&mut self, source: &str) -> Result<Value, Error> {
+        let _guard = StackGuard::new(DEFAULT_STACK_SIZE);
+        let tokens = TokenReader::new().parse(source).map_err(|e| syntax_error(vec!(e)))?;
+        let mut tree = TreeBuilder::new().build_tree(&tokens).map_err(|e| syntax_error(vec!(e)))?;
+        let mut errors = resolve_with_globals(&tree, &self.interpreter.global_names());
+        errors.extend(check_exhaustiveness(&tree));
+        errors.extend(fold_constants(&mut tree));
+        if !errors.is_empty() {
+            errors.sort_by_key(|e| e.pos);
+            return Err(syntax_error(errors))
+        }
+        self.interpreter.restart_limits();
+        let result = self.interpreter.run(&tree);
+        result.map_err(|e| self.runtime_error(e))
+    }
+
+    /// Calls the function defined by the script or registered by the host.
+    pub fn call(&mut self, name: &str, arguments: Vec<Value>) -> Result<Value, Error> {
+        let _guard = StackGuard::new(DEFAULT_STACK_SIZE);
+        match self.interpreter.global(name) {
+            Some(function) => {
+                self.interpreter.restart_limits();
+                let result = self.interpreter.call(&function, arguments, 0);
+                result.map_err(|e| self.runtime_error(e))
+            },
+            None => Err(Error::Runtime(RuntimeError { pos: 0, message: format!("Unknown function '{}'", name) })),
+        }
+    }
+
+    /// Limits the resources of the following calls of [`eval`] and [`call`], every call has the whole budget.
+    ///
+    /// [`eval`]: Engine::eval
+    /// [`call`]: Engine::call
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.interpreter.set_limits(limits)
+    }
+
+    /// Makes the Rust function available to the scripts as the global function.
+    ///
+    /// The error returned by the function becomes the runtime error of the script.
+    pub fn register_fn(&mut self, name: &str, function: impl Fn(Vec<Value>) -> Result<Value, String> + 'static) {
+        let function = NativeFunction { name: name.to_string(), function: Box::new(function) };
+        self.interpreter.set_global(name, Value::Native(Rc::new(function)))
+    }
+
+    /// Declares the immutable global variable, it replaces the variable with the same name.
+    pub fn set_value(&mut self, name: &str, value: impl Into<Value>) {
+        self.interpreter.set_global(name, value.into())
+    }
+
+    /// Returns the value of the global variable, use `TryFrom` to convert it to the Rust type.
+    pub fn get_value(&self, name: &str) -> Option<Value> {
+        self.interpreter.global(name)
+    }
+
+    /// Replaces the input of `readLine()`, which is the standard input by default.
+    pub fn set_input(&mut self, input: impl BufRead + 'static) {
+        self.interpreter.set_input(Box::new(input))
+    }
+
+    /// Replaces the output of `print()`, which is the standard output by default.
+    pub fn set_output(&mut self, output: impl Write + 'static) {
+        self.output = None;
+        self.interpreter.set_output(Box::new(output))
+    }
+
+    /// Collects the output of the scripts instead of printing it, the output is returned by [`take_output`].
+    ///
+    /// [`take_output`]: Engine::take_output
+    pub fn capture_output(&mut self) {
+        let buffer = Rc::new(RefCell::new(vec!()));
+        self.interpreter.set_output(Box::new(Capture(buffer.clone())));
+        self.output = Some(buffer);
+    }
+
+    /// Returns the output collected since the previous call, it is empty if the output is not captured.
+    pub fn take_output(&mut self) -> String {
+        match &self.output {
+            Some(buffer) => String::from_utf8_lossy(&buffer.borrow_mut().split_off(0)).to_string(),
+            None => String::new(),
+        }
+    }
+}
+
+impl Engine {
+
+    /// Tells the exceeded limit from the other runtime errors.
+    fn runtime_error(&self, error: RuntimeError) -> Error {
+        match self.interpreter.exceeded_limit() {
+            Some(limit) => Error::Limit { limit: limit.clone(), pos: error.pos },
+            None => Error::Runtime(error),
+        }
+    }
+}
+
+/// Tells the exceeded limit, e.g. the nesting depth, from the other syntax errors.
+fn syntax_error(errors: Vec<SyntaxError>) -> Error {
+    match errors.iter().find_map(|e| e.limit.clone().map(|limit| (limit, e.pos))) {
+        Some((limit, pos)) => Error::Limit { limit, pos },
+        None => Error::Syntax(errors),
+    }
+}
+
+impl Write for Capture {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Syntax(errors) => {
+                let messages: Vec<String> = errors.iter().map(|e| format!("{} at {}", e.message, e.pos)).collect();
+                write!(f, "{}", messages.join("\n"))
+            },
+            Error::Runtime(e) => write!(f, "{} at {}", e.message, e.pos),
+            Error::Limit { limit, pos } => write!(f, "{} at {}", limit, pos),
+            Error::Conversion(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<String> for Error {
+    fn from(message: String) -> Error {
+        Error::Conversion(message)
+    }
+}
+
+#[test]
+fn test_engine_eval_and_call() {
+    use std::convert::TryFrom;
+
+    let mut engine = Engine::new();
+    engine.set_value("names", vec!("a", "b"));
+    engine.eval("mut count = 0\nfn greet(name) {\n  count += 1\n  format(\"hi {}\", name)\n}").unwrap();
+    let greetings = engine.eval("names.map{ @ name -> greet(name) }").unwrap();
+    assert_eq!(vec!("hi a", "hi b"), Vec::<String>::try_from(greetings).unwrap());
+    assert_eq!(Some(Value::Int(2)), engine.get_value("count"));
+    assert_eq!(Value::Str(String::from("hi c")), engine.call("greet", vec!(Value::from("c"))).unwrap());
+    assert_eq!("Unknown function 'nope' at 0", engine.call("nope", vec!()).unwrap_err().to_string());
+}
+
+#[test]
+fn test_engine_errors_and_natives() {
+    let mut engine = Engine::new();
+    engine.register_fn("fail", |_| Err(String::from("failed in Rust")));
+    assert_eq!("failed in Rust at 4", engine.eval("1 + fail()").unwrap_err().to_string());
+    assert_eq!("Undefined variable 'x' at 0\nUndefined variable 'y' at 4", engine.eval("x + y").unwrap_err().to_string());
+    assert_eq!(Value::Bool(true), engine.eval("fail is Function").unwrap());
+}
+
+#[test]
+fn test_engine_output() {
+    let mut engine = Engine::new();
+    engine.capture_output();
+    engine.set_input(io::Cursor::new("Avo\n"));
+    engine.eval("println(\"Hello,\", readLine())").unwrap();
+    assert_eq!("Hello, Avo\n", engine.take_output());
+    assert_eq!("", engine.take_output());
+}
+
+#[test]
+fn test_engine_limits() {
+    use std::time::Duration;
+
+    let mut engine = Engine::new();
+    engine.set_limits(Limits { max_steps: Some(1000), ..Limits::default() });
+    assert!(matches!(engine.eval("loop { }"), Err(Error::Limit { limit: Limit::Steps(1000), .. })));
+    // every script has the whole budget
+    assert_eq!(Value::Int(3), engine.eval("1 + 2").unwrap());
+    engine.set_limits(Limits { max_depth: Some(50), ..Limits::default() });
+    engine.eval("fn down(n) { if n == 0 { 0 } else { down(n - 1) } }").unwrap();
+    assert_eq!(Value::Int(0), engine.call("down", vec!(Value::Int(40))).unwrap());
+    assert_eq!("Recursion depth limit of 50 is exceeded at 36", engine.call("down", vec!(Value::Int(60))).unwrap_err().to_string());
+    engine.set_limits(Limits { max_memory: Some(1000), ..Limits::default() });
+    assert!(matches!(engine.eval("mut s = \"\"\nloop { s += \"abc\" }"), Err(Error::Limit { limit: Limit::Memory(1000), .. })));
+    engine.set_limits(Limits { timeout: Some(Duration::from_millis(10)), ..Limits::default() });
+    assert!(matches!(engine.eval("loop { }"), Err(Error::Limit { limit: Limit::Timeout(_), .. })));
+    engine.set_limits(Limits::sandbox());
+    assert_eq!("Builtin 'readFile' is not allowed at 0", engine.eval("readFile(\"/etc/passwd\")").unwrap_err().to_string());
+    assert_eq!(Value::Float(2.0), engine.eval("sqrt(4)").unwrap());
+    // runtime errors are not the limits
+    assert!(matches!(engine.eval("zero = 0\n1 / zero"), Err(Error::Runtime(_))));
+}
+
+#[test]
+fn test_engine_stack_limit() {
+    // the threads spawned by the host have the default stack of 2 MiB
+    let result = std::thread::spawn(|| {
+        let mut engine = Engine::new();
+        let unbounded = engine.eval("fn g(n) { g(n + 1) }\ng(0)").map(|_| ()).map_err(|e| e.to_string());
+        engine.set_limits(Limits { max_depth: Some(100_000), ..Limits::sandbox() });
+        engine.eval("fn f(n) { if n == 0 { 0 } else { 1 + f(n - 1) } }").unwrap();
+        let deep = engine.call("f", vec!(Value::Int(50_000))).map(|_| ()).map_err(|e| e.to_string());
+        (unbounded, engine.call("f", vec!(Value::Int(20))).is_ok(), deep)
+    }).join().unwrap();
+    let message = format!("Stack limit of {} bytes is exceeded", DEFAULT_STACK_SIZE);
+    assert!(matches!(result, (Err(ref unbounded), true, Err(ref limited)) if unbounded.starts_with(&message) && limited.starts_with(&message)), "{:?}", result);
+}
+
+#[test]
+fn test_engine_nesting_limit() {
+    let mut engine = Engine::new();
+    engine.set_limits(Limits::sandbox());
+    let source = format!("{}1{}", "(".repeat(50_000), ")".repeat(50_000));
+    assert!(matches!(engine.eval(&source), Err(Error::Limit { limit: Limit::Nesting(MAX_DEPTH), .. }) | Err(Error::Limit { limit: Limit::Stack(_), .. })));
+    let source = format!("x = {}1", "-".repeat(200_000));
+    assert_eq!(format!("Stack limit of {} bytes is exceeded at {}", DEFAULT_STACK_SIZE, 200_004 - max_tree_depth()), engine.eval(&source).unwrap_err().to_string());
+    assert!(matches!(engine.eval(&"[".repeat(20_000)), Err(Error::Limit { .. })));
+    let source = format!("{}1{}", "(".repeat(60), ")".repeat(60));
+    assert_eq!(Value::Int(1), engine.eval(&source).unwrap());
+    // the long chains of the operators are not the nesting
+    let source = format!("n = 1\n{}n", "n + ".repeat(150));
+    assert_eq!(Value::Int(151), engine.eval(&source).unwrap());
+}
@@ -0,0 +1,470 @@
+use crate::dto::{ Node, NodeType, Token };
+use crate::parser::TokenReader;
+use std::collections::HashMap;
+#[cfg(test)]
+use crate::parser::parse;
+
+/// Format of the dumps of the tokens and the syntax trees.
+///
+/// The dumps are stable: the fields are always in the same order and the same input gives the same text,
+/// so they can be stored in the snapshots and compared between the versions of the parser.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    /// Pretty-printed JSON, the objects without the nested lists are written on one line.
+    Json,
+    /// S-expressions, one node per line.
+    Sexpr,
+    /// Indented tree for reading.
+    Tree,
+}
+
+impl Format {
+
+    pub fn from_name(name: &str) -> Option<Format> {
+        match name {
+            "json" => Some(Format::Json),
+            "sexpr" => Some(Format::Sexpr),
+            "tree" => Some(Format::Tree),
+            _ => None,
+        }
+    }
+}
+
+/// Returns the offsets of the first symbol of the token and the symbol after its last one.
+///
+/// Tokens keep only the offset of the start, the end is found from the source the token was read from.
+pub fn span(token: &Token, source: &str) -> (usize, usize) {
+    let start = token.pos();
+    let rest = source.as_bytes().get(start..).unwrap_or_default();
+    let count = |predicate: fn(u8) -> bool| rest.iter().take_while(|byte| predicate(**byte)).count();
+    let length = match token {
+        Token::Operator { payload, .. } => payload.as_str().len(),
+        Token::Identifier { name, .. } | Token::Function { name, .. } => name.len(),
+        Token::IntConstant { .. } => count(|byte| byte.is_ascii_digit()),
+        Token::FloatConstant { .. } => {
+            let integer = count(|byte| byte.is_ascii_digit());
+            let fraction = match rest.get(integer) {
+                Some(b'.') => 1 + rest[integer + 1..].iter().take_while(|byte| byte.is_ascii_digit()).count(),
+                _ => 0,
+            };
+            let percent = matches!(rest.get(integer + fraction), Some(b'%')) as usize;
+            integer + fraction + percent
+        },
+        // the value is the text between the quotes with the escapes as they are
+        Token::StringConstant { value, .. } => value.len() + 2,
+        Token::BoolConstant { value: true, .. } => 4,
+        Token::BoolConstant { value: false, .. } => 5,
+        Token::NoneConstant { .. } => 4,
+        Token::NewLine { .. } => count(|byte| byte == b'\n'),
+        Token::Comment { text, .. } => text.len(),
+    };
+    (start, start + length)
+}
+
+/// Dumps the tokens with their spans.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```
+/// # use avoc::internals::{ dump_tokens, Format, TokenReader };
+/// let tokens = TokenReader::new().parse("a + 1").unwrap();
+///
+/// assert_eq!(
+///     "(tokens\n  (Identifier \"a\" (span 0 1))\n  (Operator \"+\" (span 2 3))\n  (IntConstant 1 (span 4 5)))\n",
+///     dump_tokens(&tokens, "a + 1", Format::Sexpr),
+/// );
+/// ```
+pub fn dump_tokens(tokens: &[Token], source: &str, format: Format) -> String {
+    match format {
+        Format::Json => Json::Array(tokens.iter().map(|token| token_json(token, span(token, source))).collect()).to_string() + "\n",
+        Format::Sexpr => {
+            let items: Vec<Vec<String>> = tokens.iter().map(|token| vec!(token_sexpr(token, span(token, source)))).collect();
+            sexpr(String::from("tokens"), items) + "\n"
+        },
+        Format::Tree => tokens.iter().map(|token| format!("{}\n", token_label(token, span(token, source)))).collect(),
+    }
+}
+
+/// Dumps the syntax tree of the source, the span of the node covers its keywords, its closing brackets and all nodes below it.
+///
+/// The tree builder creates some tokens which are not in the source, e.g. `u-` for the unary minus,
+/// they get the span of the token of the source at the same offset. The step `None` which the tree builder adds
+/// to the range `a..b` is not dumped at all.
+pub fn dump_tree(root: &Node, source: &str, format: Format) -> String {
+    let tokens = TokenReader::new().parse(source).unwrap_or_default();
+    let ends: HashMap<usize, usize> = tokens.iter().map(|token| span(token, source)).collect();
+    let spans = |pos: usize| (pos, ends.get(&pos).copied().unwrap_or(pos));
+    match format {
+        Format::Json => node_json(root, &spans).to_string() + "\n",
+        Format::Sexpr => node_sexpr(root, &spans) + "\n",
+        Format::Tree => {
+            let mut text = format!("{}\n", node_label(root, &spans));
+            node_tree(root, &spans, "", &mut text);
+            text
+        },
+    }
+}
+
+/// Span of the node from the start of its first token to the end of the last one.
+///
+/// The nodes without the extent cover their token and the nodes below them,
+/// the synthetic nodes without the tokens and the children have no span.
+fn node_span(node: &Node, spans: &dyn Fn(usize) -> (usize, usize)) -> Option<(usize, usize)> {
+    if let Some((first, last)) = node.extent {
+        return Some((spans(first).0, spans(last).1))
+    }
+    node.data.iter().map(|token| spans(token.pos()))
+        .chain(node.condition.iter().chain(children(node)).filter_map(|child| node_span(child, spans)))
+        .reduce(|(start, end), (child_start, child_end)| (start.min(child_start), end.max(child_end)))
+}
+
+/// Children of the node without the default step of the range, which has no token in the source.
+fn children(node: &Node) -> &[Node] {
+    match (node.node_type, node.children.as_slice()) {
+        (NodeType::Range, [_, _, Node { data: Some(Token::NoneConstant { pos }), .. }]) if *pos == node.pos() => &node.children[..2],
+        _ => &node.children,
+    }
+}
+
+/// Name of the variant of the token and its value.
+fn token_parts(token: &Token) -> (&'static str, Option<Json>) {
+    match token {
+        Token::Operator { payload, .. } => ("Operator", Some(Json::String(payload.to_string()))),
+        Token::Identifier { name, .. } => ("Identifier", Some(Json::String(name.to_string()))),
+        Token::Function { name, .. } => ("Function", Some(Json::String(name.to_string()))),
+        Token::IntConstant { value, .. } => ("IntConstant", Some(Json::Number(value.to_string()))),
+        Token::FloatConstant { value, .. } if value.is_finite() => ("FloatConstant", Some(Json::Number(format!("{:?}", value)))),
+        Token::FloatConstant { .. } => ("FloatConstant", Some(Json::Null)),
+        Token::StringConstant { value, .. } => ("StringConstant", Some(Json::String(value.to_string()))),
+        Token::BoolConstant { value, .. } => ("BoolConstant", Some(Json::Bool(*value))),
+        Token::NoneConstant { .. } => ("NoneConstant", None),
+        Token::NewLine { .. } => ("NewLine", None),
+        Token::Comment { text, .. } => ("Comment", Some(Json::String(text.to_string()))),
+    }
+}
+
+fn span_json((start, end): (usize, usize)) -> Json {
+    Json::Array(vec!(Json::Number(start.to_string()), Json::Number(end.to_string())))
+}
+
+fn token_json(token: &Token, span: (usize, usize)) -> Json {
+    let (kind, value) = token_parts(token);
+    let mut fields = vec!((String::from("kind"), Json::String(kind.to_string())));
+    fields.extend(value.map(|value| (String::from("value"), value)));
+    fields.push((String::from("span"), span_json(span)));
+    Json::Object(fields)
+}
+
+fn node_json(node: &Node, spans: &dyn Fn(usize) -> (usize, usize)) -> Json {
+    let mut fields = vec!((String::from("type"), Json::String(format!("{:?}", node.node_type))));
+    fields.extend(node.data.iter().map(|token| (String::from("token"), token_json(token, spans(token.pos())))));
+    fields.extend(node_span(node, spans).map(|span| (String::from("span"), span_json(span))));
+    for (name, nodes) in [("condition", node.condition.as_slice()), ("children", children(node))] {
+        if !nodes.is_empty() {
+            fields.push((name.to_string(), Json::Array(nodes.iter().map(|child| node_json(child, spans)).collect())));
+        }
+    }
+    Json::Object(fields)
+}
+
+fn token_sexpr(token: &Token, (start, end): (usize, usize)) -> String {
+    match token_parts(token) {
+        (kind, Some(value)) => format!("({} {} (span {} {}))", kind, value, start, end),
+        (kind, None) => format!("({} (span {} {}))", kind, start, end),
+    }
+}
+
+/// The list with the head on the first line and every item on its own line, the items are lists of lines.
+fn sexpr(head: String, items: Vec<Vec<String>>) -> String {
+    let mut lines = vec!(format!("({}", head));
+    for item in items {
+        lines.extend(item.into_iter().map(|line| format!("  {}", line)));
+    }
+    let last = lines.len() - 1;
+    lines[last].push(')');
+    lines.join("\n")
+}
+
+fn node_sexpr(node: &Node, spans: &dyn Fn(usize) -> (usize, usize)) -> String {
+    let mut head = format!("{:?}", node.node_type);
+    if let Some(token) = &node.data {
+        head = format!("{} {}", head, token_sexpr(token, spans(token.pos())));
+    }
+    let is_leaf = node.condition.is_empty() && children(node).is_empty();
+    if let (false, Some((start, end))) = (is_leaf && node.data.is_some(), node_span(node, spans)) {
+        head = format!("{} (span {} {})", head, start, end);
+    }
+    let mut items = vec!();
+    if !node.condition.is_empty() {
+        let condition = node.condition.iter().map(|child| lines(node_sexpr(child, spans))).collect();
+        items.push(lines(sexpr(String::from("condition"), condition)));
+    }
+    items.extend(children(node).iter().map(|child| lines(node_sexpr(child, spans))));
+    sexpr(head, items)
+}
+
+#[inline]
+fn lines(text: String) -> Vec<String> {
+    text.lines().map(str::to_string).collect()
+}
+
+fn token_label(token: &Token, (start, end): (usize, usize)) -> String {
+    match token_parts(token) {
+        (kind, Some(value)) => format!("{} {} {}..{}", kind, value, start, end),
+        (kind, None) => format!("{} {}..{}", kind, start, end),
+    }
+}
+
+/// The token nodes are shown as their tokens, the other nodes as their types with the tokens.
+fn node_label(node: &Node, spans: &dyn Fn(usize) -> (usize, usize)) -> String {
+    let span = node_span(node, spans).map(|(start, end)| format!(" {}..{}", start, end)).unwrap_or_default();
+    match (&node.data, node.node_type) {
+        (Some(token), NodeType::Token) => token_label(token, spans(token.pos())),
+        (Some(token), node_type) => match token_parts(token) {
+            (kind, Some(value)) => format!("{:?} {} {}{}", node_type, kind, value, span),
+            (kind, None) => format!("{:?} {}{}", node_type, kind, span),
+        },
+        (None, node_type) => format!("{:?}{}", node_type, span),
+    }
+}
+
+fn node_tree(node: &Node, spans: &dyn Fn(usize) -> (usize, usize), indent: &str, text: &mut String) {
+    let children: Vec<(&str, &Node)> = node.condition.iter().map(|child| ("condition: ", child))
+        .chain(children(node).iter().map(|child| ("", child))).collect();
+    for (index, (role, child)) in children.iter().enumerate() {
+        let is_last = index + 1 == children.len();
+        text.push_str(&format!("{}{}{}{}\n", indent, if is_last { "└── " } else { "├── " }, role, node_label(child, spans)));
+        node_tree(child, spans, &format!("{}{}", indent, if is_last { "    " } else { "│   " }), text);
+    }
+}
+
+/// JSON value written with the indentation of two spaces.
+enum Json {
+    Null,
+    Bool(bool),
+    /// Number as it is written.
+    Number(String),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+
+    /// Scalars, lists of the scalars and the objects of such values fit on one line.
+    fn is_inline(&self) -> bool {
+        match self {
+            Json::Array(items) => items.iter().all(|item| !matches!(item, Json::Array(_) | Json::Object(_))),
+            Json::Object(fields) => fields.iter().all(|(_, value)| value.is_inline()),
+            _ => true,
+        }
+    }
+
+    fn write(&self, indent: &str, text: &mut String) {
+        match self {
+            Json::Null => text.push_str("null"),
+            Json::Bool(value) => text.push_str(&value.to_string()),
+            Json::Number(value) => text.push_str(value),
+            Json::String(value) => text.push_str(&quote(value)),
+            Json::Array(items) if items.is_empty() => text.push_str("[]"),
+            Json::Array(items) if self.is_inline() => {
+                let items: Vec<String> = items.iter().map(Json::to_string).collect();
+                text.push_str(&format!("[{}]", items.join(", ")));
+            },
+            Json::Object(fields) if self.is_inline() => {
+                let fields: Vec<String> = fields.iter().map(|(name, value)| format!("{}: {}", quote(name), value)).collect();
+                text.push_str(&format!("{{ {} }}", fields.join(", ")));
+            },
+            Json::Array(items) => {
+                text.push_str("[\n");
+                for (index, item) in items.iter().enumerate() {
+                    text.push_str(indent);
+                    text.push_str("  ");
+                    item.write(&format!("{}  ", indent), text);
+                    text.push_str(if index + 1 < items.len() { ",\n" } else { "\n" });
+                }
+                text.push_str(indent);
+                text.push(']');
+            },
+            Json::Object(fields) => {
+                text.push_str("{\n");
+                for (index, (name, value)) in fields.iter().enumerate() {
+                    text.push_str(&format!("{}  {}: ", indent, quote(name)));
+                    value.write(&format!("{}  ", indent), text);
+                    text.push_str(if index + 1 < fields.len() { ",\n" } else { "\n" });
+                }
+                text.push_str(indent);
+                text.push('}');
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for Json {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut text = String::new();
+        self.write("", &mut text);
+        write!(f, "{}", text)
+    }
+}
+
+/// Quotes the string for JSON, which is also the syntax of the strings in the s-expressions.
+fn quote(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    for symbol in value.chars() {
+        match symbol {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '\r' => quoted.push_str("\\r"),
+            symbol if (symbol as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", symbol as u32)),
+            symbol => quoted.push(symbol),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+fn dump_source(source: &str, format: Format) -> String {
+    dump_tree(&parse(source), source, format)
+}
+
+#[test]
+fn test_token_spans() {
+    let source = "f(a, 12, 1.5, 50%, \"s\\\"\", true, false, none) // c\n\n";
+    let tokens = TokenReader::new().parse_with_comments(source).unwrap();
+    let spans: Vec<&str> = tokens.iter().map(|token| span(token, source)).map(|(start, end)| &source[start..end]).collect();
+    assert_eq!(
+        vec!("f", "(", "a", ",", "12", ",", "1.5", ",", "50%", ",", "\"s\\\"\"", ",", "true", ",", "false", ",", "none", ")", "// c", "\n\n"),
+        spans,
+    );
+}
+
+#[test]
+fn test_dump_tokens() {
+    let source = "x = 1.5 // c\n";
+    let tokens = TokenReader::new().parse_with_comments(source).unwrap();
+    assert_eq!(
+        concat!(
+            "[\n",
+            "  { \"kind\": \"Identifier\", \"value\": \"x\", \"span\": [0, 1] },\n",
+            "  { \"kind\": \"Operator\", \"value\": \"=\", \"span\": [2, 3] },\n",
+            "  { \"kind\": \"FloatConstant\", \"value\": 1.5, \"span\": [4, 7] },\n",
+            "  { \"kind\": \"Comment\", \"value\": \"// c\", \"span\": [8, 12] },\n",
+            "  { \"kind\": \"NewLine\", \"span\": [12, 13] }\n",
+            "]\n",
+        ),
+        dump_tokens(&tokens, source, Format::Json),
+    );
+    assert_eq!(
+        "Identifier \"x\" 0..1\nOperator \"=\" 2..3\nFloatConstant 1.5 4..7\nComment \"// c\" 8..12\nNewLine 12..13\n",
+        dump_tokens(&tokens, source, Format::Tree),
+    );
+    assert_eq!("[]\n", dump_tokens(&[], "", Format::Json));
+}
+
+#[test]
+fn test_dump_tree() {
+    let source = "a = -b\nif a { println(\"\\t\") }";
+    assert_eq!(
+        concat!(
+            "{\n",
+            "  \"type\": \"Block\",\n",
+            "  \"span\": [0, 29],\n",
+            "  \"children\": [\n",
+            "    {\n",
+            "      \"type\": \"Expression\",\n",
+            "      \"token\": { \"kind\": \"Operator\", \"value\": \"=\", \"span\": [2, 3] },\n",
+            "      \"span\": [0, 6],\n",
+            "      \"children\": [\n",
+            "        { \"type\": \"Token\", \"token\": { \"kind\": \"Identifier\", \"value\": \"a\", \"span\": [0, 1] }, \"span\": [0, 1] },\n",
+            "        {\n",
+            "          \"type\": \"Expression\",\n",
+            "          \"token\": { \"kind\": \"Operator\", \"value\": \"u-\", \"span\": [4, 5] },\n",
+            "          \"span\": [4, 6],\n",
+            "          \"children\": [\n",
+            "            { \"type\": \"Token\", \"token\": { \"kind\": \"Identifier\", \"value\": \"b\", \"span\": [5, 6] }, \"span\": [5, 6] }\n",
+            "          ]\n",
+            "        }\n",
+            "      ]\n",
+            "    },\n",
+            "    {\n",
+            "      \"type\": \"If\",\n",
+            "      \"token\": { \"kind\": \"Operator\", \"value\": \"if\", \"span\": [7, 9] },\n",
+            "      \"span\": [7, 29],\n",
+            "      \"condition\": [\n",
+            "        { \"type\": \"Token\", \"token\": { \"kind\": \"Identifier\", \"value\": \"a\", \"span\": [10, 11] }, \"span\": [10, 11] }\n",
+            "      ],\n",
+            "      \"children\": [\n",
+            "        {\n",
+            "          \"type\": \"Block\",\n",
+            "          \"token\": { \"kind\": \"Operator\", \"value\": \"{\", \"span\": [12, 13] },\n",
+            "          \"span\": [12, 29],\n",
+            "          \"children\": [\n",
+            "            {\n",
+            "              \"type\": \"Call\",\n",
+            "              \"token\": { \"kind\": \"Function\", \"value\": \"println\", \"span\": [14, 21] },\n",
+            "              \"span\": [14, 27],\n",
+            "              \"children\": [\n",
+            "                { \"type\": \"Token\", \"token\": { \"kind\": \"StringConstant\", \"value\": \"\\\\t\", \"span\": [22, 26] }, \"span\": [22, 26] }\n",
+            "              ]\n",
+            "            }\n",
+            "          ]\n",
+            "        }\n",
+            "      ]\n",
+            "    }\n",
+            "  ]\n",
+            "}\n",
+        ),
+        dump_source(source, Format::Json),
+    );
+    assert_eq!(
+        concat!(
+            "(Block (span 0 29)\n",
+            "  (Expression (Operator \"=\" (span 2 3)) (span 0 6)\n",
+            "    (Token (Identifier \"a\" (span 0 1)))\n",
+            "    (Expression (Operator \"u-\" (span 4 5)) (span 4 6)\n",
+            "      (Token (Identifier \"b\" (span 5 6)))))\n",
+            "  (If (Operator \"if\" (span 7 9)) (span 7 29)\n",
+            "    (condition\n",
+            "      (Token (Identifier \"a\" (span 10 11))))\n",
+            "    (Block (Operator \"{\" (span 12 13)) (span 12 29)\n",
+            "      (Call (Function \"println\" (span 14 21)) (span 14 27)\n",
+            "        (Token (StringConstant \"\\\\t\" (span 22 26)))))))\n",
+        ),
+        dump_source(source, Format::Sexpr),
+    );
+    assert_eq!(
+        concat!(
+            "Block 0..29\n",
+            "├── Expression Operator \"=\" 0..6\n",
+            "│   ├── Identifier \"a\" 0..1\n",
+            "│   └── Expression Operator \"u-\" 4..6\n",
+            "│       └── Identifier \"b\" 5..6\n",
+            "└── If Operator \"if\" 7..29\n",
+            "    ├── condition: Identifier \"a\" 10..11\n",
+            "    └── Block Operator \"{\" 12..29\n",
+            "        └── Call Function \"println\" 14..27\n",
+            "            └── StringConstant \"\\\\t\" 22..26\n",
+        ),
+        dump_source(source, Format::Tree),
+    );
+    // the default step of the range is not in the source, the written one is
+    assert_eq!(
+        concat!(
+            "Block 0..13\n",
+            "├── Range Operator \"..\" 0..4\n",
+            "│   ├── IntConstant 1 0..1\n",
+            "│   └── IntConstant 3 3..4\n",
+            "└── Range Operator \":\" 5..13\n",
+            "    ├── IntConstant 1 5..6\n",
+            "    ├── IntConstant 3 12..13\n",
+            "    └── NoneConstant 7..11\n",
+        ),
+        dump_source("1..3\n1:None:3", Format::Tree),
+    );
+}
@@ -0,0 +1,188 @@
+use crate::dto::{ Node, NodeType, Token, SyntaxError };
+use crate::parser::{ TokenReader, TreeBuilder };
+use crate::runtime::module::Module as RuntimeModule;
+use std::collections::HashMap;
+use std::path::{ Component, Path, PathBuf };
+
+/// Parsed source file of the program.
+#[derive(Debug)]
+pub struct Module {
+    /// Path of the module as it is written in the `import` statement, or the file name of the main module.
+    pub name: String,
+    pub path: PathBuf,
+    pub source: String,
+    pub tree: Node,
+    /// Indices of the imported modules in the list returned by [`Loader::load`], by the path from the `import` statement.
+    pub imports: HashMap<String, usize>,
+}
+
+#[derive(Debug)]
+pub enum LoadError {
+    /// The main file can't be read, so there is no source to show.
+    Read { path: PathBuf, error: std::io::Error },
+    /// Error in any of the files of the program, with the source to show the place of the error.
+    Source { path: PathBuf, source: String, error: SyntaxError },
+}
+
+/// Function which reads the file, the imported module is not found if it fails.
+type ReadFile = Box<dyn Fn(&Path) -> std::io::Result<String>>;
+
+/// Reads and parses the main file and all modules reachable from it by the `import` statements.
+///
+/// `import a.b` refers to the file `a/b.avo`, which is looked up relative to the importing file
+/// and then in the directories of the search path.
+pub struct Loader {
+    search_path: Vec<PathBuf>,
+    read: ReadFile,
+    modules: Vec<Module>,
+}
+
+impl Loader {
+
+    /// Creates the loader which reads the files with the `read` function.
+    pub fn new(search_path: Vec<PathBuf>, read: impl Fn(&Path) -> std::io::Result<String> + 'static) -> Loader {
+        Loader {
+            search_path,
+            read: Box::new(read),
+            modules: vec!(),
+        }
+    }
+
+    /// Loads the program and returns its modules in the order of the execution:
+    /// every module goes after the modules it imports, so the main module is the last one.
+    pub fn load(mut self, path: &Path) -> Result<Vec<Module>, LoadError> {
+        let path = normalize(path);
+        let source = match (self.read)(&path) {
+            Ok(source) => source,
+            Err(error) => return Err(LoadError::Read { path, error }),
+        };
+        let name = path.file_stem().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+        self.load_module(name, path, source, &mut vec!())?;
+        Ok(self.modules)
+    }
+
+    /// Loads the imports of the module first, `stack` holds the modules which are being loaded to detect the cycles.
+    fn load_module(&mut self, name: String, path: PathBuf, source: String, stack: &mut Vec<(String, PathBuf)>) -> Result<usize, LoadError> {
+        let tree = match TokenReader::new().parse(&source).and_then(|tokens| TreeBuilder::new().build_tree(&tokens)) {
+            Ok(tree) => tree,
+            Err(error) => return Err(LoadError::Source { path, source, error }),
+        };
+        stack.push((name.clone(), path.clone()));
+        let mut imports = HashMap::new();
+        for statement in tree.children.iter().filter(|statement| statement.node_type == NodeType::Import) {
+            let (import_path, index) = self.load_import(statement, &path, &source, stack)?;
+            imports.insert(import_path, index);
+        }
+        stack.pop();
+        self.modules.push(Module { name, path, source, tree, imports });
+        Ok(self.modules.len() - 1)
+    }
+
+    /// Finds and loads the imported module, returns the import path and the index of the module.
+    fn load_import(&mut self, statement: &Node, importer: &Path, source: &str, stack: &mut Vec<(String, PathBuf)>) -> Result<(String, usize), LoadError> {
+        let error = |pos: usize, message: String| LoadError::Source { path: importer.to_path_buf(), source: source.to_string(), error: SyntaxError::new(pos, message) };
+        let parts: Vec<&str> = statement.children.iter().filter_map(identifier).collect();
+        let import_path = parts.join(".");
+        let relative = parts.iter().collect::<PathBuf>().with_extension("avo");
+        let directory = importer.parent().map(Path::to_path_buf).unwrap_or_default();
+        let mut found = None;
+        for directory in std::iter::once(&directory).chain(self.search_path.iter()) {
+            let path = normalize(&directory.join(&relative));
+            if let Some(index) = self.modules.iter().position(|module| module.path == path) {
+                found = Some(index);
+                break
+            }
+            if let Some(start) = stack.iter().position(|(_, loading)| *loading == path) {
+                let mut cycle: Vec<&str> = stack[start..].iter().map(|(name, _)| name.as_str()).collect();
+                cycle.push(&import_path);
+                return Err(error(statement.pos(), format!("Import cycle: {}", cycle.join(" -> "))))
+            }
+            if let Ok(source) = (self.read)(&path) {
+                found = Some(self.load_module(import_path.clone(), path, source, stack)?);
+                break
+            }
+        }
+        let index = match found {
+            Some(index) => index,
+            None => return Err(error(statement.pos(), format!("Module '{}' is not found", import_path))),
+        };
+        let exports = RuntimeModule::public_names(&self.modules[index].tree);
+        for name in &statement.condition {
+            match identifier(name) {
+                Some(name) if exports.contains(&name) => (),
+                _ => return Err(error(name.pos(), format!("Module {} has no public member '{}'", import_path, identifier(name).unwrap_or_default()))),
+            }
+        }
+        Ok((import_path, index))
+    }
+}
+
+#[inline]
+fn identifier(node: &Node) -> Option<&str> {
+    match &node.data {
+        Some(Token::Identifier { name, .. }) => Some(name),
+        _ => None,
+    }
+}
+
+/// Removes `.` and `name/..` from the path without the access to the file system, so the same file has the same path.
+fn normalize(path: &Path) -> PathBuf {
+    let mut result = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir if matches!(result.components().next_back(), Some(Component::Normal(_))) => {
+                result.pop();
+            },
+            component => result.push(component),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+fn load(files: &[(&str, &str)]) -> Result<Vec<Module>, LoadError> {
+    let files: HashMap<PathBuf, String> = files.iter().map(|(path, source)| (PathBuf::from(path), source.to_string())).collect();
+    Loader::new(vec!(PathBuf::from("lib")), move |path| files.get(path).cloned().ok_or_else(|| std::io::ErrorKind::NotFound.into()))
+        .load(Path::new("app/main.avo"))
+}
+
+/// Loads the files and returns the path of the file with the error, its offset and its message.
+#[cfg(test)]
+fn load_error(files: &[(&str, &str)]) -> (PathBuf, usize, String) {
+    match load(files) {
+        Err(LoadError::Source { path, error, .. }) => (path, error.pos, error.message),
+        result => panic!("{:?}", result),
+    }
+}
+
+#[test]
+fn test_load_modules() {
+    let modules = load(&[
+        ("app/main.avo", "import utils\nfrom shapes.circle import area\nutils.twice(area(1))"),
+        ("app/utils.avo", "from shapes.circle import area\npub fn twice(x) { x * 2 }"),
+        ("lib/shapes/circle.avo", "pub fn area(r) { 3 * r * r }"),
+    ]).unwrap();
+    let names: Vec<&str> = modules.iter().map(|module| module.name.as_str()).collect();
+    assert_eq!(vec!("shapes.circle", "utils", "main"), names);
+    assert_eq!(Some(&0), modules[1].imports.get("shapes.circle"));
+    assert_eq!(2, modules[2].imports.len());
+    assert_eq!(PathBuf::from("a/c"), normalize(Path::new("./a/b/../c")));
+}
+
+#[test]
+fn test_load_errors() {
+    assert_eq!(
+        (PathBuf::from("app/b.avo"), 1, String::from("Import cycle: a -> b -> a")),
+        load_error(&[("app/main.avo", "import a"), ("app/a.avo", "import b"), ("app/b.avo", "\nimport a")]),
+    );
+    assert_eq!("Module 'nope' is not found", load_error(&[("app/main.avo", "x = 1\nimport nope")]).2);
+    let (_, pos, message) = load_error(&[("app/main.avo", "from a import f, g"), ("app/a.avo", "pub fn f() {}\nfn g() {}")]);
+    assert_eq!((17, "Module a has no public member 'g'"), (pos, message.as_str()));
+    let (path, _, message) = load_error(&[("app/main.avo", "import a"), ("app/a.avo", "pub 1")]);
+    assert_eq!((PathBuf::from("app/a.avo"), "Only functions, structs, enums and variables can be public"), (path, message.as_str()));
+    match load(&[]) {
+        Err(LoadError::Read { path, error }) => assert_eq!((PathBuf::from("app/main.avo"), std::io::ErrorKind::NotFound), (path, error.kind())),
+        result => panic!("{:?}", result),
+    }
+}
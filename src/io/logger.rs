@@ -5,13 +5,11 @@ pub fn red(string: String) -> String {
 }
 
 #[inline]
-#[allow(dead_code)]
 pub fn yellow(string: String) -> String {
     format!("{}{}{}", "\u{001b}[33m\u{001b}[1m", string, "\u{001b}[0m")
 }
 
 #[inline]
-#[allow(dead_code)]
 pub fn green(string: String) -> String {
     format!("{}{}{}", "\u{001b}[32m\u{001b}[1m", string, "\u{001b}[0m")
 }
@@ -22,20 +20,37 @@ pub fn blue(string: String) -> String {
     format!("{}{}{}", "\u{001b}[34m\u{001b}[1m", string, "\u{001b}[0m")
 }
 
-pub fn print_error_info(file_name: &String, source: &String, offset: usize, message: String) {
-    let mut line_num = 1;
+pub fn print_error_info(file_name: &str, source: &str, offset: usize, message: String) {
+    print_info(red, String::from("error"), file_name, source, offset, message)
+}
+
+/// Prints the warning of the linter the same way as the error, with the name of the rule.
+pub fn print_warning_info(file_name: &str, source: &str, offset: usize, rule: &str, message: String) {
+    print_info(yellow, format!("warning[{}]", rule), file_name, source, offset, message)
+}
+
+/// Same as [`print_error_info`], but returns the text without the colors, e.g. for the golden files of the tests.
+pub fn error_info(file_name: &str, source: &str, offset: usize, message: String) -> String {
+    format_info(|string| string, String::from("error"), file_name, source, offset, message)
+}
+
+fn print_info(color: fn(String) -> String, title: String, file_name: &str, source: &str, offset: usize, message: String) {
+    eprint!("{}", format_info(color, title, file_name, source, offset, message))
+}
+
+fn format_info(color: fn(String) -> String, title: String, file_name: &str, source: &str, offset: usize, message: String) -> String {
     let mut sum = 0usize;
-    for line in source.lines() {
+    for (index, line) in source.lines().enumerate() {
+        let line_num = index + 1;
         let len = line.len();
         if sum + len >= offset {
             let column = offset - sum;
-            println!("\n{}: {}:{}:{}\n", red(String::from("error")), file_name, line_num, column);
-            println!("{}", line);
-            println!("{}", red(format!("{:width$}^ {}\n", "", message, width=column)));
-            return
+            return format!(
+                "\n{}: {}:{}:{}\n\n{}\n{}\n",
+                color(title), file_name, line_num, column, line, color(format!("{:width$}^ {}\n", "", message, width=column)),
+            )
         }
         sum += len + 1;
-        line_num += 1;
     }
-    println!("\nCan't extract debug info. Message: {} at {}", message, offset)
-}
\ No newline at end of file
+    format!("\nCan't extract debug info. Message: {} at {}\n", message, offset)
+}
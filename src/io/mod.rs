@@ -1,6 +1,4 @@
 pub mod logger;
-pub use logger::red;
-pub use logger::yellow;
-pub use logger::green;
-pub use logger::blue;
-pub use logger::print_error_info;
\ No newline at end of file
+pub use logger::{ print_error_info, print_warning_info, error_info };
+pub mod loader;
+pub mod dump;
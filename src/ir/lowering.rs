@@ -0,0 +1,649 @@
+use crate::dto::{ Node, NodeType, Operator, Token, SyntaxError };
+use crate::analyzer::{ declared_name, free_variables, resolve_assignments, Assignment };
+use crate::ir::program::{ Block, BlockId, Constant, Function, Instruction, Operation, Program, Terminator, Type, ValueId };
+use crate::ir::passes::infer_types;
+use crate::runtime::Function as Callable;
+use crate::runtime::builtins::find_builtin;
+use std::collections::{ HashMap, HashSet };
+#[cfg(test)]
+use crate::parser::parse;
+
+/// Lowers the checked syntax tree to the intermediate representation in the SSA form.
+///
+/// Every global function becomes the function of the program, the statements of the root block are
+/// the function `main`. Variables become the values: the assignment defines the new value and the
+/// phi at the start of the block selects the value which reaches it. Global variables used by the
+/// functions are the exception, they are loaded and stored by name because the calls may change them.
+/// Assignments declare and change the variables the way the resolver decides for them.
+///
+/// The program is translated to the assembly by `generate_x86_64`, the other backends don't use it yet.
+///
+/// # Errors
+/// Constructs which have no representation yet: lambdas, nested functions, matches, tuples, maps,
+/// structs, enums, modules and default values of the parameters.
+pub fn lower(root: &Node) -> Result<Program, Vec<SyntaxError>> {
+    let functions: Vec<&Node> = root.children.iter().map(Node::declaration).filter(|node| node.node_type == NodeType::Function).collect();
+    let mut signatures = HashMap::new();
+    let mut shared = HashSet::new();
+    for function in &functions {
+        let return_type = function.children.get(1).map(|annotation| Type::from_annotation(name_of(annotation))).unwrap_or(Type::Any);
+        signatures.insert(name_of(function).to_string(), return_type);
+        shared.extend(free_variables(function));
+    }
+    let globals: HashSet<String> = root.children.iter().filter_map(|statement| declared_name(statement.declaration())).map(|(name, _)| name.to_string()).collect();
+    let shared = shared.into_iter().filter(|name| globals.contains(name)).collect();
+    let names = Names { signatures, globals, shared, assignments: resolve_assignments(root) };
+    let mut errors = vec!();
+    let mut program = Program { functions: vec!() };
+    for function in functions {
+        let parameters = function.condition.iter().map(|parameter| {
+            (name_of(parameter).to_string(), Callable::parameter_type(parameter).map(Type::from_annotation).unwrap_or(Type::Any))
+        }).collect();
+        let mut builder = Builder::new(name_of(function), parameters, names.signatures[name_of(function)], &names, &mut errors);
+        builder.is_main = false;
+        for (index, parameter) in function.condition.iter().enumerate() {
+            if let Some(default) = parameter.children.first() {
+                builder.unsupported(default, "Default values of the parameters");
+            }
+            let value = builder.emit(Operation::Parameter(index), builder.function.parameters[index].1);
+            builder.declare(name_of(parameter), value);
+        }
+        let value = builder.expression(&function.children[0]);
+        program.functions.push(builder.finish(value));
+    }
+    let mut builder = Builder::new("main", vec!(), Type::Any, &names, &mut errors);
+    let statements: Vec<Node> = root.children.iter().filter(|node| node.declaration().node_type != NodeType::Function).cloned().collect();
+    let value = builder.statements(&statements);
+    program.functions.push(builder.finish(value));
+    match errors.is_empty() {
+        true => Ok(program),
+        false => {
+            errors.sort_by_key(|e| e.pos);
+            Err(errors)
+        },
+    }
+}
+
+/// Names of the program which are seen by all its functions.
+struct Names {
+    /// Return types of the global functions.
+    signatures: HashMap<String, Type>,
+    /// Variables declared in the root block.
+    globals: HashSet<String>,
+    /// Global variables used by the functions.
+    shared: HashSet<String>,
+    assignments: HashMap<usize, Assignment>,
+}
+
+#[derive(Clone)]
+enum Variable {
+    /// Number of the variable in the definitions of the blocks.
+    Local(usize),
+    Global(String),
+}
+
+struct Builder<'a> {
+    function: Function,
+    current: BlockId,
+    /// The current block is finished by `return`, `break` or `continue`, the next code is never executed.
+    is_terminated: bool,
+    /// Value of the variable at the end of the block.
+    definitions: HashMap<(usize, BlockId), ValueId>,
+    /// Blocks whose predecessors are all known.
+    sealed: HashSet<BlockId>,
+    /// Phis created before the block is sealed, their operands are added by `seal`.
+    incomplete: HashMap<BlockId, Vec<(usize, ValueId)>>,
+    predecessors: HashMap<BlockId, Vec<BlockId>>,
+    scopes: Vec<HashMap<String, Variable>>,
+    variables: usize,
+    /// Targets of `break` and `continue` of the enclosing loops.
+    loops: Vec<(BlockId, BlockId)>,
+    is_main: bool,
+    names: &'a Names,
+    errors: &'a mut Vec<SyntaxError>,
+    /// Offset of the node which is being lowered, it is the position of the emitted instructions.
+    pos: usize,
+}
+
+impl<'a> Builder<'a> {
+
+    fn new(name: &str, parameters: Vec<(String, Type)>, return_type: Type, names: &'a Names, errors: &'a mut Vec<SyntaxError>) -> Builder<'a> {
+        let function = Function { name: name.to_string(), parameters, return_type, blocks: vec!(), values: 0 };
+        let mut builder = Builder {
+            function,
+            current: BlockId(0),
+            is_terminated: false,
+            definitions: HashMap::new(),
+            sealed: HashSet::new(),
+            incomplete: HashMap::new(),
+            predecessors: HashMap::new(),
+            scopes: vec!(HashMap::new()),
+            variables: 0,
+            loops: vec!(),
+            is_main: true,
+            names,
+            errors,
+            pos: 0,
+        };
+        let entry = builder.new_block();
+        builder.seal(entry);
+        builder
+    }
+
+    /// Returns the value from the function and infers the types of the values.
+    fn finish(mut self, value: ValueId) -> Function {
+        self.terminate(Terminator::Return(value));
+        infer_types(&mut self.function);
+        self.function
+    }
+
+    fn new_block(&mut self) -> BlockId {
+        let id = BlockId(self.function.blocks.len());
+        // the terminator is replaced when the block is finished
+        self.function.blocks.push(Block { id, instructions: vec!(), terminator: Terminator::Return(ValueId(usize::MAX)), terminator_pos: 0 });
+        self.predecessors.insert(id, vec!());
+        id
+    }
+
+    /// Finishes the current block, the code after `return`, `break` and `continue` goes to the block without predecessors.
+    fn terminate(&mut self, terminator: Terminator) {
+        let block = self.block();
+        for successor in terminator.successors() {
+            self.predecessors.get_mut(&successor).unwrap().push(block);
+        }
+        let pos = self.pos;
+        let block = self.function.block_mut(block);
+        block.terminator = terminator;
+        block.terminator_pos = pos;
+        self.is_terminated = true;
+    }
+
+    /// Returns the current block, the block is created if the previous one is already terminated.
+    fn block(&mut self) -> BlockId {
+        if self.is_terminated {
+            self.current = self.new_block();
+            self.seal(self.current);
+            self.is_terminated = false;
+        }
+        self.current
+    }
+
+    fn switch_to(&mut self, block: BlockId) {
+        self.current = block;
+        self.is_terminated = false;
+    }
+
+    fn emit(&mut self, operation: Operation, value_type: Type) -> ValueId {
+        let block = self.block();
+        let result = ValueId(self.function.values);
+        self.function.values += 1;
+        self.function.block_mut(block).instructions.push(Instruction { result, value_type, operation, pos: self.pos });
+        result
+    }
+
+    fn read(&mut self, variable: usize) -> ValueId {
+        let block = self.block();
+        self.read_variable(variable, block)
+    }
+
+    fn write(&mut self, variable: usize, value: ValueId) {
+        let block = self.block();
+        self.write_variable(variable, block, value)
+    }
+
+    fn constant(&mut self, constant: Constant) -> ValueId {
+        let value_type = constant.value_type();
+        self.emit(Operation::Constant(constant), value_type)
+    }
+
+    /// Creates the phi at the start of the block, after the other phis.
+    fn phi(&mut self, block: BlockId) -> ValueId {
+        let result = ValueId(self.function.values);
+        self.function.values += 1;
+        let instructions = &mut self.function.block_mut(block).instructions;
+        let position = instructions.iter().take_while(|instruction| matches!(instruction.operation, Operation::Phi(_))).count();
+        instructions.insert(position, Instruction { result, value_type: Type::Any, operation: Operation::Phi(vec!()), pos: self.pos });
+        result
+    }
+
+    fn write_variable(&mut self, variable: usize, block: BlockId, value: ValueId) {
+        self.definitions.insert((variable, block), value);
+    }
+
+    fn read_variable(&mut self, variable: usize, block: BlockId) -> ValueId {
+        if let Some(value) = self.definitions.get(&(variable, block)) {
+            return *value
+        }
+        let predecessors = self.predecessors[&block].clone();
+        let value = match (self.sealed.contains(&block), predecessors.as_slice()) {
+            (false, _) => {
+                let phi = self.phi(block);
+                self.incomplete.entry(block).or_default().push((variable, phi));
+                phi
+            },
+            (true, [predecessor]) => self.read_variable(variable, *predecessor),
+            // the block is never executed
+            (true, []) => {
+                let value = ValueId(self.function.values);
+                self.function.values += 1;
+                let instruction = Instruction { result: value, value_type: Type::None, operation: Operation::Constant(Constant::None), pos: self.pos };
+                self.function.block_mut(block).instructions.insert(0, instruction);
+                value
+            },
+            (true, _) => {
+                let phi = self.phi(block);
+                self.write_variable(variable, block, phi);
+                self.add_phi_operands(variable, block, phi);
+                phi
+            },
+        };
+        self.write_variable(variable, block, value);
+        value
+    }
+
+    fn add_phi_operands(&mut self, variable: usize, block: BlockId, phi: ValueId) {
+        let incoming: Vec<(BlockId, ValueId)> = self.predecessors[&block].clone().into_iter().map(|predecessor| (predecessor, self.read_variable(variable, predecessor))).collect();
+        let instruction = self.function.block_mut(block).instructions.iter_mut().find(|instruction| instruction.result == phi).unwrap();
+        instruction.operation = Operation::Phi(incoming);
+    }
+
+    /// Marks that all predecessors of the block are known and completes its phis.
+    fn seal(&mut self, block: BlockId) {
+        for (variable, phi) in self.incomplete.remove(&block).unwrap_or_default() {
+            self.add_phi_operands(variable, block, phi);
+        }
+        self.sealed.insert(block);
+    }
+
+    /// Creates the variable without the name, it passes the value of the expression from its branches.
+    fn temporary(&mut self) -> usize {
+        self.variables += 1;
+        self.variables
+    }
+
+    fn declare(&mut self, name: &str, value: ValueId) {
+        let variable = match self.is_main && self.scopes.len() == 1 && self.names.shared.contains(name) {
+            true => {
+                self.emit(Operation::StoreGlobal(name.to_string(), value), Type::None);
+                Variable::Global(name.to_string())
+            },
+            false => {
+                let id = self.temporary();
+                self.write(id, value);
+                Variable::Local(id)
+            },
+        };
+        self.scopes.last_mut().unwrap().insert(name.to_string(), variable);
+    }
+
+    /// Finds the variable, functions see the global variables of `main` by name.
+    fn lookup(&self, name: &str) -> Option<Variable> {
+        match self.scopes.iter().rev().find_map(|scope| scope.get(name)) {
+            Some(variable) => Some(variable.clone()),
+            None if !self.is_main && self.names.globals.contains(name) => Some(Variable::Global(name.to_string())),
+            None => None,
+        }
+    }
+
+    fn error(&mut self, pos: usize, message: String) -> ValueId {
+        self.errors.push(SyntaxError::new(pos, message));
+        self.constant(Constant::None)
+    }
+
+    fn unsupported(&mut self, node: &Node, what: &str) -> ValueId {
+        self.error(node.pos(), format!("{} are not supported by the IR", what))
+    }
+
+    fn statements(&mut self, statements: &[Node]) -> ValueId {
+        let mut value = None;
+        for statement in statements {
+            value = Some(self.expression(statement));
+        }
+        value.unwrap_or_else(|| self.constant(Constant::None))
+    }
+
+    /// The instructions of the node get its position, the positions of the children are restored after them.
+    fn expression(&mut self, node: &Node) -> ValueId {
+        let pos = self.pos;
+        self.pos = node.pos();
+        let value = self.node(node);
+        self.pos = pos;
+        value
+    }
+
+    fn node(&mut self, node: &Node) -> ValueId {
+        match node.node_type {
+            NodeType::Token => self.token(node),
+            NodeType::Expression => self.operation(node),
+            NodeType::Block => {
+                self.scopes.push(HashMap::new());
+                let value = self.statements(&node.children);
+                self.scopes.pop();
+                value
+            },
+            NodeType::If => self.if_expression(node),
+            NodeType::Call => {
+                let arguments: Vec<ValueId> = node.children.iter().map(|argument| self.expression(argument)).collect();
+                self.call(name_of(node), arguments, node)
+            },
+            NodeType::Loop => {
+                let (header, exit) = (self.new_block(), self.new_block());
+                self.terminate(Terminator::Jump(header));
+                self.switch_to(header);
+                self.loops.push((exit, header));
+                self.expression(&node.children[0]);
+                self.loops.pop();
+                self.terminate(Terminator::Jump(header));
+                self.seal(header);
+                self.seal(exit);
+                self.switch_to(exit);
+                self.constant(Constant::None)
+            },
+            NodeType::For => self.for_loop(node),
+            NodeType::Break | NodeType::Continue => match self.loops.last().copied() {
+                Some((exit, next)) => {
+                    self.terminate(Terminator::Jump(if node.node_type == NodeType::Break { exit } else { next }));
+                    self.constant(Constant::None)
+                },
+                None => self.error(node.pos(), String::from("Loop control statement outside of the loop")),
+            },
+            NodeType::Return => {
+                let value = match node.children.first() {
+                    Some(value) => self.expression(value),
+                    None => self.constant(Constant::None),
+                };
+                self.terminate(Terminator::Return(value));
+                self.constant(Constant::None)
+            },
+            NodeType::List => {
+                let items = node.children.iter().map(|item| self.expression(item)).collect();
+                self.emit(Operation::List(items), Type::List)
+            },
+            NodeType::Range => {
+                let parts: Vec<ValueId> = node.children.iter().map(|part| self.expression(part)).collect();
+                self.emit(Operation::Range(parts[0], parts[1], parts[2], node.operator() != Some(Operator::Range)), Type::Range)
+            },
+            NodeType::Public => self.expression(&node.children[0]),
+            NodeType::Function => self.unsupported(node, "Nested functions"),
+            NodeType::Lambda => self.unsupported(node, "Lambdas"),
+            NodeType::Match => self.unsupported(node, "Match expressions"),
+            NodeType::Tuple => self.unsupported(node, "Tuples"),
+            NodeType::Map => self.unsupported(node, "Maps"),
+            NodeType::Struct => self.unsupported(node, "Structs"),
+            NodeType::Enum | NodeType::Variant => self.unsupported(node, "Enums"),
+            NodeType::Import => self.unsupported(node, "Modules"),
+            // the tests are run only by `avoc test`, the lowered program skips them
+            NodeType::Test => self.constant(Constant::None),
+            NodeType::Arm | NodeType::Rest => self.error(node.pos(), String::from("Pattern can be used only in the match arm")),
+            NodeType::Type => self.error(node.pos(), String::from("Type can be used only in the annotation")),
+        }
+    }
+
+    fn token(&mut self, node: &Node) -> ValueId {
+        match &node.data {
+            Some(Token::IntConstant { value, .. }) => self.constant(Constant::Int(*value)),
+            Some(Token::FloatConstant { value, .. }) => self.constant(Constant::Float(*value)),
+            Some(Token::BoolConstant { value, .. }) => self.constant(Constant::Bool(*value)),
+            Some(Token::StringConstant { value, .. }) => self.constant(Constant::String(value.to_string())),
+            Some(Token::NoneConstant { .. }) => self.constant(Constant::None),
+            Some(Token::Identifier { name, .. }) => match self.lookup(name) {
+                Some(Variable::Local(id)) => self.read(id),
+                Some(Variable::Global(name)) => self.emit(Operation::LoadGlobal(name), Type::Any),
+                None if self.names.signatures.contains_key(name.as_str()) => self.unsupported(node, "Functions as values"),
+                None => self.error(node.pos(), format!("Undefined variable '{}'", name)),
+            },
+            _ => self.error(node.pos(), String::from("Unexpected token")),
+        }
+    }
+
+    /// Goes to the first block if the condition is true, the condition is checked at its node like in the interpreter.
+    fn branch(&mut self, node: &Node, condition: ValueId, then: BlockId, otherwise: BlockId) {
+        let pos = self.pos;
+        self.pos = node.pos();
+        self.terminate(Terminator::Branch(condition, then, otherwise));
+        self.pos = pos;
+    }
+
+    /// Both branches write the value of the `if` to the temporary variable, the phi of the next block reads it.
+    fn if_expression(&mut self, node: &Node) -> ValueId {
+        let condition = self.expression(&node.condition[0]);
+        let (then, otherwise, next) = (self.new_block(), self.new_block(), self.new_block());
+        let result = self.temporary();
+        self.branch(&node.condition[0], condition, then, otherwise);
+        self.seal(then);
+        self.seal(otherwise);
+        self.switch_to(then);
+        let value = self.expression(&node.children[0]);
+        self.write(result, value);
+        self.terminate(Terminator::Jump(next));
+        self.switch_to(otherwise);
+        let value = match node.children.get(1) {
+            Some(otherwise) => self.expression(otherwise),
+            None => self.constant(Constant::None),
+        };
+        self.write(result, value);
+        self.terminate(Terminator::Jump(next));
+        self.seal(next);
+        self.switch_to(next);
+        self.read_variable(result, next)
+    }
+
+    /// `a and b` evaluates `b` only if `a` is true, `a or b` only if `a` is false.
+    fn logical(&mut self, node: &Node, is_and: bool) -> ValueId {
+        let lhs = self.expression(&node.children[0]);
+        let (right, next) = (self.new_block(), self.new_block());
+        let result = self.temporary();
+        self.write(result, lhs);
+        match is_and {
+            true => self.branch(&node.children[0], lhs, right, next),
+            false => self.branch(&node.children[0], lhs, next, right),
+        }
+        self.seal(right);
+        self.switch_to(right);
+        let rhs = self.expression(&node.children[1]);
+        self.write(result, rhs);
+        self.terminate(Terminator::Jump(next));
+        self.seal(next);
+        self.switch_to(next);
+        self.read_variable(result, next)
+    }
+
+    /// The header checks whether the iterator has the next item, the body declares the loop variable with it.
+    fn for_loop(&mut self, node: &Node) -> ValueId {
+        let iterable = self.expression(&node.condition[0]);
+        let iterator = self.emit(Operation::Iterate(iterable), Type::Iterator);
+        let (header, body, exit) = (self.new_block(), self.new_block(), self.new_block());
+        self.terminate(Terminator::Jump(header));
+        self.switch_to(header);
+        let has_next = self.emit(Operation::HasNext(iterator), Type::Bool);
+        self.terminate(Terminator::Branch(has_next, body, exit));
+        self.seal(body);
+        self.switch_to(body);
+        let item = self.emit(Operation::Next(iterator), Type::Any);
+        self.scopes.push(HashMap::new());
+        self.declare(name_of(&node.children[0]), item);
+        self.loops.push((exit, header));
+        self.expression(&node.children[1]);
+        self.loops.pop();
+        self.scopes.pop();
+        self.terminate(Terminator::Jump(header));
+        self.seal(header);
+        self.seal(exit);
+        self.switch_to(exit);
+        self.constant(Constant::None)
+    }
+
+    fn operation(&mut self, node: &Node) -> ValueId {
+        let operator = match node.operator() {
+            Some(operator) => operator,
+            None => return self.unsupported(node, "Expressions without the operator"),
+        };
+        match operator {
+            _ if operator.is_assignment() => self.assignment(operator, node),
+            Operator::Mut => self.error(node.pos(), String::from("Mutable variable must be declared with assignment: mut name = value")),
+            Operator::And | Operator::Or => self.logical(node, operator == Operator::And),
+            Operator::Dot => match node.children[1].node_type {
+                NodeType::Call => {
+                    let method = &node.children[1];
+                    let mut arguments = vec!(self.expression(&node.children[0]));
+                    arguments.extend(method.children.iter().map(|argument| self.expression(argument)));
+                    self.call(name_of(method), arguments, method)
+                },
+                _ => self.unsupported(node, "Fields, methods of the modules and variants of the enums"),
+            },
+            Operator::LeftBracket if node.children[1].node_type == NodeType::Range => self.unsupported(node, "Slices"),
+            Operator::LeftBracket => {
+                let collection = self.expression(&node.children[0]);
+                let key = self.expression(&node.children[1]);
+                self.emit(Operation::Index(collection, key), Type::Any)
+            },
+            Operator::Is => {
+                let value = self.expression(&node.children[0]);
+                let name = match &node.children[1].data {
+                    Some(Token::NoneConstant { .. }) => "None",
+                    Some(Token::Identifier { name, .. }) => name,
+                    _ => return self.unsupported(node, "Variants of the enums"),
+                };
+                self.emit(Operation::Is(value, name.to_string()), Type::Bool)
+            },
+            Operator::UnaryMinus | Operator::Not => {
+                let operand = self.expression(&node.children[0]);
+                self.emit(Operation::Unary(operator, operand), Type::Any)
+            },
+            _ => {
+                let lhs = self.expression(&node.children[0]);
+                let rhs = self.expression(&node.children[1]);
+                self.emit(Operation::Binary(operator, lhs, rhs), Type::Any)
+            },
+        }
+    }
+
+    /// The value of the assignment is the assigned value.
+    fn assignment(&mut self, operator: Operator, node: &Node) -> ValueId {
+        let target = &node.children[0];
+        let value = self.expression(&node.children[1]);
+        let compound = |builder: &mut Builder, current: ValueId| match operator.compound_base() {
+            None => value,
+            Some(base) => builder.emit(Operation::Binary(base, current, value), Type::Any),
+        };
+        match (target.operator(), &target.data) {
+            (Some(Operator::LeftBracket), _) => {
+                let collection = self.expression(&target.children[0]);
+                let key = self.expression(&target.children[1]);
+                let value = match operator {
+                    Operator::Assign => value,
+                    _ => {
+                        let current = self.emit(Operation::Index(collection, key), Type::Any);
+                        compound(self, current)
+                    },
+                };
+                self.emit(Operation::SetIndex(collection, key, value), Type::None);
+                value
+            },
+            (Some(Operator::Dot), _) => self.unsupported(target, "Fields"),
+            (Some(Operator::Mut), _) | (_, Some(Token::Identifier { .. })) => {
+                let name = name_of(target.children.first().unwrap_or(target));
+                match (self.names.assignments[&node.pos()].clone(), self.lookup(name)) {
+                    (Assignment::Declare { .. }, _) => {
+                        self.declare(name, value);
+                        value
+                    },
+                    (Assignment::Assign, Some(Variable::Local(id))) => {
+                        let current = self.read(id);
+                        let value = compound(self, current);
+                        self.write(id, value);
+                        value
+                    },
+                    (Assignment::Assign, Some(Variable::Global(name))) => {
+                        let value = match operator {
+                            Operator::Assign => value,
+                            _ => {
+                                let current = self.emit(Operation::LoadGlobal(name.clone()), Type::Any);
+                                compound(self, current)
+                            },
+                        };
+                        self.emit(Operation::StoreGlobal(name, value), Type::None);
+                        value
+                    },
+                    (Assignment::Assign, None) => self.error(target.pos(), format!("Undefined variable '{}'", name)),
+                    (Assignment::Invalid(error), _) => self.error(error.pos, error.message),
+                }
+            },
+            _ => self.error(target.pos(), String::from("Invalid assignment target")),
+        }
+    }
+
+    /// The method is called at its name like in the interpreter, not at the dot.
+    fn call(&mut self, name: &str, arguments: Vec<ValueId>, node: &Node) -> ValueId {
+        self.pos = node.pos();
+        if self.lookup(name).is_some() {
+            return self.unsupported(node, "Functions as values")
+        }
+        let return_type = match (self.names.signatures.get(name), find_builtin(name)) {
+            (Some(return_type), _) => *return_type,
+            (None, Some(builtin)) => Type::from_annotation(builtin.signature.return_type),
+            (None, None) => return self.error(node.pos(), format!("Unknown function '{}'", name)),
+        };
+        self.emit(Operation::Call(name.to_string(), arguments), return_type)
+    }
+}
+
+#[inline]
+fn name_of(node: &Node) -> &str {
+    match &node.data {
+        Some(Token::Identifier { name, .. }) | Some(Token::Function { name, .. }) => name,
+        _ => "",
+    }
+}
+
+#[cfg(test)]
+fn lower_source(source: &str) -> Result<Program, Vec<SyntaxError>> {
+    let tree = parse(source);
+    lower(&tree)
+}
+
+#[test]
+fn test_lowered_programs_are_valid() {
+    let programs = [
+        "fn fact(n: Int) -> Int {\n  if n <= 1 { return 1 }\n  n * fact(n - 1)\n}\nprintln(fact(5))",
+        "mut sum = 0\nfor i in 0..10 {\n  if i == 3 { continue }\n  if i > 7 { break }\n  sum += i\n}\nprintln(sum)",
+        "mut counter = 0\nfn bump(step) { counter += step }\nbump(2)\nprintln(counter, counter > 1 and counter < 5 or false)",
+        "mut xs = [1, 2]\nxs[0] += 5\nxs.push(3)\nx = if len(xs) > 2 { \"long\" } else { 1.5 }\nprintln(x, xs[1], 1 is Int, \"ab\" + 1)",
+        "mut i = 0\nloop {\n  mut j = 0\n  loop {\n    j += 1\n    if j > i { break }\n  }\n  i += 1\n  if i == 3 { break }\n}",
+    ];
+    for source in programs {
+        let program = lower_source(source).unwrap();
+        assert_eq!(Vec::<String>::new(), crate::ir::verify(&program), "{}\n{}", source, program);
+    }
+}
+
+#[test]
+fn test_variables_become_phis() {
+    let mut program = lower_source("fn count(n: Int) {\n  mut i = 0\n  loop {\n    if i == n { return i }\n    i += 1\n  }\n}").unwrap();
+    // the phis of the blocks with the single predecessor are removed by the passes
+    crate::ir::optimize(&mut program);
+    let function = &program.functions[0];
+    let phis: Vec<&Instruction> = function.blocks.iter().flat_map(|block| block.instructions.iter())
+        .filter(|instruction| matches!(instruction.operation, Operation::Phi(_))).collect();
+    assert_eq!(1, phis.len());
+    assert_eq!(Type::Int, phis[0].value_type);
+    assert_eq!(Type::Int, function.parameters[0].1);
+}
+
+#[test]
+fn test_globals_used_by_functions() {
+    let program = lower_source("mut total = 1\nlimit = 3\nfn add(x) { total += x }\nadd(limit)").unwrap();
+    let operations: Vec<String> = program.functions.iter().flat_map(|function| function.blocks.iter()).flat_map(|block| block.instructions.iter())
+        .filter(|instruction| matches!(instruction.operation, Operation::LoadGlobal(_) | Operation::StoreGlobal(_, _)))
+        .map(|instruction| instruction.operation.to_string()).collect();
+    assert_eq!(vec!("load_global total", "store_global total, v2", "store_global total, v0"), operations);
+}
+
+#[test]
+fn test_unsupported_constructs() {
+    let message = |source: &str| lower_source(source).unwrap_err()[0].message.clone();
+    assert_eq!("Lambdas are not supported by the IR", message("f = { @ x -> x }"));
+    assert_eq!("Tuples are not supported by the IR", message("a = (1, 2)"));
+    assert_eq!("Functions as values are not supported by the IR", message("fn f() { 1 }\ng = f"));
+    assert_eq!("Default values of the parameters are not supported by the IR", message("fn f(x = 1) { x }"));
+}
@@ -0,0 +1,8 @@
+pub mod program;
+pub mod lowering;
+pub mod verifier;
+pub mod passes;
+pub use program::{ Block, BlockId, Constant, Function, Instruction, Operation, Program, Terminator, Type, ValueId };
+pub use lowering::lower;
+pub use verifier::verify;
+pub use passes::optimize;
@@ -0,0 +1,380 @@
+use crate::dto::Operator;
+use crate::ir::program::{ BlockId, Constant, Function, Operation, Program, Terminator, Type, ValueId };
+use crate::runtime::Value;
+use crate::runtime::operations::{ binary_operation, unary_operation };
+use std::collections::{ HashMap, HashSet };
+#[cfg(test)]
+use crate::parser::parse;
+
+/// Runs the optimization passes over every function until none of them changes anything.
+pub fn optimize(program: &mut Program) {
+    for function in &mut program.functions {
+        loop {
+            let mut is_changed = propagate_constants(function);
+            is_changed |= eliminate_common_subexpressions(function);
+            is_changed |= eliminate_dead_code(function);
+            infer_types(function);
+            if !is_changed {
+                break
+            }
+        }
+    }
+}
+
+/// Infers the types of the values which depend on the types of the operands.
+///
+/// Types of the phis in the loops depend on themselves, so the types are refined until they are stable:
+/// the value whose type is not known yet does not affect the types of the others.
+pub fn infer_types(function: &mut Function) {
+    let mut types: HashMap<ValueId, Type> = HashMap::new();
+    let definitions: HashMap<ValueId, Operation> = function.blocks.iter().flat_map(|block| block.instructions.iter())
+        .map(|instruction| (instruction.result, instruction.operation.clone())).collect();
+    for instruction in function.blocks.iter().flat_map(|block| block.instructions.iter()) {
+        if !is_derived(&instruction.operation) {
+            types.insert(instruction.result, instruction.value_type);
+        }
+    }
+    let mut is_changed = true;
+    while is_changed {
+        is_changed = false;
+        for instruction in function.blocks.iter().flat_map(|block| block.instructions.iter()).filter(|instruction| is_derived(&instruction.operation)) {
+            if let Some(value_type) = derived_type(&instruction.operation, &types, &definitions) {
+                if types.insert(instruction.result, value_type) != Some(value_type) {
+                    is_changed = true;
+                }
+            }
+        }
+    }
+    for instruction in function.blocks.iter_mut().flat_map(|block| block.instructions.iter_mut()) {
+        instruction.value_type = types.get(&instruction.result).copied().unwrap_or(Type::Any);
+    }
+}
+
+#[inline]
+fn is_derived(operation: &Operation) -> bool {
+    matches!(operation, Operation::Unary(..) | Operation::Binary(..) | Operation::Index(..) | Operation::Next(_) | Operation::Phi(_))
+}
+
+/// Type of the result for the types of the operands, `None` if they are not known yet.
+fn derived_type(operation: &Operation, types: &HashMap<ValueId, Type>, definitions: &HashMap<ValueId, Operation>) -> Option<Type> {
+    if let Operation::Phi(incoming) = operation {
+        return incoming.iter().filter_map(|(_, value)| types.get(value).copied()).reduce(Type::join)
+    }
+    let operands = operation.operands().iter().map(|value| types.get(value).copied()).collect::<Option<Vec<Type>>>()?;
+    let value_type = match (operation, operands.as_slice()) {
+        (Operation::Unary(operator, _), [operand]) => match (operator, operand) {
+            (Operator::Not, _) => Type::Bool,
+            (_, Type::Int) => Type::Int,
+            (_, Type::Float) => Type::Float,
+            _ => Type::Any,
+        },
+        (Operation::Binary(operator, _, _), [lhs, rhs]) => match (operator, lhs, rhs) {
+            (Operator::Plus, Type::String, _) | (Operator::Plus, _, Type::String) => Type::String,
+            (Operator::Plus | Operator::Minus | Operator::Multiply | Operator::Divide, Type::Int, Type::Int) => Type::Int,
+            (Operator::Plus | Operator::Minus | Operator::Multiply | Operator::Divide | Operator::Power, a, b)
+                if a.is_number() && b.is_number() && (*a == Type::Float || *b == Type::Float) => Type::Float,
+            (Operator::Equal | Operator::NotEqual | Operator::Less | Operator::LessOrEqual | Operator::Greater | Operator::GreaterOrEqual
+                | Operator::In | Operator::Xor, _, _) => Type::Bool,
+            _ => Type::Any,
+        },
+        (Operation::Index(_, _), [Type::String, Type::Int]) => Type::String,
+        // the items of the range have the type of its bounds
+        (Operation::Next(iterator), [_]) => match definitions.get(iterator) {
+            Some(Operation::Iterate(iterable)) => match (definitions.get(iterable), types.get(iterable)) {
+                (Some(Operation::Range(start, end, step, _)), _) => {
+                    let bounds = [start, end, step];
+                    bounds.iter().filter_map(|value| types.get(value).copied()).filter(|value_type| *value_type != Type::None).reduce(Type::join).filter(|value_type| value_type.is_number()).unwrap_or(Type::Any)
+                },
+                (_, Some(Type::String)) => Type::String,
+                _ => Type::Any,
+            },
+            _ => Type::Any,
+        },
+        _ => Type::Any,
+    };
+    Some(value_type)
+}
+
+/// Replaces the operations whose operands are the constants with their results, the same way as the interpreter does.
+///
+/// - operators with the constant operands are evaluated, unless they fail at runtime
+/// - `is` is evaluated if the type of the value is known
+/// - phis whose incoming values are all the same value are replaced with it
+/// - `branch` on the constant condition becomes `jump`, the block which is not taken loses the predecessor
+pub fn propagate_constants(function: &mut Function) -> bool {
+    let mut is_changed = false;
+    let mut constants: HashMap<ValueId, Constant> = HashMap::new();
+    let types = function.types();
+    let mut replacements = vec!();
+    for block in &mut function.blocks {
+        for instruction in &mut block.instructions {
+            let operands: Option<Vec<Value>> = instruction.operation.operands().iter().map(|value| constants.get(value).map(to_value)).collect();
+            let folded = match (&instruction.operation, operands) {
+                (Operation::Unary(operator, _), Some(operands)) => unary_operation(*operator, &operands[0]).ok().and_then(to_constant),
+                (Operation::Binary(operator, _, _), Some(operands)) => binary_operation(*operator, &operands[0], &operands[1]).ok().and_then(to_constant),
+                (Operation::Is(value, name), _) => match (types.get(value).copied().unwrap_or(Type::Any), name.as_str()) {
+                    (_, "Any") => Some(Constant::Bool(true)),
+                    (Type::Any, _) => None,
+                    (value_type, "Number") => Some(Constant::Bool(value_type.is_number())),
+                    (value_type, name) => Some(Constant::Bool(Type::from_annotation(name) == value_type)),
+                },
+                (Operation::Phi(incoming), _) => {
+                    let mut values = incoming.iter().map(|(_, value)| *value).filter(|value| *value != instruction.result);
+                    if let Some(first) = values.next() {
+                        if values.all(|value| value == first) {
+                            replacements.push((instruction.result, first));
+                        }
+                    }
+                    None
+                },
+                _ => None,
+            };
+            if let Some(constant) = folded {
+                instruction.value_type = constant.value_type();
+                instruction.operation = Operation::Constant(constant);
+                is_changed = true;
+            }
+            if let Operation::Constant(constant) = &instruction.operation {
+                constants.insert(instruction.result, constant.clone());
+            }
+        }
+    }
+    while !replacements.is_empty() {
+        let (phi, value) = replacements.remove(0);
+        if phi == value {
+            continue
+        }
+        // the phi may be the replacement of the other phi
+        replacements.iter_mut().filter(|(_, other)| *other == phi).for_each(|(_, other)| *other = value);
+        function.replace_uses(phi, value);
+        for block in &mut function.blocks {
+            block.instructions.retain(|instruction| instruction.result != phi);
+        }
+        is_changed = true;
+    }
+    let mut removed_edges = vec!();
+    for block in &mut function.blocks {
+        if let Terminator::Branch(condition, then, otherwise) = block.terminator {
+            if let Some(Constant::Bool(value)) = constants.get(&condition) {
+                let (taken, skipped) = if *value { (then, otherwise) } else { (otherwise, then) };
+                block.terminator = Terminator::Jump(taken);
+                if taken != skipped {
+                    removed_edges.push((block.id, skipped));
+                }
+                is_changed = true;
+            }
+        }
+    }
+    for (from, to) in removed_edges {
+        remove_incoming(function, from, to);
+    }
+    is_changed
+}
+
+/// Removes the values coming from the block which is not the predecessor anymore.
+fn remove_incoming(function: &mut Function, from: BlockId, to: BlockId) {
+    for instruction in &mut function.block_mut(to).instructions {
+        if let Operation::Phi(incoming) = &mut instruction.operation {
+            incoming.retain(|(block, _)| *block != from);
+        }
+    }
+}
+
+/// Reuses the result of the same operation on the same operands computed in the dominating block.
+///
+/// Operators, constants, ranges and `is` always produce the same value for the same operands,
+/// lists, indexes and calls are not reused because the lists may be changed between them.
+pub fn eliminate_common_subexpressions(function: &mut Function) -> bool {
+    let dominators = function.dominators();
+    let mut children: HashMap<BlockId, Vec<BlockId>> = HashMap::new();
+    for block in function.reverse_postorder().into_iter().skip(1) {
+        children.entry(dominators[&block]).or_default().push(block);
+    }
+    let mut available: Vec<HashMap<String, ValueId>> = vec!();
+    let mut replacements = vec!();
+    // blocks of the dominator tree in the preorder, `None` closes the scope of the block
+    let mut stack = vec!(Some(function.blocks[0].id));
+    while let Some(item) = stack.pop() {
+        let block = match item {
+            Some(block) => block,
+            None => {
+                available.pop();
+                continue
+            },
+        };
+        let mut scope = HashMap::new();
+        for instruction in &function.block(block).instructions {
+            if !matches!(instruction.operation, Operation::Constant(_) | Operation::Unary(..) | Operation::Binary(..) | Operation::Range(..) | Operation::Is(..)) {
+                continue
+            }
+            let key = instruction.operation.to_string();
+            match available.iter().rev().find_map(|scope| scope.get(&key)).or_else(|| scope.get(&key)) {
+                Some(value) => replacements.push((instruction.result, *value)),
+                None => {
+                    scope.insert(key, instruction.result);
+                },
+            }
+        }
+        available.push(scope);
+        stack.push(None);
+        stack.extend(children.get(&block).into_iter().flatten().map(|child| Some(*child)));
+    }
+    let is_changed = !replacements.is_empty();
+    let removed: HashSet<ValueId> = replacements.iter().map(|(value, _)| *value).collect();
+    for (value, replacement) in replacements {
+        function.replace_uses(value, replacement);
+    }
+    for block in &mut function.blocks {
+        block.instructions.retain(|instruction| !removed.contains(&instruction.result));
+    }
+    is_changed
+}
+
+/// Removes the blocks which are never executed and the pure instructions whose results are never used,
+/// the block which is the only successor of its only predecessor is merged into it.
+pub fn eliminate_dead_code(function: &mut Function) -> bool {
+    let reachable: HashSet<BlockId> = function.reverse_postorder().into_iter().collect();
+    let count = function.blocks.len();
+    function.blocks.retain(|block| reachable.contains(&block.id));
+    let mut is_changed = count != function.blocks.len();
+    let predecessors = function.predecessors();
+    for block in &mut function.blocks {
+        let block_predecessors = &predecessors[&block.id];
+        for instruction in &mut block.instructions {
+            if let Operation::Phi(incoming) = &mut instruction.operation {
+                let count = incoming.len();
+                incoming.retain(|(predecessor, _)| block_predecessors.contains(predecessor));
+                is_changed |= count != incoming.len();
+            }
+        }
+    }
+    is_changed |= merge_blocks(function);
+    // the values used by the impure instructions and the terminators are live, and so are their operands
+    let types = function.types();
+    let definitions: HashMap<ValueId, Vec<ValueId>> = function.blocks.iter().flat_map(|block| block.instructions.iter())
+        .map(|instruction| (instruction.result, instruction.operation.operands())).collect();
+    let mut live = HashSet::new();
+    let mut work: Vec<ValueId> = vec!();
+    for block in &function.blocks {
+        for instruction in &block.instructions {
+            if !instruction.operation.is_pure(&types) {
+                work.push(instruction.result);
+            }
+        }
+        work.extend(block.terminator.operands());
+    }
+    while let Some(value) = work.pop() {
+        if live.insert(value) {
+            work.extend(definitions.get(&value).into_iter().flatten().copied());
+        }
+    }
+    for block in &mut function.blocks {
+        let count = block.instructions.len();
+        block.instructions.retain(|instruction| live.contains(&instruction.result));
+        is_changed |= count != block.instructions.len();
+    }
+    is_changed
+}
+
+/// Appends the block to its only predecessor if the predecessor jumps only to it.
+fn merge_blocks(function: &mut Function) -> bool {
+    let mut is_changed = false;
+    loop {
+        let predecessors = function.predecessors();
+        let mergeable = function.blocks.iter().skip(1).find(|block| match predecessors[&block.id].as_slice() {
+            [predecessor] => {
+                let has_phis = block.instructions.iter().any(|instruction| matches!(instruction.operation, Operation::Phi(_)));
+                *predecessor != block.id && !has_phis && function.block(*predecessor).terminator == Terminator::Jump(block.id)
+            },
+            _ => false,
+        }).map(|block| (predecessors[&block.id][0], block.id));
+        let (predecessor, block) = match mergeable {
+            Some(pair) => pair,
+            None => return is_changed,
+        };
+        let merged = function.blocks.remove(function.blocks.iter().position(|candidate| candidate.id == block).unwrap());
+        for successor in merged.terminator.successors() {
+            for instruction in &mut function.block_mut(successor).instructions {
+                if let Operation::Phi(incoming) = &mut instruction.operation {
+                    incoming.iter_mut().filter(|(source, _)| *source == block).for_each(|(source, _)| *source = predecessor);
+                }
+            }
+        }
+        let target = function.block_mut(predecessor);
+        target.instructions.extend(merged.instructions);
+        target.terminator = merged.terminator;
+        target.terminator_pos = merged.terminator_pos;
+        is_changed = true;
+    }
+}
+
+fn to_value(constant: &Constant) -> Value {
+    match constant {
+        Constant::None => Value::None,
+        Constant::Bool(value) => Value::Bool(*value),
+        Constant::Int(value) => Value::Int(*value),
+        Constant::Float(value) => Value::Float(*value),
+        Constant::String(value) => Value::Str(value.clone()),
+    }
+}
+
+fn to_constant(value: Value) -> Option<Constant> {
+    match value {
+        Value::None => Some(Constant::None),
+        Value::Bool(value) => Some(Constant::Bool(value)),
+        Value::Int(value) => Some(Constant::Int(value)),
+        Value::Float(value) => Some(Constant::Float(value)),
+        Value::Str(value) => Some(Constant::String(value)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+fn optimized(source: &str) -> Program {
+    let tree = parse(source);
+    let mut program = crate::ir::lower(&tree).unwrap();
+    optimize(&mut program);
+    assert_eq!(Vec::<String>::new(), crate::ir::verify(&program), "{}", program);
+    program
+}
+
+#[test]
+fn test_constants_are_propagated() {
+    // the division by zero is left for the runtime
+    let program = optimized("x = 2 * 3 + 1\ny = if x > 5 { \"big\" } else { \"small\" }\nprintln(y + x, 1 is Int, 1 / 0)");
+    assert_eq!(concat!(
+        "fn main() -> Any {\n",
+        "b0:\n",
+        "  v3: Int = const 1\n",
+        "  v6: Bool = const true\n",
+        "  v11: String = const \"big7\"\n",
+        "  v15: Int = const 0\n",
+        "  v16: Int = div v3, v15\n",
+        "  v17: None = call println(v11, v6, v16)\n",
+        "  return v17\n",
+        "}\n",
+    ), program.to_string());
+}
+
+#[test]
+fn test_common_subexpressions_are_reused() {
+    let program = optimized("fn f(a: Int, b: Int) {\n  x = a * b\n  if a > 0 { a * b + 1 } else { x }\n}");
+    let multiplications = program.functions[0].blocks.iter().flat_map(|block| block.instructions.iter())
+        .filter(|instruction| matches!(&instruction.operation, Operation::Binary(Operator::Multiply, _, _))).count();
+    assert_eq!(1, multiplications);
+    // the lists may be changed, so the indexes are not reused
+    let program = optimized("fn g(xs: List) {\n  a = xs[0]\n  xs[0] = 5\n  a + xs[0]\n}");
+    let indexes = program.functions[0].blocks.iter().flat_map(|block| block.instructions.iter())
+        .filter(|instruction| matches!(instruction.operation, Operation::Index(_, _))).count();
+    assert_eq!(2, indexes);
+}
+
+#[test]
+fn test_dead_code_is_removed() {
+    let program = optimized("fn f(a: Float, b: Int) {\n  unused = a * 2\n  failing = b * 2\n  if false { println(a) }\n  return a\n  println(b)\n}");
+    let function = &program.functions[0];
+    assert_eq!(1, function.blocks.len());
+    let operations: Vec<String> = function.blocks[0].instructions.iter().map(|instruction| instruction.operation.to_string()).collect();
+    // Int multiplication may overflow, so it stays
+    assert_eq!(vec!("param 0", "param 1", "const 2", "mul v1, v2"), operations);
+}
@@ -0,0 +1,438 @@
+use crate::dto::Operator;
+use std::collections::{ HashMap, HashSet };
+use std::fmt;
+
+/// Value defined by the instruction, every value is assigned exactly once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ValueId(pub usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BlockId(pub usize);
+
+impl fmt::Display for ValueId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "v{}", self.0)
+    }
+}
+
+impl fmt::Display for BlockId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "b{}", self.0)
+    }
+}
+
+/// Type of the value known before the execution, `Any` is checked at runtime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Type {
+    None,
+    Bool,
+    Int,
+    Float,
+    String,
+    Range,
+    List,
+    Iterator,
+    Any,
+}
+
+impl Type {
+
+    /// Type of the value which is either of the types.
+    pub fn join(self, other: Type) -> Type {
+        match self == other {
+            true => self,
+            false => Type::Any,
+        }
+    }
+
+    /// Type declared by the annotation, `Number` and the types of the structs are checked at runtime.
+    pub fn from_annotation(name: &str) -> Type {
+        match name {
+            "None" => Type::None,
+            "Bool" => Type::Bool,
+            "Int" => Type::Int,
+            "Float" => Type::Float,
+            "String" => Type::String,
+            "Range" => Type::Range,
+            "List" => Type::List,
+            _ => Type::Any,
+        }
+    }
+
+    pub fn is_number(self) -> bool {
+        matches!(self, Type::Int | Type::Float)
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Constant {
+    None,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+}
+
+impl Constant {
+
+    pub fn value_type(&self) -> Type {
+        match self {
+            Constant::None => Type::None,
+            Constant::Bool(_) => Type::Bool,
+            Constant::Int(_) => Type::Int,
+            Constant::Float(_) => Type::Float,
+            Constant::String(_) => Type::String,
+        }
+    }
+}
+
+impl fmt::Display for Constant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Constant::None => write!(f, "None"),
+            Constant::Bool(value) => write!(f, "{}", value),
+            Constant::Int(value) => write!(f, "{}", value),
+            Constant::Float(value) => write!(f, "{:?}", value),
+            Constant::String(value) => write!(f, "{:?}", value),
+        }
+    }
+}
+
+/// Operation of the instruction, the operators are the same as in the syntax tree: `+`, `u-`, `not`, `in`, ...
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operation {
+    Constant(Constant),
+    /// Argument of the function by its position.
+    Parameter(usize),
+    Unary(Operator, ValueId),
+    Binary(Operator, ValueId, ValueId),
+    /// `value is Type`, the type is the name from the source.
+    Is(ValueId, String),
+    /// Call of the global function or the builtin.
+    Call(String, Vec<ValueId>),
+    List(Vec<ValueId>),
+    /// Start, end, step and whether the end is included, `None` constants stand for the omitted parts.
+    Range(ValueId, ValueId, ValueId, bool),
+    Index(ValueId, ValueId),
+    SetIndex(ValueId, ValueId, ValueId),
+    /// Global variable of the program read or assigned by the function.
+    LoadGlobal(String),
+    StoreGlobal(String, ValueId),
+    /// Iterator over the items of the value in `for`, `Next` moves it to the next item.
+    Iterate(ValueId),
+    HasNext(ValueId),
+    Next(ValueId),
+    /// Value from the predecessor block which is executed before the block.
+    Phi(Vec<(BlockId, ValueId)>),
+}
+
+impl Operation {
+
+    /// Values used by the operation.
+    pub fn operands(&self) -> Vec<ValueId> {
+        match self {
+            Operation::Constant(_) | Operation::Parameter(_) | Operation::LoadGlobal(_) => vec!(),
+            Operation::Unary(_, value) | Operation::Is(value, _) | Operation::StoreGlobal(_, value)
+                | Operation::Iterate(value) | Operation::HasNext(value) | Operation::Next(value) => vec!(*value),
+            Operation::Binary(_, lhs, rhs) | Operation::Index(lhs, rhs) => vec!(*lhs, *rhs),
+            Operation::Range(start, end, step, _) | Operation::SetIndex(start, end, step) => vec!(*start, *end, *step),
+            Operation::Call(_, values) | Operation::List(values) => values.clone(),
+            Operation::Phi(incoming) => incoming.iter().map(|(_, value)| *value).collect(),
+        }
+    }
+
+    pub fn operands_mut(&mut self) -> Vec<&mut ValueId> {
+        match self {
+            Operation::Constant(_) | Operation::Parameter(_) | Operation::LoadGlobal(_) => vec!(),
+            Operation::Unary(_, value) | Operation::Is(value, _) | Operation::StoreGlobal(_, value)
+                | Operation::Iterate(value) | Operation::HasNext(value) | Operation::Next(value) => vec!(value),
+            Operation::Binary(_, lhs, rhs) | Operation::Index(lhs, rhs) => vec!(lhs, rhs),
+            Operation::Range(start, end, step, _) | Operation::SetIndex(start, end, step) => vec!(start, end, step),
+            Operation::Call(_, values) | Operation::List(values) => values.iter_mut().collect(),
+            Operation::Phi(incoming) => incoming.iter_mut().map(|(_, value)| value).collect(),
+        }
+    }
+
+    /// Checks that the operation changes nothing but its result, so it can be removed if the result is not used.
+    /// Operators may fail at runtime, only those which never fail for the types of the operands are pure.
+    pub fn is_pure(&self, types: &HashMap<ValueId, Type>) -> bool {
+        let type_of = |value: &ValueId| types.get(value).copied().unwrap_or(Type::Any);
+        match self {
+            Operation::Constant(_) | Operation::Parameter(_) | Operation::Phi(_) | Operation::LoadGlobal(_)
+                | Operation::List(_) | Operation::Is(_, _) => true,
+            Operation::Binary(Operator::Equal | Operator::NotEqual, _, _) => true,
+            Operation::Binary(operator, lhs, rhs) => match (operator, type_of(lhs), type_of(rhs)) {
+                (Operator::Less | Operator::LessOrEqual | Operator::Greater | Operator::GreaterOrEqual, a, b) =>
+                    a.is_number() && b.is_number() || a == b && matches!(a, Type::Bool | Type::String),
+                // the arithmetic of floats never fails, the division by zero is infinity
+                (Operator::Plus | Operator::Minus | Operator::Multiply | Operator::Divide | Operator::Power, a, b)
+                    if a.is_number() && b.is_number() && (a == Type::Float || b == Type::Float) => true,
+                (Operator::Plus, Type::String, Type::String) => true,
+                (Operator::Xor, Type::Bool, Type::Bool) => true,
+                _ => false,
+            },
+            Operation::Unary(operator, value) => matches!((operator, type_of(value)), (Operator::Not, Type::Bool) | (Operator::UnaryMinus, Type::Float)),
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instruction {
+    pub result: ValueId,
+    pub value_type: Type,
+    pub operation: Operation,
+    /// Offset of the node in the source which the instruction comes from, the runtime errors of the backends are reported at it.
+    pub pos: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Terminator {
+    Jump(BlockId),
+    /// Goes to the first block if the condition is true and to the second one otherwise.
+    Branch(ValueId, BlockId, BlockId),
+    Return(ValueId),
+}
+
+impl Terminator {
+
+    pub fn successors(&self) -> Vec<BlockId> {
+        match self {
+            Terminator::Jump(target) => vec!(*target),
+            Terminator::Branch(_, then, otherwise) => vec!(*then, *otherwise),
+            Terminator::Return(_) => vec!(),
+        }
+    }
+
+    pub fn operands(&self) -> Vec<ValueId> {
+        match self {
+            Terminator::Jump(_) => vec!(),
+            Terminator::Branch(condition, _, _) => vec!(*condition),
+            Terminator::Return(value) => vec!(*value),
+        }
+    }
+
+    pub fn operands_mut(&mut self) -> Vec<&mut ValueId> {
+        match self {
+            Terminator::Jump(_) => vec!(),
+            Terminator::Branch(condition, _, _) => vec!(condition),
+            Terminator::Return(value) => vec!(value),
+        }
+    }
+}
+
+/// Basic block: the phis, the other instructions and the terminator which passes the control to the next blocks.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    pub id: BlockId,
+    pub instructions: Vec<Instruction>,
+    pub terminator: Terminator,
+    /// Offset of the node which the terminator comes from, the condition of the branch is checked at it.
+    pub terminator_pos: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    pub name: String,
+    pub parameters: Vec<(String, Type)>,
+    pub return_type: Type,
+    /// Blocks in the order of the creation, the first one is the entry.
+    pub blocks: Vec<Block>,
+    /// Number of the values created so far, the next value gets this number.
+    pub values: usize,
+}
+
+impl Function {
+
+    pub fn block(&self, id: BlockId) -> &Block {
+        self.blocks.iter().find(|block| block.id == id).unwrap_or_else(|| panic!("unknown block {}", id))
+    }
+
+    pub fn block_mut(&mut self, id: BlockId) -> &mut Block {
+        self.blocks.iter_mut().find(|block| block.id == id).unwrap_or_else(|| panic!("unknown block {}", id))
+    }
+
+    /// Predecessors of every block, in the order of the blocks.
+    pub fn predecessors(&self) -> HashMap<BlockId, Vec<BlockId>> {
+        let mut predecessors: HashMap<BlockId, Vec<BlockId>> = self.blocks.iter().map(|block| (block.id, vec!())).collect();
+        for block in &self.blocks {
+            for successor in block.terminator.successors() {
+                let list = predecessors.entry(successor).or_default();
+                if !list.contains(&block.id) {
+                    list.push(block.id);
+                }
+            }
+        }
+        predecessors
+    }
+
+    /// Blocks reachable from the entry in the reverse postorder, every block goes before its successors except the back edges.
+    pub fn reverse_postorder(&self) -> Vec<BlockId> {
+        let mut visited = HashSet::new();
+        let mut order = vec!();
+        // blocks with the index of the next successor to visit
+        let mut stack = vec!((self.blocks[0].id, 0));
+        visited.insert(self.blocks[0].id);
+        while let Some((block, index)) = stack.pop() {
+            let successors = self.block(block).terminator.successors();
+            match successors.get(index) {
+                Some(successor) => {
+                    stack.push((block, index + 1));
+                    if visited.insert(*successor) {
+                        stack.push((*successor, 0));
+                    }
+                },
+                None => order.push(block),
+            }
+        }
+        order.reverse();
+        order
+    }
+
+    /// Immediate dominators of the reachable blocks, the entry is its own dominator.
+    ///
+    /// The algorithm of Cooper, Harvey and Kennedy: the dominators are refined in the reverse postorder until they are stable.
+    pub fn dominators(&self) -> HashMap<BlockId, BlockId> {
+        let order = self.reverse_postorder();
+        let position: HashMap<BlockId, usize> = order.iter().enumerate().map(|(index, block)| (*block, index)).collect();
+        let predecessors = self.predecessors();
+        let mut dominators = HashMap::new();
+        dominators.insert(order[0], order[0]);
+        let mut is_changed = true;
+        while is_changed {
+            is_changed = false;
+            for block in &order[1..] {
+                let mut processed = predecessors[block].iter().filter(|predecessor| dominators.contains_key(*predecessor));
+                let mut dominator = *processed.next().unwrap();
+                for predecessor in processed {
+                    let (mut a, mut b) = (*predecessor, dominator);
+                    while a != b {
+                        while position[&a] > position[&b] {
+                            a = dominators[&a];
+                        }
+                        while position[&b] > position[&a] {
+                            b = dominators[&b];
+                        }
+                    }
+                    dominator = a;
+                }
+                if dominators.insert(*block, dominator) != Some(dominator) {
+                    is_changed = true;
+                }
+            }
+        }
+        dominators
+    }
+
+    /// Types of all values defined in the function.
+    pub fn types(&self) -> HashMap<ValueId, Type> {
+        self.blocks.iter().flat_map(|block| block.instructions.iter()).map(|instruction| (instruction.result, instruction.value_type)).collect()
+    }
+
+    /// Replaces every use of the value with the other value.
+    pub fn replace_uses(&mut self, from: ValueId, to: ValueId) {
+        for block in &mut self.blocks {
+            for instruction in &mut block.instructions {
+                instruction.operation.operands_mut().into_iter().filter(|value| **value == from).for_each(|value| *value = to);
+            }
+            block.terminator.operands_mut().into_iter().filter(|value| **value == from).for_each(|value| *value = to);
+        }
+    }
+}
+
+/// Functions of the program, the root block of the program is the function `main`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program {
+    pub functions: Vec<Function>,
+}
+
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, function) in self.functions.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", function)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parameters: Vec<String> = self.parameters.iter().map(|(name, value_type)| format!("{}: {}", name, value_type)).collect();
+        writeln!(f, "fn {}({}) -> {} {{", self.name, parameters.join(", "), self.return_type)?;
+        for block in &self.blocks {
+            writeln!(f, "{}:", block.id)?;
+            for instruction in &block.instructions {
+                writeln!(f, "  {}: {} = {}", instruction.result, instruction.value_type, instruction.operation)?;
+            }
+            writeln!(f, "  {}", block.terminator)?;
+        }
+        writeln!(f, "}}")
+    }
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let list = |values: &[ValueId]| values.iter().map(ValueId::to_string).collect::<Vec<String>>().join(", ");
+        match self {
+            Operation::Constant(constant) => write!(f, "const {}", constant),
+            Operation::Parameter(index) => write!(f, "param {}", index),
+            Operation::Unary(operator, value) => write!(f, "{} {}", mnemonic(*operator), value),
+            Operation::Binary(operator, lhs, rhs) => write!(f, "{} {}, {}", mnemonic(*operator), lhs, rhs),
+            Operation::Is(value, name) => write!(f, "is {}, {}", value, name),
+            Operation::Call(name, arguments) => write!(f, "call {}({})", name, list(arguments)),
+            Operation::List(items) => write!(f, "list [{}]", list(items)),
+            Operation::Range(start, end, step, is_inclusive) => {
+                write!(f, "range {}, {}, {}{}", start, end, step, if *is_inclusive { ", inclusive" } else { "" })
+            },
+            Operation::Index(collection, key) => write!(f, "index {}, {}", collection, key),
+            Operation::SetIndex(collection, key, value) => write!(f, "set_index {}, {}, {}", collection, key, value),
+            Operation::LoadGlobal(name) => write!(f, "load_global {}", name),
+            Operation::StoreGlobal(name, value) => write!(f, "store_global {}, {}", name, value),
+            Operation::Iterate(value) => write!(f, "iterate {}", value),
+            Operation::HasNext(iterator) => write!(f, "has_next {}", iterator),
+            Operation::Next(iterator) => write!(f, "next {}", iterator),
+            Operation::Phi(incoming) => {
+                let incoming: Vec<String> = incoming.iter().map(|(block, value)| format!("{}: {}", block, value)).collect();
+                write!(f, "phi [{}]", incoming.join(", "))
+            },
+        }
+    }
+}
+
+impl fmt::Display for Terminator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Terminator::Jump(target) => write!(f, "jump {}", target),
+            Terminator::Branch(condition, then, otherwise) => write!(f, "branch {}, {}, {}", condition, then, otherwise),
+            Terminator::Return(value) => write!(f, "return {}", value),
+        }
+    }
+}
+
+/// Name of the operator in the dump: `+` is `add`, `u-` is `neg`.
+fn mnemonic(operator: Operator) -> &'static str {
+    match operator {
+        Operator::Plus => "add",
+        Operator::Minus => "sub",
+        Operator::Multiply => "mul",
+        Operator::Divide => "div",
+        Operator::Power => "pow",
+        Operator::Equal => "eq",
+        Operator::NotEqual => "ne",
+        Operator::Less => "lt",
+        Operator::LessOrEqual => "le",
+        Operator::Greater => "gt",
+        Operator::GreaterOrEqual => "ge",
+        Operator::UnaryMinus => "neg",
+        operator => operator.as_str(),
+    }
+}
@@ -0,0 +1,173 @@
+#[cfg(test)]
+use crate::dto::Operator;
+use crate::ir::program::{ BlockId, Function, Operation, Program, Terminator, Type, ValueId };
+use std::collections::{ HashMap, HashSet };
+
+/// Checks the invariants of the SSA form which the passes and the backends rely on.
+///
+/// - every value is defined exactly once and the terminators jump to the existing blocks
+/// - phis are at the start of the block and have one incoming value for every predecessor
+/// - the definition of the value dominates its uses, the incoming value of the phi must be
+///   available at the end of the predecessor
+/// - conditions of the branches are Bool, constants and parameters have their own types
+///
+/// Blocks which are never executed are checked only for the first two rules.
+/// Returns the descriptions of the violations, the empty list means the program is valid.
+pub fn verify(program: &Program) -> Vec<String> {
+    let mut errors = vec!();
+    for function in &program.functions {
+        verify_function(function, &mut errors);
+    }
+    errors
+}
+
+fn verify_function(function: &Function, errors: &mut Vec<String>) {
+    let mut report = |message: String| errors.push(format!("fn {}: {}", function.name, message));
+    if function.blocks.is_empty() {
+        return report(String::from("function has no blocks"))
+    }
+    let blocks: HashSet<BlockId> = function.blocks.iter().map(|block| block.id).collect();
+    if blocks.len() != function.blocks.len() {
+        report(String::from("block is defined twice"));
+    }
+    // block and position of the definition of every value
+    let mut definitions: HashMap<ValueId, (BlockId, usize)> = HashMap::new();
+    for block in &function.blocks {
+        for (index, instruction) in block.instructions.iter().enumerate() {
+            if definitions.insert(instruction.result, (block.id, index)).is_some() {
+                report(format!("{} is defined twice", instruction.result));
+            }
+            if instruction.result.0 >= function.values {
+                report(format!("{} is out of the numbering of the values", instruction.result));
+            }
+        }
+        for successor in block.terminator.successors() {
+            if !blocks.contains(&successor) {
+                report(format!("{} jumps to the unknown block {}", block.id, successor));
+            }
+        }
+    }
+    if !errors_are_empty(&definitions, function) {
+        return
+    }
+    let dominators = function.dominators();
+    let predecessors = function.predecessors();
+    let types = function.types();
+    let dominates = |a: BlockId, mut b: BlockId| loop {
+        if a == b {
+            return true
+        }
+        match dominators.get(&b) {
+            Some(dominator) if *dominator != b => b = *dominator,
+            _ => return false,
+        }
+    };
+    // the value is available after the instruction with the index in the block, `usize::MAX` is the end of the block
+    let is_available = |value: ValueId, block: BlockId, index: usize| match definitions.get(&value) {
+        Some((definition, position)) if *definition == block => *position < index,
+        Some((definition, _)) => dominates(*definition, block),
+        None => false,
+    };
+    for block in function.blocks.iter().filter(|block| dominators.contains_key(&block.id)) {
+        let mut is_phi_allowed = true;
+        for (index, instruction) in block.instructions.iter().enumerate() {
+            match &instruction.operation {
+                Operation::Phi(incoming) => {
+                    if !is_phi_allowed {
+                        report(format!("phi {} is not at the start of {}", instruction.result, block.id));
+                    }
+                    let mut sources: Vec<BlockId> = incoming.iter().map(|(source, _)| *source).collect();
+                    let mut expected = predecessors[&block.id].clone();
+                    sources.sort();
+                    expected.sort();
+                    if sources != expected {
+                        report(format!("phi {} has the incoming blocks [{}] instead of the predecessors [{}]", instruction.result, list(&sources), list(&expected)));
+                    }
+                    for (source, value) in incoming {
+                        if dominators.contains_key(source) && !is_available(*value, *source, usize::MAX) {
+                            report(format!("{} is not available at the end of {} for the phi {}", value, source, instruction.result));
+                        }
+                    }
+                },
+                operation => {
+                    is_phi_allowed = false;
+                    for value in operation.operands() {
+                        if !is_available(value, block.id, index) {
+                            report(format!("{} is used by {} before its definition", value, instruction.result));
+                        }
+                    }
+                },
+            }
+            match &instruction.operation {
+                Operation::Constant(constant) if constant.value_type() != instruction.value_type => {
+                    report(format!("constant {} has the type {}", instruction.result, instruction.value_type));
+                },
+                Operation::Parameter(position) if *position >= function.parameters.len() => {
+                    report(format!("{} is the parameter {} of the function with {} parameters", instruction.result, position, function.parameters.len()));
+                },
+                _ => (),
+            }
+        }
+        for value in block.terminator.operands() {
+            if !is_available(value, block.id, usize::MAX) {
+                report(format!("{} is used by the terminator of {} before its definition", value, block.id));
+            }
+        }
+        if let Terminator::Branch(condition, _, _) = &block.terminator {
+            let condition_type = types.get(condition).copied().unwrap_or(Type::Any);
+            if !matches!(condition_type, Type::Bool | Type::Any) {
+                report(format!("condition {} of {} is {}", condition, block.id, condition_type));
+            }
+        }
+    }
+}
+
+/// Values defined twice make the rest of the checks meaningless.
+fn errors_are_empty(definitions: &HashMap<ValueId, (BlockId, usize)>, function: &Function) -> bool {
+    let count: usize = function.blocks.iter().map(|block| block.instructions.len()).sum();
+    definitions.len() == count
+}
+
+fn list(blocks: &[BlockId]) -> String {
+    blocks.iter().map(BlockId::to_string).collect::<Vec<String>>().join(", ")
+}
+
+#[cfg(test)]
+fn function(blocks: Vec<crate::ir::Block>, values: usize) -> Program {
+    Program { functions: vec!(Function { name: String::from("f"), parameters: vec!(), return_type: Type::Any, blocks, values }) }
+}
+
+#[cfg(test)]
+fn instruction(result: usize, value_type: Type, operation: Operation) -> crate::ir::Instruction {
+    crate::ir::Instruction { result: ValueId(result), value_type, operation, pos: 0 }
+}
+
+#[test]
+fn test_invalid_programs() {
+    use crate::ir::{ Block, Constant };
+    let constant = |result, value| instruction(result, Type::Int, Operation::Constant(Constant::Int(value)));
+    let program = function(vec!(Block {
+        id: BlockId(0),
+        instructions: vec!(instruction(0, Type::Any, Operation::Binary(Operator::Plus, ValueId(1), ValueId(1))), constant(1, 1)),
+        terminator: Terminator::Return(ValueId(0)),
+        terminator_pos: 0,
+    }), 2);
+    assert_eq!(vec!("fn f: v1 is used by v0 before its definition", "fn f: v1 is used by v0 before its definition"), verify(&program));
+    let program = function(vec!(
+        Block { id: BlockId(0), instructions: vec!(constant(0, 1)), terminator: Terminator::Branch(ValueId(0), BlockId(1), BlockId(2)), terminator_pos: 0 },
+        Block { id: BlockId(1), instructions: vec!(constant(1, 2)), terminator: Terminator::Jump(BlockId(2)), terminator_pos: 0 },
+        Block {
+            id: BlockId(2),
+            instructions: vec!(instruction(2, Type::Int, Operation::Phi(vec!((BlockId(1), ValueId(1)))))),
+            terminator: Terminator::Return(ValueId(1)),
+            terminator_pos: 0,
+        },
+    ), 3);
+    assert_eq!(vec!(
+        "fn f: condition v0 of b0 is Int",
+        "fn f: phi v2 has the incoming blocks [b1] instead of the predecessors [b0, b1]",
+        "fn f: v1 is used by the terminator of b2 before its definition",
+    ), verify(&program));
+    let program = function(vec!(Block { id: BlockId(0), instructions: vec!(constant(0, 1), constant(0, 2)), terminator: Terminator::Jump(BlockId(3)), terminator_pos: 0 }), 1);
+    assert_eq!(vec!("fn f: v0 is defined twice", "fn f: b0 jumps to the unknown block b3"), verify(&program));
+}
@@ -0,0 +1,46 @@
+//! Compiler and interpreter of the Avo programming language.
+//!
+//! The [`Engine`] runs Avo scripts inside of the Rust program, it is configured with the [`Limits`]
+//! and returns the [`Value`] of the script or the [`Error`].
+//!
+//! The modules of the crate are the stages of the pipeline: `parser` creates the syntax tree, `analyzer` checks it,
+//! `optimizer` simplifies it and `runtime` executes it. `lint` looks for the suspicious code which is valid but probably wrong.
+//! `ir` is the representation of the program in the SSA form between the syntax tree and the backends,
+//! `codegen` translates the checked program to the other languages instead of running it.
+//! Only the x86-64 backend is built on the `ir` so far. The interpreter and the C and WebAssembly backends still walk
+//! the syntax tree: the `ir` has no lambdas and matches yet, which the interpreter and the C backend support,
+//! and WebAssembly needs the structured control flow restored from the blocks of the `ir`.
+
+mod dto;
+mod parser;
+mod runtime;
+mod analyzer;
+mod optimizer;
+mod lint;
+mod ir;
+mod codegen;
+mod io;
+mod engine;
+pub use engine::{ Engine, Error };
+pub use dto::{ SyntaxError, RuntimeError };
+pub use runtime::Value;
+pub use runtime::limits::{ Limit, Limits };
+
+/// Stages of the compiler for the `avoc` binary, the tests, the benchmarks and the fuzz targets.
+///
+/// They are not the part of the stable API and may change in any version, the programs which run the scripts use the [`Engine`].
+#[doc(hidden)]
+pub mod internals {
+    pub use crate::dto::{ Node, NodeType, Operator, Symbol, Token, StackGuard, MAX_DEPTH };
+    pub use crate::parser::{ RpnTreeBuilder, TextEdit, TokenReader, TreeBuilder };
+    pub use crate::analyzer::{ resolve, check_exhaustiveness };
+    pub use crate::optimizer::fold_constants;
+    pub use crate::lint::{ lint, Config, CONFIG_FILE };
+    pub use crate::ir::{ lower, optimize, verify };
+    pub use crate::codegen::{ generate_c, generate_wat, generate_x86_64, link_x86_64 };
+    pub use crate::runtime::Interpreter;
+    pub use crate::io::{ error_info, print_error_info, print_warning_info };
+    pub use crate::io::logger::{ red, green };
+    pub use crate::io::loader::{ LoadError, Loader, Module };
+    pub use crate::io::dump::{ dump_tokens, dump_tree, Format };
+}
@@ -0,0 +1,386 @@
+use crate::dto::{ Node, NodeType, Operator, Token };
+use crate::lint::Rule;
+use std::collections::HashSet;
+#[cfg(test)]
+use crate::parser::parse;
+
+/// Suspicious code found by the linter, unlike [`SyntaxError`] it doesn't stop the compilation.
+///
+/// [`SyntaxError`]: crate::dto::SyntaxError
+#[derive(Debug, Clone, PartialEq)]
+pub struct Warning {
+    pub rule: Rule,
+    pub pos: usize,
+    pub message: String,
+}
+
+/// Checks the tree with all rules of the linter, the warnings are in the order of their positions.
+///
+/// Only the assignments which don't declare the variable, the compound assignments and the reads are usages,
+/// so the variable which is only assigned is unused. Public declarations are used by the other modules,
+/// unused parameters, functions, structs and enums are not reported.
+pub fn check(root: &Node) -> Vec<Warning> {
+    let mut linter = Linter {
+        scopes: vec!(vec!()),
+        late_uses: HashSet::new(),
+        function_depth: 0,
+        warnings: vec!(),
+    };
+    for statement in &root.children {
+        if statement.node_type == NodeType::Import {
+            imported_names(statement).into_iter().for_each(|(name, pos)| linter.declare(name, pos, Kind::Import))
+        }
+        let statement = statement.declaration();
+        match (statement.node_type, &statement.data) {
+            (NodeType::Function, Some(Token::Identifier { name, pos }))
+            | (NodeType::Struct, Some(Token::Identifier { name, pos }))
+            | (NodeType::Enum, Some(Token::Identifier { name, pos })) => linter.declare(name, *pos, Kind::Item),
+            _ => (),
+        }
+    }
+    linter.visit_statements(&root.children);
+    linter.pop_scope();
+    linter.warnings.sort_by_key(|warning| warning.pos);
+    linter.warnings
+}
+
+#[derive(PartialEq, Clone, Copy)]
+enum Kind {
+    Variable,
+    Parameter,
+    Import,
+    Item,
+}
+
+struct Declaration {
+    name: String,
+    pos: usize,
+    kind: Kind,
+    used: bool,
+}
+
+struct Linter {
+    scopes: Vec<Vec<Declaration>>,
+    /// Names used inside of the functions which were not declared yet, they may be the globals declared after the function.
+    late_uses: HashSet<String>,
+    function_depth: usize,
+    warnings: Vec<Warning>,
+}
+
+impl Linter {
+
+    #[inline]
+    fn warn(&mut self, rule: Rule, pos: usize, message: String) {
+        self.warnings.push(Warning { rule, pos, message })
+    }
+
+    fn find(&mut self, name: &str) -> Option<&mut Declaration> {
+        self.scopes.iter_mut().rev().flat_map(|scope| scope.iter_mut().rev()).find(|declaration| declaration.name == name)
+    }
+
+    fn use_name(&mut self, name: &str) {
+        let is_function = self.function_depth > 0;
+        match self.find(name) {
+            Some(declaration) => declaration.used = true,
+            None if is_function => { self.late_uses.insert(name.to_string()); },
+            None => (),
+        }
+    }
+
+    fn declare(&mut self, name: &str, pos: usize, kind: Kind) {
+        let declaration = Declaration { name: name.to_string(), pos, kind, used: kind == Kind::Item };
+        self.scopes.last_mut().unwrap().push(declaration)
+    }
+
+    /// Declares the variable which may hide the variable with the same name, unlike the plain assignment.
+    fn declare_shadowing(&mut self, name: &str, pos: usize, kind: Kind) {
+        if name == "_" {
+            return
+        }
+        if self.find(name).map(|declaration| declaration.kind != Kind::Item).unwrap_or(false) {
+            self.warn(Rule::ShadowedName, pos, format!("'{}' shadows the variable with the same name", name))
+        }
+        self.declare(name, pos, kind)
+    }
+
+    fn declare_pattern(&mut self, pattern: &Node, kind: Kind) {
+        match (pattern.node_type, &pattern.data) {
+            (NodeType::Token, Some(Token::Identifier { name, pos })) => self.declare_shadowing(name, *pos, kind),
+            _ => pattern.children.iter().for_each(|child| self.declare_pattern(child, kind)),
+        }
+    }
+
+    fn pop_scope(&mut self) {
+        let scope = self.scopes.pop().unwrap_or_default();
+        for declaration in scope {
+            // functions look up the globals at the moment of the call
+            if declaration.used || (self.scopes.is_empty() && self.late_uses.contains(&declaration.name)) {
+                continue
+            }
+            match declaration.kind {
+                Kind::Variable => self.warn(Rule::UnusedVariable, declaration.pos, format!("Variable '{}' is never used", declaration.name)),
+                Kind::Import => self.warn(Rule::UnusedImport, declaration.pos, format!("Import '{}' is never used", declaration.name)),
+                Kind::Parameter | Kind::Item => (),
+            }
+        }
+    }
+
+    fn visit_scoped(&mut self, visit: impl FnOnce(&mut Linter)) {
+        self.scopes.push(vec!());
+        visit(self);
+        self.pop_scope();
+    }
+
+    /// Visits the statements of the block and reports the first statement which is never executed.
+    fn visit_statements(&mut self, statements: &[Node]) {
+        statements.iter().for_each(|statement| self.visit(statement));
+        let unreachable = statements.windows(2).find_map(|pair| diverges(&pair[0]).map(|reason| (reason, &pair[1])));
+        if let Some((reason, statement)) = unreachable {
+            self.warn(Rule::UnreachableCode, start(statement), format!("Unreachable code after {}", reason))
+        }
+    }
+
+    fn visit(&mut self, node: &Node) {
+        match (node.node_type, &node.data) {
+            (NodeType::Token, Some(Token::Identifier { name, .. })) => self.use_name(name),
+            (NodeType::Call, Some(Token::Function { name, .. })) => {
+                self.use_name(name);
+                node.children.iter().for_each(|child| self.visit(child))
+            },
+            (NodeType::Expression, Some(Token::Operator { payload, .. })) if payload.is_assignment() => self.visit_assignment(node, *payload),
+            (NodeType::Expression, Some(Token::Operator { payload, pos })) if matches!(payload, Operator::Equal | Operator::NotEqual) => {
+                if node.children.iter().any(is_float) {
+                    self.warn(Rule::FloatEquality, *pos, format!("Floats are compared with '{}', the rounding errors may break the comparison", payload))
+                }
+                node.children.iter().for_each(|child| self.visit(child))
+            },
+            // names of the fields, methods and types are not variables
+            (NodeType::Expression, Some(Token::Operator { payload, .. })) if matches!(payload, Operator::Dot | Operator::Is) => {
+                self.visit(&node.children[0]);
+                if *payload == Operator::Dot && node.children[1].node_type == NodeType::Call {
+                    node.children[1].children.iter().for_each(|child| self.visit(child))
+                }
+            },
+            (NodeType::Block, _) => self.visit_scoped(|linter| linter.visit_statements(&node.children)),
+            (NodeType::If, _) => {
+                let condition = &node.condition[0];
+                if condition.operator().map(Operator::is_assignment).unwrap_or(false) {
+                    self.warn(Rule::AssignmentInCondition, condition.pos(), String::from("Assignment in the condition of 'if', did you mean '=='?"))
+                }
+                self.visit(condition);
+                self.check_empty(&node.children[0], "if");
+                if let Some(otherwise) = node.children.get(1) {
+                    self.check_empty(otherwise, "else")
+                }
+                node.children.iter().for_each(|child| self.visit(child))
+            },
+            (NodeType::For, _) => {
+                node.condition.iter().for_each(|child| self.visit(child));
+                self.check_empty(&node.children[1], "for");
+                self.visit_scoped(|linter| {
+                    linter.declare_pattern(&node.children[0], Kind::Variable);
+                    linter.visit(&node.children[1])
+                })
+            },
+            (NodeType::Loop, _) => {
+                self.check_empty(&node.children[0], "loop");
+                self.visit(&node.children[0])
+            },
+            (NodeType::Arm, _) => self.visit_scoped(|linter| {
+                linter.declare_pattern(&node.condition[0], Kind::Variable);
+                node.condition[1..].iter().chain(node.children.iter()).for_each(|child| linter.visit(child))
+            }),
+            (NodeType::Lambda, _) => self.visit_function(node),
+            (NodeType::Function, Some(Token::Identifier { name, pos })) => {
+                if self.scopes.len() > 1 {
+                    self.declare(name, *pos, Kind::Item)
+                }
+                self.visit_function(node)
+            },
+            (NodeType::Struct, Some(Token::Identifier { name, pos })) | (NodeType::Enum, Some(Token::Identifier { name, pos })) => {
+                if self.scopes.len() > 1 {
+                    self.declare(name, *pos, Kind::Item)
+                }
+            },
+            (NodeType::Public, _) => {
+                self.visit(&node.children[0]);
+                if let Some(name) = declared_name(&node.children[0]) {
+                    self.use_name(name)
+                }
+            },
+            (NodeType::Import, _) | (NodeType::Type, _) => (),
+            _ => {
+                node.condition.iter().for_each(|child| self.visit(child));
+                node.children.iter().for_each(|child| self.visit(child));
+            },
+        }
+    }
+
+    /// `name = value` declares the variable unless it is already declared, `mut name = value` always does.
+    fn visit_assignment(&mut self, node: &Node, operator: Operator) {
+        let (target, value) = (&node.children[0], &node.children[1]);
+        if operator == Operator::Assign && target.operator() != Some(Operator::Mut) && same(target, value) {
+            self.warn(Rule::SelfAssignment, node.pos(), String::from("Value is assigned to itself"))
+        }
+        self.visit(value);
+        match (target.operator(), &target.data) {
+            (Some(Operator::Mut), _) => self.declare_pattern(&target.children[0], Kind::Variable),
+            (_, Some(Token::Identifier { name, pos })) if operator == Operator::Assign => {
+                if self.find(name).is_none() {
+                    self.declare(name, *pos, Kind::Variable)
+                }
+            },
+            _ => self.visit(target),
+        }
+    }
+
+    fn visit_function(&mut self, node: &Node) {
+        self.function_depth += 1;
+        self.visit_scoped(|linter| {
+            for parameter in &node.condition {
+                parameter.children.iter().for_each(|default| linter.visit(default));
+                linter.declare_pattern(parameter, Kind::Parameter);
+            }
+            node.children.iter().for_each(|child| linter.visit(child))
+        });
+        self.function_depth -= 1;
+    }
+
+    fn check_empty(&mut self, block: &Node, statement: &str) {
+        if block.node_type == NodeType::Block && block.children.is_empty() {
+            self.warn(Rule::EmptyBlock, block.pos(), format!("Empty block of '{}'", statement))
+        }
+    }
+}
+
+/// Describes the statement after which the next statements of the same block are never executed.
+fn diverges(statement: &Node) -> Option<&'static str> {
+    match statement.node_type {
+        NodeType::Return => Some("'return'"),
+        NodeType::Break => Some("'break'"),
+        NodeType::Continue => Some("'continue'"),
+        NodeType::Loop if !has_break(&statement.children[0]) => Some("the loop without 'break'"),
+        _ => None,
+    }
+}
+
+/// Looks for `break` of the current loop, so the nested loops and functions are skipped.
+fn has_break(node: &Node) -> bool {
+    match node.node_type {
+        NodeType::Break => true,
+        NodeType::Loop | NodeType::For | NodeType::Lambda | NodeType::Function => false,
+        _ => node.condition.iter().chain(node.children.iter()).any(has_break),
+    }
+}
+
+/// Offset of the first token of the statement, the node of the operator has the position of the operator.
+fn start(node: &Node) -> usize {
+    node.condition.iter().chain(node.children.iter()).map(start).fold(node.pos(), usize::min)
+}
+
+fn is_float(node: &Node) -> bool {
+    match (node.node_type, &node.data) {
+        (NodeType::Token, Some(Token::FloatConstant { .. })) => true,
+        (NodeType::Expression, _) if node.operator() == Some(Operator::UnaryMinus) => is_float(&node.children[0]),
+        _ => false,
+    }
+}
+
+/// Compares the trees ignoring the positions.
+fn same(a: &Node, b: &Node) -> bool {
+    a.node_type == b.node_type
+        && a.data.as_ref().map(without_pos) == b.data.as_ref().map(without_pos)
+        && a.condition.len() == b.condition.len()
+        && a.children.len() == b.children.len()
+        && a.condition.iter().zip(b.condition.iter()).all(|(a, b)| same(a, b))
+        && a.children.iter().zip(b.children.iter()).all(|(a, b)| same(a, b))
+}
+
+fn without_pos(token: &Token) -> Token {
+    let mut token = token.clone();
+    match &mut token {
+        Token::Operator { pos, .. }
+        | Token::Identifier { pos, .. }
+        | Token::Function { pos, .. }
+        | Token::IntConstant { pos, .. }
+        | Token::FloatConstant { pos, .. }
+        | Token::StringConstant { pos, .. }
+        | Token::BoolConstant { pos, .. }
+        | Token::NoneConstant { pos }
+        | Token::NewLine { pos }
+        | Token::Comment { pos, .. } => *pos = 0,
+    }
+    token
+}
+
+/// Names declared by `import a.b` or `from a.b import c, d` with their positions.
+fn imported_names(statement: &Node) -> Vec<(&str, usize)> {
+    let names = match statement.condition.is_empty() {
+        true => &statement.children[statement.children.len() - 1..],
+        false => &statement.condition[..],
+    };
+    names.iter().filter_map(|name| match &name.data {
+        Some(Token::Identifier { name, pos }) => Some((name.as_str(), *pos)),
+        _ => None,
+    }).collect()
+}
+
+/// Name of the variable declared by `name = value` or `mut name = value`.
+fn declared_name(statement: &Node) -> Option<&str> {
+    if statement.operator() != Some(Operator::Assign) {
+        return None
+    }
+    let target = match statement.children[0].operator() {
+        Some(Operator::Mut) => &statement.children[0].children[0],
+        _ => &statement.children[0],
+    };
+    match &target.data {
+        Some(Token::Identifier { name, .. }) => Some(name),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+fn warnings(source: &str) -> Vec<(Rule, usize)> {
+    let tree = parse(source);
+    check(&tree).into_iter().map(|warning| (warning.rule, warning.pos)).collect()
+}
+
+#[test]
+fn test_unused_names() {
+    let source = "import math\nfrom text import upper, lower\nfn f() { total + lower(1) }\nmut total = 1\nunused = 2\nunused = 3\npub shared = 4\nfor i in 0..3 { mut count = 0\n  count += 1 }\nmatch x { Some(y) -> 1, _ -> 2 }";
+    assert_eq!(vec!(
+        (Rule::UnusedImport, 7),
+        (Rule::UnusedImport, 29),
+        (Rule::UnusedVariable, 84),
+        (Rule::UnusedVariable, 125),
+        (Rule::UnusedVariable, 181),
+    ), warnings(source));
+}
+
+#[test]
+fn test_unreachable_code() {
+    let source = "fn f(x) {\n  return x\n  x + 1\n}\nloop {\n  loop { break }\n  if x { g({ @ -> loop { break } }) }\n}\nafter()\nloop { if x { break } }\nreached()";
+    assert_eq!(vec!((Rule::UnreachableCode, 23), (Rule::UnreachableCode, 95)), warnings(source));
+}
+
+#[test]
+fn test_suspicious_code() {
+    let source = "x = 1.5\nx = x\nif x == 10.0 { }\nif -0.5 != x { a = 1 } else { }\nxs = [1]\nxs[0] = xs[0]\nxs[0] = xs[1]\nif x = 2 { print(xs) }";
+    assert_eq!(vec!(
+        (Rule::SelfAssignment, 10),
+        (Rule::FloatEquality, 19),
+        (Rule::EmptyBlock, 27),
+        (Rule::FloatEquality, 39),
+        (Rule::UnusedVariable, 46),
+        (Rule::EmptyBlock, 59),
+        (Rule::SelfAssignment, 78),
+        (Rule::AssignmentInCondition, 105),
+    ), warnings(source));
+}
+
+#[test]
+fn test_shadowed_names() {
+    let source = "x = 1\nfn f(x) { x }\nfor x in [x] { print(x) }\nmut y = { @ z -> mut z = z + 1\n  z }\nprint(y)";
+    assert_eq!(vec!((Rule::ShadowedName, 11), (Rule::ShadowedName, 24), (Rule::ShadowedName, 67)), warnings(source));
+}
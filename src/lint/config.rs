@@ -0,0 +1,136 @@
+use std::collections::HashSet;
+use std::fmt;
+
+/// Name of the file with the rules of the linter, it is looked up in the directory of the linted file and its parents.
+pub const CONFIG_FILE: &str = ".avoclint";
+
+/// Rule of the linter, every rule is enabled by default.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Rule {
+    UnusedVariable,
+    UnusedImport,
+    UnreachableCode,
+    SelfAssignment,
+    FloatEquality,
+    ShadowedName,
+    EmptyBlock,
+    AssignmentInCondition,
+}
+
+/// Rules which are enabled or disabled for the whole file.
+///
+/// The config file has one rule per line in the form `rule = on` or `rule = off`,
+/// the empty lines and the lines starting with `#` are ignored:
+///
+/// ```text
+/// # floats in this project are compared with the tolerance
+/// float-equality = off
+/// shadowed-name = off
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    disabled: HashSet<Rule>,
+}
+
+impl Rule {
+
+    pub const ALL: [Rule; 8] = [
+        Rule::UnusedVariable,
+        Rule::UnusedImport,
+        Rule::UnreachableCode,
+        Rule::SelfAssignment,
+        Rule::FloatEquality,
+        Rule::ShadowedName,
+        Rule::EmptyBlock,
+        Rule::AssignmentInCondition,
+    ];
+
+    /// Name of the rule in the config file and in the `// avoc-allow(rule)` comments.
+    pub fn name(self) -> &'static str {
+        match self {
+            Rule::UnusedVariable => "unused-variable",
+            Rule::UnusedImport => "unused-import",
+            Rule::UnreachableCode => "unreachable-code",
+            Rule::SelfAssignment => "self-assignment",
+            Rule::FloatEquality => "float-equality",
+            Rule::ShadowedName => "shadowed-name",
+            Rule::EmptyBlock => "empty-block",
+            Rule::AssignmentInCondition => "assignment-in-condition",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Rule> {
+        Rule::ALL.iter().copied().find(|rule| rule.name() == name)
+    }
+}
+
+impl Default for Config {
+
+    fn default() -> Config {
+        Config::new()
+    }
+}
+
+impl Config {
+
+    /// Config with all rules enabled.
+    pub fn new() -> Config {
+        Config { disabled: HashSet::new() }
+    }
+
+    /// Reads the content of the config file.
+    ///
+    /// # Errors
+    /// Unknown rules, values other than `on` and `off` and lines without `=`, with the number of the line.
+    pub fn parse(text: &str) -> Result<Config, String> {
+        let mut config = Config::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue
+            }
+            let (name, value) = match line.split_once('=') {
+                Some((name, value)) => (name.trim(), value.trim()),
+                None => return Err(format!("Expected 'rule = on' or 'rule = off' at line {}", index + 1)),
+            };
+            let rule = match Rule::from_name(name) {
+                Some(rule) => rule,
+                None => return Err(format!("Unknown rule '{}' at line {}", name, index + 1)),
+            };
+            match value {
+                "on" => config.set_enabled(rule, true),
+                "off" => config.set_enabled(rule, false),
+                _ => return Err(format!("Expected 'on' or 'off', found '{}' at line {}", value, index + 1)),
+            }
+        }
+        Ok(config)
+    }
+
+    pub fn set_enabled(&mut self, rule: Rule, enabled: bool) {
+        match enabled {
+            true => self.disabled.remove(&rule),
+            false => self.disabled.insert(rule),
+        };
+    }
+
+    pub fn is_enabled(&self, rule: Rule) -> bool {
+        !self.disabled.contains(&rule)
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[test]
+fn test_config() {
+    let config = Config::parse("# comment\n\nfloat-equality = off\n  empty-block=on  \nshadowed-name = off\nshadowed-name = on").unwrap();
+    let disabled: Vec<Rule> = Rule::ALL.iter().copied().filter(|rule| !config.is_enabled(*rule)).collect();
+    assert_eq!(vec!(Rule::FloatEquality), disabled);
+    assert_eq!(Err(String::from("Unknown rule 'unused' at line 2")), Config::parse("empty-block = off\nunused = off"));
+    assert_eq!(Err(String::from("Expected 'on' or 'off', found 'no' at line 1")), Config::parse("empty-block = no"));
+    assert_eq!(Err(String::from("Expected 'rule = on' or 'rule = off' at line 1")), Config::parse("empty-block"));
+    assert_eq!(Some(Rule::AssignmentInCondition), Rule::from_name("assignment-in-condition"));
+}
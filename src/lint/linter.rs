@@ -0,0 +1,83 @@
+use crate::dto::{ Token, SyntaxError };
+use crate::lint::{ check, Config, Rule, Warning };
+use crate::parser::{ TokenReader, TreeBuilder };
+use std::collections::HashSet;
+
+/// Checks the source with the rules enabled by the config.
+///
+/// The comment `// avoc-allow(rule, other-rule)` disables the rules for the line of the comment,
+/// and for the next line if nothing but the comment is written on its line.
+///
+/// # Errors
+/// Syntax errors of the source and unknown rules in the `avoc-allow` comments.
+pub fn lint(source: &str, config: &Config) -> Result<Vec<Warning>, SyntaxError> {
+    let tokens = TokenReader::new().parse_with_comments(source)?;
+    let lines = LineIndex::new(source);
+    let allowed = allowed_rules(&tokens, &lines)?;
+    let code: Vec<Token> = tokens.into_iter().filter(|token| !matches!(token, Token::Comment { .. })).collect();
+    let tree = TreeBuilder::new().build_tree(&code)?;
+    let mut warnings = check(&tree);
+    warnings.retain(|warning| config.is_enabled(warning.rule) && !allowed.contains(&(lines.line(warning.pos), warning.rule)));
+    Ok(warnings)
+}
+
+/// Finds the lines of the `avoc-allow` comments and the rules they allow.
+fn allowed_rules(tokens: &[Token], lines: &LineIndex) -> Result<HashSet<(usize, Rule)>, SyntaxError> {
+    let mut allowed = HashSet::new();
+    for (index, token) in tokens.iter().enumerate() {
+        let (text, pos) = match token {
+            Token::Comment { text, pos } => (text, *pos),
+            _ => continue,
+        };
+        let rules = match text.find("avoc-allow(") {
+            Some(start) => &text[start + "avoc-allow(".len()..],
+            None => continue,
+        };
+        let rules = match rules.find(')') {
+            Some(end) => &rules[..end],
+            None => return Err(SyntaxError::new(pos, String::from("Expected ')' after the allowed rules"))),
+        };
+        let line = lines.line(pos);
+        let is_standalone = index == 0 || lines.line(tokens[index - 1].pos()) != line;
+        for name in rules.split(',').map(str::trim) {
+            let rule = match Rule::from_name(name) {
+                Some(rule) => rule,
+                None => return Err(SyntaxError::new(pos, format!("Unknown rule '{}'", name))),
+            };
+            allowed.insert((line, rule));
+            if is_standalone {
+                allowed.insert((line + 1, rule));
+            }
+        }
+    }
+    Ok(allowed)
+}
+
+/// Converts the offsets of the tokens to the numbers of the lines.
+struct LineIndex {
+    /// Offsets of the first symbols of the lines.
+    starts: Vec<usize>,
+}
+
+impl LineIndex {
+
+    fn new(source: &str) -> LineIndex {
+        let breaks = source.chars().enumerate().filter(|(_, symbol)| *symbol == '\n').map(|(offset, _)| offset + 1);
+        LineIndex { starts: std::iter::once(0).chain(breaks).collect() }
+    }
+
+    /// Number of the line starting from 1.
+    fn line(&self, offset: usize) -> usize {
+        self.starts.partition_point(|start| *start <= offset)
+    }
+}
+
+#[test]
+fn test_lint() {
+    let source = "a = 1.5 // avoc-allow(unused-variable)\n// avoc-allow(float-equality, empty-block)\nif a == 2.0 { }\nb = a\nif b == 2.0 { }\nc = 1 // avoc-allow(unused-variable)";
+    let mut config = Config::new();
+    config.set_enabled(Rule::EmptyBlock, false);
+    let warnings: Vec<(Rule, usize)> = lint(source, &config).unwrap().into_iter().map(|warning| (warning.rule, warning.pos)).collect();
+    assert_eq!(vec!((Rule::FloatEquality, 109)), warnings);
+    assert_eq!("Unknown rule 'float'", lint("// avoc-allow(float)", &config).unwrap_err().message);
+}
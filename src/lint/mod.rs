@@ -0,0 +1,6 @@
+pub mod checks;
+pub mod config;
+pub mod linter;
+pub use checks::{ check, Warning };
+pub use config::{ Config, Rule, CONFIG_FILE };
+pub use linter::lint;
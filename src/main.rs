@@ -1,29 +1,332 @@
+use std::path::{ Path, PathBuf };
+use std::rc::Rc;
+use avoc::internals::{ Interpreter, NodeType, StackGuard, Token, TokenReader, TreeBuilder, LoadError, Loader, Module };
+use avoc::internals::{ resolve, check_exhaustiveness, fold_constants, lint, Config, CONFIG_FILE };
+use avoc::internals::{ print_error_info, print_warning_info, red, green, dump_tokens, dump_tree, Format };
+use avoc::internals::{ lower, optimize, verify, generate_c, generate_wat, generate_x86_64, link_x86_64 };
+use avoc::{ Limits, SyntaxError };
 
-mod dto;
-mod parser;
-mod io;
-
-use std::io::prelude::*;
-use std::fs::File;
-use parser::TokenReader;
-use parser::TreeBuilder;
-use io::print_error_info;
-
+/// Runs the program from the file given as the first argument, exits with the code 1 if it can't be checked or fails.
+/// The other commands exit with the code 1 if any of their files has the errors.
+///
+/// Imported modules are looked up next to the importing file and in the directories of the `AVO_PATH` variable.
+/// `avoc test [--filter name] path...` runs the `test` blocks of the files and of the directories.
+/// `avoc lint file...` checks the files with the linter instead of running them.
+/// `avoc tokens file...` and `avoc ast file...` print the tokens and the syntax trees of the files,
+/// `--format json|sexpr|tree` chooses the format of the dump, JSON by default.
+/// `avoc ir file...` prints the optimized intermediate representation of the files.
+/// `avoc build --target c file` writes the program translated to C next to the file, `--target wat` to WebAssembly text,
+/// `--target x86_64` to the assembly which is linked to the executable.
 fn main() {
     let args: Vec<String> = std::env::args().collect();
-    let source = read_source_file(&args[1]);
-    match TokenReader::new().parse(&source) {
-        Ok(tokens) => match TreeBuilder::new().build_tree(&tokens) {
-            Ok(tree_node) => println!("{:?}", tree_node),
-            Err(e) => print_error_info(&args[1], &source, e.pos, e.message),
+    // the interpreter recurses on the nested calls, so it runs on the thread with the stack large enough for `MAX_CALL_DEPTH` of them,
+    // the guard lets the parser and the interpreter use the half of it instead of the default stack of the library
+    let command = std::thread::Builder::new().stack_size(STACK_SIZE).spawn(move || {
+        let _guard = StackGuard::new(STACK_SIZE / 2);
+        run_command(&args)
+    }).unwrap();
+    match command.join() {
+        Ok(true) => {},
+        Ok(false) => std::process::exit(1),
+        Err(_) => std::process::exit(101),
+    }
+}
+
+/// Nested calls of the functions allowed to the program, the deeper recursion is the runtime error instead of the stack overflow.
+///
+/// It is the `max_depth` of the [`Limits`] of the interpreter, 200 by default for the embedded scripts, and it is not related
+/// to the `MAX_DEPTH` of the parser, which limits the nested constructions of the source.
+const MAX_CALL_DEPTH: usize = 1000;
+/// Stack of the thread which runs the command.
+const STACK_SIZE: usize = 256 * 1024 * 1024;
+
+const USAGE: &str = "usage: avoc file
+       avoc test [--filter name] path...
+       avoc lint file...
+       avoc tokens|ast [--format json|sexpr|tree] file...
+       avoc ir file...
+       avoc build --target c|wat|x86_64 file...";
+
+/// Runs the command of the arguments, returns `false` if it has printed any error.
+fn run_command(args: &[String]) -> bool {
+    if args.len() < 2 {
+        eprintln!("{}", USAGE);
+        return false
+    }
+    if args[1] == "lint" {
+        return for_all(&args[2..], lint_file)
+    }
+    if args[1] == "tokens" || args[1] == "ast" {
+        return match args.get(2..4) {
+            Some([option, format]) if option == "--format" => match Format::from_name(format) {
+                Some(format) => for_all(&args[4..], |path| dump(path, &args[1], format)),
+                None => error(format!("expected 'avoc {} --format json|sexpr|tree file...'", args[1])),
+            },
+            _ => for_all(&args[2..], |path| dump(path, &args[1], Format::Json)),
+        }
+    }
+    if args[1] == "test" {
+        return match (args.get(2).map(String::as_str), args.get(3)) {
+            (Some("--filter"), Some(filter)) => run_tests(&args[4..], Some(filter)),
+            (Some("--filter"), None) => error("expected 'avoc test [--filter name] path...'"),
+            _ => run_tests(&args[2..], None),
+        }
+    }
+    if args[1] == "ir" {
+        return for_all(&args[2..], print_ir)
+    }
+    if args[1] == "build" {
+        return match args.get(2..4) {
+            Some([option, target]) if option == "--target" && ["c", "wat", "x86_64"].contains(&target.as_str()) => for_all(&args[4..], |path| build(path, target)),
+            _ => error("expected 'avoc build --target c|wat|x86_64 file...'"),
+        }
+    }
+    execute(Path::new(&args[1])).is_some()
+}
+
+/// Runs the command for every file, even after the failed one, returns `false` if it has failed for any file.
+fn for_all(files: &[String], mut command: impl FnMut(&Path) -> bool) -> bool {
+    files.iter().filter(|file| !command(Path::new(file))).count() == 0
+}
+
+/// Prints the error which has no place in the source, returns `false` for the command which has failed.
+fn error(message: impl std::fmt::Display) -> bool {
+    eprintln!("\n{}: {}", red(String::from("error")), message);
+    false
+}
+
+/// Loads the program with the imported modules, checks and runs them, the errors are printed.
+/// Returns the main module with the interpreter which executed it, e.g. to run its tests.
+fn execute(path: &Path) -> Option<(Module, Interpreter)> {
+    let search_path = std::env::var_os("AVO_PATH").map(|paths| std::env::split_paths(&paths).collect()).unwrap_or_default();
+    let loader = Loader::new(search_path, |path| std::fs::read_to_string(path));
+    let mut modules = match loader.load(path) {
+        Ok(modules) => modules,
+        Err(LoadError::Read { path, error: e }) => {
+            error(format!("{}: {}", path.to_string_lossy(), e));
+            return None
+        },
+        Err(LoadError::Source { path, source, error: e }) => {
+            print_error_info(&path.to_string_lossy(), &source, e.pos, e.message);
+            return None
+        },
+    };
+    let mut has_errors = false;
+    for module in &mut modules {
+        let mut errors = resolve(&module.tree);
+        errors.extend(check_exhaustiveness(&module.tree));
+        errors.extend(fold_constants(&mut module.tree));
+        errors.sort_by_key(|e| e.pos);
+        has_errors |= !errors.is_empty();
+        errors.into_iter().for_each(|e| print_error_info(&module.path.to_string_lossy(), &module.source, e.pos, e.message));
+    }
+    if has_errors {
+        return None
+    }
+    let mut executed = vec!();
+    let mut interpreter = Interpreter::new();
+    for module in &modules {
+        interpreter = Interpreter::new();
+        interpreter.set_limits(Limits { max_depth: Some(MAX_CALL_DEPTH), ..Limits::default() });
+        for (path, index) in &module.imports {
+            interpreter.add_module(path, Rc::clone(&executed[*index]));
+        }
+        match interpreter.run_module(&module.name, &module.tree) {
+            Ok(executed_module) => executed.push(executed_module),
+            Err(e) => {
+                print_error_info(&module.path.to_string_lossy(), &module.source, e.pos, e.message);
+                return None
+            },
+        }
+    }
+    modules.pop().map(|module| (module, interpreter))
+}
+
+/// Runs the tests of the files and of the `.avo` files in the directories, only the tests whose names contain the filter.
+/// Every file is executed before its tests. Returns `false` if any test fails or any file can't be executed.
+fn run_tests(paths: &[String], filter: Option<&str>) -> bool {
+    let mut files = vec!();
+    let are_found = paths.iter().filter(|path| !find_sources(Path::new(path), &mut files)).count() == 0;
+    let (mut passed, mut failed, mut filtered_out, mut broken_files) = (0, 0, 0, 0);
+    for file in &files {
+        let (module, mut interpreter) = match execute(file) {
+            Some(executed) => executed,
+            None => {
+                broken_files += 1;
+                continue
+            },
+        };
+        for test in module.tree.children.iter().filter(|statement| statement.node_type == NodeType::Test) {
+            let name = match &test.data {
+                Some(Token::StringConstant { value, .. }) => value,
+                _ => continue,
+            };
+            if filter.is_some_and(|filter| !name.contains(filter)) {
+                filtered_out += 1;
+                continue
+            }
+            match interpreter.run_test(test) {
+                Ok(()) => {
+                    passed += 1;
+                    println!("test {} {} ... {}", module.path.to_string_lossy(), name, green(String::from("ok")))
+                },
+                Err(e) => {
+                    failed += 1;
+                    println!("test {} {} ... {}", module.path.to_string_lossy(), name, red(String::from("FAILED")));
+                    print_error_info(&module.path.to_string_lossy(), &module.source, e.pos, e.message)
+                },
+            }
+        }
+    }
+    let result = match failed + broken_files {
+        0 => green(String::from("ok")),
+        _ => red(String::from("FAILED")),
+    };
+    println!("\ntest result: {}. {} passed; {} failed; {} filtered out", result, passed, failed, filtered_out);
+    if broken_files > 0 {
+        println!("{} of {} files can't be executed", broken_files, files.len());
+    }
+    are_found && failed + broken_files == 0
+}
+
+/// Adds the file, or all `.avo` files of the directory and its subdirectories in the order of their names.
+/// Returns `false` if any directory can't be read.
+fn find_sources(path: &Path, files: &mut Vec<PathBuf>) -> bool {
+    if !path.is_dir() {
+        files.push(path.to_path_buf());
+        return true
+    }
+    let mut entries: Vec<PathBuf> = match std::fs::read_dir(path) {
+        Ok(entries) => entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()).collect(),
+        Err(e) => return error(format!("{}: {}", path.to_string_lossy(), e)),
+    };
+    entries.sort();
+    entries.iter()
+        .filter(|entry| entry.is_dir() || entry.extension() == Some("avo".as_ref()))
+        .filter(|entry| !find_sources(entry, files))
+        .count() == 0
+}
+
+/// Prints the warnings of the linter, the rules are configured by the nearest config file.
+/// The warnings are not the errors, only the file which can't be read or parsed fails the command.
+fn lint_file(path: &Path) -> bool {
+    let file_name = path.to_string_lossy();
+    let source = match std::fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(e) => return error(format!("{}: {}", file_name, e)),
+    };
+    let config = match find_config(path) {
+        Some(config_path) => match std::fs::read_to_string(&config_path).map_err(|e| e.to_string()).and_then(|text| Config::parse(&text)) {
+            Ok(config) => config,
+            Err(message) => return error(format!("{}: {}", config_path.to_string_lossy(), message)),
+        },
+        None => Config::new(),
+    };
+    match lint(&source, &config) {
+        Ok(warnings) => {
+            warnings.into_iter().for_each(|w| print_warning_info(&file_name, &source, w.pos, w.rule.name(), w.message));
+            true
         },
-        Err(e) => print_error_info(&args[1], &source, e.pos, e.message),
+        Err(e) => print_errors(&file_name, &source, vec!(e)),
+    }
+}
+
+/// Translates the program to C, WebAssembly text or x86-64 assembly, the file with the extension of the target is written next to the program.
+/// C is compiled with `cc file.c -lm`, the assembly is written to `file.s` and linked to `file` by `cc`. Imports are not supported.
+fn build(path: &Path, target: &str) -> bool {
+    let file_name = path.to_string_lossy();
+    let source = match std::fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(e) => return error(format!("{}: {}", file_name, e)),
+    };
+    let tree = TokenReader::new().parse(&source).and_then(|tokens| TreeBuilder::new().build_tree(&tokens));
+    let mut tree = match tree {
+        Ok(tree) => tree,
+        Err(e) => return print_errors(&file_name, &source, vec!(e)),
     };
+    let mut errors = resolve(&tree);
+    errors.extend(check_exhaustiveness(&tree));
+    errors.extend(fold_constants(&mut tree));
+    let program = match errors.is_empty() {
+        true if target == "c" => generate_c(&tree),
+        true if target == "wat" => generate_wat(&tree),
+        true => generate_x86_64(&tree),
+        false => Err(errors),
+    };
+    let output = path.with_extension(if target == "x86_64" { "s" } else { target });
+    match program {
+        Ok(program) => match std::fs::write(&output, program) {
+            Err(e) => error(format!("{}: {}", output.to_string_lossy(), e)),
+            Ok(()) if target == "x86_64" => link_x86_64(&output, &path.with_extension("")).map_or_else(error, |_| true),
+            Ok(()) => true,
+        },
+        Err(errors) => print_errors(&file_name, &source, errors),
+    }
 }
 
-fn read_source_file(file_name: &String) -> String {
-    let mut file = File::open(file_name).expect("Can't open source file!");
-    let mut source_content = String::new();
-    file.read_to_string(&mut source_content).expect("Can't read source file!");
-    source_content
-}
\ No newline at end of file
+/// Prints the tokens or the syntax tree of the file as it is built by the parser, before the analysis and the optimizations.
+fn dump(path: &Path, what: &str, format: Format) -> bool {
+    let file_name = path.to_string_lossy();
+    let source = match std::fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(e) => return error(format!("{}: {}", file_name, e)),
+    };
+    let dumped = match what {
+        "tokens" => TokenReader::new().parse_with_comments(&source).map(|tokens| dump_tokens(&tokens, &source, format)),
+        _ => TokenReader::new().parse(&source)
+            .and_then(|tokens| TreeBuilder::new().build_tree(&tokens))
+            .map(|tree| dump_tree(&tree, &source, format)),
+    };
+    match dumped {
+        Ok(dumped) => {
+            print!("{}", dumped);
+            true
+        },
+        Err(e) => print_errors(&file_name, &source, vec!(e)),
+    }
+}
+
+/// Lowers the program to the intermediate representation, optimizes it and prints it. Imports are not supported.
+fn print_ir(path: &Path) -> bool {
+    let file_name = path.to_string_lossy();
+    let source = match std::fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(e) => return error(format!("{}: {}", file_name, e)),
+    };
+    let tree = TokenReader::new().parse(&source).and_then(|tokens| TreeBuilder::new().build_tree(&tokens));
+    let tree = match tree {
+        Ok(tree) => tree,
+        Err(e) => return print_errors(&file_name, &source, vec!(e)),
+    };
+    let mut errors = resolve(&tree);
+    errors.extend(check_exhaustiveness(&tree));
+    let program = match errors.is_empty() {
+        true => lower(&tree),
+        false => Err(errors),
+    };
+    let mut program = match program {
+        Ok(program) => program,
+        Err(errors) => return print_errors(&file_name, &source, errors),
+    };
+    optimize(&mut program);
+    match verify(&program).as_slice() {
+        [] => {
+            print!("{}", program);
+            true
+        },
+        errors => errors.iter().fold(false, |_, e| error(format!("{}: invalid IR: {}", file_name, e))),
+    }
+}
+
+/// Prints the errors of the file in the order of their positions, returns `false` if there are any.
+fn print_errors(file_name: &str, source: &str, mut errors: Vec<SyntaxError>) -> bool {
+    errors.sort_by_key(|e| e.pos);
+    errors.iter().for_each(|e| print_error_info(file_name, source, e.pos, e.message.clone()));
+    errors.is_empty()
+}
+
+/// Looks for the config of the linter in the directory of the file and its parents.
+fn find_config(path: &Path) -> Option<PathBuf> {
+    let directory = std::fs::canonicalize(path).ok()?.parent()?.to_path_buf();
+    directory.ancestors().map(|directory| directory.join(CONFIG_FILE)).find(|config| config.is_file())
+}
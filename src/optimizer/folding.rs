@@ -0,0 +1,259 @@
+use crate::dto::{ Node, NodeType, Operator, Symbol, Token, SyntaxError };
+use crate::runtime::Value;
+use crate::runtime::operations::{ binary_operation, unary_operation };
+use crate::runtime::patterns::literal;
+#[cfg(test)]
+use crate::parser::parse;
+
+/// Evaluates the constant parts of the expressions before the execution.
+///
+/// - operators with the constant operands are replaced with their result: `60 * 60 * 24` is `86400`
+/// - algebraic identities `x * 1`, `x / 1`, `x ^ 1`, `x + 0` and `x - 0` are replaced with `x`
+///   when `x` is known to be a number, so `name + 0` still converts the string
+/// - `if` with the constant condition is replaced with the branch which is taken
+/// - `false and x` and `true or x` are replaced with the constant without `x`
+///
+/// # Errors
+/// Constant expressions which would fail at runtime, e.g. the division by zero, the integer overflow
+/// or the constant condition which is not Bool. Such expressions are left as they are.
+pub fn fold_constants(root: &mut Node) -> Vec<SyntaxError> {
+    // the tree may be built by hand, while the folding walks it recursively
+    if let Err(error) = root.depth() {
+        return vec!(error)
+    }
+    let mut errors = vec!();
+    fold(root, &mut errors);
+    errors
+}
+
+fn fold(node: &mut Node, errors: &mut Vec<SyntaxError>) {
+    match node.node_type {
+        // patterns, declarations of the types and imports have no expressions to fold
+        NodeType::Type | NodeType::Variant | NodeType::Struct | NodeType::Enum | NodeType::Import => return,
+        NodeType::Arm => node.condition.iter_mut().skip(1).for_each(|guard| fold(guard, errors)),
+        _ => node.condition.iter_mut().for_each(|child| fold(child, errors)),
+    }
+    let child_errors: Vec<Vec<SyntaxError>> = node.children.iter_mut().map(|child| {
+        let mut child_errors = vec!();
+        fold(child, &mut child_errors);
+        child_errors
+    }).collect();
+    // the errors of the code which is never evaluated are dropped with it
+    let dead_child = dead_child(node);
+    for (index, child_errors) in child_errors.into_iter().enumerate() {
+        if Some(index) != dead_child {
+            errors.extend(child_errors)
+        }
+    }
+    let folded = match (node.node_type, node.operator()) {
+        (NodeType::Expression, Some(Operator::UnaryMinus | Operator::Not)) => fold_unary(node, errors),
+        (NodeType::Expression, Some(Operator::And | Operator::Or)) => fold_logical(node, errors),
+        (NodeType::Expression, Some(operator)) if is_foldable(operator) => fold_binary(node, operator, errors),
+        (NodeType::If, _) => eliminate_branch(node, errors),
+        _ => None,
+    };
+    if let Some(folded) = folded {
+        *node = folded
+    }
+}
+
+fn fold_unary(node: &Node, errors: &mut Vec<SyntaxError>) -> Option<Node> {
+    let operand = constant(&node.children[0])?;
+    match unary_operation(node.operator()?, &operand) {
+        Ok(value) => to_node(value, node.pos()),
+        Err(message) => report(errors, node.pos(), message),
+    }
+}
+
+/// `false and x` is `false` without the evaluation of `x`, the same way as at runtime.
+fn fold_logical(node: &Node, errors: &mut Vec<SyntaxError>) -> Option<Node> {
+    let is_or = node.operator() == Some(Operator::Or);
+    match (constant(&node.children[0])?, constant(&node.children[1])) {
+        (Value::Bool(lhs), _) if lhs == is_or => to_node(Value::Bool(lhs), node.pos()),
+        (Value::Bool(_), Some(rhs @ Value::Bool(_))) => to_node(rhs, node.pos()),
+        (Value::Bool(_), Some(rhs)) => report(errors, node.children[1].pos(), condition_error(&rhs)),
+        (Value::Bool(_), None) => None,
+        (lhs, _) => report(errors, node.children[0].pos(), condition_error(&lhs)),
+    }
+}
+
+fn fold_binary(node: &Node, operator: Operator, errors: &mut Vec<SyntaxError>) -> Option<Node> {
+    let (lhs, rhs) = (&node.children[0], &node.children[1]);
+    match (constant(lhs), constant(rhs)) {
+        (Some(a), Some(b)) => match binary_operation(operator, &a, &b) {
+            Ok(value) => to_node(value, node.pos()),
+            Err(message) => report(errors, node.pos(), message),
+        },
+        (None, Some(Value::Int(1))) if matches!(operator, Operator::Multiply | Operator::Divide | Operator::Power) && is_number(lhs) => Some(lhs.clone()),
+        (None, Some(Value::Int(0))) if matches!(operator, Operator::Plus | Operator::Minus) && is_number(lhs) => Some(lhs.clone()),
+        (Some(Value::Int(1)), None) if operator == Operator::Multiply && is_number(rhs) => Some(rhs.clone()),
+        (Some(Value::Int(0)), None) if operator == Operator::Plus && is_number(rhs) => Some(rhs.clone()),
+        _ => None,
+    }
+}
+
+/// `if true { a } else { b }` is the block `{ a }`, `if false { a }` without `else` is `None`.
+fn eliminate_branch(node: &Node, errors: &mut Vec<SyntaxError>) -> Option<Node> {
+    match constant(&node.condition[0])? {
+        Value::Bool(true) => Some(node.children[0].clone()),
+        Value::Bool(false) => match node.children.get(1) {
+            Some(otherwise) => Some(otherwise.clone()),
+            None => Some(Node::from(Token::NoneConstant { pos: node.pos() })),
+        },
+        value => report(errors, node.condition[0].pos(), condition_error(&value)),
+    }
+}
+
+/// Index of the child which is never evaluated: the branch of `if` which is not taken or `x` of `false and x`.
+fn dead_child(node: &Node) -> Option<usize> {
+    match (node.node_type, node.operator()) {
+        (NodeType::If, _) => match constant(&node.condition[0])? {
+            Value::Bool(true) => Some(1),
+            Value::Bool(false) => Some(0),
+            _ => None,
+        },
+        (NodeType::Expression, Some(operator @ (Operator::And | Operator::Or))) => match constant(&node.children[0])? {
+            Value::Bool(lhs) if lhs == (operator == Operator::Or) => Some(1),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[inline]
+fn is_foldable(operator: Operator) -> bool {
+    matches!(operator, Operator::Plus | Operator::Minus | Operator::Multiply | Operator::Divide | Operator::Power
+        | Operator::Equal | Operator::NotEqual | Operator::Less | Operator::LessOrEqual | Operator::Greater | Operator::GreaterOrEqual
+        | Operator::Xor | Operator::In)
+}
+
+/// Checks that the expression evaluates to Int or Float or fails: arithmetic operators
+/// other than `+` never produce anything else, and `+` does so only for the numbers.
+fn is_number(node: &Node) -> bool {
+    match (node.node_type, &node.data) {
+        (NodeType::Token, Some(Token::IntConstant { .. })) | (NodeType::Token, Some(Token::FloatConstant { .. })) => true,
+        (NodeType::Expression, _) => match node.operator() {
+            Some(Operator::Minus | Operator::Multiply | Operator::Divide | Operator::Power | Operator::UnaryMinus) => true,
+            Some(Operator::Plus) => node.children.iter().all(is_number),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+#[inline]
+fn constant(node: &Node) -> Option<Value> {
+    match (node.node_type, &node.data) {
+        (NodeType::Token, Some(token)) => literal(token),
+        _ => None,
+    }
+}
+
+fn to_node(value: Value, pos: usize) -> Option<Node> {
+    let token = match value {
+        Value::None => Token::NoneConstant { pos },
+        Value::Bool(value) => Token::BoolConstant { value, pos },
+        Value::Int(value) => Token::IntConstant { value, pos },
+        Value::Float(value) => Token::FloatConstant { value, pos },
+        Value::Str(value) => Token::StringConstant { value: Symbol::from(value), pos },
+        _ => return None,
+    };
+    Some(Node::from(token))
+}
+
+#[inline]
+fn condition_error(value: &Value) -> String {
+    format!("Condition must be Bool, found {}", value.type_name())
+}
+
+#[inline]
+fn report(errors: &mut Vec<SyntaxError>, pos: usize, message: String) -> Option<Node> {
+    errors.push(SyntaxError::new(pos, message));
+    None
+}
+
+#[cfg(test)]
+fn optimize(source: &str) -> (Node, Vec<String>) {
+    let mut tree = parse(source);
+    let errors = fold_constants(&mut tree);
+    (tree, errors.into_iter().map(|error| error.message).collect())
+}
+
+#[test]
+fn test_fold_constants() {
+    let (tree, errors) = optimize("day = 60 * 60 * 24\nhalf = 50% * x\ns = \"a\" + 1 + 2.5\nb = not (1 < 2 and 2 == 2.0)");
+    assert!(errors.is_empty());
+    let values: Vec<Option<Value>> = tree.children.iter().map(|statement| constant(&statement.children[1])).collect();
+    assert_eq!(vec!(Some(Value::Int(86400)), None, Some(Value::Str(String::from("a12.5"))), Some(Value::Bool(false))), values);
+    assert_eq!(Some(Value::Float(0.5)), constant(&tree.children[1].children[1].children[0]));
+}
+
+#[test]
+fn test_algebraic_identities() {
+    let (tree, _) = optimize("(a - b) * 1 + 0\nx * 1\nname + 0\n1 * -y\nfalse and f()\nx == 1 - 1");
+    let (expected, _) = optimize("a - b\nx * 1\nname + 0\n-y\nfalse\nx == 0");
+    assert_eq!(
+        expected.children.iter().map(strip).collect::<Vec<String>>(),
+        tree.children.iter().map(strip).collect::<Vec<String>>(),
+    );
+}
+
+#[test]
+fn test_dead_branches() {
+    let (tree, errors) = optimize("if 1 > 2 { a } else if true { b } else { c }\nif false { a }\nif x { 1 + 1 }");
+    assert!(errors.is_empty());
+    assert_eq!(NodeType::Block, tree.children[0].node_type);
+    assert_eq!(Some("b"), tree.children[0].children[0].data.as_ref().map(|token| match token {
+        Token::Identifier { name, .. } => name.as_str(),
+        _ => "",
+    }));
+    assert_eq!(NodeType::Token, tree.children[1].node_type);
+    assert_eq!(Some(Value::Int(2)), constant(&tree.children[2].children[0].children[0]));
+    // the errors of the eliminated code are not reported, it is never evaluated
+    let (_, errors) = optimize("if false { 1 / 0 }\nif true { 1 } else { 2 / 0 }\nx = true or 1 / 0 == 1\nif true { 3 / 0 }");
+    assert_eq!(vec!("Division by zero"), errors);
+}
+
+#[test]
+fn test_constant_errors() {
+    let (_, errors) = optimize("a = 1 / 0\nb = 9223372036854775807 + 1\nc = \"a\" - 1\nif 1 { }\nd = 1 and true\nf(2 / 0)");
+    assert_eq!(vec!(
+        "Division by zero",
+        "Integer overflow",
+        "Operator '-' can't be applied to String and Int",
+        "Condition must be Bool, found Int",
+        "Condition must be Bool, found Int",
+        "Division by zero",
+    ), errors);
+}
+
+/// Tree without the positions, so the folded and the expected trees can be compared.
+#[cfg(test)]
+fn strip(node: &Node) -> String {
+    let mut node = node.clone();
+    let mut nodes = vec!(&mut node);
+    while let Some(node) = nodes.pop() {
+        node.extent = None;
+        nodes.extend(node.condition.iter_mut().chain(node.children.iter_mut()))
+    }
+    let text = format!("{:?}", node);
+    let mut result = String::new();
+    for part in text.split("pos: ") {
+        match result.is_empty() {
+            true => result.push_str(part),
+            false => result.push_str(part.trim_start_matches(char::is_numeric)),
+        }
+    }
+    result
+}
+
+#[test]
+fn test_too_deep_tree() {
+    let mut tree = (1..=1000).fold(Node::from(Token::IntConstant { value: 1, pos: 0 }), |operand, pos| {
+        let mut node = Node::new(Some(Token::Operator { payload: crate::dto::Operator::UnaryMinus, pos }), NodeType::Expression);
+        node.add_child(operand);
+        node
+    });
+    let errors: Vec<String> = fold_constants(&mut tree).into_iter().map(|error| error.message).collect();
+    assert_eq!(vec!(SyntaxError::no_stack(0).message), errors);
+}
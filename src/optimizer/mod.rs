@@ -0,0 +1,2 @@
+pub mod folding;
+pub use folding::fold_constants;
@@ -2,5 +2,16 @@ pub mod treebuilder;
 pub mod tokenreader;
 pub mod rpntreebuilder;
 pub use treebuilder::TreeBuilder;
-pub use tokenreader::TokenReader;
-pub use rpntreebuilder::RpnTreeBuilder;
\ No newline at end of file
+pub use tokenreader::{ TextEdit, TokenReader };
+pub use rpntreebuilder::RpnTreeBuilder;
+/// Builds the syntax tree of the source in the tests, the source must have no syntax errors.
+#[cfg(test)]
+pub fn parse(source: &str) -> crate::dto::Node {
+    try_parse(source).unwrap()
+}
+
+/// Same as [`parse`], but returns the error of the tree builder, the tokens must have no errors.
+#[cfg(test)]
+pub fn try_parse(source: &str) -> Result<crate::dto::Node, crate::dto::SyntaxError> {
+    TreeBuilder::new().build_tree(&TokenReader::new().parse(source).unwrap())
+}
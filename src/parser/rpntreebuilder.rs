@@ -1,10 +1,26 @@
 
-use crate::dto::{ Token, Node, SyntaxError };
+use crate::dto::{ Token, Node, NodeType, Operator, SyntaxError };
+use crate::dto::stack::max_tree_depth;
+#[cfg(test)]
+use crate::dto::Symbol;
+#[cfg(test)]
+use crate::dto::stack::stack_size;
+#[cfg(test)]
+use crate::runtime::limits::Limit;
 use std::collections::VecDeque;
 
 pub struct RpnTreeBuilder {
     stack: VecDeque<Node>,
     output: Vec<Node>,
+    /// Offsets of the `[` index operators and of their closing brackets, which are not the tokens of the expression.
+    closings: Vec<(usize, usize)>,
+}
+
+impl Default for RpnTreeBuilder {
+
+    fn default() -> RpnTreeBuilder {
+        RpnTreeBuilder::new()
+    }
 }
 
 impl RpnTreeBuilder {
@@ -13,6 +29,7 @@ impl RpnTreeBuilder {
         RpnTreeBuilder {
             stack: VecDeque::new(),
             output: vec!(),
+            closings: vec!(),
         }
     }
 
@@ -21,54 +38,122 @@ impl RpnTreeBuilder {
     pub fn push_token(&mut self, token: Token) -> Result<(), SyntaxError> {
         match &token {
             // if token is constant or variable, put it into output
-            Token::FloatConstant { value: _, pos: _ } => Ok(self.output.push(Node::from(token))),
-            Token::IntConstant { value: _, pos: _ } => Ok(self.output.push(Node::from(token))),
-            Token::StringConstant { value: _, pos: _ } => Ok(self.output.push(Node::from(token))),
-            Token::Identifier { name: _, pos: _ } => Ok(self.output.push(Node::from(token))),
+            Token::FloatConstant { value: _, pos: _ }
+            | Token::IntConstant { value: _, pos: _ }
+            | Token::StringConstant { value: _, pos: _ }
+            | Token::BoolConstant { value: _, pos: _ }
+            | Token::NoneConstant { pos: _ }
+            | Token::Identifier { name: _, pos: _ } => self.output.push(Node::from(token)),
             // if token is prefix function, push it into stack
-            Token::Function { name: _, pos: _ } => Ok(self.stack.push_back(Node::from(token))),
+            Token::Function { name: _, pos: _ } => self.stack.push_back(Node::from(token)),
             // if token is operator
-            Token::Operator { payload, pos } => match payload.as_str() {
+            Token::Operator { payload, pos } => match payload {
                 // if token is operator and it is left bracket '(', put it into stack
-                "(" => Ok(self.stack.push_back(Node::from(token))),
+                Operator::LeftParen => self.stack.push_back(Node::from(token)),
                 // if token is operator and it is right bracket ')', handle necessary operations
-                ")" => handle_right_bracket(&pos, &mut self.stack, &mut self.output),
-                // if token is any other operator, handle it
-                _ => match handle_operator(&payload, &pos, &mut self.stack, &mut self.output) {
-                    Ok(_) => Ok(self.stack.push_back(Node::from(token))), // ...and then put it to stack
-                    Err(e) => Err(e),
+                Operator::RightParen => handle_right_bracket(pos, &mut self.stack, &mut self.output)?,
+                // if token is prefix operator, there is nothing to pop from the stack, just push it
+                op if is_prefix_operator(*op) => self.stack.push_back(Node::from(token)),
+                // if token is any other operator, handle it...
+                _ => {
+                    handle_operator(*payload, pos, &mut self.stack, &mut self.output)?;
+                    // ...and then put it to stack
+                    self.stack.push_back(Node::from(token))
                 },
             },
             Token::NewLine { pos } => 
-                Err(SyntaxError { message: String::from("No need to pass the NewLine token to the push_token function, call notify_met_separator instead"), pos: *pos }),
+                return Err(SyntaxError::new(*pos, String::from("No need to pass the NewLine token to the push_token function, call notify_met_separator instead"))),
+            Token::Comment { pos, .. } =>
+                return Err(SyntaxError::new(*pos, String::from("Comments are not the part of the expression, parse the source without them"))),
         }
+        Ok(())
+    }
+
+    /// Puts an already built subtree (call, block, parenthesized expression etc.) into output as an operand.
+    pub fn push_node(&mut self, node: Node) {
+        self.output.push(node)
+    }
+
+    /// Tells that the index operator `[` at the offset `open` is closed by the `]` at the offset `close`,
+    /// so the node of the operator ends at the bracket.
+    pub fn notify_closed_index(&mut self, open: usize, close: usize) {
+        self.closings.push((open, close))
     }
 
     /// When expression ends, push all operators from stack to output
     pub fn notify_met_separator(&mut self, pos: usize) -> Result<(), SyntaxError> {
-        loop {
-            match &self.stack.back() {
-                Some(node) => match &node.data {
-                    Some(token) => match token {
-                        Token::Operator { payload, pos } => match payload.as_str() {
-                            "(" | ")" => return Err(SyntaxError { message: String::from("The expression contains an extra or inconsistent parenthesis"), pos: *pos }),
-                            _ => (),
-                        },
-                        _ => (),
-                    },
-                    None => return Err(SyntaxError { message: format!("Unexpected node without token: {:?}", node), pos: pos }),
-                },
-                None => break,
+        while let Some(node) = self.stack.back() {
+            match &node.data {
+                Some(Token::Operator { payload: Operator::LeftParen | Operator::RightParen, pos }) => 
+                    return Err(SyntaxError::new(*pos, String::from("The expression contains an extra or inconsistent parenthesis"))),
+                Some(_) => (),
+                None => return Err(SyntaxError::new(pos, format!("Unexpected node without token: {:?}", node))),
             };
             self.output.push(self.stack.pop_back().unwrap())
         }
         Ok(())
     }
+
+    /// Converts the reverse polish notation from output into the expression tree.
+    /// 
+    /// Operators become `NodeType::Expression` nodes with operands as children,
+    /// so `a b + c *` turns into `*(+(a, b), c)`.
+    /// The tree deeper than [`max_tree_depth`] is the error, e.g. the long chain of the unary minuses.
+    pub fn build_tree(&mut self, pos: usize) -> Result<Node, SyntaxError> {
+        let max_depth = max_tree_depth();
+        let mut operands: Vec<Node> = vec!();
+        let mut depths: Vec<usize> = vec!();
+        for node in self.output.drain(..) {
+            let arity = match (&node.node_type, &node.data) {
+                (NodeType::Token, Some(Token::Operator { payload, .. })) => match is_prefix_operator(*payload) {
+                    true => 1,
+                    false => 2,
+                },
+                // prefix functions pushed with `push_token` take exactly one argument
+                (NodeType::Token, Some(Token::Function { .. })) => 1,
+                _ => 0,
+            };
+            if operands.len() < arity {
+                return Err(SyntaxError::new(node.pos(), String::from("Operator is missing an operand")))
+            }
+            let mut node = node;
+            let depth = match arity {
+                0 => node.depth()?,
+                _ => 1 + (0..arity).filter_map(|_| depths.pop()).max().unwrap_or(0),
+            };
+            if depth > max_depth {
+                return Err(SyntaxError::no_stack(node.pos()))
+            }
+            if arity > 0 {
+                node.node_type = match &node.data {
+                    Some(Token::Function { .. }) => NodeType::Call,
+                    _ => NodeType::Expression,
+                };
+                node.children = operands.split_off(operands.len() - arity);
+                // the node covers its operator, the operands and the closing bracket of the index
+                let close = self.closings.iter().find(|(open, _)| *open == node.pos()).map(|(_, close)| (*close, *close));
+                let (mut start, mut end) = node.bounds();
+                for (first, last) in node.children.iter().map(Node::bounds).chain(close) {
+                    start = start.min(first);
+                    end = end.max(last)
+                }
+                node.extent = Some((start, end));
+                node = into_range_if_needed(node);
+            }
+            operands.push(node);
+            depths.push(depth)
+        }
+        match operands.len() {
+            1 => Ok(operands.pop().unwrap()),
+            0 => Err(SyntaxError::new(pos, String::from("Expected expression"))),
+            _ => Err(SyntaxError::new(operands[1].pos(), String::from("Expected operator between operands"))),
+        }
+    }
 }
 
 #[inline]
 fn handle_right_bracket(
-    pos: &usize, 
+    pos: &usize,
     stack: &mut VecDeque<Node>,
     output: &mut Vec<Node>, 
 ) -> Result<(), SyntaxError> {
@@ -76,17 +161,11 @@ fn handle_right_bracket(
     loop {
         let peek_is_left_bracket = match &stack.back() {
             Some(node) => match &node.data {
-                Some(token) => match token {
-                    Token::Operator { payload, pos: _ } => match payload.as_str() {
-                        "(" => true,
-                        _ => false,
-                    },
-                    _ => false,
-                },
-                None => return Err(SyntaxError { message: format!("Unexpected node without token: {:?}", node), pos: *pos }),
+                Some(token) => token.is_operator(Operator::LeftParen),
+                None => return Err(SyntaxError::new(*pos, format!("Unexpected node without token: {:?}", node))),
             },
             // if stack ended earlier than we met '(', then the expression does not match parentheses
-            None => return Err(SyntaxError { message: String::from("This closing parenthesis has no matching opening parenthesis"), pos: *pos }),
+            None => return Err(SyntaxError::new(*pos, String::from("This closing parenthesis has no matching opening parenthesis"))),
         };
         let peek = stack.pop_back().unwrap();
         if peek_is_left_bracket {
@@ -100,8 +179,8 @@ fn handle_right_bracket(
 
 #[inline]
 fn handle_operator(
-    op: &String, 
-    pos: &usize, 
+    op: Operator,
+    pos: &usize,
     stack: &mut VecDeque<Node>,
     output: &mut Vec<Node>,
 ) -> Result<(), SyntaxError> {
@@ -113,10 +192,13 @@ fn handle_operator(
                     // ...prefix function
                     Token::Function { name: _, pos: _ } => true,
                     // ... or peek operator priority higher or equals than handled ooperator
-                    Token::Operator { payload, pos: _} => get_priority(payload) >= get_priority(op),
+                    Token::Operator { payload, pos: _} => match is_right_associative(op) {
+                        true => get_priority(*payload) > get_priority(op),
+                        false => get_priority(*payload) >= get_priority(op),
+                    },
                     _ => false,
                 },
-                None => return Err(SyntaxError { message: format!("Unexpected node without token: {:?}", node), pos: *pos }),
+                None => return Err(SyntaxError::new(*pos, format!("Unexpected node without token: {:?}", node))),
             },
             None => false,
         };
@@ -132,49 +214,93 @@ fn handle_operator(
 
 /// Same as the Java operator precedence table: 
 /// http://www.cs.bilkent.edu.tr/~guvenir/courses/CS101/op_precedence.html
+/// 
+/// Range operators are placed between the additive and relational ones, as in Kotlin.
 #[inline]
-fn get_priority(operator: &String) -> u8 {
-    match operator.as_str() {
-        "." => 15,
-        "u-" | "not" => 13,
-        "^" => 12,
-        "*" | "/" => 11,
-        "+" | "-" => 10,
-        "<" | "<=" | ">" | ">=" | "is" => 9,
-        "==" | "!=" => 8,
-        "and" => 7,
-        "or" | "xor" => 6,
-        "if" | "match" => 2,
-        "=" | "+=" | "-=" | "*=" | "/=" => 1,
+fn get_priority(operator: Operator) -> u8 {
+    match operator {
+        Operator::Dot | Operator::LeftBracket => 16,
+        Operator::Mut => 15,
+        Operator::UnaryMinus | Operator::Not => 14,
+        Operator::Power => 13,
+        Operator::Multiply | Operator::Divide => 12,
+        Operator::Plus | Operator::Minus => 11,
+        Operator::Range | Operator::InclusiveRange | Operator::Colon => 10,
+        Operator::Less | Operator::LessOrEqual | Operator::Greater | Operator::GreaterOrEqual | Operator::Is | Operator::In => 9,
+        Operator::Equal | Operator::NotEqual => 8,
+        Operator::And => 7,
+        Operator::Or | Operator::Xor => 6,
+        Operator::If | Operator::Match => 2,
+        Operator::Assign | Operator::AddAssign | Operator::SubtractAssign | Operator::MultiplyAssign | Operator::DivideAssign => 1,
         _ => 0,
     }
 }
 
+/// Range operators become `NodeType::Range` nodes with children `[start, end, step]`.
+/// 
+/// The step range `start:step:end` is read as `(start:step):end`,
+/// so the inner incomplete `:` expression is merged with the outer one.
+/// Omitted bounds of the open ranges are `None` constants.
+#[inline]
+fn into_range_if_needed(mut node: Node) -> Node {
+    match node.operator() {
+        Some(Operator::Range | Operator::InclusiveRange) => {
+            let step = Node::from(Token::NoneConstant { pos: node.pos() });
+            node.node_type = NodeType::Range;
+            node.children.push(step);
+            node
+        },
+        Some(Operator::Colon) if node.children[0].node_type == NodeType::Expression && node.children[0].operator() == Some(Operator::Colon) => {
+            let end = node.children.pop().unwrap();
+            let mut start_and_step = node.children.pop().unwrap().children;
+            let step = start_and_step.pop().unwrap();
+            let start = start_and_step.pop().unwrap();
+            node.node_type = NodeType::Range;
+            node.children = vec!(start, end, step);
+            node
+        },
+        _ => node,
+    }
+}
+
+/// Prefix operators take a single operand placed to the right of them.
+#[inline]
+fn is_prefix_operator(operator: Operator) -> bool {
+    matches!(operator, Operator::UnaryMinus | Operator::Not | Operator::Mut)
+}
+
+/// `2 ^ 3 ^ 2` is `2 ^ (3 ^ 2)` and `a = b = c` is `a = (b = c)`.
+#[inline]
+fn is_right_associative(operator: Operator) -> bool {
+    matches!(operator,
+        Operator::Power | Operator::Assign | Operator::AddAssign | Operator::SubtractAssign | Operator::MultiplyAssign | Operator::DivideAssign)
+}
+
 #[test]
 fn test_proirity() {
-    assert_eq!(get_priority(&String::from("and")), 7);
+    assert_eq!(get_priority(Operator::And), 7);
 }
 
 #[test]
 fn test_simple_math_expressions() {
     let source = vec!(
-        Token::Identifier { name: "a".to_string(), pos: 0 },
-        Token::Operator { payload: "+".to_string(), pos: 0 },
-        Token::Identifier { name: "b".to_string(), pos: 0 },
-        Token::Operator { payload: "-".to_string(), pos: 0 },
-        Token::Identifier { name: "c".to_string(), pos: 0 },
-        Token::Operator { payload: "*".to_string(), pos: 0 },
-        Token::Identifier { name: "d".to_string(), pos: 0 },
+        Token::Identifier { name: Symbol::from("a"), pos: 0 },
+        Token::Operator { payload: Operator::Plus, pos: 0 },
+        Token::Identifier { name: Symbol::from("b"), pos: 0 },
+        Token::Operator { payload: Operator::Minus, pos: 0 },
+        Token::Identifier { name: Symbol::from("c"), pos: 0 },
+        Token::Operator { payload: Operator::Multiply, pos: 0 },
+        Token::Identifier { name: Symbol::from("d"), pos: 0 },
     );
-    let expected: Vec<Node> = vec!(
-        Token::Identifier { name: "a".to_string(), pos: 0 },
-        Token::Identifier { name: "b".to_string(), pos: 0 },
-        Token::Operator { payload: "+".to_string(), pos: 0 },
-        Token::Identifier { name: "c".to_string(), pos: 0 },
-        Token::Identifier { name: "d".to_string(), pos: 0 },
-        Token::Operator { payload: "*".to_string(), pos: 0 },
-        Token::Operator { payload: "-".to_string(), pos: 0 },
-    ).iter().map(|token| Node::from(token.clone())).collect();
+    let expected: Vec<Node> = [
+        Token::Identifier { name: Symbol::from("a"), pos: 0 },
+        Token::Identifier { name: Symbol::from("b"), pos: 0 },
+        Token::Operator { payload: Operator::Plus, pos: 0 },
+        Token::Identifier { name: Symbol::from("c"), pos: 0 },
+        Token::Identifier { name: Symbol::from("d"), pos: 0 },
+        Token::Operator { payload: Operator::Multiply, pos: 0 },
+        Token::Operator { payload: Operator::Minus, pos: 0 },
+    ].iter().map(|token| Node::from(token.clone())).collect();
 
     let mut builder = RpnTreeBuilder::new();
     for token in source {
@@ -182,4 +308,59 @@ fn test_simple_math_expressions() {
     };
     builder.notify_met_separator(0).unwrap();
     assert_eq!(expected, builder.output);
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_build_tree_with_prefix_and_right_associative_operators() {
+    // mut a = -b ^ 2 ^ c
+    let source = vec!(
+        Token::Operator { payload: Operator::Mut, pos: 0 },
+        Token::Identifier { name: Symbol::from("a"), pos: 4 },
+        Token::Operator { payload: Operator::Assign, pos: 6 },
+        Token::Operator { payload: Operator::UnaryMinus, pos: 8 },
+        Token::Identifier { name: Symbol::from("b"), pos: 9 },
+        Token::Operator { payload: Operator::Power, pos: 11 },
+        Token::IntConstant { value: 2, pos: 13 },
+        Token::Operator { payload: Operator::Power, pos: 15 },
+        Token::Identifier { name: Symbol::from("c"), pos: 17 },
+    );
+    let mut builder = RpnTreeBuilder::new();
+    for token in source {
+        builder.push_token(token).unwrap();
+    };
+    builder.notify_met_separator(18).unwrap();
+    let tree = builder.build_tree(18).unwrap();
+
+    // the extent is the offsets of the first and the last token of the expression
+    let expression = |op: Operator, pos: usize, extent: (usize, usize), children: Vec<Node>| {
+        let mut node = Node::from(Token::Operator { payload: op, pos });
+        node.node_type = NodeType::Expression;
+        node.children = children;
+        node.extent = Some(extent);
+        node
+    };
+    let expected = expression(Operator::Assign, 6, (0, 17), vec!(
+        expression(Operator::Mut, 0, (0, 4), vec!(Node::from(Token::Identifier { name: Symbol::from("a"), pos: 4 }))),
+        expression(Operator::Power, 11, (8, 17), vec!(
+            expression(Operator::UnaryMinus, 8, (8, 9), vec!(Node::from(Token::Identifier { name: Symbol::from("b"), pos: 9 }))),
+            expression(Operator::Power, 15, (13, 17), vec!(
+                Node::from(Token::IntConstant { value: 2, pos: 13 }),
+                Node::from(Token::Identifier { name: Symbol::from("c"), pos: 17 }),
+            )),
+        )),
+    ));
+    assert_eq!(expected, tree);
+}
+
+#[test]
+fn test_build_too_deep_tree() {
+    let mut builder = RpnTreeBuilder::new();
+    for pos in 0..200_000 {
+        builder.push_token(Token::Operator { payload: Operator::UnaryMinus, pos }).unwrap();
+    }
+    builder.push_token(Token::IntConstant { value: 1, pos: 200_000 }).unwrap();
+    builder.notify_met_separator(200_001).unwrap();
+    let error = builder.build_tree(200_001).unwrap_err();
+    assert_eq!(Some(Limit::Stack(stack_size())), error.limit);
+    assert_eq!(200_000 - max_tree_depth(), error.pos);
+}
@@ -1,31 +1,30 @@
-use crate::dto::{ Token, SyntaxError };
+use crate::dto::{ Operator, Symbol, Token, SyntaxError };
 use std::cell::Cell;
+use std::collections::HashSet;
+use std::io::{ ErrorKind, Read };
 
-const OPERATORS: &'static &str = &"{}=+-*/^\\().,<>:@";
-const KEYWORDS: &'static [&'static str] = &[
-    // flow
-    "if", 
-    "else",
-    "loop",
-    "for",
-    "in",
-    "match",
-    // variables
-    "mut",
-    // logical and bits
-    "and",
-    "or",
-    "xor",
-    "not",
-];
+const OPERATORS: &str = "{}[]=!+-*/^().,<>:@";
+const BOOL_TRUE: &str = "true";
+const BOOL_FALSE: &str = "false";
+const NONE: &str = "None";
+const WILDCARD: &str = "_";
+const READ_CHUNK_SIZE: usize = 8 * 1024;
+const MAX_SYMBOLS_SIZE: usize = 64 * 1024;
 
 /// Simple `String` to `Vec<Token>` converter.
 /// 
 /// The `TokenReader` reads all chars from string and creates an list of tokens after calling the [`parse`] method.
+/// Positions of the tokens are the byte offsets in the source.
 /// 
 /// [`parse`]: TokenReader::parse
-pub struct TokenReader {
-    state: Cell<State>,
+pub struct TokenReader;
+
+/// Replacement of the byte range `start..end` of the source with the `text`, e.g. the change made in the editor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextEdit {
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -43,6 +42,7 @@ struct State {
     is_inside_string: bool,
     // numbers
     is_percent_float: bool,
+    is_range_after_number: bool,
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -58,6 +58,71 @@ enum Expected {
     LineComment,
 }
 
+/// Tokens read one by one from the input.
+struct Tokens<I> {
+    input: I,
+    state: Cell<State>,
+    // offsets are in bytes, `offset` is the offset of the current char plus one
+    offset: usize,
+    current_char: Option<char>,
+    prev_char: char,
+    is_finished: bool,
+    /// Pushed token which is not returned yet, the vector is kept to not allocate it for every token.
+    pushed: Vec<Token>,
+    /// Texts of the names, the strings and the comments which are read recently, the same text is shared by the tokens.
+    symbols: Symbols,
+}
+
+/// Texts which are read already, at most [`MAX_SYMBOLS_SIZE`] bytes of them, so the long source read by chunks
+/// doesn't keep all its texts in memory.
+#[derive(Default)]
+struct Symbols {
+    texts: HashSet<Symbol>,
+    size: usize,
+}
+
+/// Source of the chars for the [`Tokens`].
+trait Input {
+
+    /// Returns the offset of the next char and the char, or the length of the source at its end.
+    fn next_char(&mut self) -> Result<(usize, Option<char>), SyntaxError>;
+
+    /// Returns the text which starts at the returned offset and contains the tokens which are not read completely.
+    fn text(&self) -> (&str, usize);
+
+    /// Allows to forget the text before the offset, no token starts before it.
+    fn release(&mut self, _offset: usize) {}
+}
+
+/// Chars of the string from the offset, which must be the start of the token or the string.
+///
+/// The lexer is in the same state at the start of every token, so the tokens after the offset
+/// are the same as in the whole string.
+struct StrInput<'a> {
+    source: &'a str,
+    chars: std::str::CharIndices<'a>,
+    from: usize,
+}
+
+/// Chars decoded from the reader by chunks, the text before the current token is dropped.
+struct ReadInput<R> {
+    reader: R,
+    text: String,
+    /// Offset of the first byte of the `text` in the source.
+    base: usize,
+    /// Offset of the next char in the source.
+    next: usize,
+    /// Bytes which are read, but not decoded yet, e.g. the first byte of the char split between the chunks.
+    undecoded: Vec<u8>,
+}
+
+impl Default for TokenReader {
+
+    fn default() -> TokenReader {
+        TokenReader::new()
+    }
+}
+
 impl TokenReader {
 
     /// Creates an new `TokenReader` from source code string.
@@ -69,27 +134,11 @@ impl TokenReader {
     /// Basic usage:
     /// 
     /// ```
-    /// let source_code = get_source_code();
+    /// # use avoc::internals::TokenReader;
     /// let token_reader = TokenReader::new();
     /// ```
     pub fn new() -> TokenReader {
-        TokenReader {
-            state: Cell::from(State {
-                // commons
-                start_offset: 0,
-                expected: Expected::Nothing,
-                is_ready_to_push: false,
-                is_prev_escape_symbol: false,
-                // identifiers
-                identifier_is_function: false,
-                //comments
-                is_inside_block_comment: false,
-                // strings
-                is_inside_string: false,
-                // numbers
-                is_percent_float: false,
-            })
-        }
+        TokenReader
     }
 
     /// Creates an `Vec<Token>` from source string.
@@ -106,145 +155,419 @@ impl TokenReader {
     /// Basic usage:
     /// 
     /// ```
+    /// # use avoc::internals::{ Operator, Token, TokenReader };
     /// let token_reader = TokenReader::new();
-    /// let tokens = token_reader.parse(String::from("2+2")).unwrap();
+    /// let tokens = token_reader.parse("2+2").unwrap();
     /// 
     /// assert_eq!(
     ///     vec![
     ///         Token::IntConstant { value: 2, pos: 0 },
-    ///         Token::Operator { payload: String::from("+"), pos: 1 },
+    ///         Token::Operator { payload: Operator::Plus, pos: 1 },
     ///         Token::IntConstant { value: 2, pos: 2 },
     ///     ],
     ///     tokens,
     /// );
     /// ```
-    pub fn parse(&self, source: &String) -> Result<Vec<Token>, SyntaxError> {
-        let mut iter = source.chars();
-        let mut offset = 0usize;
-        let mut tokens: Vec<Token> = vec!();
-        let mut current_char = None;
-        let mut prev_char = '\n';
-        let mut it = 0;
-        loop {
-            if !self.state.get().is_ready_to_push {
-                prev_char = match current_char {
-                    Some(val) => val,
-                    None => '\n',
-                };
-                current_char = iter.next();
-                offset += 1;
+    pub fn parse(&self, source: &str) -> Result<Vec<Token>, SyntaxError> {
+        let mut tokens = self.parse_with_comments(source)?;
+        tokens.retain(|token| !matches!(token, Token::Comment { .. }));
+        Ok(tokens)
+    }
+
+    /// Same as [`parse`], but keeps the comments as [`Token::Comment`], e.g. for the linter.
+    ///
+    /// [`parse`]: TokenReader::parse
+    /// [`Token::Comment`]: crate::dto::Token::Comment
+    pub fn parse_with_comments(&self, source: &str) -> Result<Vec<Token>, SyntaxError> {
+        self.tokens(source).collect()
+    }
+
+    /// Reads the tokens lazily one by one, comments included. The iteration ends after the first error.
+    ///
+    /// The [`TreeBuilder`] parses only the whole slice of the tokens, so the lazy reading is for the tools
+    /// which need the tokens only, e.g. the highlighting or the counting of the tokens of the large generated source.
+    ///
+    /// [`TreeBuilder`]: crate::parser::TreeBuilder
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use avoc::internals::{ Symbol, Token, TokenReader };
+    /// let mut tokens = TokenReader::new().tokens("x = 1 $");
+    ///
+    /// assert_eq!(Token::Identifier { name: Symbol::from("x"), pos: 0 }, tokens.next().unwrap().unwrap());
+    /// assert_eq!(2, tokens.filter_map(Result::ok).count());
+    /// ```
+    pub fn tokens<'a>(&self, source: &'a str) -> impl Iterator<Item = Result<Token, SyntaxError>> + 'a {
+        Tokens::new(StrInput::new(source, 0))
+    }
+
+    /// Same as [`tokens`], but the source is read from the reader, e.g. from the file or the standard input.
+    ///
+    /// Only the text of the current token and at most 64 KiB of the recent names, strings and comments, which are shared
+    /// by the tokens with the same text, are kept in memory. The reading and decoding errors are returned as the syntax errors.
+    ///
+    /// [`tokens`]: TokenReader::tokens
+    pub fn read_tokens<R: Read>(&self, reader: R) -> impl Iterator<Item = Result<Token, SyntaxError>> {
+        Tokens::new(ReadInput { reader, text: String::new(), base: 0, next: 0, undecoded: vec!() })
+    }
+
+    /// Updates the tokens of the source after the edit, the result is the same as [`parse_with_comments`] of the new source.
+    ///
+    /// The `tokens` are read by [`parse_with_comments`] from the source before the edit, the `source` is the one after it.
+    /// Only the tokens around the edit are read again: the reading stops at the first new token after the edit
+    /// which starts at the same place as the old one, the rest of the old tokens are shifted by the length change.
+    ///
+    /// [`parse_with_comments`]: TokenReader::parse_with_comments
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use avoc::internals::{ TextEdit, TokenReader };
+    /// let reader = TokenReader::new();
+    /// let tokens = reader.parse_with_comments("a = 1\nb = a").unwrap();
+    /// let edit = TextEdit { start: 4, end: 5, text: String::from("\"one\"") };
+    /// let source = "a = \"one\"\nb = a";
+    ///
+    /// assert_eq!(reader.parse_with_comments(source).unwrap(), reader.relex(&tokens, source, &edit).unwrap());
+    /// ```
+    pub fn relex(&self, tokens: &[Token], source: &str, edit: &TextEdit) -> Result<Vec<Token>, SyntaxError> {
+        // the lexer looks at most two symbols after the token, e.g. `1` in `1..` ends only at the second dot,
+        // so the token before the last one which starts before the edit may be changed too
+        let kept = tokens.partition_point(|token| token.pos() < edit.start).saturating_sub(2);
+        let from = match kept {
+            0 => 0,
+            kept => tokens[kept].pos(),
+        };
+        let inserted_end = edit.start + edit.text.len();
+        let mut result = tokens[..kept].to_vec();
+        for token in Tokens::new(StrInput::new(source, from)) {
+            let token = token?;
+            if token.pos() >= inserted_end {
+                let old_pos = token.pos() - inserted_end + edit.end;
+                if let Ok(index) = tokens.binary_search_by_key(&old_pos, Token::pos) {
+                    result.extend(tokens[index..].iter().map(|token| token.with_pos(token.pos() - edit.end + inserted_end)));
+                    return Ok(result)
+                }
             }
-            match push_token_if_ready(&self.state, source, offset, &mut tokens) {
-                Ok(_) => { /* no-op */ }
-                Err(e) => return Err(e)
-            };
-            match current_char {
-                Some(val) => {
-                    match reduce_state(val, prev_char, offset - 1, self.state.get()) {
-                        Ok(new_state) => self.state.set(new_state),
-                        Err(e) => return Err(e), 
-                    }
+            result.push(token)
+        }
+        Ok(result)
+    }
+}
+
+impl State {
+
+    fn new() -> State {
+        State {
+            // commons
+            start_offset: 0,
+            expected: Expected::Nothing,
+            is_ready_to_push: false,
+            is_prev_escape_symbol: false,
+            // identifiers
+            identifier_is_function: false,
+            //comments
+            is_inside_block_comment: false,
+            // strings
+            is_inside_string: false,
+            // numbers
+            is_percent_float: false,
+            is_range_after_number: false,
+        }
+    }
+}
+
+impl<I: Input> Tokens<I> {
+
+    fn new(input: I) -> Tokens<I> {
+        Tokens {
+            input,
+            state: Cell::from(State::new()),
+            offset: 0,
+            current_char: None,
+            prev_char: '\n',
+            is_finished: false,
+            pushed: Vec::with_capacity(1),
+            symbols: Symbols::default(),
+        }
+    }
+
+    /// Reads the next char unless the token which ends before it is not pushed yet, then reduces the state by the char.
+    fn step(&mut self, tokens: &mut Vec<Token>) -> Result<(), SyntaxError> {
+        let state = self.state.get();
+        if !state.is_ready_to_push {
+            // the current char is the whitespace between the tokens
+            if let (Expected::Nothing, true) = (state.expected, self.offset > 0) {
+                self.input.release(self.offset - 1);
+            }
+            let (index, next) = self.input.next_char()?;
+            self.prev_char = self.current_char.unwrap_or('\n');
+            self.offset = index + 1;
+            self.current_char = next;
+        }
+        let (text, base) = self.input.text();
+        push_token_if_ready(&self.state, text, base, self.offset, &mut self.symbols, tokens)?;
+        match self.current_char {
+            Some(val) => self.state.set(reduce_state(val, self.prev_char, self.offset - 1, self.state.get())?),
+            None => {
+                self.is_finished = true;
+                let state = self.state.get();
+                if state.is_inside_string {
+                    return Err(SyntaxError::new(state.start_offset, String::from("This string has no closing quote")))
+                }
+                self.state.set(State { is_ready_to_push: true, ..state });
+                let (text, base) = self.input.text();
+                push_token_if_ready(&self.state, text, base, self.offset, &mut self.symbols, tokens)?;
+            },
+        };
+        Ok(())
+    }
+}
+
+impl<I: Input> Iterator for Tokens<I> {
+    type Item = Result<Token, SyntaxError>;
+
+    fn next(&mut self) -> Option<Result<Token, SyntaxError>> {
+        let mut pushed = std::mem::take(&mut self.pushed);
+        let mut result = None;
+        while !self.is_finished && result.is_none() {
+            result = match self.step(&mut pushed) {
+                Ok(_) => pushed.pop().map(Ok),
+                Err(e) => {
+                    self.is_finished = true;
+                    Some(Err(e))
                 },
-                None => break,
             };
-            it += 1;
-            if it > 1000 {
-                panic!()
+        }
+        self.pushed = pushed;
+        result
+    }
+}
+
+impl<'a> StrInput<'a> {
+
+    fn new(source: &'a str, from: usize) -> StrInput<'a> {
+        StrInput { source, chars: source[from..].char_indices(), from }
+    }
+}
+
+impl<'a> Input for StrInput<'a> {
+
+    fn next_char(&mut self) -> Result<(usize, Option<char>), SyntaxError> {
+        Ok(match self.chars.next() {
+            Some((index, symbol)) => (self.from + index, Some(symbol)),
+            None => (self.source.len(), None),
+        })
+    }
+
+    fn text(&self) -> (&str, usize) {
+        (self.source, 0)
+    }
+}
+
+impl<R: Read> ReadInput<R> {
+
+    /// Decodes the next chunk of the reader to the text, returns `false` at the end of the reader.
+    fn read_chunk(&mut self) -> Result<bool, SyntaxError> {
+        let pos = self.base + self.text.len();
+        let mut chunk = [0u8; READ_CHUNK_SIZE];
+        let count = loop {
+            match self.reader.read(&mut chunk) {
+                Ok(count) => break count,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(SyntaxError::new(pos, format!("Can't read the source: {}", e))),
+            }
+        };
+        if count == 0 {
+            return match self.undecoded.is_empty() {
+                true => Ok(false),
+                false => Err(SyntaxError::new(pos, String::from("The source is not valid UTF-8"))),
             }
         }
-        self.state.set(State { is_ready_to_push: true, ..self.state.get() });
-        match push_token_if_ready(&self.state, source, offset, &mut tokens) {
-            Ok(_) => { /* no-op */ }
-            Err(e) => return Err(e)
+        self.undecoded.extend_from_slice(&chunk[..count]);
+        let decoded = match std::str::from_utf8(&self.undecoded) {
+            Ok(text) => text.len(),
+            // the last char is not read completely
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            // the tokens before the invalid bytes are read first
+            Err(e) if e.valid_up_to() > 0 => e.valid_up_to(),
+            Err(_) => return Err(SyntaxError::new(pos, String::from("The source is not valid UTF-8"))),
         };
-        Ok(tokens)
+        if let Ok(text) = std::str::from_utf8(&self.undecoded[..decoded]) {
+            self.text.push_str(text);
+        }
+        self.undecoded.drain(..decoded);
+        Ok(true)
+    }
+}
+
+impl<R: Read> Input for ReadInput<R> {
+
+    fn next_char(&mut self) -> Result<(usize, Option<char>), SyntaxError> {
+        loop {
+            if let Some(symbol) = self.text[(self.next - self.base)..].chars().next() {
+                let index = self.next;
+                self.next += symbol.len_utf8();
+                return Ok((index, Some(symbol)))
+            }
+            if !self.read_chunk()? {
+                return Ok((self.next, None))
+            }
+        }
+    }
+
+    fn text(&self) -> (&str, usize) {
+        (&self.text, self.base)
+    }
+
+    fn release(&mut self, offset: usize) {
+        // the text is moved only when the most of it is released, so every byte is moved once on average
+        let released = offset - self.base;
+        if released > self.text.len() / 2 {
+            self.text.drain(..released);
+            self.base = offset;
+        }
     }
 }
 
-fn push_token_if_ready(state_cell: &Cell<State>, source: &String, offset: usize, tokens: &mut Vec<Token>) -> Result<(), SyntaxError> {
+/// Pushes the token which ends before the `offset` minus one, the `source` is the text from the offset `base`.
+fn push_token_if_ready(
+    state_cell: &Cell<State>,
+    source: &str,
+    base: usize,
+    offset: usize,
+    symbols: &mut Symbols,
+    tokens: &mut Vec<Token>,
+) -> Result<(), SyntaxError> {
     let state = state_cell.get();
     if state.is_ready_to_push {
         let start = state.start_offset;
         let end = offset - 1;
-        let token_content = String::from(&source[start..end]);
-        let result = match state.expected {
+        // only the names, the strings and the comments are copied from the source, each distinct text once
+        let token_content = &source[(start - base)..(end - base)];
+        let token = match state.expected {
             Expected::IntNumber => match token_content.parse() {
-                Ok(int_value) => Ok(tokens.push(Token::IntConstant { value: int_value, pos: start })),
-                Err(_) => Err(SyntaxError { pos: start, message: format!("Can't parse int number {}", token_content) }),
+                Ok(int_value) => Ok(Some(Token::IntConstant { value: int_value, pos: start })),
+                Err(_) => Err(SyntaxError::new(start, format!("Can't parse int number {}", token_content))),
             },
-            Expected::FloatNumber => push_float_token_if_ready(&state, source, start, end, tokens, &token_content),
+            Expected::FloatNumber if state.is_range_after_number => {
+                let token = read_number_before_range(state_cell, start, end, token_content)?;
+                tokens.push(token);
+                return Ok(())
+            },
+            Expected::FloatNumber => read_float_token(&state, start, token_content).map(Some),
             Expected::StringConstant => {
-                let token_content = String::from(&source[(start + 1)..(end - 1)]);
-                Ok(tokens.push(Token::StringConstant { value: token_content, pos: start }))
+                let value = symbols.intern(&token_content[1..(token_content.len() - 1)]);
+                Ok(Some(Token::StringConstant { value, pos: start }))
+            },
+            Expected::Identifier if token_content.starts_with('_') && token_content != WILDCARD =>
+                Err(SyntaxError::new(start, String::from("Identifier names must not start with an underscore"))),
+            Expected::Identifier => Ok(Some(get_keyword_or_identifier(token_content, start, state.identifier_is_function, symbols))),
+            Expected::Operator => match Operator::parse(token_content) {
+                Some(operator) => Ok(Some(Token::Operator { payload: operator, pos: start })),
+                None => Err(SyntaxError::new(start, format!("Unknown operator '{}'", token_content))),
             },
-            Expected::Identifier => Ok(tokens.push(get_keyword_or_identifier(token_content, start, state.identifier_is_function))),
-            Expected::Operator => Ok(tokens.push(Token::Operator { payload: token_content, pos: start })),
-            Expected::Newline => Ok(tokens.push(Token::NewLine { pos: start })),
-            Expected::BlockComment => Ok(()),
-            Expected::LineComment => Ok(()),
-            Expected::Nothing => Ok(()),
+            Expected::Newline => Ok(Some(Token::NewLine { pos: start })),
+            Expected::BlockComment | Expected::LineComment => Ok(Some(Token::Comment { text: symbols.intern(token_content), pos: start })),
+            Expected::Nothing => Ok(None),
         };
         state_cell.set(State { 
             is_ready_to_push: false, 
             expected: Expected::Nothing,
             is_percent_float: false,
+            identifier_is_function: false,
             ..state
         });
-        result
-    } else {
-        Ok(())
+        tokens.extend(token?);
     }
+    Ok(())
 }
 
 #[inline]
-fn push_float_token_if_ready(
-    state: &State, 
-    source: &String, 
-    start: usize, 
-    end: usize,
-    tokens: &mut Vec<Token>,
-    token_content: &String,
-) -> Result<(), SyntaxError> {
+fn read_float_token(state: &State, start: usize, token_content: &str) -> Result<Token, SyntaxError> {
     match state.is_percent_float {
         true => {
-            let token_content = String::from(&source[start..(end - 1)]);
+            let token_content = &token_content[..(token_content.len() - 1)];
             match token_content.parse() {
-                Ok(float_value) => Ok(tokens.push(Token::FloatConstant { value: get_percent_float(float_value), pos: start })),
-                Err(_) => Err(SyntaxError { pos: start, message: format!("Can't parse percentage number {}%", token_content) }),
+                Ok(float_value) => Ok(Token::FloatConstant { value: get_percent_float(float_value), pos: start }),
+                Err(_) => Err(SyntaxError::new(start, format!("Can't parse percentage number {}%", token_content))),
             }
             
         }
         false => match token_content.parse() {
-            Ok(float_value) => Ok(tokens.push(Token::FloatConstant { value: float_value, pos: start })),
-            Err(_) => Err(SyntaxError { pos: start, message: format!("Can't parse float number {}", token_content) }),
+            Ok(float_value) => Ok(Token::FloatConstant { value: float_value, pos: start }),
+            Err(_) => Err(SyntaxError::new(start, format!("Can't parse float number {}", token_content))),
         },
     }
 }
 
+/// Integer followed by the range operator (`1..10`) looks like a float number `1.` until the second dot,
+/// so the number is read without the first dot, which becomes the beginning of the operator.
 #[inline]
-fn get_percent_float(float_value: f32) -> f32 { float_value / 100.0 }
+fn read_number_before_range(state_cell: &Cell<State>, start: usize, end: usize, token_content: &str) -> Result<Token, SyntaxError> {
+    let state = state_cell.get();
+    state_cell.set(State {
+        is_ready_to_push: false,
+        expected: Expected::Operator,
+        start_offset: end - 1,
+        is_range_after_number: false,
+        ..state
+    });
+    let token_content = &token_content[..(token_content.len() - 1)];
+    match token_content.parse() {
+        Ok(int_value) => Ok(Token::IntConstant { value: int_value, pos: start }),
+        Err(_) => Err(SyntaxError::new(start, format!("Can't parse int number {}", token_content))),
+    }
+}
+
+#[inline]
+fn get_percent_float(float_value: f64) -> f64 { float_value / 100.0 }
 
 #[inline]
 fn get_keyword_or_identifier(
-    token_content: String, 
+    token_content: &str, 
     start: usize,
     maybe_identifier_is_function: bool,
+    symbols: &mut Symbols,
 ) -> Token {
     match token_content {
-        val if KEYWORDS.iter().any(|k| k.to_string() == val) => Token::Operator { payload: val, pos: start },
-        _ => match maybe_identifier_is_function {
-            false => Token::Identifier { name: token_content, pos: start },
-            true => Token::Function { name: token_content, pos: start },
+        BOOL_TRUE => Token::BoolConstant { value: true, pos: start },
+        BOOL_FALSE => Token::BoolConstant { value: false, pos: start },
+        NONE => Token::NoneConstant { pos: start },
+        _ => match (Operator::parse(token_content), maybe_identifier_is_function) {
+            (Some(keyword), _) => Token::Operator { payload: keyword, pos: start },
+            (None, false) => Token::Identifier { name: symbols.intern(token_content), pos: start },
+            (None, true) => Token::Function { name: symbols.intern(token_content), pos: start },
         }
     }
 }
 
+impl Symbols {
+
+    /// Returns the symbol with the text, it is allocated only when the text is not read recently.
+    #[inline]
+    fn intern(&mut self, text: &str) -> Symbol {
+        if let Some(symbol) = self.texts.get(text) {
+            return symbol.clone()
+        }
+        if self.size + text.len() > MAX_SYMBOLS_SIZE {
+            self.texts.clear();
+            self.size = 0;
+        }
+        let symbol = Symbol::from(text);
+        self.size += text.len();
+        self.texts.insert(symbol.clone());
+        symbol
+    }
+}
+
 #[inline]
 fn reduce_state(symbol: char, prev_symbol: char, offset: usize, state: State) -> Result<State, SyntaxError> {
     match state.expected {
         Expected::Nothing => reduce_state_nothing(symbol, offset, state),
         Expected::IntNumber => reduce_state_int_number(symbol, offset, state),
-        Expected::FloatNumber => reduce_state_float_number(symbol, offset, state),
+        Expected::FloatNumber => reduce_state_float_number(symbol, prev_symbol, offset, state),
         Expected::StringConstant => reduce_state_string_constant(symbol, state),
         Expected::Identifier => reduce_state_identifier(symbol, state),
         Expected::Operator => reduce_state_operator(symbol, prev_symbol, state),
@@ -257,23 +580,24 @@ fn reduce_state(symbol: char, prev_symbol: char, offset: usize, state: State) ->
 #[inline]
 fn reduce_state_nothing(symbol: char, offset: usize, state: State) -> Result<State, SyntaxError> {
     match symbol {
-        val if val.is_digit(10) => Ok(State { expected: Expected::IntNumber, start_offset: offset, ..state }),
+        val if val.is_ascii_digit() => Ok(State { expected: Expected::IntNumber, start_offset: offset, ..state }),
         val if val.is_alphabetic() => Ok(State { expected: Expected::Identifier, start_offset: offset, ..state }),
         '\n' => Ok(State { expected: Expected::Newline, start_offset: offset, ..state }),
         val if val.is_whitespace() => Ok(State { start_offset: offset, ..state }),
         val if OPERATORS.chars().any(|s| s == val) => 
             Ok(State { expected: Expected::Operator, start_offset: offset, ..state }),
         '"' => Ok(State { expected: Expected::StringConstant, is_inside_string: true, start_offset: offset, ..state }),
-        '_' => Err(SyntaxError { pos: offset, message: String::from("Identifier names must not start with an underscore") }),
-        _ => Err(SyntaxError { pos: offset, message: format!("Unexpected symbol '{}'", symbol) }),
+        // the single underscore is the wildcard, other names are checked when the identifier is read
+        '_' => Ok(State { expected: Expected::Identifier, start_offset: offset, ..state }),
+        _ => Err(SyntaxError::new(offset, format!("Unexpected symbol '{}'", symbol))),
     }
 }
 
 #[inline]
 fn reduce_state_int_number(symbol: char, offset: usize, state: State) -> Result<State, SyntaxError> {
     match symbol {
-        val if val.is_digit(10) => Ok(state),
-        val if val.is_alphabetic() => Err(SyntaxError { pos: offset, message: format!("Invalid character in integer number record: {:?}", symbol) }),
+        val if val.is_ascii_digit() => Ok(state),
+        val if val.is_alphabetic() => Err(SyntaxError::new(offset, format!("Invalid character in integer number record: {:?}", symbol))),
         '%' => Ok(State { expected: Expected::FloatNumber, is_percent_float: true, ..state }),
         '.' => Ok(State { expected: Expected::FloatNumber, ..state }),
         _ => Ok(State { is_ready_to_push: true, ..state }),
@@ -281,13 +605,14 @@ fn reduce_state_int_number(symbol: char, offset: usize, state: State) -> Result<
 }
 
 #[inline]
-fn reduce_state_float_number(symbol: char, offset: usize, state: State) -> Result<State, SyntaxError> {
+fn reduce_state_float_number(symbol: char, prev_symbol: char, offset: usize, state: State) -> Result<State, SyntaxError> {
     match symbol {
-        val if val.is_digit(10) => Ok(state),
-        val if val.is_alphabetic() => Err(SyntaxError { pos: offset, message: format!("Invalid character in floating point number record: {:?}", symbol) }),
+        '.' if prev_symbol == '.' => Ok(State { is_ready_to_push: true, is_range_after_number: true, ..state }),
+        val if val.is_ascii_digit() => Ok(state),
+        val if val.is_alphabetic() => Err(SyntaxError::new(offset, format!("Invalid character in floating point number record: {:?}", symbol))),
         '%' => match state.is_percent_float { 
             false => Ok(State { is_percent_float: true, ..state }),
-            true => Err(SyntaxError { pos: offset, message: String::from("You cannot use the percent symbol twice on the same number") })
+            true => Err(SyntaxError::new(offset, String::from("You cannot use the percent symbol twice on the same number")))
         },
         _ => Ok(State { is_ready_to_push: true, ..state }),
     }
@@ -320,6 +645,11 @@ fn reduce_state_operator(symbol: char, prev_symbol: char, state: State) -> Resul
             },
             _ => State { is_ready_to_push: true, ..state },
         },
+        // `..` and `..=` range operators
+        '.' => match symbol {
+            '.' | '=' => state,
+            _ => State { is_ready_to_push: true, ..state },
+        },
         _ => State { is_ready_to_push: true, ..state },
     };
     Ok(new_state)
@@ -336,16 +666,13 @@ fn reduce_state_newline(symbol: char, state: State) -> Result<State, SyntaxError
 
 #[inline]
 fn reduce_state_string_constant(symbol: char, state: State) -> Result<State, SyntaxError> {
-    let new_state = match state.is_prev_escape_symbol {
-        false => match symbol {
-            '"' => State { is_inside_string: false, ..state },
-            '\\' => State { is_prev_escape_symbol: true, ..state },
-            _ => match state.is_inside_string {
-                true => state,
-                false => State { is_ready_to_push: true, ..state },
-            },
-        },
-        true => State { is_prev_escape_symbol: false, ..state },
+    // any symbol after the closing quote ends the string, even the quote or the backslash
+    let new_state = match (state.is_inside_string, state.is_prev_escape_symbol, symbol) {
+        (false, _, _) => State { is_ready_to_push: true, ..state },
+        (true, true, _) => State { is_prev_escape_symbol: false, ..state },
+        (true, false, '"') => State { is_inside_string: false, ..state },
+        (true, false, '\\') => State { is_prev_escape_symbol: true, ..state },
+        (true, false, _) => state,
     };
     Ok(new_state)
 }
@@ -381,29 +708,45 @@ fn reduce_state_line_comment(symbol: char, state: State) -> Result<State, Syntax
 fn test_string_literals() {
     let source = String::from("\"hello world\"\n\"\\\"quoted hello world\\\"\"");
     let expected = vec!(
-        Token::StringConstant { value: String::from("hello world"), pos: 0 },
+        Token::StringConstant { value: Symbol::from("hello world"), pos: 0 },
         Token::NewLine { pos: 13 },
-        Token::StringConstant { value: String::from("\\\"quoted hello world\\\""), pos: 14 },
+        Token::StringConstant { value: Symbol::from("\\\"quoted hello world\\\""), pos: 14 },
     );
     let actual = TokenReader::new().parse(&source).unwrap();
     assert_eq!(expected, actual)
 }
 
+/// Testing the sources with the multibyte symbols and the strings which end right before the other string.
+#[test]
+fn test_multibyte_symbols_and_adjacent_strings() {
+    let source = "jé = \"ж\"\"€\"";
+    let expected = vec!(
+        Token::Identifier { name: Symbol::from("jé"), pos: 0 },
+        Token::Operator { payload: Operator::Assign, pos: 4 },
+        Token::StringConstant { value: Symbol::from("ж"), pos: 6 },
+        Token::StringConstant { value: Symbol::from("€"), pos: 10 },
+    );
+    assert_eq!(expected, TokenReader::new().parse(source).unwrap());
+    let reader = TokenReader::new();
+    assert_eq!("This string has no closing quote", reader.parse("a = \"b\\\"").unwrap_err().message);
+    assert_eq!(vec!(Token::IntConstant { value: 1, pos: 0 }), reader.parse("1").unwrap());
+}
+
 /// Testing the correct finding of integer literals.
 #[test]
 fn test_integer_literals() {
     let source = String::from("1+22*333/44^5-678");
     let expected = vec!(
         Token::IntConstant { value: 1, pos: 0 },
-        Token::Operator { payload: String::from("+"), pos: 1 },
+        Token::Operator { payload: Operator::Plus, pos: 1 },
         Token::IntConstant { value: 22, pos: 2 },
-        Token::Operator { payload: String::from("*"), pos: 4 },
+        Token::Operator { payload: Operator::Multiply, pos: 4 },
         Token::IntConstant { value: 333, pos: 5 },
-        Token::Operator { payload: String::from("/"), pos: 8 },
+        Token::Operator { payload: Operator::Divide, pos: 8 },
         Token::IntConstant { value: 44, pos: 9 },
-        Token::Operator { payload: String::from("^"), pos: 11 },
+        Token::Operator { payload: Operator::Power, pos: 11 },
         Token::IntConstant { value: 5, pos: 12 },
-        Token::Operator { payload: String::from("-"), pos: 13 },
+        Token::Operator { payload: Operator::Minus, pos: 13 },
         Token::IntConstant { value: 678, pos: 14 },
     );
     let actual = TokenReader::new().parse(&source).unwrap();
@@ -418,15 +761,15 @@ fn test_float_literals() {
     let source = String::from("1.0+22*3./4.44^0.5-67.8");
     let expected = vec!(
         Token::FloatConstant { value: 1.0, pos: 0 },
-        Token::Operator { payload: String::from("+"), pos: 3 },
+        Token::Operator { payload: Operator::Plus, pos: 3 },
         Token::IntConstant { value: 22, pos: 4 },
-        Token::Operator { payload: String::from("*"), pos: 6 },
+        Token::Operator { payload: Operator::Multiply, pos: 6 },
         Token::FloatConstant { value: 3.0, pos: 7 },
-        Token::Operator { payload: String::from("/"), pos: 9},
+        Token::Operator { payload: Operator::Divide, pos: 9},
         Token::FloatConstant { value: 4.44, pos: 10 },
-        Token::Operator { payload: String::from("^"), pos: 14 },
+        Token::Operator { payload: Operator::Power, pos: 14 },
         Token::FloatConstant { value: 0.5, pos: 15 },
-        Token::Operator { payload: String::from("-"), pos: 18 },
+        Token::Operator { payload: Operator::Minus, pos: 18 },
         Token::FloatConstant { value: 67.8, pos: 19 },
     );
     let actual = TokenReader::new().parse(&source).unwrap();
@@ -458,6 +801,24 @@ fn test_formatted_float_literals() {
     assert_eq!(expected, actual)
 }
 
+/// Testing the correct finding of bool and none literals.
+/// 
+/// `true`, `false` and `None` are reserved words, so they can't be used as identifiers.
+#[test]
+fn test_bool_and_none_literals() {
+    let source = String::from("true false None trueish None2 isNone");
+    let expected = vec!(
+        Token::BoolConstant { value: true, pos: 0 },
+        Token::BoolConstant { value: false, pos: 5 },
+        Token::NoneConstant { pos: 11 },
+        Token::Identifier { name: Symbol::from("trueish"), pos: 16 },
+        Token::Identifier { name: Symbol::from("None2"), pos: 24 },
+        Token::Identifier { name: Symbol::from("isNone"), pos: 30 },
+    );
+    let actual = TokenReader::new().parse(&source).unwrap();
+    assert_eq!(expected, actual)
+}
+
 /// Testing the correct finding of identifiers (variables and functions names)
 /// 
 /// Identifiers names matches `[a-zA-Z][a-zA-Z0-9_]*` regexp.
@@ -465,12 +826,12 @@ fn test_formatted_float_literals() {
 fn test_identifiers() {
     let source = String::from("a foo bar2 x_yz123 functionName variableName");
     let expected = vec!(
-        Token::Identifier { name: String::from("a"), pos: 0 },
-        Token::Identifier { name: String::from("foo"), pos: 2 },
-        Token::Identifier { name: String::from("bar2"), pos: 6 },
-        Token::Identifier { name: String::from("x_yz123"), pos: 11 },
-        Token::Identifier { name: String::from("functionName"), pos: 19 },
-        Token::Identifier { name: String::from("variableName"), pos: 32 },
+        Token::Identifier { name: Symbol::from("a"), pos: 0 },
+        Token::Identifier { name: Symbol::from("foo"), pos: 2 },
+        Token::Identifier { name: Symbol::from("bar2"), pos: 6 },
+        Token::Identifier { name: Symbol::from("x_yz123"), pos: 11 },
+        Token::Identifier { name: Symbol::from("functionName"), pos: 19 },
+        Token::Identifier { name: Symbol::from("variableName"), pos: 32 },
     );
     let actual = TokenReader::new().parse(&source).unwrap();
     assert_eq!(expected, actual)
@@ -494,28 +855,28 @@ fn test_identifiers() {
 fn test_arithmetical_operators() {
     let source = String::from("a+b-c*d/e^f+=(-=)(*=/=)a=b");
     let expected = vec!(
-        Token::Identifier { name: String::from("a"), pos: 0 },
-        Token::Operator { payload: String::from("+"), pos: 1 },
-        Token::Identifier { name: String::from("b"), pos: 2 },
-        Token::Operator { payload: String::from("-"), pos: 3 },
-        Token::Identifier { name: String::from("c"), pos: 4 },
-        Token::Operator { payload: String::from("*"), pos: 5 },
-        Token::Identifier { name: String::from("d"), pos: 6 },
-        Token::Operator { payload: String::from("/"), pos: 7 },
-        Token::Identifier { name: String::from("e"), pos: 8 },
-        Token::Operator { payload: String::from("^"), pos: 9 },
-        Token::Identifier { name: String::from("f"), pos: 10 },
-        Token::Operator { payload: String::from("+="), pos: 11 },
-        Token::Operator { payload: String::from("("), pos: 13 },
-        Token::Operator { payload: String::from("-="), pos: 14 },
-        Token::Operator { payload: String::from(")"), pos: 16 },
-        Token::Operator { payload: String::from("("), pos: 17 },
-        Token::Operator { payload: String::from("*="), pos: 18 },
-        Token::Operator { payload: String::from("/="), pos: 20 },
-        Token::Operator { payload: String::from(")"), pos: 22 },
-        Token::Identifier { name: String::from("a"), pos: 23 },
-        Token::Operator { payload: String::from("="), pos: 24 },
-        Token::Identifier { name: String::from("b"), pos: 25 },
+        Token::Identifier { name: Symbol::from("a"), pos: 0 },
+        Token::Operator { payload: Operator::Plus, pos: 1 },
+        Token::Identifier { name: Symbol::from("b"), pos: 2 },
+        Token::Operator { payload: Operator::Minus, pos: 3 },
+        Token::Identifier { name: Symbol::from("c"), pos: 4 },
+        Token::Operator { payload: Operator::Multiply, pos: 5 },
+        Token::Identifier { name: Symbol::from("d"), pos: 6 },
+        Token::Operator { payload: Operator::Divide, pos: 7 },
+        Token::Identifier { name: Symbol::from("e"), pos: 8 },
+        Token::Operator { payload: Operator::Power, pos: 9 },
+        Token::Identifier { name: Symbol::from("f"), pos: 10 },
+        Token::Operator { payload: Operator::AddAssign, pos: 11 },
+        Token::Operator { payload: Operator::LeftParen, pos: 13 },
+        Token::Operator { payload: Operator::SubtractAssign, pos: 14 },
+        Token::Operator { payload: Operator::RightParen, pos: 16 },
+        Token::Operator { payload: Operator::LeftParen, pos: 17 },
+        Token::Operator { payload: Operator::MultiplyAssign, pos: 18 },
+        Token::Operator { payload: Operator::DivideAssign, pos: 20 },
+        Token::Operator { payload: Operator::RightParen, pos: 22 },
+        Token::Identifier { name: Symbol::from("a"), pos: 23 },
+        Token::Operator { payload: Operator::Assign, pos: 24 },
+        Token::Identifier { name: Symbol::from("b"), pos: 25 },
     );
     let actual = TokenReader::new().parse(&source).unwrap();
     assert_eq!(expected, actual)
@@ -532,19 +893,19 @@ fn test_arithmetical_operators() {
 fn test_logical_operators() {
     let source = String::from("(A and B) or not (C and not D)");
     let expected = vec!(
-        Token::Operator { payload: String::from("("), pos: 0 },
-        Token::Identifier { name: String::from("A"), pos: 1 },
-        Token::Operator { payload: String::from("and"), pos: 3 },
-        Token::Identifier { name: String::from("B"), pos: 7 },
-        Token::Operator { payload: String::from(")"), pos: 8 },
-        Token::Operator { payload: String::from("or"), pos: 10 },
-        Token::Operator { payload: String::from("not"), pos: 13 },
-        Token::Operator { payload: String::from("("), pos: 17 },
-        Token::Identifier { name: String::from("C"), pos: 18 },
-        Token::Operator { payload: String::from("and"), pos: 20 },
-        Token::Operator { payload: String::from("not"), pos: 24 },
-        Token::Identifier { name: String::from("D"), pos: 28 },
-        Token::Operator { payload: String::from(")"), pos: 29 },
+        Token::Operator { payload: Operator::LeftParen, pos: 0 },
+        Token::Identifier { name: Symbol::from("A"), pos: 1 },
+        Token::Operator { payload: Operator::And, pos: 3 },
+        Token::Identifier { name: Symbol::from("B"), pos: 7 },
+        Token::Operator { payload: Operator::RightParen, pos: 8 },
+        Token::Operator { payload: Operator::Or, pos: 10 },
+        Token::Operator { payload: Operator::Not, pos: 13 },
+        Token::Operator { payload: Operator::LeftParen, pos: 17 },
+        Token::Identifier { name: Symbol::from("C"), pos: 18 },
+        Token::Operator { payload: Operator::And, pos: 20 },
+        Token::Operator { payload: Operator::Not, pos: 24 },
+        Token::Identifier { name: Symbol::from("D"), pos: 28 },
+        Token::Operator { payload: Operator::RightParen, pos: 29 },
     );
     let actual = TokenReader::new().parse(&source).unwrap();
     assert_eq!(expected, actual)
@@ -565,21 +926,21 @@ fn test_flow_operators() {
     // if happy path
     let source = String::from("if a > b { foo() } else { bar() }");
     let expected = vec!(
-        Token::Operator { payload: String::from("if"), pos: 0 },
-        Token::Identifier { name: String::from("a"), pos: 3 },
-        Token::Operator { payload: String::from(">"), pos: 5 },
-        Token::Identifier { name: String::from("b"), pos: 7 },
-        Token::Operator { payload: String::from("{"), pos: 9 },
-        Token::Function { name: String::from("foo"), pos: 11 },
-        Token::Operator { payload: String::from("("), pos: 14 },
-        Token::Operator { payload: String::from(")"), pos: 15 },
-        Token::Operator { payload: String::from("}"), pos: 17 },
-        Token::Operator { payload: String::from("else"), pos: 19 },
-        Token::Operator { payload: String::from("{"), pos: 24 },
-        Token::Function { name: String::from("bar"), pos: 26 },
-        Token::Operator { payload: String::from("("), pos: 29 },
-        Token::Operator { payload: String::from(")"), pos: 30 },
-        Token::Operator { payload: String::from("}"), pos: 32 },
+        Token::Operator { payload: Operator::If, pos: 0 },
+        Token::Identifier { name: Symbol::from("a"), pos: 3 },
+        Token::Operator { payload: Operator::Greater, pos: 5 },
+        Token::Identifier { name: Symbol::from("b"), pos: 7 },
+        Token::Operator { payload: Operator::LeftBrace, pos: 9 },
+        Token::Function { name: Symbol::from("foo"), pos: 11 },
+        Token::Operator { payload: Operator::LeftParen, pos: 14 },
+        Token::Operator { payload: Operator::RightParen, pos: 15 },
+        Token::Operator { payload: Operator::RightBrace, pos: 17 },
+        Token::Operator { payload: Operator::Else, pos: 19 },
+        Token::Operator { payload: Operator::LeftBrace, pos: 24 },
+        Token::Function { name: Symbol::from("bar"), pos: 26 },
+        Token::Operator { payload: Operator::LeftParen, pos: 29 },
+        Token::Operator { payload: Operator::RightParen, pos: 30 },
+        Token::Operator { payload: Operator::RightBrace, pos: 32 },
     );
     let actual = TokenReader::new().parse(&source).unwrap();
     assert_eq!(expected, actual);
@@ -587,12 +948,12 @@ fn test_flow_operators() {
     // loop happy path
     let source = String::from("loop { foo() }");
     let expected = vec!(
-        Token::Operator { payload: String::from("loop"), pos: 0 },
-        Token::Operator { payload: String::from("{"), pos: 5 },
-        Token::Function { name: String::from("foo"), pos: 7 },
-        Token::Operator { payload: String::from("("), pos: 10 },
-        Token::Operator { payload: String::from(")"), pos: 11 },
-        Token::Operator { payload: String::from("}"), pos: 13 },
+        Token::Operator { payload: Operator::Loop, pos: 0 },
+        Token::Operator { payload: Operator::LeftBrace, pos: 5 },
+        Token::Function { name: Symbol::from("foo"), pos: 7 },
+        Token::Operator { payload: Operator::LeftParen, pos: 10 },
+        Token::Operator { payload: Operator::RightParen, pos: 11 },
+        Token::Operator { payload: Operator::RightBrace, pos: 13 },
     );
     let actual = TokenReader::new().parse(&source).unwrap();
     assert_eq!(expected, actual);
@@ -600,10 +961,10 @@ fn test_flow_operators() {
     // loop happy path
     let source = String::from("for i in range");
     let expected = vec!(
-        Token::Operator { payload: String::from("for"), pos: 0 },
-        Token::Identifier { name: String::from("i"), pos: 4 },
-        Token::Operator { payload: String::from("in"), pos: 6 },
-        Token::Identifier { name: String::from("range"), pos: 9 },
+        Token::Operator { payload: Operator::For, pos: 0 },
+        Token::Identifier { name: Symbol::from("i"), pos: 4 },
+        Token::Operator { payload: Operator::In, pos: 6 },
+        Token::Identifier { name: Symbol::from("range"), pos: 9 },
     );
     let actual = TokenReader::new().parse(&source).unwrap();
     assert_eq!(expected, actual);
@@ -611,13 +972,95 @@ fn test_flow_operators() {
     // loop happy path
     let source = String::from("match expr { a -> None }");
     let expected = vec!(
-        Token::Operator { payload: String::from("match"), pos: 0 },
-        Token::Identifier { name: String::from("expr"), pos: 6 },
-        Token::Operator { payload: String::from("{"), pos: 11 },
-        Token::Identifier { name: String::from("a"), pos: 13 },
-        Token::Operator { payload: String::from("->"), pos: 15 },
-        Token::Identifier { name: String::from("None"), pos: 18 },
-        Token::Operator { payload: String::from("}"), pos: 23 },
+        Token::Operator { payload: Operator::Match, pos: 0 },
+        Token::Identifier { name: Symbol::from("expr"), pos: 6 },
+        Token::Operator { payload: Operator::LeftBrace, pos: 11 },
+        Token::Identifier { name: Symbol::from("a"), pos: 13 },
+        Token::Operator { payload: Operator::Arrow, pos: 15 },
+        Token::NoneConstant { pos: 18 },
+        Token::Operator { payload: Operator::RightBrace, pos: 23 },
+    );
+    let actual = TokenReader::new().parse(&source).unwrap();
+    assert_eq!(expected, actual)
+}
+
+/// Testing for correct finding of function declaration keywords
+/// 
+/// # Keywords
+/// - `fn`
+/// - `return`
+#[test]
+fn test_function_keywords() {
+    let source = String::from("fn half(x: Int) -> Float { return x / 2 }");
+    let expected = vec!(
+        Token::Operator { payload: Operator::Fn, pos: 0 },
+        Token::Function { name: Symbol::from("half"), pos: 3 },
+        Token::Operator { payload: Operator::LeftParen, pos: 7 },
+        Token::Identifier { name: Symbol::from("x"), pos: 8 },
+        Token::Operator { payload: Operator::Colon, pos: 9 },
+        Token::Identifier { name: Symbol::from("Int"), pos: 11 },
+        Token::Operator { payload: Operator::RightParen, pos: 14 },
+        Token::Operator { payload: Operator::Arrow, pos: 16 },
+        Token::Identifier { name: Symbol::from("Float"), pos: 19 },
+        Token::Operator { payload: Operator::LeftBrace, pos: 25 },
+        Token::Operator { payload: Operator::Return, pos: 27 },
+        Token::Identifier { name: Symbol::from("x"), pos: 34 },
+        Token::Operator { payload: Operator::Divide, pos: 36 },
+        Token::IntConstant { value: 2, pos: 38 },
+        Token::Operator { payload: Operator::RightBrace, pos: 40 },
+    );
+    let actual = TokenReader::new().parse(&source).unwrap();
+    assert_eq!(expected, actual)
+}
+
+/// Testing for correct finding of enum declaration and type test keywords
+/// 
+/// # Keywords
+/// - `enum`
+/// - `is`
+#[test]
+fn test_enum_keywords() {
+    let source = String::from("enum E { A(x) }\ne is E.A");
+    let expected = vec!(
+        Token::Operator { payload: Operator::Enum, pos: 0 },
+        Token::Identifier { name: Symbol::from("E"), pos: 5 },
+        Token::Operator { payload: Operator::LeftBrace, pos: 7 },
+        Token::Function { name: Symbol::from("A"), pos: 9 },
+        Token::Operator { payload: Operator::LeftParen, pos: 10 },
+        Token::Identifier { name: Symbol::from("x"), pos: 11 },
+        Token::Operator { payload: Operator::RightParen, pos: 12 },
+        Token::Operator { payload: Operator::RightBrace, pos: 14 },
+        Token::NewLine { pos: 15 },
+        Token::Identifier { name: Symbol::from("e"), pos: 16 },
+        Token::Operator { payload: Operator::Is, pos: 18 },
+        Token::Identifier { name: Symbol::from("E"), pos: 21 },
+        Token::Operator { payload: Operator::Dot, pos: 22 },
+        Token::Identifier { name: Symbol::from("A"), pos: 23 },
+    );
+    let actual = TokenReader::new().parse(&source).unwrap();
+    assert_eq!(expected, actual)
+}
+
+/// Testing for correct finding of module keywords
+/// 
+/// # Keywords
+/// - `import`
+/// - `from`
+/// - `pub`
+#[test]
+fn test_module_keywords() {
+    let source = String::from("from a.b import c
+pub fn");
+    let expected = vec!(
+        Token::Operator { payload: Operator::From, pos: 0 },
+        Token::Identifier { name: Symbol::from("a"), pos: 5 },
+        Token::Operator { payload: Operator::Dot, pos: 6 },
+        Token::Identifier { name: Symbol::from("b"), pos: 7 },
+        Token::Operator { payload: Operator::Import, pos: 9 },
+        Token::Identifier { name: Symbol::from("c"), pos: 16 },
+        Token::NewLine { pos: 17 },
+        Token::Operator { payload: Operator::Pub, pos: 18 },
+        Token::Operator { payload: Operator::Fn, pos: 22 },
     );
     let actual = TokenReader::new().parse(&source).unwrap();
     assert_eq!(expected, actual)
@@ -634,18 +1077,65 @@ fn test_flow_operators() {
 fn test_other_operators() {
     let source = String::from("{ @ x, y -> x:0.1:y }");
     let expected = vec!(
-        Token::Operator { payload: String::from("{"), pos: 0 },
-        Token::Operator { payload: String::from("@"), pos: 2 },
-        Token::Identifier { name: String::from("x"), pos: 4 },
-        Token::Operator { payload: String::from(","), pos: 5 },
-        Token::Identifier { name: String::from("y"), pos: 7 },
-        Token::Operator { payload: String::from("->"), pos: 9 },
-        Token::Identifier { name: String::from("x"), pos: 12 },
-        Token::Operator { payload: String::from(":"), pos: 13 },
+        Token::Operator { payload: Operator::LeftBrace, pos: 0 },
+        Token::Operator { payload: Operator::At, pos: 2 },
+        Token::Identifier { name: Symbol::from("x"), pos: 4 },
+        Token::Operator { payload: Operator::Comma, pos: 5 },
+        Token::Identifier { name: Symbol::from("y"), pos: 7 },
+        Token::Operator { payload: Operator::Arrow, pos: 9 },
+        Token::Identifier { name: Symbol::from("x"), pos: 12 },
+        Token::Operator { payload: Operator::Colon, pos: 13 },
         Token::FloatConstant { value: 0.1, pos: 14 },
-        Token::Operator { payload: String::from(":"), pos: 17 },
-        Token::Identifier { name: String::from("y"), pos: 18 },
-        Token::Operator { payload: String::from("}"), pos: 20 },
+        Token::Operator { payload: Operator::Colon, pos: 17 },
+        Token::Identifier { name: Symbol::from("y"), pos: 18 },
+        Token::Operator { payload: Operator::RightBrace, pos: 20 },
+    );
+    let actual = TokenReader::new().parse(&source).unwrap();
+    assert_eq!(expected, actual)
+}
+
+/// Testing for correct finding of the wildcard and brackets used in patterns
+/// 
+/// # Operators
+/// - `[`, `]`
+/// - `_` wildcard is an identifier, but other names can't start with the underscore
+#[test]
+fn test_pattern_symbols() {
+    let source = String::from("[_, x_1]");
+    let expected = vec!(
+        Token::Operator { payload: Operator::LeftBracket, pos: 0 },
+        Token::Identifier { name: Symbol::from("_"), pos: 1 },
+        Token::Operator { payload: Operator::Comma, pos: 2 },
+        Token::Identifier { name: Symbol::from("x_1"), pos: 4 },
+        Token::Operator { payload: Operator::RightBracket, pos: 7 },
+    );
+    let actual = TokenReader::new().parse(&source).unwrap();
+    assert_eq!(expected, actual);
+    assert_eq!(3, TokenReader::new().parse(&String::from("a, _b")).unwrap_err().pos);
+}
+
+/// Testing for correct finding of range operators
+/// 
+/// # Operators
+/// - `..` exclusive range
+/// - `..=` inclusive range
+#[test]
+fn test_range_operators() {
+    let source = String::from("1..10 a..=b 1.5..x ..5 3..");
+    let expected = vec!(
+        Token::IntConstant { value: 1, pos: 0 },
+        Token::Operator { payload: Operator::Range, pos: 1 },
+        Token::IntConstant { value: 10, pos: 3 },
+        Token::Identifier { name: Symbol::from("a"), pos: 6 },
+        Token::Operator { payload: Operator::InclusiveRange, pos: 7 },
+        Token::Identifier { name: Symbol::from("b"), pos: 10 },
+        Token::FloatConstant { value: 1.5, pos: 12 },
+        Token::Operator { payload: Operator::Range, pos: 15 },
+        Token::Identifier { name: Symbol::from("x"), pos: 17 },
+        Token::Operator { payload: Operator::Range, pos: 19 },
+        Token::IntConstant { value: 5, pos: 21 },
+        Token::IntConstant { value: 3, pos: 23 },
+        Token::Operator { payload: Operator::Range, pos: 24 },
     );
     let actual = TokenReader::new().parse(&source).unwrap();
     assert_eq!(expected, actual)
@@ -661,13 +1151,28 @@ fn test_line_comments() {
     // comment 3");
     let expected = vec!(
         Token::NewLine { pos: 12 },
-        Token::Identifier { name: String::from("identifier"), pos: 17 },
+        Token::Identifier { name: Symbol::from("identifier"), pos: 17 },
         Token::NewLine { pos: 40 },
     );
     let actual = TokenReader::new().parse(&source).unwrap();
     assert_eq!(expected, actual)
 }
 
+/// Testing for correct finding of comments which are kept for the linter.
+#[test]
+fn test_retained_comments() {
+    let source = "a // avoc-allow(rule)\n/* block */b";
+    let tokens = TokenReader::new().parse_with_comments(source).unwrap();
+    let expected = vec!(
+        Token::Identifier { name: Symbol::from("a"), pos: 0 },
+        Token::Comment { text: Symbol::from("// avoc-allow(rule)"), pos: 2 },
+        Token::NewLine { pos: 21 },
+        Token::Comment { text: Symbol::from("/* block */"), pos: 22 },
+        Token::Identifier { name: Symbol::from("b"), pos: 33 },
+    );
+    assert_eq!(expected, tokens);
+}
+
 /// Testing for ignoring block comments.
 /// 
 /// Block comment starts with `/*` and ends with `*/`.
@@ -680,9 +1185,121 @@ fn test_block_comments() {
     identifier1 /*no-op*/identifier2 /* comment 2 *//* kek */");
     let expected = vec!(
         Token::NewLine { pos: 47 },
-        Token::Identifier { name: String::from("identifier1"), pos: 52 },
-        Token::Identifier { name: String::from("identifier2"), pos: 73 },
+        Token::Identifier { name: Symbol::from("identifier1"), pos: 52 },
+        Token::Identifier { name: Symbol::from("identifier2"), pos: 73 },
     );
     let actual = TokenReader::new().parse(&source).unwrap();
     assert_eq!(expected, actual)
-}
\ No newline at end of file
+}
+/// Testing the update of the tokens after the edits which join, split and swallow the tokens.
+#[test]
+fn test_relex() {
+    let reader = TokenReader::new();
+    let source = "x = 1..5 // range\nname = \"a b\" /* c */ f(x)\n\ny = 2";
+    let tokens = reader.parse_with_comments(source).unwrap();
+    let edits = [
+        (4, 5, "10"), (5, 6, "."), (5, 6, "5."), (4, 4, "f("), (0, 0, "\""), (18, 18, "\""), (27, 27, "\" + \""),
+        (33, 33, "*/"), (33, 35, ""), (10, 10, "\n"), (17, 18, ""), (38, 39, " "), (0, source.len(), "z"), (49, 50, "3.5"),
+    ];
+    for (start, end, text) in edits.iter() {
+        let edit = TextEdit { start: *start, end: *end, text: text.to_string() };
+        let new_source = format!("{}{}{}", &source[..*start], text, &source[*end..]);
+        assert_eq!(
+            reader.parse_with_comments(&new_source).map_err(|e| e.pos),
+            reader.relex(&tokens, &new_source, &edit).map_err(|e| e.pos),
+            "{:?}", new_source,
+        );
+    }
+    let long_source = "a = 1\n".repeat(100);
+    let tokens = reader.parse_with_comments(&long_source).unwrap();
+    let edit = TextEdit { start: 300, end: 301, text: String::from("bc") };
+    let relexed = reader.relex(&tokens, &format!("{}bc{}", &long_source[..300], &long_source[301..]), &edit).unwrap();
+    assert_eq!(Token::Identifier { name: Symbol::from("bc"), pos: 300 }, relexed[200]);
+    assert_eq!(Token::NewLine { pos: 600 }, relexed[399]);
+}
+
+/// Reader which returns one byte at a time, so the multibyte chars are split between the chunks.
+#[cfg(test)]
+struct ByteReader<'a>(&'a [u8]);
+
+#[cfg(test)]
+impl<'a> Read for ByteReader<'a> {
+
+    fn read(&mut self, buffer: &mut [u8]) -> std::io::Result<usize> {
+        match self.0.split_first() {
+            Some((byte, rest)) if !buffer.is_empty() => {
+                buffer[0] = *byte;
+                self.0 = rest;
+                Ok(1)
+            },
+            _ => Ok(0),
+        }
+    }
+}
+
+/// Testing the lazy reading of the tokens from the string and from the reader.
+#[test]
+fn test_streaming_tokens() {
+    let reader = TokenReader::new();
+    let source = "jé = \"ж€\" // 😀\n/* b */ f(1..2, 3.5%)\n";
+    let expected = reader.parse_with_comments(source).unwrap();
+    assert_eq!(expected, reader.tokens(source).collect::<Result<Vec<Token>, SyntaxError>>().unwrap());
+    assert_eq!(expected, reader.read_tokens(ByteReader(source.as_bytes())).collect::<Result<Vec<Token>, SyntaxError>>().unwrap());
+    let long_source = "name = \"value\" + 1.5 // comment\n".repeat(1000);
+    assert_eq!(
+        reader.parse_with_comments(&long_source).unwrap(),
+        reader.read_tokens(long_source.as_bytes()).collect::<Result<Vec<Token>, SyntaxError>>().unwrap(),
+    );
+
+    let mut tokens = reader.read_tokens(&b"a = 1\n\"b\xff\""[..]);
+    assert_eq!(Token::Identifier { name: Symbol::from("a"), pos: 0 }, tokens.next().unwrap().unwrap());
+    let error = tokens.find_map(Result::err).unwrap();
+    assert_eq!((8, "The source is not valid UTF-8"), (error.pos, error.message.as_str()));
+    assert!(tokens.next().is_none());
+    let error = reader.read_tokens(&b"a\xe2\x82"[..]).find_map(Result::err).unwrap();
+    assert_eq!((1, "The source is not valid UTF-8"), (error.pos, error.message.as_str()));
+}
+
+/// Testing that the sequences of the operator symbols which are not the operators are not read.
+#[test]
+fn test_unknown_operators() {
+    let reader = TokenReader::new();
+    for (source, pos, operator) in [("a +== 1", 2, "+=="), ("a ! b", 2, "!"), ("x ->= 1", 2, "->="), ("1 ... 3", 2, "...")].iter() {
+        let error = reader.parse(source).unwrap_err();
+        assert_eq!((*pos, format!("Unknown operator '{}'", operator)), (error.pos, error.message));
+    }
+    assert_eq!(vec!(Token::Operator { payload: Operator::InclusiveRange, pos: 1 }), reader.parse(" ..= ").unwrap());
+    // the backslash is not the operator symbol at all
+    assert_eq!((2, String::from("Unexpected symbol '\\'")), reader.parse("a \\ b").map_err(|e| (e.pos, e.message)).unwrap_err());
+}
+
+/// Testing that the tokens with the same text share it instead of copying it from the source again.
+#[test]
+fn test_repeated_texts_are_shared() {
+    let tokens = TokenReader::new().parse_with_comments("x = \"x\"\nx(x) // x\n// x").unwrap();
+    let texts: Vec<&str> = tokens.iter().filter_map(|token| match token {
+        Token::Identifier { name, .. } | Token::Function { name, .. } => Some(name.as_str()),
+        Token::StringConstant { value, .. } => Some(value.as_str()),
+        Token::Comment { text, .. } => Some(text.as_str()),
+        _ => None,
+    }).collect();
+    assert_eq!(vec!("x", "x", "x", "x", "// x", "// x"), texts);
+    assert!(texts[..4].iter().all(|text| std::ptr::eq(*text, texts[0])));
+    assert!(std::ptr::eq(texts[4], texts[5]));
+}
+
+/// Testing that the texts kept for sharing are limited, so the long source with the distinct names may be read by chunks.
+#[test]
+fn test_shared_texts_are_limited() {
+    let source: String = (0..20_000).map(|index| format!("name{} = {}\n", index, index)).collect();
+    let mut tokens = Tokens::new(ReadInput { reader: source.as_bytes(), text: String::new(), base: 0, next: 0, undecoded: vec!() });
+    let mut count = 0;
+    while let Some(token) = tokens.next() {
+        token.unwrap();
+        assert!(tokens.symbols.size <= MAX_SYMBOLS_SIZE);
+        count += 1;
+    }
+    assert_eq!(80_000, count);
+    let name = Symbols::default().intern("name");
+    std::thread::spawn(move || assert_eq!("name", name.as_str())).join().unwrap();
+}
@@ -1,6 +1,23 @@
-use crate::dto::{ Node, Token };
+use crate::dto::{ Node, NodeType, Operator, Symbol, Token, SyntaxError, StackGuard, MAX_DEPTH };
+use crate::dto::stack::{ has_stack, DEFAULT_STACK_SIZE };
+use crate::parser::RpnTreeBuilder;
 use std::cell::Cell;
+#[cfg(test)]
+use crate::parser::{ parse, try_parse };
 
+/// Converts the list of tokens from [`TokenReader`] into the syntax tree.
+///
+/// Statements and blocks are read by the recursive descent, while the operator
+/// expressions are delegated to the [`RpnTreeBuilder`].
+///
+/// The parser needs all the tokens of the source in the slice: it looks ahead of the statement, e.g. for the test
+/// declaration, and back to the first token of the node for its extent. Only the lexer is streaming,
+/// so the tokens of [`TokenReader::tokens`] or [`TokenReader::read_tokens`] are collected before the parsing.
+///
+/// [`TokenReader`]: crate::parser::TokenReader
+/// [`RpnTreeBuilder`]: crate::parser::RpnTreeBuilder
+/// [`TokenReader::tokens`]: crate::parser::TokenReader::tokens
+/// [`TokenReader::read_tokens`]: crate::parser::TokenReader::read_tokens
 #[derive(Debug)]
 pub struct TreeBuilder {
     state: Cell<State>,
@@ -8,28 +25,1395 @@ pub struct TreeBuilder {
 
 #[derive(Copy, Clone, Debug)]
 struct State {
-    reading: Reading,
+    position: usize,
+    /// Number of the blocks, expressions and patterns being read, see [`TreeBuilder::nested`].
+    depth: usize,
 }
 
-#[derive(Copy, Clone, Debug)]
-enum Reading {
-    Nothing,
-}
+impl Default for TreeBuilder {
 
-#[derive(Debug)]
-pub struct SyntaxError { pub pos: usize, pub message: String }
+    fn default() -> TreeBuilder {
+        TreeBuilder::new()
+    }
+}
 
 impl TreeBuilder {
 
     pub fn new() -> TreeBuilder {
         TreeBuilder {
             state: Cell::from(State {
-                reading: Reading::Nothing,
+                position: 0,
+                depth: 0,
             })
         }
     }
 
-    pub fn build_tree(&self, _: &Vec<Token>) -> Result<Node, SyntaxError> {
-        todo!()
+    /// Creates the root `NodeType::Block` node from the list of tokens.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// # use avoc::internals::{ NodeType, TokenReader, TreeBuilder };
+    /// let tokens = TokenReader::new().parse("a = 2 + 2").unwrap();
+    /// let tree = TreeBuilder::new().build_tree(&tokens).unwrap();
+    ///
+    /// assert_eq!(NodeType::Block, tree.node_type);
+    /// ```
+    pub fn build_tree(&self, tokens: &[Token]) -> Result<Node, SyntaxError> {
+        let _guard = StackGuard::new(DEFAULT_STACK_SIZE);
+        self.state.set(State { position: 0, depth: 0 });
+        let mut root = Node::new(None, NodeType::Block);
+        loop {
+            self.skip_new_lines(tokens);
+            match tokens.get(self.position()) {
+                Some(_) => root.add_child(self.read_spanned(tokens, || self.read_root_statement(tokens))?),
+                None => break,
+            }
+        }
+        // the statements are nested without the expressions, e.g. in the loops, so only the whole tree has the exact depth
+        root.depth()?;
+        Ok(root)
+    }
+
+    #[inline]
+    fn position(&self) -> usize {
+        self.state.get().position
+    }
+
+    #[inline]
+    fn peek<'a>(&self, tokens: &'a [Token]) -> Option<&'a Token> {
+        tokens.get(self.position())
+    }
+
+    #[inline]
+    fn advance(&self) {
+        self.state.set(State { position: self.position() + 1, ..self.state.get() })
+    }
+
+    /// Reads the nested construction with the `read` function.
+    /// The constructions nested deeper than [`MAX_DEPTH`] are the error, because they are read recursively,
+    /// and so are the shallower ones when the stack is used up, e.g. in the debug build.
+    fn nested<T>(&self, tokens: &[Token], read: impl FnOnce() -> Result<T, SyntaxError>) -> Result<T, SyntaxError> {
+        let depth = self.state.get().depth;
+        if depth == MAX_DEPTH {
+            return Err(SyntaxError::too_deep(self.end_pos(tokens)))
+        }
+        if !has_stack() {
+            return Err(SyntaxError::no_stack(self.end_pos(tokens)))
+        }
+        self.state.set(State { depth: depth + 1, ..self.state.get() });
+        let result = read();
+        self.state.set(State { depth, ..self.state.get() });
+        result
+    }
+
+    #[inline]
+    fn skip_new_lines(&self, tokens: &[Token]) {
+        while let Some(Token::NewLine { .. }) = self.peek(tokens) {
+            self.advance()
+        }
+    }
+
+    /// Reads the node with the `read` function and records the offsets of the first and the last tokens it has read,
+    /// so the node covers its keywords and closing brackets. The expression in the parentheses covers them too.
+    fn read_spanned(&self, tokens: &[Token], read: impl FnOnce() -> Result<Node, SyntaxError>) -> Result<Node, SyntaxError> {
+        let start = self.position();
+        let mut node = read()?;
+        if self.position() > start {
+            node.extent = Some((tokens[start].pos(), tokens[self.position() - 1].pos()))
+        }
+        Ok(node)
+    }
+
+    /// Offset for the errors at the end of the source: right after the last token.
+    fn end_pos(&self, tokens: &[Token]) -> usize {
+        match self.peek(tokens) {
+            Some(token) => token.pos(),
+            None => tokens.last().map(|token| token.pos() + 1).unwrap_or(0),
+        }
+    }
+
+    fn expect_operator(&self, tokens: &[Token], expected: Operator) -> Result<Token, SyntaxError> {
+        match self.peek(tokens) {
+            Some(token) if token.is_operator(expected) => {
+                self.advance();
+                Ok(token.clone())
+            },
+            _ => Err(SyntaxError::new(self.end_pos(tokens), format!("Expected '{}'", expected))),
+        }
+    }
+
+    /// Advances past the operator if it is the next token.
+    fn skip_operator(&self, tokens: &[Token], operator: Operator) -> bool {
+        match self.peek(tokens) {
+            Some(token) if token.is_operator(operator) => {
+                self.advance();
+                true
+            },
+            _ => false,
+        }
+    }
+
+    /// Imports and public declarations are allowed only in the root block of the module.
+    fn read_root_statement(&self, tokens: &[Token]) -> Result<Node, SyntaxError> {
+        match self.peek(tokens) {
+            Some(token) if token.is_operator(Operator::Import) || token.is_operator(Operator::From) => {
+                let node = self.read_import(tokens)?;
+                self.expect_end_of_statement(tokens)?;
+                Ok(node)
+            },
+            Some(_) if self.is_test_ahead(tokens) => {
+                let node = self.read_test(tokens)?;
+                self.expect_end_of_statement(tokens)?;
+                Ok(node)
+            },
+            Some(token) if token.is_operator(Operator::Pub) => {
+                let mut node = Node::new(Some(token.clone()), NodeType::Public);
+                self.advance();
+                let declaration = self.read_spanned(tokens, || self.read_statement(tokens))?;
+                let target = match declaration.children.first() {
+                    Some(target) if target.operator() == Some(Operator::Mut) => target.children.first(),
+                    target => target,
+                };
+                let is_variable = declaration.operator() == Some(Operator::Assign)
+                    && matches!(target.and_then(|target| target.data.as_ref()), Some(Token::Identifier { .. }));
+                match declaration.node_type {
+                    NodeType::Function | NodeType::Struct | NodeType::Enum => (),
+                    _ if is_variable => (),
+                    _ => return Err(SyntaxError::new(node.pos(), String::from("Only functions, structs, enums and variables can be public"))),
+                }
+                node.add_child(declaration);
+                Ok(node)
+            },
+            _ => self.read_statement(tokens),
+        }
+    }
+
+    /// Reads `import a.b` or `from a.b import c, d` into the `NodeType::Import` node.
+    ///
+    /// The parts of the module path go to the `children`, the imported names go to the `condition`.
+    fn read_import(&self, tokens: &[Token]) -> Result<Node, SyntaxError> {
+        let mut node = Node::new(self.peek(tokens).cloned(), NodeType::Import);
+        let is_from = self.skip_operator(tokens, Operator::From);
+        if !is_from {
+            self.expect_operator(tokens, Operator::Import)?;
+        }
+        loop {
+            match self.peek(tokens) {
+                Some(token @ Token::Identifier { .. }) => {
+                    node.add_child(Node::from(token.clone()));
+                    self.advance()
+                },
+                _ => return Err(SyntaxError::new(self.end_pos(tokens), String::from("Expected module name"))),
+            };
+            if !self.skip_operator(tokens, Operator::Dot) {
+                break
+            }
+        }
+        if !is_from {
+            return Ok(node)
+        }
+        self.expect_operator(tokens, Operator::Import)?;
+        loop {
+            match self.peek(tokens) {
+                Some(token @ Token::Identifier { .. }) => {
+                    node.add_condition_child(Node::from(token.clone()));
+                    self.advance()
+                },
+                _ => return Err(SyntaxError::new(self.end_pos(tokens), String::from("Expected imported name"))),
+            };
+            if !self.skip_operator(tokens, Operator::Comma) {
+                return Ok(node)
+            }
+        }
+    }
+
+    /// `test` is not a keyword, it starts the test only when it is followed by the name of the test.
+    fn is_test_ahead(&self, tokens: &[Token]) -> bool {
+        match (self.peek(tokens), tokens.get(self.position() + 1)) {
+            (Some(Token::Identifier { name, .. }), Some(Token::StringConstant { .. })) => name == "test",
+            _ => false,
+        }
+    }
+
+    /// Reads `test "name" { ... }` into the `NodeType::Test` node, the name is its token and the block is its child.
+    fn read_test(&self, tokens: &[Token]) -> Result<Node, SyntaxError> {
+        self.advance();
+        let mut node = Node::new(self.peek(tokens).cloned(), NodeType::Test);
+        self.advance();
+        node.add_child(self.read_block(tokens)?);
+        Ok(node)
+    }
+
+    /// Statement is a loop, a loop control, a function declaration or any expression
+    /// terminated by the new line, `}` or the end of the source.
+    fn read_statement(&self, tokens: &[Token]) -> Result<Node, SyntaxError> {
+        let statement = match self.peek(tokens) {
+            Some(Token::Operator { payload, pos }) if matches!(payload, Operator::Import | Operator::From | Operator::Pub) =>
+                return Err(SyntaxError::new(*pos, format!("'{}' is allowed only at the top level of the module", payload))),
+            Some(token) if self.is_test_ahead(tokens) =>
+                return Err(SyntaxError::new(token.pos(), String::from("'test' is allowed only at the top level of the module"))),
+            Some(token) if token.is_operator(Operator::Fn) => self.read_function(tokens)?,
+            Some(token) if token.is_operator(Operator::Struct) => self.read_struct(tokens)?,
+            Some(token) if token.is_operator(Operator::Enum) => self.read_enum(tokens)?,
+            Some(token) if token.is_operator(Operator::For) => self.read_for(tokens)?,
+            Some(token) if token.is_operator(Operator::Loop) => {
+                let mut node = Node::new(Some(token.clone()), NodeType::Loop);
+                self.advance();
+                node.add_child(self.read_block(tokens)?);
+                node
+            },
+            Some(token) if token.is_operator(Operator::Break) => {
+                self.advance();
+                Node::new(Some(token.clone()), NodeType::Break)
+            },
+            Some(token) if token.is_operator(Operator::Continue) => {
+                self.advance();
+                Node::new(Some(token.clone()), NodeType::Continue)
+            },
+            // `return` without the value returns `None`
+            Some(token) if token.is_operator(Operator::Return) => {
+                let mut node = Node::new(Some(token.clone()), NodeType::Return);
+                self.advance();
+                match self.peek(tokens) {
+                    None | Some(Token::NewLine { .. }) => (),
+                    Some(token) if token.is_operator(Operator::RightBrace) => (),
+                    Some(_) => node.add_child(self.read_expression(tokens)?),
+                };
+                node
+            },
+            _ => self.read_expression(tokens)?,
+        };
+        self.expect_end_of_statement(tokens)?;
+        Ok(statement)
+    }
+
+    /// Statement ends at the new line, at `}` or at the end of the source.
+    fn expect_end_of_statement(&self, tokens: &[Token]) -> Result<(), SyntaxError> {
+        match self.peek(tokens) {
+            None | Some(Token::NewLine { .. }) => Ok(()),
+            Some(token) if token.is_operator(Operator::RightBrace) => Ok(()),
+            Some(token) => Err(SyntaxError::new(token.pos(), String::from("Expected new line after the statement"))),
+        }
+    }
+
+    /// Reads `{ statement* }` into the `NodeType::Block` node.
+    fn read_block(&self, tokens: &[Token]) -> Result<Node, SyntaxError> {
+        let block = Node::new(Some(self.expect_operator(tokens, Operator::LeftBrace)?), NodeType::Block);
+        self.read_statements_until_closing_brace(tokens, block)
+    }
+
+    /// Adds statements to the block until the `}`, the opening brace must be already read.
+    fn read_statements_until_closing_brace(&self, tokens: &[Token], mut block: Node) -> Result<Node, SyntaxError> {
+        self.nested(tokens, || {
+            loop {
+                self.skip_new_lines(tokens);
+                match self.peek(tokens) {
+                    Some(token) if token.is_operator(Operator::RightBrace) => {
+                        block.extent = Some((block.pos(), token.pos()));
+                        self.advance();
+                        return Ok(block)
+                    },
+                    Some(_) => block.add_child(self.read_spanned(tokens, || self.read_statement(tokens))?),
+                    None => return Err(SyntaxError::new(block.pos(), String::from("This block has no matching closing brace"))),
+                }
+            }
+        })
+    }
+
+    /// Reads `if condition { ... } else if condition { ... } else { ... }`.
+    ///
+    /// The condition goes to the `condition` of the `NodeType::If` node,
+    /// the `then` block and the optional `else` branch go to its `children`.
+    fn read_if(&self, tokens: &[Token]) -> Result<Node, SyntaxError> {
+        let mut node = Node::new(Some(self.expect_operator(tokens, Operator::If)?), NodeType::If);
+        node.add_condition_child(self.read_expression(tokens)?);
+        node.add_child(self.read_block(tokens)?);
+        // `else` may be placed on the next line after the closing brace
+        let position = self.position();
+        self.skip_new_lines(tokens);
+        match self.peek(tokens) {
+            Some(token) if token.is_operator(Operator::Else) => {
+                self.advance();
+                match self.peek(tokens) {
+                    Some(token) if token.is_operator(Operator::If) => node.add_child(self.read_spanned(tokens, || self.read_if(tokens))?),
+                    _ => node.add_child(self.read_block(tokens)?),
+                }
+            },
+            _ => self.state.set(State { position, ..self.state.get() }),
+        };
+        Ok(node)
+    }
+
+    /// Reads `for name in iterable { ... }`.
+    ///
+    /// The iterable goes to the `condition` of the `NodeType::For` node,
+    /// the loop variable and the body go to its `children`.
+    fn read_for(&self, tokens: &[Token]) -> Result<Node, SyntaxError> {
+        let mut node = Node::new(Some(self.expect_operator(tokens, Operator::For)?), NodeType::For);
+        match self.peek(tokens) {
+            Some(token @ Token::Identifier { .. }) => {
+                node.add_child(Node::from(token.clone()));
+                self.advance()
+            },
+            _ => return Err(SyntaxError::new(self.end_pos(tokens), String::from("Expected loop variable name"))),
+        };
+        self.expect_operator(tokens, Operator::In)?;
+        node.add_condition_child(self.read_expression(tokens)?);
+        node.add_child(self.read_block(tokens)?);
+        Ok(node)
+    }
+
+    /// Reads `{ @ name, name = default -> statements }` into the `NodeType::Lambda` node.
+    ///
+    /// Parameters go to the `condition` of the node, each of them has the default value
+    /// expression as a child if it is specified. The body block goes to the `children`.
+    fn read_lambda(&self, tokens: &[Token]) -> Result<Node, SyntaxError> {
+        let mut node = Node::new(Some(self.expect_operator(tokens, Operator::LeftBrace)?), NodeType::Lambda);
+        self.skip_new_lines(tokens);
+        self.expect_operator(tokens, Operator::At)?;
+        loop {
+            match self.peek(tokens) {
+                Some(token) if token.is_operator(Operator::Arrow) => break,
+                Some(token @ Token::Identifier { .. }) => {
+                    let mut parameter = Node::from(token.clone());
+                    self.advance();
+                    if let Some(token) = self.peek(tokens) {
+                        if token.is_operator(Operator::Assign) {
+                            self.advance();
+                            parameter.add_child(self.read_expression(tokens)?)
+                        }
+                    }
+                    node.add_condition_child(parameter);
+                    match self.peek(tokens) {
+                        Some(token) if token.is_operator(Operator::Comma) => self.advance(),
+                        Some(token) if token.is_operator(Operator::Arrow) => (),
+                        _ => return Err(SyntaxError::new(self.end_pos(tokens), String::from("Expected ',' or '->' after the lambda parameter"))),
+                    }
+                },
+                _ => return Err(SyntaxError::new(self.end_pos(tokens), String::from("Expected lambda parameter name or '->'"))),
+            }
+        }
+        let body = Node::new(Some(self.expect_operator(tokens, Operator::Arrow)?), NodeType::Block);
+        node.add_child(self.read_statements_until_closing_brace(tokens, body)?);
+        Ok(node)
+    }
+
+    /// Reads `fn name(name: Type, name = default) -> Type { ... }` into the `NodeType::Function` node.
+    ///
+    /// The name is the data of the node. Parameters go to the `condition` like the parameters of the lambda,
+    /// the optional type of the parameter is the `condition` of its node.
+    /// The body block and the optional return type go to the `children`.
+    fn read_function(&self, tokens: &[Token]) -> Result<Node, SyntaxError> {
+        self.expect_operator(tokens, Operator::Fn)?;
+        let name = match self.peek(tokens) {
+            Some(Token::Function { name, pos }) => Token::Identifier { name: name.clone(), pos: *pos },
+            _ => return Err(SyntaxError::new(self.end_pos(tokens), String::from("Expected function name"))),
+        };
+        self.advance();
+        let mut node = Node::new(Some(name), NodeType::Function);
+        let open = self.expect_operator(tokens, Operator::LeftParen)?;
+        loop {
+            self.skip_new_lines(tokens);
+            match self.peek(tokens) {
+                Some(token) if token.is_operator(Operator::RightParen) => {
+                    self.advance();
+                    break
+                },
+                Some(token @ Token::Identifier { .. }) => {
+                    let mut parameter = Node::from(token.clone());
+                    self.advance();
+                    if self.skip_operator(tokens, Operator::Colon) {
+                        parameter.add_condition_child(self.read_type(tokens)?)
+                    }
+                    if self.skip_operator(tokens, Operator::Assign) {
+                        parameter.add_child(self.read_expression(tokens)?)
+                    }
+                    node.add_condition_child(parameter);
+                    self.skip_new_lines(tokens);
+                    match self.peek(tokens) {
+                        Some(token) if token.is_operator(Operator::Comma) => self.advance(),
+                        Some(token) if token.is_operator(Operator::RightParen) => (),
+                        _ => return Err(SyntaxError::new(open.pos(), String::from("This bracket has no matching ')'"))),
+                    }
+                },
+                _ => return Err(SyntaxError::new(self.end_pos(tokens), String::from("Expected parameter name"))),
+            }
+        }
+        let return_type = match self.skip_operator(tokens, Operator::Arrow) {
+            true => Some(self.read_type(tokens)?),
+            false => None,
+        };
+        node.add_child(self.read_block(tokens)?);
+        if let Some(return_type) = return_type {
+            node.add_child(return_type)
+        }
+        Ok(node)
     }
+
+    /// Reads `struct Name { field: Type, mut field }` into the `NodeType::Struct` node.
+    ///
+    /// The name is the data of the node, fields go to the `children`. Each field is the identifier
+    /// with the optional type in the `condition`, the mutable field is wrapped into the `mut` expression.
+    /// Fields are separated by commas or new lines.
+    fn read_struct(&self, tokens: &[Token]) -> Result<Node, SyntaxError> {
+        self.expect_operator(tokens, Operator::Struct)?;
+        let mut node = match self.peek(tokens) {
+            Some(token @ Token::Identifier { .. }) => Node::new(Some(token.clone()), NodeType::Struct),
+            _ => return Err(SyntaxError::new(self.end_pos(tokens), String::from("Expected struct name"))),
+        };
+        self.advance();
+        let open = self.expect_operator(tokens, Operator::LeftBrace)?;
+        loop {
+            self.skip_new_lines(tokens);
+            let mutable = match self.peek(tokens) {
+                Some(token) if token.is_operator(Operator::RightBrace) => {
+                    self.advance();
+                    return Ok(node)
+                },
+                Some(token) if token.is_operator(Operator::Comma) => {
+                    self.advance();
+                    continue
+                },
+                Some(token) if token.is_operator(Operator::Mut) => {
+                    self.advance();
+                    Some(token.clone())
+                },
+                Some(_) => None,
+                None => return Err(SyntaxError::new(open.pos(), String::from("This block has no matching closing brace"))),
+            };
+            let mut field = match self.peek(tokens) {
+                Some(token @ Token::Identifier { .. }) => Node::from(token.clone()),
+                _ => return Err(SyntaxError::new(self.end_pos(tokens), String::from("Expected field name"))),
+            };
+            self.advance();
+            if self.skip_operator(tokens, Operator::Colon) {
+                field.add_condition_child(self.read_type(tokens)?)
+            }
+            node.add_child(match mutable {
+                Some(token) => {
+                    let mut wrapper = Node::new(Some(token), NodeType::Expression);
+                    wrapper.add_child(field);
+                    wrapper
+                },
+                None => field,
+            });
+        }
+    }
+
+    /// Reads `enum Name { Variant(field, field: Type), Variant }` into the `NodeType::Enum` node.
+    ///
+    /// The name is the data of the node, `NodeType::Variant` nodes go to the `children`
+    /// with their fields as children. Variants are separated by commas or new lines.
+    fn read_enum(&self, tokens: &[Token]) -> Result<Node, SyntaxError> {
+        self.expect_operator(tokens, Operator::Enum)?;
+        let mut node = match self.peek(tokens) {
+            Some(token @ Token::Identifier { .. }) => Node::new(Some(token.clone()), NodeType::Enum),
+            _ => return Err(SyntaxError::new(self.end_pos(tokens), String::from("Expected enum name"))),
+        };
+        self.advance();
+        let open = self.expect_operator(tokens, Operator::LeftBrace)?;
+        loop {
+            self.skip_new_lines(tokens);
+            let (name, pos, has_fields) = match self.peek(tokens) {
+                Some(token) if token.is_operator(Operator::RightBrace) => {
+                    self.advance();
+                    return Ok(node)
+                },
+                Some(token) if token.is_operator(Operator::Comma) => {
+                    self.advance();
+                    continue
+                },
+                Some(Token::Identifier { name, pos }) => (name.clone(), *pos, false),
+                Some(Token::Function { name, pos }) => (name.clone(), *pos, true),
+                Some(token) => return Err(SyntaxError::new(token.pos(), String::from("Expected variant name"))),
+                None => return Err(SyntaxError::new(open.pos(), String::from("This block has no matching closing brace"))),
+            };
+            self.advance();
+            let mut variant = Node::new(Some(Token::Identifier { name, pos }), NodeType::Variant);
+            if has_fields {
+                let open = self.expect_operator(tokens, Operator::LeftParen)?;
+                loop {
+                    self.skip_new_lines(tokens);
+                    match self.peek(tokens) {
+                        Some(token) if token.is_operator(Operator::RightParen) => {
+                            self.advance();
+                            break
+                        },
+                        Some(token @ Token::Identifier { .. }) => {
+                            let mut field = Node::from(token.clone());
+                            self.advance();
+                            if self.skip_operator(tokens, Operator::Colon) {
+                                field.add_condition_child(self.read_type(tokens)?)
+                            }
+                            variant.add_child(field);
+                            self.skip_new_lines(tokens);
+                            if !self.skip_operator(tokens, Operator::Comma) && !matches!(self.peek(tokens), Some(token) if token.is_operator(Operator::RightParen)) {
+                                return Err(SyntaxError::new(open.pos(), String::from("This bracket has no matching ')'")))
+                            }
+                        },
+                        _ => return Err(SyntaxError::new(self.end_pos(tokens), String::from("Expected field name"))),
+                    }
+                }
+            }
+            node.add_child(variant)
+        }
+    }
+
+    /// Reads the name of the type: `Int`, `String`, `None`, etc.
+    fn read_type(&self, tokens: &[Token]) -> Result<Node, SyntaxError> {
+        let name = match self.peek(tokens) {
+            Some(token @ Token::Identifier { .. }) => token.clone(),
+            Some(Token::NoneConstant { pos }) => Token::Identifier { name: Symbol::from("None"), pos: *pos },
+            _ => return Err(SyntaxError::new(self.end_pos(tokens), String::from("Expected type name"))),
+        };
+        self.advance();
+        Ok(Node::new(Some(name), NodeType::Type))
+    }
+
+    /// Reads `name(arg, arg, ...)` into the `NodeType::Call` node with arguments as children.
+    ///
+    /// The lambda right after the call is the last argument: `name(a){ @ x -> x }` or `name{ @ x -> x }`,
+    /// but the name followed by an ordinary block (`if a > b{`) is just a variable.
+    fn read_call(&self, tokens: &[Token]) -> Result<Node, SyntaxError> {
+        let mut node = Node::new(self.peek(tokens).cloned(), NodeType::Call);
+        self.advance();
+        if self.is_lambda_ahead(tokens) {
+            node.add_child(self.read_spanned(tokens, || self.read_lambda(tokens))?);
+            return Ok(node)
+        }
+        if let Some(token) = self.peek(tokens) {
+            if token.is_operator(Operator::LeftBrace) {
+                return match node.data {
+                    Some(Token::Function { name, pos }) => Ok(Node::from(Token::Identifier { name, pos })),
+                    _ => Ok(node),
+                }
+            }
+        }
+        let open = self.expect_operator(tokens, Operator::LeftParen)?;
+        for argument in self.read_comma_separated(tokens, &open, Operator::RightParen)? {
+            node.add_child(argument)
+        }
+        if self.is_lambda_ahead(tokens) {
+            node.add_child(self.read_spanned(tokens, || self.read_lambda(tokens))?)
+        }
+        Ok(node)
+    }
+
+    /// Reads expressions separated by commas until the closing bracket. The opening bracket must be already read.
+    ///
+    /// New lines between the brackets are ignored and the trailing comma is allowed.
+    fn read_comma_separated(&self, tokens: &[Token], open: &Token, close: Operator) -> Result<Vec<Node>, SyntaxError> {
+        let mut items = vec!();
+        loop {
+            self.skip_new_lines(tokens);
+            match self.peek(tokens) {
+                Some(token) if token.is_operator(close) => {
+                    self.advance();
+                    return Ok(items)
+                },
+                Some(_) => items.push(self.read_expression(tokens)?),
+                None => (),
+            }
+            self.skip_new_lines(tokens);
+            match self.peek(tokens) {
+                Some(token) if token.is_operator(Operator::Comma) => self.advance(),
+                Some(token) if token.is_operator(close) => (),
+                _ => return Err(SyntaxError::new(open.pos(), format!("This bracket has no matching '{}'", close))),
+            }
+        }
+    }
+
+    /// Reads `{key: value, key: value}` into the `NodeType::Map` node, the opening brace must be already read.
+    ///
+    /// Keys and values go to the `children` one after another. New lines between the braces are ignored.
+    fn read_map(&self, tokens: &[Token], open: Token) -> Result<Node, SyntaxError> {
+        let mut node = Node::new(Some(open.clone()), NodeType::Map);
+        loop {
+            self.skip_new_lines(tokens);
+            match self.peek(tokens) {
+                Some(token) if token.is_operator(Operator::RightBrace) => {
+                    self.advance();
+                    return Ok(node)
+                },
+                Some(_) => {
+                    node.add_child(self.read_expression_until(tokens, Some(Operator::Colon))?);
+                    self.expect_operator(tokens, Operator::Colon)?;
+                    self.skip_new_lines(tokens);
+                    node.add_child(self.read_expression(tokens)?)
+                },
+                None => (),
+            }
+            self.skip_new_lines(tokens);
+            match self.peek(tokens) {
+                Some(token) if token.is_operator(Operator::Comma) => self.advance(),
+                Some(token) if token.is_operator(Operator::RightBrace) => (),
+                _ => return Err(SyntaxError::new(open.pos(), String::from("This brace has no matching '}'"))),
+            }
+        }
+    }
+
+    /// Lambda starts with `{ @`, any other `{` is the beginning of the block.
+    #[inline]
+    fn is_lambda_ahead(&self, tokens: &[Token]) -> bool {
+        match (self.peek(tokens), tokens.get(self.position() + 1)) {
+            (Some(token), Some(next)) => token.is_operator(Operator::LeftBrace) && next.is_operator(Operator::At),
+            _ => false,
+        }
+    }
+
+    /// Reads `( expression )` or the tuple `(a, b, c)`. New lines inside of parentheses are ignored.
+    ///
+    /// Tuple with a single item must have the trailing comma: `(a,)`, and `()` is the empty tuple.
+    fn read_group(&self, tokens: &[Token]) -> Result<Node, SyntaxError> {
+        let open = self.expect_operator(tokens, Operator::LeftParen)?;
+        self.skip_new_lines(tokens);
+        if let Some(token) = self.peek(tokens) {
+            if token.is_operator(Operator::RightParen) {
+                self.advance();
+                return Ok(Node::new(Some(open), NodeType::Tuple))
+            }
+        }
+        let node = self.read_expression(tokens)?;
+        self.skip_new_lines(tokens);
+        match self.peek(tokens) {
+            Some(token) if token.is_operator(Operator::RightParen) => {
+                self.advance();
+                Ok(node)
+            },
+            Some(token) if token.is_operator(Operator::Comma) => {
+                self.advance();
+                let mut tuple = Node::new(Some(open.clone()), NodeType::Tuple);
+                tuple.add_child(node);
+                for item in self.read_comma_separated(tokens, &open, Operator::RightParen)? {
+                    tuple.add_child(item)
+                }
+                Ok(tuple)
+            },
+            _ => Err(SyntaxError::new(open.pos(), String::from("This parenthesis has no matching closing parenthesis"))),
+        }
+    }
+
+    /// Reads `match value { pattern -> result ... }` into the `NodeType::Match` node.
+    ///
+    /// The matched value goes to the `condition`, arms go to the `children`.
+    /// Each `NodeType::Arm` has the pattern and the optional guard in the `condition`
+    /// and the result expression or block in the `children`.
+    fn read_match(&self, tokens: &[Token]) -> Result<Node, SyntaxError> {
+        let mut node = Node::new(Some(self.expect_operator(tokens, Operator::Match)?), NodeType::Match);
+        node.add_condition_child(self.read_expression(tokens)?);
+        let open = self.expect_operator(tokens, Operator::LeftBrace)?;
+        loop {
+            self.skip_new_lines(tokens);
+            match self.peek(tokens) {
+                Some(token) if token.is_operator(Operator::RightBrace) => {
+                    self.advance();
+                    return Ok(node)
+                },
+                Some(token) if token.is_operator(Operator::Comma) => self.advance(),
+                Some(_) => node.add_child(self.read_spanned(tokens, || self.read_arm(tokens))?),
+                None => return Err(SyntaxError::new(open.pos(), String::from("This block has no matching closing brace"))),
+            }
+        }
+    }
+
+    fn read_arm(&self, tokens: &[Token]) -> Result<Node, SyntaxError> {
+        let pattern = self.read_spanned(tokens, || self.read_pattern(tokens))?;
+        let guard = match self.peek(tokens) {
+            Some(token) if token.is_operator(Operator::If) => {
+                self.advance();
+                Some(self.read_expression(tokens)?)
+            },
+            _ => None,
+        };
+        let mut arm = Node::new(Some(self.expect_operator(tokens, Operator::Arrow)?), NodeType::Arm);
+        arm.add_condition_child(pattern);
+        if let Some(guard) = guard {
+            arm.add_condition_child(guard)
+        }
+        match self.peek(tokens) {
+            Some(token) if token.is_operator(Operator::LeftBrace) && !self.is_lambda_ahead(tokens) => arm.add_child(self.read_block(tokens)?),
+            _ => arm.add_child(self.read_expression(tokens)?),
+        };
+        Ok(arm)
+    }
+
+    /// Reads the pattern of the match arm.
+    ///
+    /// # Patterns
+    /// - `1`, `-2.5`, `"text"`, `true`, `None` literals
+    /// - `1..10`, `..=0`, `"a"..="z"` ranges with literal bounds
+    /// - `_` wildcard and `name` binding
+    /// - `(a, _)` tuples and `[first, ..rest]` lists
+    /// - `Circle(r)`, `Shape.Empty` enum variants and `Point(x, y)` structs
+    ///
+    /// Names of types and variants start with the uppercase letter, so `Empty` is the variant, not the binding.
+    fn read_pattern(&self, tokens: &[Token]) -> Result<Node, SyntaxError> {
+        let token = match self.peek(tokens) {
+            Some(token) => token.clone(),
+            None => return Err(SyntaxError::new(self.end_pos(tokens), String::from("Expected pattern"))),
+        };
+        match &token {
+            Token::Identifier { name, .. } | Token::Function { name, .. } if is_type_name(name) => self.read_variant_pattern(tokens),
+            Token::Identifier { .. } => {
+                self.advance();
+                Ok(Node::from(token))
+            },
+            Token::Operator { payload: Operator::LeftParen, .. } => {
+                self.advance();
+                let items = self.read_patterns_until(tokens, &token, Operator::RightParen)?;
+                match (items.len(), self.has_trailing_comma(tokens)) {
+                    (1, false) => Ok(items.into_iter().next().unwrap()),
+                    _ => {
+                        let mut tuple = Node::new(Some(token), NodeType::Tuple);
+                        tuple.children = items;
+                        Ok(tuple)
+                    },
+                }
+            },
+            Token::Operator { payload: Operator::LeftBracket, .. } => {
+                self.advance();
+                let mut list = Node::new(Some(token.clone()), NodeType::List);
+                list.children = self.read_patterns_until(tokens, &token, Operator::RightBracket)?;
+                let rest_count = list.children.iter().filter(|item| item.node_type == NodeType::Rest).count();
+                match list.children.iter().position(|item| item.node_type == NodeType::Rest) {
+                    Some(index) if index + 1 != list.children.len() || rest_count > 1 =>
+                        Err(SyntaxError::new(list.children[index].pos(), String::from("Rest pattern must be the last item of the list"))),
+                    _ => Ok(list),
+                }
+            },
+            Token::Operator { payload: Operator::Range | Operator::InclusiveRange, .. } => {
+                self.advance();
+                let start = Node::from(Token::NoneConstant { pos: token.pos() });
+                let end = self.read_literal_pattern(tokens)?;
+                Ok(range_pattern(token, start, end))
+            },
+            _ => {
+                let start = self.read_literal_pattern(tokens)?;
+                match self.peek(tokens) {
+                    Some(operator) if operator.is_operator(Operator::Range) || operator.is_operator(Operator::InclusiveRange) => {
+                        let operator = operator.clone();
+                        self.advance();
+                        let end = match self.peek(tokens) {
+                            Some(token) if is_literal(token) || token.is_operator(Operator::Minus) => self.read_literal_pattern(tokens)?,
+                            _ => Node::from(Token::NoneConstant { pos: operator.pos() }),
+                        };
+                        Ok(range_pattern(operator, start, end))
+                    },
+                    _ => Ok(start),
+                }
+            },
+        }
+    }
+
+    /// Reads `Variant`, `Variant(patterns)` or the qualified `Enum.Variant(patterns)` into the `NodeType::Variant` node.
+    ///
+    /// The name of the enum goes to the `condition`, patterns of the fields go to the `children`.
+    fn read_variant_pattern(&self, tokens: &[Token]) -> Result<Node, SyntaxError> {
+        let mut qualifier = None;
+        loop {
+            let (name, pos, has_fields) = match self.peek(tokens) {
+                Some(Token::Identifier { name, pos }) if is_type_name(name) => (name.clone(), *pos, false),
+                Some(Token::Function { name, pos }) if is_type_name(name) => (name.clone(), *pos, true),
+                _ => return Err(SyntaxError::new(self.end_pos(tokens), String::from("Expected variant name"))),
+            };
+            self.advance();
+            if qualifier.is_none() && !has_fields && self.skip_operator(tokens, Operator::Dot) {
+                qualifier = Some(Node::from(Token::Identifier { name, pos }));
+                continue
+            }
+            let mut node = Node::new(Some(Token::Identifier { name, pos }), NodeType::Variant);
+            if let Some(qualifier) = qualifier {
+                node.add_condition_child(qualifier)
+            }
+            if has_fields {
+                let open = self.expect_operator(tokens, Operator::LeftParen)?;
+                node.children = self.read_patterns_until(tokens, &open, Operator::RightParen)?;
+            }
+            return Ok(node)
+        }
+    }
+
+    /// Reads patterns separated by commas, the list pattern may contain the rest `..` or `..name`.
+    fn read_patterns_until(&self, tokens: &[Token], open: &Token, close: Operator) -> Result<Vec<Node>, SyntaxError> {
+        self.nested(tokens, || {
+            let mut items = vec!();
+            loop {
+                self.skip_new_lines(tokens);
+                match self.peek(tokens) {
+                    Some(token) if token.is_operator(close) => {
+                        self.advance();
+                        return Ok(items)
+                    },
+                    Some(token) if token.is_operator(Operator::Range) && close == Operator::RightBracket => {
+                        let mut rest = Node::new(Some(token.clone()), NodeType::Rest);
+                        self.advance();
+                        if let Some(name @ Token::Identifier { .. }) = self.peek(tokens) {
+                            rest.add_child(Node::from(name.clone()));
+                            self.advance()
+                        }
+                        items.push(rest)
+                    },
+                    Some(_) => items.push(self.read_spanned(tokens, || self.read_pattern(tokens))?),
+                    None => (),
+                }
+                self.skip_new_lines(tokens);
+                match self.peek(tokens) {
+                    Some(token) if token.is_operator(Operator::Comma) => self.advance(),
+                    Some(token) if token.is_operator(close) => (),
+                    _ => return Err(SyntaxError::new(open.pos(), format!("This bracket has no matching '{}'", close))),
+                }
+            }
+        })
+    }
+
+    /// Checks whether the closing bracket which was just read follows the comma: `(a,)`.
+    #[inline]
+    fn has_trailing_comma(&self, tokens: &[Token]) -> bool {
+        self.position() >= 2 && tokens[self.position() - 2].is_operator(Operator::Comma)
+    }
+
+    /// Reads the literal, negative numbers are allowed: `-1`.
+    fn read_literal_pattern(&self, tokens: &[Token]) -> Result<Node, SyntaxError> {
+        match self.peek(tokens) {
+            Some(token) if is_literal(token) => {
+                self.advance();
+                Ok(Node::from(token.clone()))
+            },
+            Some(Token::Operator { payload: Operator::Minus, pos }) => {
+                let pos = *pos;
+                self.advance();
+                let token = match self.peek(tokens) {
+                    Some(Token::IntConstant { value, .. }) => Token::IntConstant { value: -value, pos },
+                    Some(Token::FloatConstant { value, .. }) => Token::FloatConstant { value: -value, pos },
+                    _ => return Err(SyntaxError::new(pos, String::from("Expected number after '-' in the pattern"))),
+                };
+                self.advance();
+                Ok(Node::from(token))
+            },
+            _ => Err(SyntaxError::new(self.end_pos(tokens), String::from("Expected pattern"))),
+        }
+    }
+
+    /// Reads tokens of the expression while they can be a part of it and builds the tree with [`RpnTreeBuilder`].
+    ///
+    /// The expression ends at the new line, at the opening brace of the block,
+    /// or at any closing bracket or separator which belongs to the outer construction.
+    fn read_expression(&self, tokens: &[Token]) -> Result<Node, SyntaxError> {
+        self.read_expression_until(tokens, None)
+    }
+
+    /// Same as [`read_expression`], but the expression also ends at the `stop` operator, e.g. at `:` after the map key.
+    ///
+    /// [`read_expression`]: TreeBuilder::read_expression
+    fn read_expression_until(&self, tokens: &[Token], stop: Option<Operator>) -> Result<Node, SyntaxError> {
+        self.nested(tokens, || self.read_operators_until(tokens, stop))
+    }
+
+    /// Feeds the operands and the operators of the expression to the [`RpnTreeBuilder`],
+    /// the nested groups, calls, collections and blocks are read recursively and pushed as the operands.
+    fn read_operators_until(&self, tokens: &[Token], stop: Option<Operator>) -> Result<Node, SyntaxError> {
+        let mut builder = RpnTreeBuilder::new();
+        let mut is_operand_expected = true;
+        while let Some(token) = self.peek(tokens) {
+            match token {
+                Token::NewLine { .. } => break,
+                Token::Operator { payload, .. } if !is_operand_expected && stop == Some(*payload) => break,
+                Token::IntConstant { .. }
+                | Token::FloatConstant { .. }
+                | Token::StringConstant { .. }
+                | Token::BoolConstant { .. }
+                | Token::NoneConstant { .. }
+                | Token::Identifier { .. } => {
+                    if !is_operand_expected {
+                        return Err(SyntaxError::new(token.pos(), String::from("Expected operator between operands")))
+                    }
+                    builder.push_token(token.clone())?;
+                    self.advance();
+                    is_operand_expected = false
+                },
+                Token::Function { .. } => {
+                    if !is_operand_expected {
+                        return Err(SyntaxError::new(token.pos(), String::from("Expected operator between operands")))
+                    }
+                    builder.push_node(self.read_spanned(tokens, || self.read_call(tokens))?);
+                    is_operand_expected = false
+                },
+                Token::Operator { payload, pos } => match payload {
+                    Operator::LeftParen if is_operand_expected => {
+                        builder.push_node(self.read_spanned(tokens, || self.read_group(tokens))?);
+                        is_operand_expected = false
+                    },
+                    Operator::LeftBrace if is_operand_expected => {
+                        let node = self.read_spanned(tokens, || match self.is_lambda_ahead(tokens) {
+                            true => self.read_lambda(tokens),
+                            false => {
+                                let open = self.expect_operator(tokens, Operator::LeftBrace)?;
+                                self.read_map(tokens, open)
+                            },
+                        })?;
+                        builder.push_node(node);
+                        is_operand_expected = false
+                    },
+                    Operator::LeftBracket if is_operand_expected => {
+                        let list = self.read_spanned(tokens, || {
+                            let open = self.expect_operator(tokens, Operator::LeftBracket)?;
+                            let mut list = Node::new(Some(open.clone()), NodeType::List);
+                            list.children = self.read_comma_separated(tokens, &open, Operator::RightBracket)?;
+                            Ok(list)
+                        })?;
+                        builder.push_node(list);
+                        is_operand_expected = false
+                    },
+                    // index or slice `xs[i]` is the operator with the index expression as the right operand
+                    Operator::LeftBracket => {
+                        builder.push_token(token.clone())?;
+                        self.advance();
+                        self.skip_new_lines(tokens);
+                        let index = self.read_expression(tokens)?;
+                        self.skip_new_lines(tokens);
+                        match self.peek(tokens) {
+                            Some(close) if close.is_operator(Operator::RightBracket) => builder.notify_closed_index(*pos, close.pos()),
+                            _ => return Err(SyntaxError::new(*pos, String::from("This bracket has no matching ']'"))),
+                        }
+                        self.advance();
+                        builder.push_node(index);
+                    },
+                    Operator::Match if is_operand_expected => {
+                        builder.push_node(self.read_spanned(tokens, || self.read_match(tokens))?);
+                        is_operand_expected = false
+                    },
+                    Operator::If if is_operand_expected => {
+                        builder.push_node(self.read_spanned(tokens, || self.read_if(tokens))?);
+                        is_operand_expected = false
+                    },
+                    Operator::Minus if is_operand_expected => {
+                        builder.push_token(Token::Operator { payload: Operator::UnaryMinus, pos: *pos })?;
+                        self.advance()
+                    },
+                    // open ranges `..end` and `start..` get `None` instead of the omitted bound
+                    Operator::Range | Operator::InclusiveRange => {
+                        if is_operand_expected {
+                            builder.push_node(Node::from(Token::NoneConstant { pos: *pos }))
+                        }
+                        builder.push_token(token.clone())?;
+                        self.advance();
+                        is_operand_expected = self.peek(tokens).map(can_start_operand).unwrap_or(false);
+                        if !is_operand_expected {
+                            builder.push_node(Node::from(Token::NoneConstant { pos: *pos }))
+                        }
+                    },
+                    Operator::Not | Operator::Mut => {
+                        if !is_operand_expected {
+                            return Err(SyntaxError::new(*pos, format!("Unexpected '{}' after the operand", payload)))
+                        }
+                        builder.push_token(token.clone())?;
+                        self.advance()
+                    },
+                    // field name or the call after the `.` is read as the right operand
+                    Operator::Dot => {
+                        if is_operand_expected {
+                            return Err(SyntaxError::new(*pos, String::from("Expected operand before '.'")))
+                        }
+                        builder.push_token(token.clone())?;
+                        self.advance();
+                        match self.peek(tokens) {
+                            Some(name @ Token::Identifier { .. }) => {
+                                builder.push_token(name.clone())?;
+                                self.advance()
+                            },
+                            Some(Token::Function { .. }) => builder.push_node(self.read_spanned(tokens, || self.read_call(tokens))?),
+                            _ => return Err(SyntaxError::new(self.end_pos(tokens), String::from("Expected field name after '.'"))),
+                        };
+                        is_operand_expected = false
+                    },
+                    Operator::Plus | Operator::Minus | Operator::Multiply | Operator::Divide | Operator::Power
+                    | Operator::Less | Operator::LessOrEqual | Operator::Greater | Operator::GreaterOrEqual | Operator::Equal | Operator::NotEqual
+                    | Operator::And | Operator::Or | Operator::Xor | Operator::Is | Operator::In | Operator::Colon
+                    | Operator::Assign | Operator::AddAssign | Operator::SubtractAssign | Operator::MultiplyAssign | Operator::DivideAssign => {
+                        if is_operand_expected {
+                            return Err(SyntaxError::new(*pos, format!("Expected operand before '{}'", payload)))
+                        }
+                        builder.push_token(token.clone())?;
+                        self.advance();
+                        is_operand_expected = true
+                    },
+                    // this token belongs to the outer construction
+                    _ if !is_operand_expected => break,
+                    _ => return Err(SyntaxError::new(*pos, format!("Unexpected '{}'", payload))),
+                },
+                Token::Comment { pos, .. } => return Err(SyntaxError::new(*pos, String::from("Comments must be removed before building the tree"))),
+            }
+        }
+        let pos = self.end_pos(tokens);
+        if is_operand_expected {
+            return Err(SyntaxError::new(pos, String::from("Expected expression")))
+        }
+        builder.notify_met_separator(pos)?;
+        let node = builder.build_tree(pos)?;
+        check_step_ranges(&node)?;
+        Ok(node)
+    }
+}
+
+#[inline]
+fn is_type_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_uppercase())
+}
+
+#[inline]
+fn is_literal(token: &Token) -> bool {
+    matches!(token,
+        Token::IntConstant { .. } | Token::FloatConstant { .. } | Token::StringConstant { .. }
+        | Token::BoolConstant { .. } | Token::NoneConstant { .. })
+}
+
+#[inline]
+fn range_pattern(operator: Token, start: Node, end: Node) -> Node {
+    let step = Node::from(Token::NoneConstant { pos: operator.pos() });
+    let mut node = Node::new(Some(operator), NodeType::Range);
+    node.children = vec!(start, end, step);
+    node
+}
+
+/// Returns `false` for the tokens which end the expression, so the operator before them has no right operand.
+#[inline]
+fn can_start_operand(token: &Token) -> bool {
+    match token {
+        Token::NewLine { .. } => false,
+        Token::Operator { payload, .. } =>
+            matches!(payload, Operator::LeftParen | Operator::LeftBracket | Operator::Minus | Operator::Not | Operator::If | Operator::Match),
+        _ => true,
+    }
+}
+
+/// Single `:` without the second one is not a valid range.
+/// The operators may be chained as deep as the tree allows, so they are walked without the recursion.
+fn check_step_ranges(node: &Node) -> Result<(), SyntaxError> {
+    let mut nodes = vec!(node);
+    while let Some(node) = nodes.pop() {
+        if node.node_type == NodeType::Expression && node.operator() == Some(Operator::Colon) {
+            return Err(SyntaxError::new(node.pos(), String::from("Step range must have three parts: start:step:end")))
+        }
+        if let NodeType::Expression | NodeType::Range = node.node_type {
+            nodes.extend(node.children.iter().rev())
+        }
+    }
+    Ok(())
+}
+
+/// Testing that operators are grouped by priority and parentheses.
+#[test]
+fn test_expression_tree() {
+    let tree = parse("a = (1 + 2) * -b");
+    assert_eq!(1, tree.children.len());
+    let assignment = &tree.children[0];
+    assert_eq!(Some(Operator::Assign), assignment.operator());
+    let product = &assignment.children[1];
+    assert_eq!(Some(Operator::Multiply), product.operator());
+    assert_eq!(Some(Operator::Plus), product.children[0].operator());
+    assert_eq!(Some(Operator::UnaryMinus), product.children[1].operator());
+}
+
+/// Testing `if` statements with `else if` and `else` branches on the separate lines.
+#[test]
+fn test_if_else_statements() {
+    let tree = parse("if a == true {\n  b = 1\n}\nelse if a == None { b = 2 } else {\n}\nc = 3");
+    assert_eq!(2, tree.children.len());
+    let node = &tree.children[0];
+    assert_eq!(NodeType::If, node.node_type);
+    assert_eq!(Some(Operator::Equal), node.condition[0].operator());
+    assert_eq!(Some(&Token::BoolConstant { value: true, pos: 8 }), node.condition[0].children[1].data.as_ref());
+    assert_eq!(NodeType::Block, node.children[0].node_type);
+    assert_eq!(NodeType::If, node.children[1].node_type);
+    assert_eq!(Some(&Token::NoneConstant { pos: 38 }), node.children[1].condition[0].children[1].data.as_ref());
+    assert_eq!(NodeType::Block, node.children[1].children[1].node_type);
+}
+
+/// Testing calls with nested calls and expressions in arguments.
+#[test]
+fn test_calls() {
+    let tree = parse("foo(1, bar(x) + 2, (3))");
+    let call = &tree.children[0];
+    assert_eq!(NodeType::Call, call.node_type);
+    assert_eq!(3, call.children.len());
+    assert_eq!(NodeType::Call, call.children[1].children[0].node_type);
+}
+
+/// Testing closed, open and step ranges.
+#[test]
+fn test_ranges() {
+    let tree = parse("a = 1 + 1..=n * 2\nb = ..5\nc = 3..\nd = 0:0.1:1");
+    let range = &tree.children[0].children[1];
+    assert_eq!(NodeType::Range, range.node_type);
+    assert_eq!(Some(Operator::InclusiveRange), range.operator());
+    assert_eq!(Some(Operator::Plus), range.children[0].operator());
+    assert_eq!(Some(Operator::Multiply), range.children[1].operator());
+    assert_eq!(Some(&Token::NoneConstant { pos: 9 }), range.children[2].data.as_ref());
+    let range = &tree.children[1].children[1];
+    assert_eq!(Some(&Token::NoneConstant { pos: 22 }), range.children[0].data.as_ref());
+    let range = &tree.children[2].children[1];
+    assert_eq!(Some(&Token::NoneConstant { pos: 31 }), range.children[1].data.as_ref());
+    let range = &tree.children[3].children[1];
+    assert_eq!(Some(Operator::Colon), range.operator());
+    assert_eq!(Some(&Token::IntConstant { value: 0, pos: 38 }), range.children[0].data.as_ref());
+    assert_eq!(Some(&Token::IntConstant { value: 1, pos: 44 }), range.children[1].data.as_ref());
+    assert_eq!(Some(&Token::FloatConstant { value: 0.1, pos: 40 }), range.children[2].data.as_ref());
+    assert_eq!(5, try_parse("a = 1:2").unwrap_err().pos);
+}
+
+/// Testing `for` and `loop` statements.
+#[test]
+fn test_loops() {
+    let tree = parse("for i in 0..n {\n  if i > 2 { break }\n  continue\n}\nloop { }");
+    let node = &tree.children[0];
+    assert_eq!(NodeType::For, node.node_type);
+    assert_eq!(NodeType::Range, node.condition[0].node_type);
+    assert_eq!(Some(&Token::Identifier { name: Symbol::from("i"), pos: 4 }), node.children[0].data.as_ref());
+    let body = &node.children[1];
+    assert_eq!(NodeType::Break, body.children[0].children[0].children[0].node_type);
+    assert_eq!(NodeType::Continue, body.children[1].node_type);
+    assert_eq!(NodeType::Loop, tree.children[1].node_type);
+    assert!(try_parse("for 1 in x {}").is_err());
+}
+
+/// Testing lambdas with default parameters and the trailing lambda syntax.
+#[test]
+fn test_lambdas() {
+    let tree = parse("f = { @ x, y = x * 2 ->\n  z = x + y\n  z * z\n}\napply(f, 3)\ng = { @ x, y -> x:0.1:y }");
+    let lambda = &tree.children[0].children[1];
+    assert_eq!(NodeType::Lambda, lambda.node_type);
+    assert_eq!(2, lambda.condition.len());
+    assert!(lambda.condition[0].children.is_empty());
+    assert_eq!(Some(Operator::Multiply), lambda.condition[1].children[0].operator());
+    assert_eq!(2, lambda.children[0].children.len());
+    assert_eq!(NodeType::Call, tree.children[1].node_type);
+    assert!(parse("map{ @ x -> x }").children[0].children[0].node_type == NodeType::Lambda);
+    assert_eq!(NodeType::Range, tree.children[2].children[1].children[0].children[0].node_type);
+    // name glued to the block is not a call
+    assert_eq!(NodeType::If, parse("if a > b{ c }").children[0].node_type);
+    assert!(try_parse("f = { x -> x }").is_err());
+}
+
+/// Testing function declarations with type annotations and returns.
+#[test]
+fn test_functions() {
+    let tree = parse("fn add(a: Int, b = 1,\n  c: Float = 0.5) -> Float {\n  if a > 0 { return }\n  return a + b + c\n}\nfn noop() {}");
+    let function = &tree.children[0];
+    assert_eq!(NodeType::Function, function.node_type);
+    assert_eq!(Some(&Token::Identifier { name: Symbol::from("add"), pos: 3 }), function.data.as_ref());
+    assert_eq!(3, function.condition.len());
+    assert_eq!(NodeType::Type, function.condition[0].condition[0].node_type);
+    assert!(function.condition[1].condition.is_empty());
+    assert_eq!(1, function.condition[1].children.len());
+    assert_eq!(1, function.condition[2].children.len());
+    assert_eq!(Some(&Token::Identifier { name: Symbol::from("Float"), pos: 43 }), function.children[1].data.as_ref());
+    let body = &function.children[0];
+    assert!(body.children[0].children[0].children[0].children.is_empty());
+    assert_eq!(NodeType::Return, body.children[1].node_type);
+    assert_eq!(Some(Operator::Plus), body.children[1].children[0].operator());
+    assert_eq!(1, tree.children[1].children.len());
+    assert!(try_parse("fn (a) {}").is_err());
+    assert!(try_parse("fn f(a: 1) {}").is_err());
+}
+
+/// Testing struct declarations and the field access.
+#[test]
+fn test_structs() {
+    let tree = parse("struct Point {\n  x: Float, mut y\n}\np.y = Point(1, 2).x + a.b.c");
+    let declaration = &tree.children[0];
+    assert_eq!(NodeType::Struct, declaration.node_type);
+    assert_eq!(2, declaration.children.len());
+    assert_eq!(NodeType::Type, declaration.children[0].condition[0].node_type);
+    assert_eq!(Some(Operator::Mut), declaration.children[1].operator());
+    let assignment = &tree.children[1];
+    assert_eq!(Some(Operator::Dot), assignment.children[0].operator());
+    let sum = &assignment.children[1];
+    assert_eq!(NodeType::Call, sum.children[0].children[0].node_type);
+    // `.` is left associative: `(a.b).c`
+    assert_eq!(Some(Operator::Dot), sum.children[1].children[0].operator());
+    assert_eq!(Some(&Token::Identifier { name: Symbol::from("c"), pos: 61 }), sum.children[1].children[1].data.as_ref());
+    assert!(try_parse("a.1").is_err());
+    assert!(try_parse("struct { x }").is_err());
+}
+
+/// Testing enum declarations, variant constructors and patterns.
+#[test]
+fn test_enums() {
+    let tree = parse("enum Shape {\n  Circle(r: Float)\n  Rect(w, h), Empty\n}\ns = Shape.Circle(1)\nr = match s { Circle(r) -> r, Shape.Rect(w, _) -> w, Shape.Empty -> 0, Empty -> 0 }\ns is Shape.Circle");
+    let declaration = &tree.children[0];
+    assert_eq!(NodeType::Enum, declaration.node_type);
+    assert_eq!(3, declaration.children.len());
+    assert_eq!(NodeType::Variant, declaration.children[1].node_type);
+    assert_eq!(2, declaration.children[1].children.len());
+    assert_eq!(NodeType::Type, declaration.children[0].children[0].condition[0].node_type);
+    assert!(declaration.children[2].children.is_empty());
+    let constructor = &tree.children[1].children[1];
+    assert_eq!(Some(Operator::Dot), constructor.operator());
+    assert_eq!(NodeType::Call, constructor.children[1].node_type);
+    let arms = &tree.children[2].children[1].children;
+    assert_eq!(NodeType::Variant, arms[0].condition[0].node_type);
+    assert_eq!(1, arms[0].condition[0].children.len());
+    assert_eq!(Some(&Token::Identifier { name: Symbol::from("Shape"), pos: 104 }), arms[1].condition[0].condition[0].data.as_ref());
+    assert_eq!(NodeType::Variant, arms[2].condition[0].node_type);
+    assert!(arms[3].condition[0].children.is_empty());
+    assert_eq!(Some(Operator::Is), tree.children[3].operator());
+    assert!(try_parse("enum E { 1 }").is_err());
+    assert!(try_parse("match x { E.f -> 1 }").is_err());
+}
+
+/// Testing tuples and the parentheses around the single expression.
+#[test]
+fn test_tuples() {
+    let tree = parse("a = (1, b + 2, (3,))\nc = (4)\nd = ()");
+    let tuple = &tree.children[0].children[1];
+    assert_eq!(NodeType::Tuple, tuple.node_type);
+    assert_eq!(3, tuple.children.len());
+    assert_eq!(NodeType::Tuple, tuple.children[2].node_type);
+    assert_eq!(1, tuple.children[2].children.len());
+    assert_eq!(NodeType::Token, tree.children[1].children[1].node_type);
+    assert_eq!(0, tree.children[2].children[1].children.len());
+}
+
+/// Testing `import`, `from` and `pub` declarations of the module.
+#[test]
+fn test_modules() {
+    let tree = parse("import a.b\nfrom c import d, e\npub fn f() {}\npub mut x = 1");
+    let import = &tree.children[0];
+    assert_eq!(NodeType::Import, import.node_type);
+    assert_eq!(2, import.children.len());
+    assert!(import.condition.is_empty());
+    assert_eq!(Some(Operator::From), tree.children[1].operator());
+    assert_eq!(2, tree.children[1].condition.len());
+    assert_eq!(NodeType::Public, tree.children[2].node_type);
+    assert_eq!(NodeType::Function, tree.children[2].declaration().node_type);
+    assert_eq!(Some(Operator::Assign), tree.children[3].declaration().operator());
+    assert_eq!("'import' is allowed only at the top level of the module", try_parse("if a {\n  import b\n}").unwrap_err().message);
+    assert!(try_parse("pub x + 1").is_err());
+    assert!(try_parse("from a import").is_err());
+    assert!(try_parse("import a b").is_err());
+}
+
+/// Testing test blocks, `test` is still the valid name outside of them.
+#[test]
+fn test_tests() {
+    let tree = parse("test \"adds\" {\n  assertEq(1 + 1, 2)\n}\ntest = 1\ntest(test)");
+    assert_eq!(NodeType::Test, tree.children[0].node_type);
+    assert_eq!(Some(&Token::StringConstant { value: Symbol::from("adds"), pos: 5 }), tree.children[0].data.as_ref());
+    assert_eq!(NodeType::Block, tree.children[0].children[0].node_type);
+    assert_eq!(Some(Operator::Assign), tree.children[1].operator());
+    assert_eq!(NodeType::Call, tree.children[2].node_type);
+    assert_eq!("'test' is allowed only at the top level of the module", try_parse("fn f() {\n  test \"a\" {}\n}").unwrap_err().message);
+    assert!(try_parse("test \"a\"").is_err());
+}
+
+/// Testing list and map literals, indexing, slicing and the `in` operator.
+#[test]
+fn test_collections() {
+    let tree = parse("a = [1, [2],\n  3][0]\nm = {\"k\": 1, n: 1:1:2}\nb = -a.items[1..] in {}");
+    let index = &tree.children[0].children[1];
+    assert_eq!(Some(Operator::LeftBracket), index.operator());
+    assert_eq!(NodeType::List, index.children[0].node_type);
+    assert_eq!(3, index.children[0].children.len());
+    let map = &tree.children[1].children[1];
+    assert_eq!(NodeType::Map, map.node_type);
+    assert_eq!(4, map.children.len());
+    assert_eq!(NodeType::Range, map.children[3].node_type);
+    let test = &tree.children[2].children[1];
+    assert_eq!(Some(Operator::In), test.operator());
+    assert_eq!(Some(Operator::UnaryMinus), test.children[0].operator());
+    let slice = &test.children[0].children[0];
+    assert_eq!(Some(Operator::Dot), slice.children[0].operator());
+    assert_eq!(NodeType::Range, slice.children[1].node_type);
+    assert_eq!(NodeType::Map, test.children[1].node_type);
+    assert!(try_parse("a = [1, 2").is_err());
+    assert!(try_parse("a = {1: 2").is_err());
+    assert!(try_parse("a = b[1").is_err());
+}
+
+/// Testing match arms with all kinds of patterns and guards.
+#[test]
+fn test_match() {
+    let source = "r = match value {\n  0 -> \"zero\"\n  -5..=-1 -> { \"negative\" }\n  (a, _) if a > 1 -> a, [x, ..rest] -> x\n  [_, ..] -> 1\n  100.. -> 2\n  n -> n\n}";
+    let tree = try_parse(source).unwrap();
+    let node = &tree.children[0].children[1];
+    assert_eq!(NodeType::Match, node.node_type);
+    assert_eq!(7, node.children.len());
+    let arms = &node.children;
+    assert_eq!(Some(&Token::IntConstant { value: 0, pos: 20 }), arms[0].condition[0].data.as_ref());
+    assert_eq!(NodeType::Range, arms[1].condition[0].node_type);
+    assert_eq!(Some(&Token::IntConstant { value: -5, pos: 34 }), arms[1].condition[0].children[0].data.as_ref());
+    assert_eq!(NodeType::Block, arms[1].children[0].node_type);
+    assert_eq!(NodeType::Tuple, arms[2].condition[0].node_type);
+    assert_eq!(Some(Operator::Greater), arms[2].condition[1].operator());
+    assert_eq!(NodeType::List, arms[3].condition[0].node_type);
+    assert_eq!(NodeType::Rest, arms[3].condition[0].children[1].node_type);
+    assert_eq!(1, arms[3].condition[0].children[1].children.len());
+    assert!(arms[4].condition[0].children[1].children.is_empty());
+    assert_eq!(Some(&Token::NoneConstant { pos: 121 }), arms[5].condition[0].children[1].data.as_ref());
+    assert!(try_parse("match x { [.., a] -> 1 }").is_err());
+    assert!(try_parse("match x { a + 1 -> 1 }").is_err());
+}
+
+/// Testing that syntax errors point to the right place.
+#[test]
+fn test_syntax_errors() {
+    assert_eq!(4, try_parse("a = + 1").unwrap_err().pos);
+    assert_eq!(2, try_parse("a b").unwrap_err().pos);
+    assert_eq!(5, try_parse("if a { b").unwrap_err().pos);
+    assert_eq!(3, try_parse("foo(1, 2").unwrap_err().pos);
+}
+
+/// Testing that the deeply nested sources are the syntax errors instead of the stack overflow.
+#[test]
+fn test_nesting_depth_limit() {
+    use crate::runtime::limits::Limit;
+
+    let nested = |open: &str, inner: &str, close: &str, depth: usize| format!("{}{}{}", open.repeat(depth), inner, close.repeat(depth));
+    let sources = [
+        nested("(", "1", ")", 50_000),
+        nested("[", "", "]", 20_000),
+        nested("f(", "1", ")", 20_000),
+        nested("if a {\n", "1", "\n}", 10_000),
+        nested("loop {\n", "break", "\n}", 10_000),
+        format!("match a {{ {} -> 1 }}", nested("[", "_", "]", 10_000)),
+    ];
+    // with the large stack the nesting is limited only by the count, while the default stack may be used up before it
+    let limits = std::thread::Builder::new().stack_size(64 * 1024 * 1024).spawn({
+        let sources = sources.clone();
+        move || {
+            let _guard = StackGuard::new(32 * 1024 * 1024);
+            sources.iter().map(|source| try_parse(source).unwrap_err().limit).collect::<Vec<_>>()
+        }
+    }).unwrap().join().unwrap();
+    assert_eq!(vec!(Some(Limit::Nesting(MAX_DEPTH)); sources.len()), limits);
+    for source in &sources {
+        assert!(matches!(try_parse(source).unwrap_err().limit, Some(Limit::Nesting(_)) | Some(Limit::Stack(_))));
+    }
+    assert_eq!(20, try_parse(&nested("(", "1", ")", 20)).unwrap().children[0].pos());
+    // the operators are not the nesting, their chains are limited by the depth of the tree
+    assert!(try_parse(&format!("x = {}1", "1 + ".repeat(150))).is_ok());
+    let max_depth = crate::dto::stack::max_tree_depth();
+    for source in [nested("-", "1", "", 200_000), nested("a + ", "1", "", 20_000)] {
+        assert_eq!(Some(Limit::Stack(DEFAULT_STACK_SIZE)), try_parse(&source).unwrap_err().limit);
+    }
+    assert!(try_parse(&nested("-", "1", "", max_depth - 2)).is_ok());
+    assert_eq!(max_depth - 1, try_parse(&nested("-", "1", "", max_depth - 1)).unwrap_err().pos);
+}
+
+/// Testing that the nodes cover their keywords and closing brackets.
+#[test]
+fn test_extents() {
+    let tree = parse("fn f(x) {\n  xs[i + 1] * (a + b)\n}\nt = (1, 2)");
+    let function = &tree.children[0];
+    assert_eq!(Some((0, 32)), function.extent);
+    assert_eq!(Some((8, 32)), function.children[0].extent);
+    let product = &function.children[0].children[0];
+    assert_eq!(Some((12, 30)), product.extent);
+    assert_eq!(Some((12, 20)), product.children[0].extent);
+    assert_eq!(Some((15, 19)), product.children[0].children[1].extent);
+    assert_eq!(Some((24, 30)), product.children[1].extent);
+    assert_eq!(Some((38, 43)), tree.children[1].children[1].extent);
 }
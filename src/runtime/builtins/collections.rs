@@ -0,0 +1,104 @@
+use crate::dto::RuntimeError;
+use crate::runtime::{ Interpreter, Value };
+use crate::runtime::builtins::{ Builtin, error };
+#[cfg(test)]
+use crate::runtime::builtins::run;
+use crate::runtime::collections::len;
+use crate::runtime::operations::compare;
+use std::cmp::Ordering;
+use std::mem;
+
+pub const BUILTINS: &[Builtin] = &[
+    Builtin::new("map", &["Any", "Any"], "List", map),
+    Builtin::new("filter", &["Any", "Any"], "List", filter),
+    Builtin::new("reduce", &["Any", "Any", "Any"], "Any", reduce),
+    Builtin::new("len", &["Any"], "Int", length),
+    Builtin::new("push", &["List", "Any"], "None", push),
+    Builtin::new("keys", &["Map"], "List", |_, arguments, _| Ok(entries(&arguments[0], |(key, _)| key.clone()))),
+    Builtin::new("values", &["Map"], "List", |_, arguments, _| Ok(entries(&arguments[0], |(_, value)| value.clone()))),
+    Builtin::new("sorted", &["Any"], "List", sorted),
+];
+
+/// `map(iterable, transform)` creates the list of transformed items.
+fn map(interpreter: &mut Interpreter, arguments: Vec<Value>, pos: usize) -> Result<Value, RuntimeError> {
+    let (iterable, transform) = (&arguments[0], &arguments[1]);
+    let mut result = vec!();
+    for item in iterable.iter().map_err(|message| RuntimeError { pos, message })? {
+        result.push(interpreter.call(transform, vec!(item), pos)?);
+    }
+    Ok(Value::list(result))
+}
+
+/// `filter(iterable, predicate)` creates the list of items for which the predicate returns `true`.
+fn filter(interpreter: &mut Interpreter, arguments: Vec<Value>, pos: usize) -> Result<Value, RuntimeError> {
+    let (iterable, predicate) = (&arguments[0], &arguments[1]);
+    let mut result = vec!();
+    for item in iterable.iter().map_err(|message| RuntimeError { pos, message })? {
+        match interpreter.call(predicate, vec!(item.clone()), pos)? {
+            Value::Bool(true) => result.push(item),
+            Value::Bool(false) => (),
+            value => return Err(error(pos, format!("Filter predicate must return Bool, found {}", value.type_name()))),
+        }
+    }
+    Ok(Value::list(result))
+}
+
+/// `reduce(iterable, initial, combine)` folds the items into the single value, starting from the initial one.
+fn reduce(interpreter: &mut Interpreter, arguments: Vec<Value>, pos: usize) -> Result<Value, RuntimeError> {
+    let mut arguments = arguments.into_iter();
+    let (iterable, mut result, combine) = (arguments.next().unwrap(), arguments.next().unwrap(), arguments.next().unwrap());
+    for item in iterable.iter().map_err(|message| RuntimeError { pos, message })? {
+        result = interpreter.call(&combine, vec!(result, item), pos)?;
+    }
+    Ok(result)
+}
+
+/// `len(collection)` is the number of items of the list, tuple or map, or the number of characters of the string.
+fn length(_: &mut Interpreter, arguments: Vec<Value>, pos: usize) -> Result<Value, RuntimeError> {
+    len(&arguments[0]).map(|len| Value::Int(len as i64)).map_err(|message| error(pos, message))
+}
+
+/// `push(list, item)` adds the item to the end of the list.
+fn push(interpreter: &mut Interpreter, arguments: Vec<Value>, pos: usize) -> Result<Value, RuntimeError> {
+    if let Value::List(values) = &arguments[0] {
+        values.borrow_mut().push(arguments[1].clone());
+    }
+    interpreter.allocate(mem::size_of::<Value>(), pos)?;
+    Ok(Value::None)
+}
+
+/// `sorted(iterable)` creates the sorted list of the items, the items must be comparable with each other.
+fn sorted(_: &mut Interpreter, arguments: Vec<Value>, pos: usize) -> Result<Value, RuntimeError> {
+    let mut items: Vec<Value> = arguments[0].iter().map_err(|message| error(pos, message))?.collect();
+    let mut failure = None;
+    items.sort_by(|a, b| compare(a, b).unwrap_or_else(|message| {
+        failure.get_or_insert(message);
+        Ordering::Equal
+    }));
+    match failure {
+        Some(message) => Err(error(pos, message)),
+        None => Ok(Value::list(items)),
+    }
+}
+
+/// Creates the list from the entries of the map, the map is checked by the signature.
+#[inline]
+fn entries(map: &Value, entry: impl Fn(&(Value, Value)) -> Value) -> Value {
+    match map {
+        Value::Map(map) => Value::list(map.borrow().entries().map(entry).collect()),
+        _ => Value::None,
+    }
+}
+
+#[test]
+fn test_collection_builtins() {
+    let strings = |items: &[&str]| Value::list(items.iter().map(|item| Value::Str(item.to_string())).collect());
+    let expected = Value::tuple(vec!(
+        strings(&["b", "a"]),
+        Value::list(vec!(Value::Int(1), Value::Int(2))),
+        Value::list(vec!(Value::Float(1.5), Value::Int(2), Value::Int(3))),
+        strings(&["a", "b"]),
+    ));
+    assert_eq!(Ok(expected), run("m = {\"b\": 1, \"a\": 2}\n(keys(m), m.values(), sorted([3, 1.5, 2]), sorted(m))"));
+    assert_eq!("Can't compare String and Int", run("sorted([1, \"a\"])").unwrap_err());
+}
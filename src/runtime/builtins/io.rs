@@ -0,0 +1,77 @@
+use crate::dto::RuntimeError;
+use crate::runtime::{ Interpreter, Value };
+use crate::runtime::builtins::{ Builtin, error, string };
+#[cfg(test)]
+use crate::runtime::builtins::{ run, run_with_input };
+use std::fs;
+
+pub const BUILTINS: &[Builtin] = &[
+    Builtin::variadic("print", &["Any"], "None", print),
+    Builtin::variadic("println", &["Any"], "None", println),
+    Builtin::new("readLine", &[], "Any", read_line),
+    Builtin::new("readFile", &["String"], "String", read_file),
+    Builtin::new("writeFile", &["String", "Any"], "None", write_file),
+];
+
+/// Builtins which read the files or the input of the process, they are disabled by [`Limits::sandbox`].
+///
+/// [`Limits::sandbox`]: crate::runtime::limits::Limits::sandbox
+pub const SYSTEM_ACCESS: &[&str] = &["readLine", "readFile", "writeFile"];
+
+/// `print(values...)` writes the values separated by spaces to the output of the interpreter.
+fn print(interpreter: &mut Interpreter, arguments: Vec<Value>, pos: usize) -> Result<Value, RuntimeError> {
+    write(interpreter, arguments, "", pos)
+}
+
+/// `println(values...)` is `print` followed by the new line.
+fn println(interpreter: &mut Interpreter, arguments: Vec<Value>, pos: usize) -> Result<Value, RuntimeError> {
+    write(interpreter, arguments, "\n", pos)
+}
+
+/// `readLine()` reads the line from the input of the interpreter without the line break, it returns `None` at the end of the input.
+fn read_line(interpreter: &mut Interpreter, _: Vec<Value>, pos: usize) -> Result<Value, RuntimeError> {
+    let mut line = String::new();
+    match interpreter.input().read_line(&mut line) {
+        Ok(0) => Ok(Value::None),
+        Ok(_) => Ok(Value::Str(line.trim_end_matches(&['\n', '\r'][..]).to_string())),
+        Err(e) => Err(error(pos, format!("Can't read the input: {}", e))),
+    }
+}
+
+/// `readFile(path)` returns the content of the file.
+fn read_file(_: &mut Interpreter, arguments: Vec<Value>, pos: usize) -> Result<Value, RuntimeError> {
+    let path = string(&arguments[0]);
+    fs::read_to_string(path).map(Value::Str).map_err(|e| error(pos, format!("Can't read the file {}: {}", path, e)))
+}
+
+/// `writeFile(path, content)` replaces the content of the file with the content converted to the string.
+fn write_file(_: &mut Interpreter, arguments: Vec<Value>, pos: usize) -> Result<Value, RuntimeError> {
+    let path = string(&arguments[0]);
+    fs::write(path, arguments[1].to_string()).map(|_| Value::None).map_err(|e| error(pos, format!("Can't write the file {}: {}", path, e)))
+}
+
+#[inline]
+fn write(interpreter: &mut Interpreter, arguments: Vec<Value>, end: &str, pos: usize) -> Result<Value, RuntimeError> {
+    let text: Vec<String> = arguments.iter().map(Value::to_string).collect();
+    write!(interpreter.output(), "{}{}", text.join(" "), end)
+        .and_then(|_| interpreter.output().flush())
+        .map(|_| Value::None)
+        .map_err(|e| error(pos, format!("Can't write the output: {}", e)))
+}
+
+#[test]
+fn test_print_and_read_line() {
+    assert_eq!("a 1 [2.5]\n3\n", run_with_input("println(\"a\", 1, [2.5])\nprint(3)\nprintln()", "").1);
+    let (result, text) = run_with_input("name = readLine()\nprintln(\"Hello, \" + name)\nreadLine()\nreadLine()", "Avo\r\n\n");
+    assert_eq!((Ok(Value::None), "Hello, Avo\n"), (result, text.as_str()));
+}
+
+#[test]
+fn test_files() {
+    let path = std::env::temp_dir().join(format!("avo-test-files-{}.txt", std::process::id()));
+    let path = path.to_string_lossy().replace('\\', "/");
+    let source = format!("writeFile(\"{0}\", [1, 2])\nreadFile(\"{0}\")", path);
+    assert_eq!(Ok(Value::Str(String::from("[1, 2]"))), run(&source));
+    fs::remove_file(&path).unwrap();
+    assert!(run(&source[source.find('\n').unwrap() + 1..]).unwrap_err().starts_with("Can't read the file"));
+}
@@ -0,0 +1,118 @@
+use crate::dto::{ Operator, RuntimeError };
+use crate::runtime::{ Interpreter, Value };
+use crate::runtime::builtins::{ Builtin, error, float };
+#[cfg(test)]
+use crate::runtime::builtins::run;
+use std::time::{ SystemTime, UNIX_EPOCH };
+
+pub const BUILTINS: &[Builtin] = &[
+    Builtin::new("sqrt", &["Float"], "Float", |_, arguments, _| Ok(Value::Float(float(&arguments[0]).sqrt()))),
+    Builtin::new("pow", &["Number", "Number"], "Number", pow),
+    Builtin::new("abs", &["Number"], "Number", abs),
+    Builtin::new("floor", &["Float"], "Int", |_, arguments, pos| to_int(float(&arguments[0]).floor(), pos)),
+    Builtin::new("ceil", &["Float"], "Int", |_, arguments, pos| to_int(float(&arguments[0]).ceil(), pos)),
+    Builtin::new("round", &["Float"], "Int", |_, arguments, pos| to_int(float(&arguments[0]).round(), pos)),
+    Builtin::new("sin", &["Float"], "Float", |_, arguments, _| Ok(Value::Float(float(&arguments[0]).sin()))),
+    Builtin::new("cos", &["Float"], "Float", |_, arguments, _| Ok(Value::Float(float(&arguments[0]).cos()))),
+    Builtin::new("tan", &["Float"], "Float", |_, arguments, _| Ok(Value::Float(float(&arguments[0]).tan()))),
+    Builtin::new("random", &[], "Float", |interpreter, _, _| Ok(Value::Float(interpreter.random().next_float()))),
+    Builtin::new("seed", &["Int"], "None", seed),
+];
+
+/// Pseudo-random generator of the interpreter, xorshift64*.
+///
+/// It is seeded with the current time, `seed(n)` makes the sequence of `random()` reproducible.
+#[derive(Debug)]
+pub struct Random {
+    state: u64,
+}
+
+impl Default for Random {
+
+    fn default() -> Random {
+        Random::new()
+    }
+}
+
+impl Random {
+
+    pub fn new() -> Random {
+        let time = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_nanos() as u64).unwrap_or_default();
+        Random::with_seed(time)
+    }
+
+    /// The seed is mixed by splitmix64, so the close seeds start the different sequences.
+    pub fn with_seed(seed: u64) -> Random {
+        let mut state = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        state = (state ^ (state >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        state = (state ^ (state >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        state ^= state >> 31;
+        // zero state of xorshift produces only zeros
+        Random { state: if state == 0 { 0x9E37_79B9_7F4A_7C15 } else { state } }
+    }
+
+    /// Returns the number in the range `[0, 1)`.
+    pub fn next_float(&mut self) -> f64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        let value = self.state.wrapping_mul(0x2545_F491_4F6C_DD1D);
+        // 53 high bits fill the mantissa of f64
+        (value >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// `pow(base, exponent)` is `base ^ exponent`.
+fn pow(_: &mut Interpreter, arguments: Vec<Value>, pos: usize) -> Result<Value, RuntimeError> {
+    crate::runtime::operations::binary_operation(Operator::Power, &arguments[0], &arguments[1]).map_err(|message| error(pos, message))
+}
+
+/// `abs(x)` keeps the type of the number.
+fn abs(_: &mut Interpreter, arguments: Vec<Value>, pos: usize) -> Result<Value, RuntimeError> {
+    match arguments[0] {
+        Value::Int(value) => value.checked_abs().map(Value::Int).ok_or_else(|| error(pos, String::from("Integer overflow"))),
+        ref value => Ok(Value::Float(float(value).abs())),
+    }
+}
+
+/// `seed(n)` restarts the sequence of `random()`.
+fn seed(interpreter: &mut Interpreter, arguments: Vec<Value>, _: usize) -> Result<Value, RuntimeError> {
+    if let Value::Int(seed) = arguments[0] {
+        *interpreter.random() = Random::with_seed(seed as u64);
+    }
+    Ok(Value::None)
+}
+
+#[inline]
+fn to_int(value: f64, pos: usize) -> Result<Value, RuntimeError> {
+    match value.is_finite() && value.abs() < i64::MAX as f64 {
+        true => Ok(Value::Int(value as i64)),
+        false => Err(error(pos, format!("{:?} can't be converted to Int", value))),
+    }
+}
+
+#[test]
+fn test_math() {
+    assert_eq!(Ok(Value::Float(3.0)), run("sqrt(9)"));
+    assert_eq!(Ok(Value::Int(8)), run("pow(2, 3)"));
+    assert_eq!(Ok(Value::Int(5)), run("abs(-5)"));
+    assert_eq!(Ok(Value::Float(0.5)), run("abs(-0.5)"));
+    assert_eq!(Ok(Value::tuple(vec!(Value::Int(1), Value::Int(2), Value::Int(-2)))), run("(floor(1.7), ceil(1.2), round(-2.4))"));
+    assert_eq!(Ok(Value::Float(0.0)), run("sin(0)"));
+    assert_eq!("Argument 1 of 'sqrt' must be Float, found String", run("sqrt(\"4\")").unwrap_err());
+    assert_eq!("inf can't be converted to Int", run("floor(1.0 / 0.0)").unwrap_err());
+}
+
+#[test]
+fn test_random() {
+    assert_eq!(Ok(Value::Bool(true)), run("seed(42)\na = random()\nseed(42)\na == random() and 0.0 <= a and a < 1.0"));
+    let mut random = Random::with_seed(0);
+    let values: Vec<f64> = (0..1000).map(|_| random.next_float()).collect();
+    assert!(values.iter().all(|value| (0.0..1.0).contains(value)));
+    assert!(values.windows(2).all(|pair| pair[0] != pair[1]));
+    // the state is never zero, xorshift would return only zeros
+    for seed in [0, 0x9E37_79B9_7F4A_7C15, u64::MAX] {
+        let mut random = Random::with_seed(seed);
+        assert!((0..10).any(|_| random.next_float() != 0.0));
+    }
+}
@@ -0,0 +1,183 @@
+pub mod io;
+pub mod math;
+pub mod strings;
+pub mod collections;
+pub mod testing;
+
+use crate::dto::RuntimeError;
+use crate::runtime::{ Interpreter, Value };
+use crate::runtime::limits::size_of;
+use crate::runtime::types::conform;
+#[cfg(test)]
+use crate::parser::parse;
+
+type BuiltinFunction = fn(&mut Interpreter, Vec<Value>, usize) -> Result<Value, RuntimeError>;
+
+/// Function of the standard library with the signature which is checked before the call.
+pub struct Builtin {
+    pub name: &'static str,
+    pub signature: Signature,
+    function: BuiltinFunction,
+}
+
+/// Types of the parameters and of the result, the names are the same as in the type annotations.
+///
+/// The last parameter of the variadic function may be repeated any number of times, including zero:
+/// `print(values: Any...)` accepts any number of arguments.
+pub struct Signature {
+    pub parameters: &'static [&'static str],
+    pub is_variadic: bool,
+    pub return_type: &'static str,
+}
+
+/// All modules of the standard library.
+const MODULES: &[&[Builtin]] = &[io::BUILTINS, math::BUILTINS, strings::BUILTINS, collections::BUILTINS, testing::BUILTINS];
+
+/// Calls the builtin function by name, returns `None` if there is no such function.
+///
+/// Builtins are the methods of their first argument as well: `xs.len()` is `len(xs)`.
+pub fn call_builtin(interpreter: &mut Interpreter, name: &str, arguments: Vec<Value>, pos: usize) -> Option<Result<Value, RuntimeError>> {
+    find_builtin(name).map(|builtin| {
+        interpreter.check_builtin(name, pos)?;
+        let arguments = builtin.check(arguments).map_err(|message| RuntimeError { pos, message })?;
+        let result = (builtin.function)(interpreter, arguments, pos)?;
+        interpreter.allocate(size_of(&result), pos)?;
+        Ok(result)
+    })
+}
+
+pub fn is_builtin(name: &str) -> bool {
+    find_builtin(name).is_some()
+}
+
+pub fn find_builtin(name: &str) -> Option<&'static Builtin> {
+    MODULES.iter().flat_map(|module| module.iter()).find(|builtin| builtin.name == name)
+}
+
+/// Names of the builtins without the access to the files and to the input of the process.
+pub fn pure_builtins() -> impl Iterator<Item = &'static str> {
+    MODULES.iter().flat_map(|module| module.iter()).map(|builtin| builtin.name).filter(|name| !io::SYSTEM_ACCESS.contains(name))
+}
+
+impl Builtin {
+
+    const fn new(name: &'static str, parameters: &'static [&'static str], return_type: &'static str, function: BuiltinFunction) -> Builtin {
+        Builtin { name, signature: Signature { parameters, is_variadic: false, return_type }, function }
+    }
+
+    const fn variadic(name: &'static str, parameters: &'static [&'static str], return_type: &'static str, function: BuiltinFunction) -> Builtin {
+        Builtin { name, signature: Signature { parameters, is_variadic: true, return_type }, function }
+    }
+
+    /// Checks the number of arguments, for the variadic function it is the minimal number.
+    pub fn check_count(&self, count: usize) -> Result<(), String> {
+        let parameters = &self.signature.parameters;
+        match self.signature.is_variadic {
+            true if count + 1 < parameters.len() =>
+                Err(format!("'{}' expects at least {}, found {}", self.name, arguments(parameters.len() - 1), count)),
+            false if count != parameters.len() => Err(format!("'{}' expects {}, found {}", self.name, arguments(parameters.len()), count)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Type of the parameter for the argument with the index.
+    pub fn parameter_type(&self, index: usize) -> Option<&'static str> {
+        let parameters = self.signature.parameters;
+        match self.signature.is_variadic {
+            true => parameters.get(index).or_else(|| parameters.last()).copied(),
+            false => parameters.get(index).copied(),
+        }
+    }
+
+    /// Checks the arguments against the signature and converts them to the types of the parameters.
+    fn check(&self, arguments: Vec<Value>) -> Result<Vec<Value>, String> {
+        self.check_count(arguments.len())?;
+        let mut checked = Vec::with_capacity(arguments.len());
+        for (index, argument) in arguments.into_iter().enumerate() {
+            let type_name = self.parameter_type(index).unwrap_or("Any");
+            checked.push(conform(type_name, argument).map_err(|argument| {
+                format!("Argument {} of '{}' must be {}, found {}", index + 1, self.name, type_name, argument.type_name())
+            })?);
+        }
+        Ok(checked)
+    }
+}
+
+/// Number of the arguments with the noun in the singular or plural form, "1 argument" or "2 arguments".
+#[inline]
+pub fn arguments(count: usize) -> String {
+    match count {
+        1 => String::from("1 argument"),
+        _ => format!("{} arguments", count),
+    }
+}
+
+/// Accessors of the arguments which are already checked by the signature.
+#[inline]
+fn float(value: &Value) -> f64 {
+    match value {
+        Value::Float(value) => *value,
+        Value::Int(value) => *value as f64,
+        _ => unreachable!("Float argument is checked by the signature"),
+    }
+}
+
+#[inline]
+fn string(value: &Value) -> &str {
+    match value {
+        Value::Str(value) => value,
+        _ => unreachable!("String argument is checked by the signature"),
+    }
+}
+
+#[inline]
+fn error(pos: usize, message: String) -> RuntimeError {
+    RuntimeError { pos, message }
+}
+
+/// Runs the source, returns the value or the message of the error.
+#[cfg(test)]
+fn run(source: &str) -> Result<Value, String> {
+    run_with_input(source, "").0
+}
+
+/// Runs the source with the input, returns the result with the message of the error and the output.
+#[cfg(test)]
+fn run_with_input(source: &str, input: &str) -> (Result<Value, String>, String) {
+    use std::cell::RefCell;
+    use std::io::{ Cursor, Write };
+    use std::rc::Rc;
+
+    #[derive(Clone)]
+    struct Output(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let output = Output(Rc::new(RefCell::new(vec!())));
+    let tree = parse(source);
+    let result = Interpreter::with_io(Box::new(Cursor::new(input.to_string())), Box::new(output.clone())).run(&tree).map_err(|e| e.message);
+    let text = String::from_utf8(output.0.borrow().clone()).unwrap();
+    (result, text)
+}
+
+#[test]
+fn test_signatures() {
+    let sqrt = find_builtin("sqrt").unwrap();
+    assert_eq!(Ok(vec!(Value::Float(4.0))), sqrt.check(vec!(Value::Int(4))));
+    assert_eq!(Err(String::from("Argument 1 of 'sqrt' must be Float, found String")), sqrt.check(vec!(Value::Str(String::new()))));
+    assert_eq!(Err(String::from("'sqrt' expects 1 argument, found 2")), sqrt.check_count(2));
+    let format = find_builtin("format").unwrap();
+    assert_eq!(Ok(()), format.check_count(3));
+    assert_eq!(Err(String::from("'format' expects at least 1 argument, found 0")), format.check_count(0));
+    assert_eq!(Some("Any"), format.parameter_type(5));
+    assert!(find_builtin("println").is_some());
+    assert!(!is_builtin("nope"));
+}
@@ -0,0 +1,74 @@
+use crate::dto::RuntimeError;
+use crate::runtime::{ Interpreter, Value };
+use crate::runtime::builtins::{ Builtin, error, string };
+#[cfg(test)]
+use crate::runtime::builtins::run;
+
+pub const BUILTINS: &[Builtin] = &[
+    Builtin::new("split", &["String", "String"], "List", split),
+    Builtin::new("join", &["Any", "String"], "String", join),
+    Builtin::new("trim", &["String"], "String", |_, arguments, _| Ok(Value::Str(string(&arguments[0]).trim().to_string()))),
+    Builtin::variadic("format", &["String", "Any"], "String", format),
+];
+
+/// `split(text, separator)` creates the list of the parts of the string, the empty separator splits it into characters.
+fn split(_: &mut Interpreter, arguments: Vec<Value>, _: usize) -> Result<Value, RuntimeError> {
+    let (text, separator) = (string(&arguments[0]), string(&arguments[1]));
+    let parts = match separator {
+        "" => text.chars().map(|c| Value::Str(c.to_string())).collect(),
+        _ => text.split(separator).map(|part| Value::Str(part.to_string())).collect(),
+    };
+    Ok(Value::list(parts))
+}
+
+/// `join(items, separator)` converts the items of the iterable to strings and concatenates them with the separator.
+fn join(_: &mut Interpreter, arguments: Vec<Value>, pos: usize) -> Result<Value, RuntimeError> {
+    let items = arguments[0].iter().map_err(|message| error(pos, message))?;
+    Ok(Value::Str(items.map(|item| item.to_string()).collect::<Vec<String>>().join(string(&arguments[1]))))
+}
+
+/// `format(template, values...)` replaces every `{}` of the template with the next value, `{{` and `}}` are the braces.
+fn format(_: &mut Interpreter, arguments: Vec<Value>, pos: usize) -> Result<Value, RuntimeError> {
+    let template = string(&arguments[0]);
+    let mut values = arguments[1..].iter();
+    let mut result = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('{', Some('{')) | ('}', Some('}')) => {
+                chars.next();
+                result.push(c)
+            },
+            ('{', Some('}')) => {
+                chars.next();
+                match values.next() {
+                    Some(value) => result.push_str(&value.to_string()),
+                    None => return Err(error(pos, format!("'format' has more placeholders than {} values", arguments.len() - 1))),
+                }
+            },
+            ('{', _) | ('}', _) => return Err(error(pos, format!("Unmatched '{}' in the format string, use '{}{}' for the brace", c, c, c))),
+            _ => result.push(c),
+        }
+    }
+    match values.len() {
+        0 => Ok(Value::Str(result)),
+        _ => Err(error(pos, format!("'format' has fewer placeholders than {} values", arguments.len() - 1))),
+    }
+}
+
+#[test]
+fn test_strings() {
+    let list = |items: &[&str]| Value::list(items.iter().map(|item| Value::Str(item.to_string())).collect());
+    assert_eq!(Ok(list(&["a", "b", ""])), run("split(\"a,b,\", \",\")"));
+    assert_eq!(Ok(list(&["x", "y"])), run("\"xy\".split(\"\")"));
+    assert_eq!(Ok(Value::Str(String::from("1-2.5-a"))), run("join([1, 2.5, \"a\"], \"-\")"));
+    assert_eq!(Ok(Value::Str(String::from("a b"))), run("trim(\"  a b \")"));
+}
+
+#[test]
+fn test_format() {
+    assert_eq!(Ok(Value::Str(String::from("x = 1, {y} = [2]"))), run("format(\"x = {}, {{y}} = {}\", 1, [2])"));
+    assert_eq!("'format' has more placeholders than 1 values", run("format(\"{} {}\", 1)").unwrap_err());
+    assert_eq!("'format' has fewer placeholders than 2 values", run("format(\"{}\", 1, 2)").unwrap_err());
+    assert_eq!("Unmatched '}' in the format string, use '}}' for the brace", run("format(\"}\")").unwrap_err());
+}
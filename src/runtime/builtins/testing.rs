@@ -0,0 +1,89 @@
+use crate::dto::RuntimeError;
+use crate::runtime::{ Interpreter, Value };
+use crate::runtime::builtins::{ Builtin, error };
+#[cfg(test)]
+use crate::runtime::builtins::run;
+use crate::runtime::operations::is_equal;
+
+pub const BUILTINS: &[Builtin] = &[
+    Builtin::variadic("assert", &["Bool", "Any"], "None", assert),
+    Builtin::new("assertEq", &["Any", "Any"], "None", assert_eq),
+];
+
+/// `assert(condition, message...)` fails with the message joined by spaces like in `print` when the condition is `false`.
+fn assert(_: &mut Interpreter, arguments: Vec<Value>, pos: usize) -> Result<Value, RuntimeError> {
+    match (&arguments[0], &arguments[1..]) {
+        (Value::Bool(true), _) => Ok(Value::None),
+        (_, []) => Err(error(pos, String::from("Assertion failed"))),
+        (_, message) => {
+            let message: Vec<String> = message.iter().map(Value::to_string).collect();
+            Err(error(pos, format!("Assertion failed: {}", message.join(" "))))
+        },
+    }
+}
+
+/// `assertEq(actual, expected)` fails when the values are not equal by `==`.
+///
+/// The multiline strings are shown as the diff of their lines, the other values as they are written in the source.
+fn assert_eq(_: &mut Interpreter, arguments: Vec<Value>, pos: usize) -> Result<Value, RuntimeError> {
+    let (actual, expected) = (&arguments[0], &arguments[1]);
+    if is_equal(actual, expected) {
+        return Ok(Value::None)
+    }
+    let message = match (actual, expected) {
+        (Value::Str(actual), Value::Str(expected)) if actual.contains('\n') || expected.contains('\n') =>
+            format!("--- expected\n+++ actual\n{}", diff(expected, actual)),
+        _ => format!("expected: {}\n  actual: {}", expected.repr(), actual.repr()),
+    };
+    Err(error(pos, format!("Assertion failed: values are not equal\n{}", message)))
+}
+
+/// Lines of both texts marked with `-` if they are only in the expected text, with `+` if they are only in the actual one.
+///
+/// The common lines are the longest common subsequence, so the diff is the shortest one.
+fn diff(expected: &str, actual: &str) -> String {
+    let expected: Vec<&str> = expected.split('\n').collect();
+    let actual: Vec<&str> = actual.split('\n').collect();
+    // common[i][j] is the length of the longest common subsequence of expected[i..] and actual[j..]
+    let mut common = vec!(vec!(0; actual.len() + 1); expected.len() + 1);
+    for i in (0..expected.len()).rev() {
+        for j in (0..actual.len()).rev() {
+            common[i][j] = match expected[i] == actual[j] {
+                true => common[i + 1][j + 1] + 1,
+                false => common[i + 1][j].max(common[i][j + 1]),
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut lines = vec!();
+    while i < expected.len() || j < actual.len() {
+        if i < expected.len() && j < actual.len() && expected[i] == actual[j] {
+            lines.push(format!(" {}", expected[i]));
+            i += 1;
+            j += 1;
+        } else if j == actual.len() || (i < expected.len() && common[i + 1][j] >= common[i][j + 1]) {
+            lines.push(format!("-{}", expected[i]));
+            i += 1;
+        } else {
+            lines.push(format!("+{}", actual[j]));
+            j += 1;
+        }
+    }
+    lines.join("\n")
+}
+
+#[test]
+fn test_assertions() {
+    assert_eq!(Ok(Value::None), run("assert(1 < 2)\nassertEq([1, 2], [1, 2])\nassertEq(2.0, 2)"));
+    assert_eq!(Err(String::from("Assertion failed")), run("assert(false)"));
+    assert_eq!(Err(String::from("Assertion failed: x is 1")), run("x = 1\nassert(x > 1, \"x is\", x)"));
+    assert_eq!(Err(String::from("Argument 1 of 'assert' must be Bool, found Int")), run("assert(1)"));
+    assert_eq!(
+        Err(String::from("Assertion failed: values are not equal\nexpected: \"1\"\n  actual: 1")),
+        run("assertEq(1, \"1\")"),
+    );
+    assert_eq!(
+        Err(String::from("Assertion failed: values are not equal\n--- expected\n+++ actual\n a\n-b\n+B\n c\n+d")),
+        run("assertEq(\"a\nB\nc\nd\", \"a\nb\nc\")"),
+    );
+}
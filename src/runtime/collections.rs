@@ -0,0 +1,227 @@
+use crate::runtime::{ Range, Value };
+use crate::runtime::operations::is_equal;
+use crate::runtime::value::visit_once;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::ptr;
+
+/// Dictionary created by the literal `{"a": 1, "b": 2}`, entries keep the order of insertion.
+#[derive(Debug, Clone, Default)]
+pub struct Map {
+    entries: Vec<(Value, Value)>,
+    positions: HashMap<Key, usize>,
+}
+
+/// Hashable form of the map key. Only immutable values of the types with the exact equality can be the keys,
+/// so `Float`, `List` and `Map` are not allowed.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Key {
+    None,
+    Bool(bool),
+    Int(i64),
+    Str(String),
+    Tuple(Vec<Key>),
+}
+
+impl Key {
+
+    fn from(value: &Value) -> Result<Key, String> {
+        match value {
+            Value::None => Ok(Key::None),
+            Value::Bool(value) => Ok(Key::Bool(*value)),
+            Value::Int(value) => Ok(Key::Int(*value)),
+            Value::Str(value) => Ok(Key::Str(value.clone())),
+            Value::Tuple(values) => Ok(Key::Tuple(values.iter().map(Key::from).collect::<Result<Vec<Key>, String>>()?)),
+            _ => Err(format!("{} can't be the map key", value.type_name())),
+        }
+    }
+}
+
+impl Map {
+
+    pub fn new() -> Map {
+        Map::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, key: &Value) -> Result<Option<Value>, String> {
+        Ok(self.positions.get(&Key::from(key)?).map(|position| self.entries[*position].1.clone()))
+    }
+
+    /// Adds the new entry to the end of the map or replaces the value of the existing key.
+    pub fn insert(&mut self, key: Value, value: Value) -> Result<(), String> {
+        match self.positions.get(&Key::from(&key)?) {
+            Some(position) => self.entries[*position].1 = value,
+            None => {
+                self.positions.insert(Key::from(&key)?, self.entries.len());
+                self.entries.push((key, value))
+            },
+        }
+        Ok(())
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &Value> {
+        self.entries.iter().map(|(key, _)| key)
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &(Value, Value)> {
+        self.entries.iter()
+    }
+}
+
+/// Maps are equal if they have the same entries in any order.
+impl PartialEq for Map {
+    fn eq(&self, other: &Map) -> bool {
+        ptr::eq(self, other) || visit_once((self as *const Map as *const (), other as *const Map as *const ()), || {
+            self.len() == other.len() && self.entries.iter().all(|(key, value)| match other.get(key) {
+                Ok(Some(other)) => is_equal(value, &other),
+                _ => false,
+            })
+        }).unwrap_or(true)
+    }
+}
+
+/// The map which contains itself is printed as `{...}` inside of itself.
+impl fmt::Display for Map {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let entries = visit_once((self as *const Map as *const (), ptr::null()), || {
+            self.entries.iter().map(|(key, value)| format!("{}: {}", key.repr(), value.repr())).collect::<Vec<String>>()
+        });
+        match entries {
+            Some(entries) => write!(f, "{{{}}}", entries.join(", ")),
+            None => write!(f, "{{...}}"),
+        }
+    }
+}
+
+/// Evaluates `collection[index]`: the item of the list, tuple or string, or the value of the map key.
+///
+/// The range index `xs[1..3]` creates the slice of the same type as the collection.
+pub fn index(collection: &Value, index: &Value) -> Result<Value, String> {
+    match (collection, index) {
+        (Value::Map(map), key) => map.borrow().get(key)?.ok_or_else(|| format!("Key {} is not found in the map", key.repr())),
+        (Value::List(values), Value::Range(range)) => Ok(Value::list(slice(&values.borrow(), range)?)),
+        (Value::Tuple(values), Value::Range(range)) => Ok(Value::tuple(slice(values, range)?)),
+        (Value::Str(value), Value::Range(range)) => {
+            let chars: Vec<char> = value.chars().collect();
+            Ok(Value::Str(slice(&chars, range)?.into_iter().collect()))
+        },
+        (Value::List(values), index) => {
+            let values = values.borrow();
+            Ok(values[position(collection, index, values.len())?].clone())
+        },
+        (Value::Tuple(values), index) => Ok(values[position(collection, index, values.len())?].clone()),
+        (Value::Str(value), index) => {
+            let position = position(collection, index, value.chars().count())?;
+            Ok(Value::Str(value.chars().nth(position).unwrap().to_string()))
+        },
+        _ => Err(format!("{} can't be indexed", collection.type_name())),
+    }
+}
+
+/// Changes the item of the list or the value of the map key, tuples and strings are immutable.
+pub fn set_index(collection: &Value, index: &Value, value: Value) -> Result<(), String> {
+    match collection {
+        Value::Map(map) => map.borrow_mut().insert(index.clone(), value),
+        Value::List(values) => {
+            let position = position(collection, index, values.borrow().len())?;
+            values.borrow_mut()[position] = value;
+            Ok(())
+        },
+        Value::Tuple(_) | Value::Str(_) => Err(format!("{} is immutable", collection.type_name())),
+        _ => Err(format!("{} can't be indexed", collection.type_name())),
+    }
+}
+
+/// Evaluates `item in collection`: the item of the list or tuple, the key of the map,
+/// the substring of the string or the number within the range.
+pub fn contains(collection: &Value, item: &Value) -> Result<bool, String> {
+    match (collection, item) {
+        (Value::List(values), item) => Ok(values.borrow().iter().any(|value| is_equal(value, item))),
+        (Value::Tuple(values), item) => Ok(values.iter().any(|value| is_equal(value, item))),
+        (Value::Map(map), key) => Ok(Key::from(key).is_ok() && map.borrow().get(key)?.is_some()),
+        (Value::Str(value), Value::Str(item)) => Ok(value.contains(item.as_str())),
+        (Value::Str(_), item) => Err(format!("String can contain only String, found {}", item.type_name())),
+        (Value::Range(range), item) => Ok(range.contains(item)),
+        _ => Err(format!("{} is not a collection", collection.type_name())),
+    }
+}
+
+/// Number of the items of the collection, the length of the string is the number of its characters.
+pub fn len(collection: &Value) -> Result<usize, String> {
+    match collection {
+        Value::List(values) => Ok(values.borrow().len()),
+        Value::Tuple(values) => Ok(values.len()),
+        Value::Map(map) => Ok(map.borrow().len()),
+        Value::Str(value) => Ok(value.chars().count()),
+        _ => Err(format!("{} has no length", collection.type_name())),
+    }
+}
+
+fn position(collection: &Value, index: &Value, len: usize) -> Result<usize, String> {
+    match index {
+        Value::Int(index) => usize::try_from(*index).ok().filter(|index| *index < len)
+            .ok_or_else(|| format!("Index {} is out of bounds for the {} of length {}", index, collection.type_name(), len)),
+        _ => Err(format!("{} index must be Int, found {}", collection.type_name(), index.type_name())),
+    }
+}
+
+/// Omitted bounds of the range are the bounds of the collection, so `xs[..2]` and `xs[2..]` split the list.
+fn slice<T: Clone>(items: &[T], range: &Range) -> Result<Vec<T>, String> {
+    let len = items.len() as i64;
+    let is_reversed = matches!(range.step, Some(Value::Int(step)) if step < 0);
+    let start = range.start.clone().unwrap_or(Value::Int(if is_reversed { len - 1 } else { 0 }));
+    let (end, is_inclusive) = match &range.end {
+        Some(end) => (end.clone(), range.is_inclusive),
+        None if is_reversed => (Value::Int(0), true),
+        None => (Value::Int(len), false),
+    };
+    let step = range.step.clone().unwrap_or(Value::None);
+    let bounds = Range::new(start, end, step, is_inclusive, range.is_step_range)?;
+    let mut result = vec!();
+    for index in bounds.iter()? {
+        match index {
+            Value::Int(index) if 0 <= index && index < len => result.push(items[index as usize].clone()),
+            Value::Int(_) => return Err(format!("Slice {} is out of bounds for the length {}", range, len)),
+            _ => return Err(format!("Slice bounds must be Int, found {}", index.type_name())),
+        }
+    }
+    Ok(result)
+}
+
+#[test]
+fn test_index_and_slice() {
+    let list = Value::list(vec!(Value::Int(1), Value::Int(2), Value::Int(3)));
+    let range = |start: Value, end: Value| Value::Range(std::rc::Rc::new(Range::new(start, end, Value::None, false, false).unwrap()));
+    assert_eq!(Ok(Value::Int(2)), index(&list, &Value::Int(1)));
+    assert_eq!(Ok(Value::list(vec!(Value::Int(2), Value::Int(3)))), index(&list, &range(Value::Int(1), Value::None)));
+    assert_eq!(Ok(Value::Str(String::from("bc"))), index(&Value::Str(String::from("abcd")), &range(Value::Int(1), Value::Int(3))));
+    assert_eq!(Err(String::from("Index 3 is out of bounds for the List of length 3")), index(&list, &Value::Int(3)));
+    assert_eq!(Err(String::from("Slice ..4 is out of bounds for the length 3")), index(&list, &range(Value::None, Value::Int(4))));
+    assert_eq!(Err(String::from("List index must be Int, found Float")), index(&list, &Value::Float(0.0)));
+    assert_eq!(Ok(()), set_index(&list, &Value::Int(0), Value::None));
+    assert_eq!(Ok(true), contains(&list, &Value::None));
+    assert_eq!(Err(String::from("Tuple is immutable")), set_index(&Value::tuple(vec!()), &Value::Int(0), Value::None));
+}
+
+#[test]
+fn test_maps() {
+    let mut map = Map::new();
+    map.insert(Value::Str(String::from("b")), Value::Int(1)).unwrap();
+    map.insert(Value::tuple(vec!(Value::Int(1), Value::None)), Value::Int(2)).unwrap();
+    map.insert(Value::Str(String::from("b")), Value::Int(3)).unwrap();
+    assert_eq!("{\"b\": 3, (1, None): 2}", map.to_string());
+    assert_eq!(Ok(Some(Value::Int(2))), map.get(&Value::tuple(vec!(Value::Int(1), Value::None))));
+    assert_eq!(Err(String::from("Float can't be the map key")), map.insert(Value::Float(1.0), Value::None));
+    let map = Value::map(map);
+    assert_eq!(Err(String::from("Key \"a\" is not found in the map")), index(&map, &Value::Str(String::from("a"))));
+    assert_eq!(Ok(false), contains(&map, &Value::list(vec!())));
+}
@@ -0,0 +1,162 @@
+//! Conversions between the Rust types and the values of the Avo program for the host of the [`Engine`].
+//!
+//! [`Engine`]: crate::Engine
+
+use crate::runtime::Value;
+use crate::runtime::collections::Map;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+impl From<()> for Value {
+    fn from(_: ()) -> Value {
+        Value::None
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Value {
+        Value::Bool(value)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Value {
+        Value::Int(value)
+    }
+}
+
+impl From<i32> for Value {
+    fn from(value: i32) -> Value {
+        Value::Int(value as i64)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Value {
+        Value::Float(value)
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Value {
+        Value::Str(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Value {
+        Value::Str(value.to_string())
+    }
+}
+
+/// `None` becomes the Avo `None`.
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Value {
+        value.map(Into::into).unwrap_or(Value::None)
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(values: Vec<T>) -> Value {
+        Value::list(values.into_iter().map(Into::into).collect())
+    }
+}
+
+/// Keys of the map are sorted, so the order of the entries does not depend on the hashing.
+impl<T: Into<Value>> From<HashMap<String, T>> for Value {
+    fn from(entries: HashMap<String, T>) -> Value {
+        let mut entries: Vec<(String, T)> = entries.into_iter().collect();
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let mut map = Map::new();
+        for (key, value) in entries {
+            // String is always the valid key
+            map.insert(Value::Str(key), value.into()).unwrap();
+        }
+        Value::map(map)
+    }
+}
+
+impl TryFrom<Value> for bool {
+    type Error = String;
+
+    fn try_from(value: Value) -> Result<bool, String> {
+        match value {
+            Value::Bool(value) => Ok(value),
+            value => Err(mismatch("Bool", &value)),
+        }
+    }
+}
+
+impl TryFrom<Value> for i64 {
+    type Error = String;
+
+    fn try_from(value: Value) -> Result<i64, String> {
+        match value {
+            Value::Int(value) => Ok(value),
+            value => Err(mismatch("Int", &value)),
+        }
+    }
+}
+
+/// Int is converted to the float like in the type annotations.
+impl TryFrom<Value> for f64 {
+    type Error = String;
+
+    fn try_from(value: Value) -> Result<f64, String> {
+        match value {
+            Value::Int(value) => Ok(value as f64),
+            Value::Float(value) => Ok(value),
+            value => Err(mismatch("Float", &value)),
+        }
+    }
+}
+
+impl TryFrom<Value> for String {
+    type Error = String;
+
+    fn try_from(value: Value) -> Result<String, String> {
+        match value {
+            Value::Str(value) => Ok(value),
+            value => Err(mismatch("String", &value)),
+        }
+    }
+}
+
+impl TryFrom<Value> for () {
+    type Error = String;
+
+    fn try_from(value: Value) -> Result<(), String> {
+        match value {
+            Value::None => Ok(()),
+            value => Err(mismatch("None", &value)),
+        }
+    }
+}
+
+/// Lists and tuples are converted item by item.
+impl<T: TryFrom<Value, Error = String>> TryFrom<Value> for Vec<T> {
+    type Error = String;
+
+    fn try_from(value: Value) -> Result<Vec<T>, String> {
+        let items = match value {
+            Value::List(values) => values.borrow().clone(),
+            Value::Tuple(values) => values.as_ref().clone(),
+            value => return Err(mismatch("List", &value)),
+        };
+        items.into_iter().map(T::try_from).collect()
+    }
+}
+
+#[inline]
+fn mismatch(expected: &str, value: &Value) -> String {
+    format!("Expected {}, found {}", expected, value.type_name())
+}
+
+#[test]
+fn test_conversions() {
+    assert_eq!(Value::list(vec!(Value::Int(1), Value::None)), Value::from(vec!(Some(1), None)));
+    assert_eq!("{\"a\": 1, \"b\": 2}", Value::from([("b", 2), ("a", 1)].iter().map(|(k, v)| (k.to_string(), *v)).collect::<HashMap<String, i64>>()).to_string());
+    assert_eq!(Ok(vec!(1.0, 2.5)), Vec::<f64>::try_from(Value::tuple(vec!(Value::Int(1), Value::Float(2.5)))));
+    assert_eq!(Err(String::from("Expected Int, found String")), i64::try_from(Value::from("1")));
+    assert_eq!(Err(String::from("Expected Bool, found None")), Vec::<bool>::try_from(Value::from(vec!(Value::None))));
+}
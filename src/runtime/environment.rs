@@ -0,0 +1,125 @@
+use crate::runtime::Value;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// Storage of the single variable. Shared between the scope and everything that refers to it.
+pub type Slot = Rc<RefCell<Variable>>;
+
+/// Global variables of the module.
+pub type Globals = Rc<RefCell<HashMap<String, Slot>>>;
+
+#[derive(Debug)]
+pub struct Variable {
+    pub value: Value,
+    pub is_mutable: bool,
+}
+
+/// Global variables and the stack of the local lexical scopes.
+///
+/// Globals are shared between all environments created with [`for_call`], so the functions
+/// see the global variables declared after them.
+///
+/// [`for_call`]: Environment::for_call
+#[derive(Debug)]
+pub struct Environment {
+    globals: Globals,
+    scopes: Vec<HashMap<String, Slot>>,
+}
+
+impl Default for Environment {
+
+    fn default() -> Environment {
+        Environment::new()
+    }
+}
+
+impl Environment {
+
+    pub fn new() -> Environment {
+        Environment {
+            globals: Rc::new(RefCell::new(HashMap::new())),
+            scopes: vec!(),
+        }
+    }
+
+    /// Creates the environment for the function body: the globals of the module where the function
+    /// is declared and the captured variables as the only local scope.
+    pub fn for_call(globals: &Globals, captures: &HashMap<String, Slot>) -> Environment {
+        Environment {
+            globals: globals.clone(),
+            scopes: vec!(captures.clone()),
+        }
+    }
+
+    pub fn globals(&self) -> &Globals {
+        &self.globals
+    }
+
+    pub fn push_scope(&mut self) {
+        self.scopes.push(HashMap::new())
+    }
+
+    pub fn pop_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    /// Finds the variable in the innermost scope where it is declared.
+    pub fn lookup(&self, name: &str) -> Option<Slot> {
+        match self.lookup_local(name) {
+            Some(slot) => Some(slot),
+            None => self.globals.borrow().get(name).cloned(),
+        }
+    }
+
+    /// Same as [`lookup`], but ignores the global variables.
+    ///
+    /// [`lookup`]: Environment::lookup
+    pub fn lookup_local(&self, name: &str) -> Option<Slot> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name).cloned())
+    }
+
+    /// Returns `true` if the variable is declared in the innermost scope.
+    pub fn is_declared_in_current_scope(&self, name: &str) -> bool {
+        match self.scopes.last() {
+            Some(scope) => scope.contains_key(name),
+            None => self.globals.borrow().contains_key(name),
+        }
+    }
+
+    /// Declares the variable in the innermost scope, shadowing the variables with the same name from outer scopes.
+    pub fn declare(&mut self, name: &str, value: Value, is_mutable: bool) -> Slot {
+        let slot = Rc::new(RefCell::new(Variable { value, is_mutable }));
+        match self.scopes.last_mut() {
+            Some(scope) => scope.insert(name.to_string(), slot.clone()),
+            None => self.globals.borrow_mut().insert(name.to_string(), slot.clone()),
+        };
+        slot
+    }
+}
+
+#[test]
+fn test_scopes() {
+    let mut environment = Environment::new();
+    environment.declare("a", Value::Int(1), false);
+    environment.push_scope();
+    assert!(!environment.is_declared_in_current_scope("a"));
+    environment.declare("a", Value::Int(2), true);
+    assert_eq!(Value::Int(2), environment.lookup("a").unwrap().borrow().value);
+    environment.pop_scope();
+    assert_eq!(Value::Int(1), environment.lookup("a").unwrap().borrow().value);
+    assert!(environment.lookup_local("a").is_none());
+    assert!(environment.lookup("b").is_none());
+}
+
+#[test]
+fn test_call_environment_shares_globals() {
+    let mut environment = Environment::new();
+    let captured = Rc::new(RefCell::new(Variable { value: Value::Int(1), is_mutable: true }));
+    let mut captures = HashMap::new();
+    captures.insert(String::from("c"), captured);
+    let call = Environment::for_call(environment.globals(), &captures);
+    environment.declare("late", Value::Int(2), false);
+    assert_eq!(Value::Int(2), call.lookup("late").unwrap().borrow().value);
+    assert_eq!(Value::Int(1), call.lookup_local("c").unwrap().borrow().value);
+}
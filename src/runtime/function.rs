@@ -0,0 +1,85 @@
+use crate::dto::{ Node, Token };
+use crate::runtime::Value;
+use crate::runtime::environment::{ Slot, Globals };
+use std::collections::HashMap;
+use std::fmt;
+
+/// Lambda or the declared function with the variables captured at the moment of its creation.
+///
+/// Parameters may have the type annotation, and the declared function may have the return type.
+/// Immutable variables are captured by value and `mut` variables are captured by reference,
+/// so the changes made by the lambda are visible outside of it and vice versa.
+/// Global variables are looked up in the module where the function is created.
+pub struct Function {
+    pub name: Option<String>,
+    pub parameters: Vec<Node>,
+    pub body: Node,
+    pub return_type: Option<String>,
+    pub captures: HashMap<String, Slot>,
+    pub globals: Globals,
+}
+
+impl Function {
+
+    /// Number of parameters without default values.
+    pub fn required_parameters(&self) -> usize {
+        self.parameters.iter().filter(|parameter| parameter.children.is_empty()).count()
+    }
+
+    /// Type annotation of the parameter, if any.
+    pub fn parameter_type(parameter: &Node) -> Option<&str> {
+        match parameter.condition.first().and_then(|annotation| annotation.data.as_ref()) {
+            Some(Token::Identifier { name, .. }) => Some(name),
+            _ => None,
+        }
+    }
+}
+
+/// Function implemented in Rust and registered by the host program, see [`Engine::register_fn`].
+///
+/// [`Engine::register_fn`]: crate::Engine::register_fn
+pub struct NativeFunction {
+    pub name: String,
+    pub function: Box<dyn Fn(Vec<Value>) -> Result<Value, String>>,
+}
+
+/// Functions are equal only to themselves.
+impl PartialEq for Function {
+    fn eq(&self, other: &Function) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+// captures may refer to the function itself, so they are not printed
+impl fmt::Debug for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+impl PartialEq for NativeFunction {
+    fn eq(&self, other: &NativeFunction) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl fmt::Debug for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+impl fmt::Display for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<native fn {}>", self.name)
+    }
+}
+
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "<fn {}>", name),
+            None => write!(f, "<lambda>"),
+        }
+    }
+}
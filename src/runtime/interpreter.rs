@@ -0,0 +1,960 @@
+use crate::dto::{ Node, NodeType, Operator, Token, RuntimeError, StackGuard };
+use crate::dto::stack::DEFAULT_STACK_SIZE;
+use crate::analyzer::free_variables;
+use crate::runtime::{ Value, Environment, Range, Function };
+use crate::runtime::builtins::{ self, call_builtin, is_builtin };
+use crate::runtime::builtins::math::Random;
+use crate::runtime::collections::{ Map, index, set_index };
+use crate::runtime::module::Module;
+use crate::runtime::environment::{ Slot, Variable };
+use crate::runtime::limits::{ Budget, Limit, Limits, size_of };
+use crate::runtime::operations::{ binary_operation, unary_operation };
+use crate::runtime::patterns::matches;
+use crate::runtime::types::{ conform, is_instance_of };
+use crate::runtime::structs::{ Struct, Enum, Field };
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{ self, BufRead, Write };
+use std::mem;
+use std::rc::Rc;
+#[cfg(test)]
+use crate::parser::parse;
+
+/// Tree-walking interpreter of the syntax tree built by the [`TreeBuilder`].
+///
+/// [`TreeBuilder`]: crate::parser::TreeBuilder
+pub struct Interpreter {
+    environment: Environment,
+    /// Modules available for the import by the path written in the `import` statement.
+    modules: HashMap<String, Rc<Module>>,
+    /// Streams of the I/O builtins, the standard ones by default.
+    input: Box<dyn BufRead>,
+    output: Box<dyn Write>,
+    random: Random,
+    budget: Budget,
+}
+
+/// Reason to stop the evaluation of the current node: an error, the loop control or the `return` statement.
+#[derive(Debug)]
+enum Interrupt {
+    Error(RuntimeError),
+    Break { pos: usize },
+    Continue { pos: usize },
+    Return { value: Value, pos: usize },
+}
+
+impl fmt::Debug for Interpreter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Interpreter").field("environment", &self.environment).field("modules", &self.modules).finish()
+    }
+}
+
+impl From<RuntimeError> for Interrupt {
+    fn from(error: RuntimeError) -> Interrupt {
+        Interrupt::Error(error)
+    }
+}
+
+type EvalResult<T> = Result<T, Interrupt>;
+
+impl Default for Interpreter {
+
+    fn default() -> Interpreter {
+        Interpreter::new()
+    }
+}
+
+impl Interpreter {
+
+    pub fn new() -> Interpreter {
+        Interpreter::with_io(Box::new(io::BufReader::new(io::stdin())), Box::new(io::stdout()))
+    }
+
+    /// Creates the interpreter which reads the input of `readLine()` and writes the output of `print()` to the given streams.
+    pub fn with_io(input: Box<dyn BufRead>, output: Box<dyn Write>) -> Interpreter {
+        Interpreter {
+            environment: Environment::new(),
+            modules: HashMap::new(),
+            input,
+            output,
+            random: Random::new(),
+            budget: Budget::new(Limits::default()),
+        }
+    }
+
+    pub fn input(&mut self) -> &mut dyn BufRead {
+        self.input.as_mut()
+    }
+
+    pub fn output(&mut self) -> &mut dyn Write {
+        self.output.as_mut()
+    }
+
+    pub fn random(&mut self) -> &mut Random {
+        &mut self.random
+    }
+
+    pub fn set_input(&mut self, input: Box<dyn BufRead>) {
+        self.input = input
+    }
+
+    pub fn set_output(&mut self, output: Box<dyn Write>) {
+        self.output = output
+    }
+
+    /// Sets the limits of the execution, they are counted from the next [`restart_limits`].
+    ///
+    /// [`restart_limits`]: Interpreter::restart_limits
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.budget = Budget::new(limits)
+    }
+
+    /// Resets the used resources, e.g. before the next script.
+    pub fn restart_limits(&mut self) {
+        self.budget.start()
+    }
+
+    /// The limit which stopped the last execution, if any.
+    pub fn exceeded_limit(&self) -> Option<&Limit> {
+        self.budget.exceeded.as_ref()
+    }
+
+    /// Counts the memory allocated by the builtin, see [`Limits::max_memory`].
+    pub fn allocate(&mut self, size: usize, pos: usize) -> Result<(), RuntimeError> {
+        self.budget.allocate(size).map_err(|limit| RuntimeError { pos, message: limit.to_string() })
+    }
+
+    /// Checks that the builtin is allowed by [`Limits::allowed_builtins`].
+    pub fn check_builtin(&mut self, name: &str, pos: usize) -> Result<(), RuntimeError> {
+        self.budget.check_builtin(name).map_err(|limit| RuntimeError { pos, message: limit.to_string() })
+    }
+
+    /// Returns the value of the global variable.
+    pub fn global(&self, name: &str) -> Option<Value> {
+        self.environment.globals().borrow().get(name).map(|slot| slot.borrow().value.clone())
+    }
+
+    /// Declares the immutable global variable or replaces the existing one.
+    pub fn set_global(&mut self, name: &str, value: Value) {
+        let slot = Rc::new(RefCell::new(Variable { value, is_mutable: false }));
+        self.environment.globals().borrow_mut().insert(name.to_string(), slot);
+    }
+
+    pub fn global_names(&self) -> Vec<String> {
+        self.environment.globals().borrow().keys().cloned().collect()
+    }
+
+    /// Makes the already executed module available for the `import` statements of the program.
+    pub fn add_module(&mut self, path: &str, module: Rc<Module>) {
+        self.modules.insert(path.to_string(), module);
+    }
+
+    /// Executes the root block of the module and returns the module with its public members.
+    pub fn run_module(&mut self, name: &str, root: &Node) -> Result<Rc<Module>, RuntimeError> {
+        self.run(root)?;
+        Ok(Rc::new(Module {
+            name: name.to_string(),
+            globals: self.environment.globals().clone(),
+            exports: Module::public_names(root).into_iter().map(String::from).collect(),
+        }))
+    }
+
+    /// Executes the statements of the root block in the global scope
+    /// and returns the value of the last one.
+    ///
+    /// Imports, functions, structs and enums of the root block are hoisted, so they can be used before the declaration.
+    /// The recursion which uses more than [`DEFAULT_STACK_SIZE`] of the stack, or the stack of the outer [`StackGuard`],
+    /// stops with the runtime error.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// # use avoc::Value;
+    /// # use avoc::internals::{ TokenReader, TreeBuilder, Interpreter };
+    /// let tokens = TokenReader::new().parse("a = 2\na * 21").unwrap();
+    /// let tree = TreeBuilder::new().build_tree(&tokens).unwrap();
+    ///
+    /// assert_eq!(Value::Int(42), Interpreter::new().run(&tree).unwrap());
+    /// ```
+    pub fn run(&mut self, root: &Node) -> Result<Value, RuntimeError> {
+        let _guard = StackGuard::new(DEFAULT_STACK_SIZE);
+        for statement in root.children.iter().filter(|statement| statement.node_type == NodeType::Import) {
+            self.import(statement)?;
+        }
+        for statement in root.children.iter().map(Node::declaration) {
+            match statement.node_type {
+                NodeType::Function => self.declare_function(statement),
+                NodeType::Struct => self.declare_struct(statement),
+                NodeType::Enum => self.declare_enum(statement),
+                _ => (),
+            }
+        }
+        match self.eval_statements(&root.children) {
+            Ok(value) => Ok(value),
+            Err(Interrupt::Error(e)) => Err(e),
+            Err(Interrupt::Break { pos }) => Err(RuntimeError { pos, message: String::from("'break' outside of the loop") }),
+            Err(Interrupt::Continue { pos }) => Err(RuntimeError { pos, message: String::from("'continue' outside of the loop") }),
+            Err(Interrupt::Return { pos, .. }) => Err(RuntimeError { pos, message: String::from("'return' outside of the function") }),
+        }
+    }
+
+    /// Runs the body of the `test` from the root block, the module must be already executed by [`run`],
+    /// so the test sees its global variables. `return` ends the test successfully.
+    ///
+    /// [`run`]: Interpreter::run
+    pub fn run_test(&mut self, test: &Node) -> Result<(), RuntimeError> {
+        let _guard = StackGuard::new(DEFAULT_STACK_SIZE);
+        let environment = Environment::for_call(self.environment.globals(), &HashMap::new());
+        let module_environment = mem::replace(&mut self.environment, environment);
+        let result = self.eval(&test.children[0]);
+        self.environment = module_environment;
+        match result {
+            Ok(_) | Err(Interrupt::Return { .. }) => Ok(()),
+            Err(Interrupt::Error(e)) => Err(e),
+            Err(Interrupt::Break { pos }) | Err(Interrupt::Continue { pos }) =>
+                Err(RuntimeError { pos, message: String::from("Loop control statement outside of the loop") }),
+        }
+    }
+
+    /// Calls the function value with the arguments, e.g. the lambda passed to the builtin function.
+    pub fn call(&mut self, function: &Value, arguments: Vec<Value>, pos: usize) -> Result<Value, RuntimeError> {
+        let _guard = StackGuard::new(DEFAULT_STACK_SIZE);
+        match self.call_value(function, arguments, pos) {
+            Ok(value) => Ok(value),
+            Err(Interrupt::Error(e)) => Err(e),
+            Err(Interrupt::Break { pos }) | Err(Interrupt::Continue { pos }) | Err(Interrupt::Return { pos, .. }) =>
+                Err(RuntimeError { pos, message: String::from("Loop control statement outside of the loop") }),
+        }
+    }
+
+    /// Functions are called and structs create their instances.
+    fn call_value(&mut self, function: &Value, arguments: Vec<Value>, pos: usize) -> EvalResult<Value> {
+        match function {
+            Value::Function(function) => self.call_function(function, arguments, pos),
+            Value::Native(function) => (function.function)(arguments).map_err(|message| Interrupt::Error(RuntimeError { pos, message })),
+            Value::Struct(definition) => Struct::instantiate(definition, arguments)
+                .map_err(|message| Interrupt::Error(RuntimeError { pos, message })),
+            _ => Err(Interrupt::Error(RuntimeError { pos, message: format!("{} is not a function", function.type_name()) })),
+        }
+    }
+
+    fn eval_statements(&mut self, statements: &[Node]) -> EvalResult<Value> {
+        let mut value = Value::None;
+        for statement in statements {
+            value = self.eval(statement)?;
+        }
+        Ok(value)
+    }
+
+    /// Evaluates the node within the limits: every node is the step, and the new strings and collections use the memory.
+    fn eval(&mut self, node: &Node) -> EvalResult<Value> {
+        self.budget.step().map_err(|limit| error(node, limit.to_string()))?;
+        let value = self.eval_node(node)?;
+        match (node.node_type, &value) {
+            (NodeType::List, _) | (NodeType::Map, _) | (NodeType::Tuple, _) | (NodeType::Expression, Value::Str(_)) =>
+                self.allocate(size_of(&value), node.pos())?,
+            _ => (),
+        }
+        Ok(value)
+    }
+
+    fn eval_node(&mut self, node: &Node) -> EvalResult<Value> {
+        match node.node_type {
+            NodeType::Token => self.eval_token(node),
+            NodeType::Expression => self.eval_expression(node),
+            NodeType::Block => {
+                self.environment.push_scope();
+                let result = self.eval_statements(&node.children);
+                self.environment.pop_scope();
+                result
+            },
+            NodeType::If => self.eval_if(node),
+            NodeType::Call => self.eval_call(node),
+            NodeType::Lambda => self.eval_lambda(node),
+            NodeType::Range => self.eval_range(node),
+            NodeType::For => self.eval_for(node),
+            NodeType::Loop => self.eval_loop(node),
+            NodeType::Break => Err(Interrupt::Break { pos: node.pos() }),
+            NodeType::Continue => Err(Interrupt::Continue { pos: node.pos() }),
+            NodeType::Tuple => Ok(Value::tuple(self.eval_items(&node.children)?)),
+            NodeType::List => Ok(Value::list(self.eval_items(&node.children)?)),
+            NodeType::Map => {
+                let mut map = Map::new();
+                for entry in node.children.chunks(2) {
+                    let key = self.eval(&entry[0])?;
+                    let value = self.eval(&entry[1])?;
+                    map.insert(key, value).map_err(|message| error(&entry[0], message))?;
+                }
+                Ok(Value::map(map))
+            },
+            NodeType::Match => self.eval_match(node),
+            NodeType::Function => {
+                self.declare_function(node);
+                Ok(Value::None)
+            },
+            NodeType::Struct => {
+                self.declare_struct(node);
+                Ok(Value::None)
+            },
+            NodeType::Enum => {
+                self.declare_enum(node);
+                Ok(Value::None)
+            },
+            NodeType::Return => {
+                let value = match node.children.first() {
+                    Some(value) => self.eval(value)?,
+                    None => Value::None,
+                };
+                Err(Interrupt::Return { value, pos: node.pos() })
+            },
+            NodeType::Arm | NodeType::Rest | NodeType::Variant => Err(error(node, String::from("Pattern can be used only in the match arm"))),
+            NodeType::Type => Err(error(node, String::from("Type can be used only in the annotation"))),
+            // imports are executed before the other statements of the module
+            NodeType::Import => Ok(Value::None),
+            // tests are run only by `avoc test`, see `run_test`
+            NodeType::Test => Ok(Value::None),
+            NodeType::Public => self.eval(&node.children[0]),
+        }
+    }
+
+    /// `import a.b` declares the module as `b`, `from a.b import c` declares the public member `c` of the module.
+    fn import(&mut self, node: &Node) -> Result<(), RuntimeError> {
+        let path: Vec<&str> = node.children.iter().map(name_of).collect();
+        let path = path.join(".");
+        let module = match self.modules.get(&path) {
+            Some(module) => module.clone(),
+            None => return Err(RuntimeError { pos: node.pos(), message: format!("Module '{}' is not loaded", path) }),
+        };
+        if node.condition.is_empty() {
+            let name = name_of(node.children.last().unwrap());
+            self.environment.declare(name, Value::Module(module), false);
+            return Ok(())
+        }
+        for imported in &node.condition {
+            let value = module.get(name_of(imported)).map_err(|message| RuntimeError { pos: imported.pos(), message })?;
+            self.environment.declare(name_of(imported), value, false);
+        }
+        Ok(())
+    }
+
+    fn eval_items(&mut self, nodes: &[Node]) -> EvalResult<Vec<Value>> {
+        let mut values = Vec::with_capacity(nodes.len());
+        for node in nodes {
+            values.push(self.eval(node)?);
+        }
+        Ok(values)
+    }
+
+    fn eval_token(&mut self, node: &Node) -> EvalResult<Value> {
+        match &node.data {
+            Some(Token::IntConstant { value, .. }) => Ok(Value::Int(*value)),
+            Some(Token::FloatConstant { value, .. }) => Ok(Value::Float(*value)),
+            Some(Token::StringConstant { value, .. }) => Ok(Value::Str(value.to_string())),
+            Some(Token::BoolConstant { value, .. }) => Ok(Value::Bool(*value)),
+            Some(Token::NoneConstant { .. }) => Ok(Value::None),
+            Some(Token::Identifier { name, .. }) => match self.environment.lookup(name) {
+                Some(slot) => Ok(slot.borrow().value.clone()),
+                None => Err(error(node, format!("Undefined variable '{}'", name))),
+            },
+            _ => Err(error(node, String::from("Unexpected token"))),
+        }
+    }
+
+    fn eval_expression(&mut self, node: &Node) -> EvalResult<Value> {
+        let operator = match node.operator() {
+            Some(operator) => operator,
+            None => return Err(error(node, String::from("Unexpected token"))),
+        };
+        match operator {
+            _ if operator.is_assignment() => self.eval_assignment(operator, node),
+            Operator::Mut => Err(error(node, String::from("Mutable variable must be declared with assignment: mut name = value"))),
+            Operator::Dot => self.eval_member(node),
+            Operator::LeftBracket => {
+                let collection = self.eval(&node.children[0])?;
+                let key = self.eval(&node.children[1])?;
+                index(&collection, &key).map_err(|message| error(node, message))
+            },
+            Operator::Is => {
+                let value = self.eval(&node.children[0])?;
+                Ok(Value::Bool(is_of_type(&value, &node.children[1])))
+            },
+            // logical operators do not evaluate the right operand if the result is already known
+            Operator::And | Operator::Or => {
+                let lhs = self.eval_condition(&node.children[0])?;
+                if lhs == (operator == Operator::Or) {
+                    return Ok(Value::Bool(lhs))
+                }
+                Ok(Value::Bool(self.eval_condition(&node.children[1])?))
+            },
+            Operator::UnaryMinus | Operator::Not => {
+                let operand = self.eval(&node.children[0])?;
+                unary_operation(operator, &operand).map_err(|message| error(node, message))
+            },
+            _ => {
+                let lhs = self.eval(&node.children[0])?;
+                let rhs = self.eval(&node.children[1])?;
+                binary_operation(operator, &lhs, &rhs).map_err(|message| error(node, message))
+            },
+        }
+    }
+
+    /// Assignment to the new name declares the variable in the current scope,
+    /// only variables declared with `mut` can be assigned again.
+    fn eval_assignment(&mut self, operator: Operator, node: &Node) -> EvalResult<Value> {
+        let target = &node.children[0];
+        let value = self.eval(&node.children[1])?;
+        match (target.operator(), &target.data) {
+            (Some(Operator::Dot), _) => {
+                let instance = match self.eval(&target.children[0])? {
+                    Value::Instance(instance) => instance,
+                    object => return Err(error(target, format!("{} has no fields", object.type_name()))),
+                };
+                let name = identifier_name(&target.children[1])?;
+                let value = match operator.compound_base() {
+                    None => value,
+                    Some(base) => instance.get(name).and_then(|current| binary_operation(base, &current, &value)).map_err(|message| error(node, message))?,
+                };
+                instance.set(name, value).map_err(|message| error(node, message))
+            },
+            (Some(Operator::LeftBracket), _) => {
+                let collection = self.eval(&target.children[0])?;
+                let key = self.eval(&target.children[1])?;
+                let value = match operator.compound_base() {
+                    None => value,
+                    Some(base) => index(&collection, &key).and_then(|current| binary_operation(base, &current, &value)).map_err(|message| error(node, message))?,
+                };
+                set_index(&collection, &key, value.clone()).map_err(|message| error(node, message))?;
+                Ok(value)
+            },
+            (Some(Operator::Mut), _) => {
+                let name = identifier_name(&target.children[0])?;
+                if operator != Operator::Assign {
+                    return Err(error(node, format!("Variable '{}' must be declared with '='", name)))
+                }
+                if self.environment.is_declared_in_current_scope(name) {
+                    return Err(error(target, format!("Variable '{}' is already declared in this scope", name)))
+                }
+                self.environment.declare(name, value.clone(), true);
+                Ok(value)
+            },
+            (_, Some(Token::Identifier { name, .. })) => match self.environment.lookup(name) {
+                Some(slot) => {
+                    let mut variable = slot.borrow_mut();
+                    if !variable.is_mutable {
+                        return Err(error(node, format!("Cannot assign twice to immutable variable '{}', declare it with 'mut'", name)))
+                    }
+                    variable.value = match operator.compound_base() {
+                        None => value,
+                        Some(base) => binary_operation(base, &variable.value, &value).map_err(|message| error(node, message))?,
+                    };
+                    Ok(variable.value.clone())
+                },
+                None if operator == Operator::Assign => {
+                    self.environment.declare(name, value.clone(), false);
+                    Ok(value)
+                },
+                None => Err(error(target, format!("Undefined variable '{}'", name))),
+            },
+            _ => Err(error(target, String::from("Invalid assignment target"))),
+        }
+    }
+
+    /// Evaluates `instance.field`, `Enum.Variant`, `Enum.Variant(arguments)`, `module.member` and method calls.
+    ///
+    /// Methods are the builtin functions called with the object as the first argument: `xs.map(f)` is `map(xs, f)`.
+    fn eval_member(&mut self, node: &Node) -> EvalResult<Value> {
+        let object = self.eval(&node.children[0])?;
+        let member = &node.children[1];
+        match (&object, member.node_type) {
+            (Value::Instance(instance), NodeType::Token) => instance.get(identifier_name(member)?).map_err(|message| error(node, message)),
+            (Value::Enum(definition), NodeType::Token) => definition.variant(identifier_name(member)?).map_err(|message| error(node, message)),
+            (Value::Enum(definition), NodeType::Call) => {
+                let constructor = definition.variant(name_of(member)).map_err(|message| error(member, message))?;
+                let arguments = self.eval_items(&member.children)?;
+                self.call_value(&constructor, arguments, member.pos())
+            },
+            (Value::Module(module), NodeType::Token) => module.get(identifier_name(member)?).map_err(|message| error(member, message)),
+            (Value::Module(module), NodeType::Call) => {
+                let function = module.get(name_of(member)).map_err(|message| error(member, message))?;
+                let arguments = self.eval_items(&member.children)?;
+                self.call_value(&function, arguments, member.pos())
+            },
+            (_, NodeType::Call) if is_builtin(name_of(member)) => {
+                let mut arguments = vec!(object);
+                arguments.extend(self.eval_items(&member.children)?);
+                Ok(call_builtin(self, name_of(member), arguments, member.pos()).unwrap()?)
+            },
+            (_, NodeType::Call) => Err(error(member, format!("{} has no method '{}'", object.type_name(), name_of(member)))),
+            _ => Err(error(node, format!("{} has no fields", object.type_name()))),
+        }
+    }
+
+    fn eval_if(&mut self, node: &Node) -> EvalResult<Value> {
+        match self.eval_condition(&node.condition[0])? {
+            true => self.eval(&node.children[0]),
+            false => match node.children.get(1) {
+                Some(otherwise) => self.eval(otherwise),
+                None => Ok(Value::None),
+            },
+        }
+    }
+
+    /// Evaluates the first arm whose pattern matches the value and whose guard is `true`.
+    ///
+    /// Names bound by the pattern are visible in the guard and the result of the arm only.
+    fn eval_match(&mut self, node: &Node) -> EvalResult<Value> {
+        let value = self.eval(&node.condition[0])?;
+        for arm in &node.children {
+            let mut bindings = vec!();
+            if !matches(&arm.condition[0], &value, &mut bindings) {
+                continue
+            }
+            self.environment.push_scope();
+            for (name, value) in bindings {
+                self.environment.declare(&name, value, false);
+            }
+            let result = match arm.condition.get(1) {
+                Some(guard) => match self.eval_condition(guard) {
+                    Ok(true) => self.eval(&arm.children[0]).map(Some),
+                    Ok(false) => Ok(None),
+                    Err(e) => Err(e),
+                },
+                None => self.eval(&arm.children[0]).map(Some),
+            };
+            self.environment.pop_scope();
+            if let Some(result) = result? {
+                return Ok(result)
+            }
+        }
+        Err(error(node, format!("No match arm for value {}", value.repr())))
+    }
+
+    fn eval_condition(&mut self, node: &Node) -> EvalResult<bool> {
+        match self.eval(node)? {
+            Value::Bool(value) => Ok(value),
+            value => Err(error(node, format!("Condition must be Bool, found {}", value.type_name()))),
+        }
+    }
+
+    /// Ranges are created lazily: only the bounds are evaluated here.
+    fn eval_range(&mut self, node: &Node) -> EvalResult<Value> {
+        let start = self.eval(&node.children[0])?;
+        let end = self.eval(&node.children[1])?;
+        let step = self.eval(&node.children[2])?;
+        let is_step_range = node.operator() == Some(Operator::Colon);
+        let is_inclusive = is_step_range || node.operator() == Some(Operator::InclusiveRange);
+        match Range::new(start, end, step, is_inclusive, is_step_range) {
+            Ok(range) => Ok(Value::Range(Rc::new(range))),
+            Err(message) => Err(error(node, message)),
+        }
+    }
+
+    fn eval_for(&mut self, node: &Node) -> EvalResult<Value> {
+        let iterable = self.eval(&node.condition[0])?;
+        let name = identifier_name(&node.children[0])?;
+        let body = &node.children[1];
+        let values = iterable.iter().map_err(|message| error(&node.condition[0], message))?;
+        for value in values {
+            self.environment.push_scope();
+            self.environment.declare(name, value, false);
+            let result = self.eval(body);
+            self.environment.pop_scope();
+            match result {
+                Ok(_) | Err(Interrupt::Continue { .. }) => (),
+                Err(Interrupt::Break { .. }) => break,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(Value::None)
+    }
+
+    fn eval_lambda(&mut self, node: &Node) -> EvalResult<Value> {
+        Ok(Value::Function(Rc::new(Function {
+            name: None,
+            parameters: node.condition.clone(),
+            body: node.children[0].clone(),
+            return_type: None,
+            captures: self.capture(node, None),
+            globals: self.environment.globals().clone(),
+        })))
+    }
+
+    /// Declares the immutable variable with the function value in the current scope.
+    ///
+    /// The function refers to itself by its name without capturing, see [`eval_function_body`].
+    ///
+    /// [`eval_function_body`]: Interpreter::eval_function_body
+    fn declare_function(&mut self, node: &Node) {
+        let name = name_of(node);
+        let function = Function {
+            name: Some(name.to_string()),
+            parameters: node.condition.clone(),
+            body: node.children[0].clone(),
+            return_type: node.children.get(1).map(|annotation| name_of(annotation).to_string()),
+            captures: self.capture(node, Some(name)),
+            globals: self.environment.globals().clone(),
+        };
+        self.environment.declare(name, Value::Function(Rc::new(function)), false);
+    }
+
+    /// Declares the immutable variable with the struct type, it is called to create the instances.
+    fn declare_struct(&mut self, node: &Node) {
+        let name = name_of(node);
+        let fields = node.children.iter().map(field).collect();
+        self.environment.declare(name, Value::Struct(Rc::new(Struct { name: name.to_string(), fields, enum_name: None })), false);
+    }
+
+    /// Declares the enum and its variants, so variants can be used without the name of the enum.
+    fn declare_enum(&mut self, node: &Node) {
+        let name = name_of(node);
+        let variants: Vec<Rc<Struct>> = node.children.iter().map(|variant| Rc::new(Struct {
+            name: name_of(variant).to_string(),
+            fields: variant.children.iter().map(field).collect(),
+            enum_name: Some(name.to_string()),
+        })).collect();
+        let definition = Enum { name: name.to_string(), variants };
+        for variant in &definition.variants {
+            if let Ok(value) = definition.variant(&variant.name) {
+                self.environment.declare(&variant.name, value, false);
+            }
+        }
+        self.environment.declare(name, Value::Enum(Rc::new(definition)), false);
+    }
+
+    /// Captures the variables of the enclosing local scopes used by the lambda or the function.
+    ///
+    /// Global variables are not captured, they are looked up at the moment of the call.
+    fn capture(&self, node: &Node, own_name: Option<&str>) -> HashMap<String, Slot> {
+        let mut captures = HashMap::new();
+        for name in free_variables(node) {
+            if Some(name.as_str()) == own_name {
+                continue
+            }
+            if let Some(slot) = self.environment.lookup_local(&name) {
+                let captured = match slot.borrow().is_mutable {
+                    true => slot.clone(),
+                    false => Rc::new(RefCell::new(Variable { value: slot.borrow().value.clone(), is_mutable: false })),
+                };
+                captures.insert(name, captured);
+            }
+        }
+        captures
+    }
+
+    /// Calls the function from the variable or the builtin function with the same name.
+    fn eval_call(&mut self, node: &Node) -> EvalResult<Value> {
+        let name = name_of(node);
+        let arguments = self.eval_items(&node.children)?;
+        let function = self.environment.lookup(name).map(|slot| slot.borrow().value.clone());
+        match function {
+            Some(function @ Value::Function(_)) | Some(function @ Value::Native(_)) | Some(function @ Value::Struct(_)) =>
+                self.call_value(&function, arguments, node.pos()),
+            Some(value) => Err(error(node, format!("'{}' is {}, not a function", name, value.type_name()))),
+            None => match call_builtin(self, name, arguments, node.pos()) {
+                Some(result) => result.map_err(Interrupt::Error),
+                None => Err(error(node, format!("Unknown function '{}'", name))),
+            },
+        }
+    }
+
+    fn call_function(&mut self, function: &Rc<Function>, arguments: Vec<Value>, pos: usize) -> EvalResult<Value> {
+        let required = function.required_parameters();
+        let total = function.parameters.len();
+        if arguments.len() < required || arguments.len() > total {
+            let expected = match required == total {
+                true => builtins::arguments(total),
+                false => format!("{} to {} arguments", required, total),
+            };
+            return Err(Interrupt::Error(RuntimeError { pos, message: format!("Expected {}, found {}", expected, arguments.len()) }))
+        }
+        self.budget.enter().map_err(|limit| RuntimeError { pos, message: limit.to_string() })?;
+        let environment = Environment::for_call(&function.globals, &function.captures);
+        let caller_environment = mem::replace(&mut self.environment, environment);
+        let result = self.eval_function_body(function, arguments, pos);
+        self.environment = caller_environment;
+        self.budget.leave();
+        let value = match result {
+            Ok(value) | Err(Interrupt::Return { value, .. }) => value,
+            Err(Interrupt::Break { pos }) => return Err(Interrupt::Error(RuntimeError { pos, message: String::from("'break' outside of the loop") })),
+            Err(Interrupt::Continue { pos }) => return Err(Interrupt::Error(RuntimeError { pos, message: String::from("'continue' outside of the loop") })),
+            Err(e) => return Err(e),
+        };
+        match &function.return_type {
+            Some(return_type) => conform(return_type, value).map_err(|value| Interrupt::Error(RuntimeError {
+                pos,
+                message: format!("{} must return {}, found {}", function, return_type, value.type_name()),
+            })),
+            None => Ok(value),
+        }
+    }
+
+    /// Binds the arguments and evaluates the body, default values may refer to the previous parameters.
+    ///
+    /// The declared function sees itself by its name, so it may be recursive even inside of the other function.
+    fn eval_function_body(&mut self, function: &Rc<Function>, arguments: Vec<Value>, pos: usize) -> EvalResult<Value> {
+        self.environment.push_scope();
+        if let Some(name) = &function.name {
+            self.environment.declare(name, Value::Function(function.clone()), false);
+        }
+        let mut arguments = arguments.into_iter();
+        for parameter in &function.parameters {
+            let name = identifier_name(parameter)?;
+            let value = match (arguments.next(), parameter.children.first()) {
+                (Some(value), _) => value,
+                (None, Some(default)) => self.eval(default)?,
+                (None, None) => Value::None,
+            };
+            let value = match Function::parameter_type(parameter) {
+                Some(type_name) => conform(type_name, value).map_err(|value| Interrupt::Error(RuntimeError {
+                    pos,
+                    message: format!("Parameter '{}' must be {}, found {}", name, type_name, value.type_name()),
+                }))?,
+                None => value,
+            };
+            self.environment.declare(name, value, false);
+        }
+        self.eval(&function.body)
+    }
+
+    fn eval_loop(&mut self, node: &Node) -> EvalResult<Value> {
+        loop {
+            match self.eval(&node.children[0]) {
+                Ok(_) | Err(Interrupt::Continue { .. }) => (),
+                Err(Interrupt::Break { .. }) => return Ok(Value::None),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+#[inline]
+fn error(node: &Node, message: String) -> Interrupt {
+    Interrupt::Error(RuntimeError { pos: node.pos(), message })
+}
+
+/// Creates the field of the struct or the variant from its declaration: `name`, `name: Type` or `mut name`.
+fn field(node: &Node) -> Field {
+    let (declaration, is_mutable) = match node.operator() {
+        Some(Operator::Mut) => (&node.children[0], true),
+        _ => (node, false),
+    };
+    Field {
+        name: name_of(declaration).to_string(),
+        type_name: Function::parameter_type(declaration).map(str::to_string),
+        is_mutable,
+    }
+}
+
+/// Right operand of `is` is the name of the type: `x is Int`, `s is Circle` or `s is Shape.Circle`.
+fn is_of_type(value: &Value, node: &Node) -> bool {
+    match (&node.data, node.operator()) {
+        (Some(Token::NoneConstant { .. }), _) => *value == Value::None,
+        (Some(Token::Identifier { name, .. }), _) => is_instance_of(value, name),
+        (_, Some(Operator::Dot)) => match value {
+            Value::Instance(instance) =>
+                instance.definition.name == name_of(&node.children[1]) && instance.definition.enum_name.as_deref() == Some(name_of(&node.children[0])),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+#[inline]
+fn name_of(node: &Node) -> &str {
+    match &node.data {
+        Some(Token::Identifier { name, .. }) | Some(Token::Function { name, .. }) => name,
+        _ => "",
+    }
+}
+
+fn identifier_name(node: &Node) -> EvalResult<&str> {
+    match &node.data {
+        Some(Token::Identifier { name, .. }) => Ok(name),
+        _ => Err(error(node, String::from("Expected variable name"))),
+    }
+}
+
+#[cfg(test)]
+fn run(source: &str) -> Result<Value, RuntimeError> {
+    let tree = parse(source);
+    Interpreter::new().run(&tree)
+}
+
+#[test]
+fn test_arithmetic_and_variables() {
+    assert_eq!(Value::Int(42), run("a = 2\nb = a * 20\nb + 2").unwrap());
+    assert_eq!(Value::Int(-7), run("-(3 + 4)").unwrap());
+    assert_eq!(Value::Float(1.5), run("mut x = 1\nx += 0.5\nx").unwrap());
+}
+
+#[test]
+fn test_bool_and_none_in_conditions() {
+    assert_eq!(Value::Int(1), run("a = None\nif a == None { 1 } else { 2 }").unwrap());
+    assert_eq!(Value::Int(2), run("if not true or false { 1 } else if 1 < 2 and true != false { 2 }").unwrap());
+    assert_eq!(Value::None, run("if false { 1 }").unwrap());
+    // `and` does not evaluate the undefined variable on the right
+    assert_eq!(Value::Bool(false), run("false and undefined").unwrap());
+    assert_eq!("Condition must be Bool, found None", run("if None { 1 }").unwrap_err().message);
+}
+
+#[test]
+fn test_loops_over_ranges() {
+    assert_eq!(Value::Int(45), run("mut sum = 0\nfor i in 0..10 { sum += i }\nsum").unwrap());
+    assert_eq!(Value::Int(55), run("mut sum = 0\nfor i in 1..=10 { sum += i }\nsum").unwrap());
+    assert_eq!(Value::Int(2), run("mut n = 0\nfor x in 0:0.5:1 { if x == 0.5 { continue }\n n += 1 }\nn").unwrap());
+    // open range is iterated lazily until break
+    assert_eq!(Value::Int(128), run("mut p = 1\nfor i in 0.. { if i == 7 { break }\n p *= 2 }\np").unwrap());
+    assert_eq!(Value::Int(3), run("mut i = 0\nloop {\n i += 1\n if i >= 3 { break }\n}\ni").unwrap());
+    assert_eq!("'break' outside of the loop", run("if true { break }").unwrap_err().message);
+    assert_eq!("Range without start can't be iterated", run("for i in ..3 { }").unwrap_err().message);
+    assert_eq!(Value::Str(String::from("1..=3")), run("r = 1..=3\n\"\" + r").unwrap());
+}
+
+#[test]
+fn test_values_which_contain_themselves() {
+    let source = "xs = [1]\nxs.push(xs)\nys = [1]\nys.push(ys)\nstruct Node { value, mut next }\nn = Node(1, None)\nn.next = n\n";
+    assert_eq!(Value::Str(String::from("[1, [...]] Node(value: 1, next: Node(...))")), run(&format!("{}format(\"{{}} {{}}\", xs, n)", source)).unwrap());
+    assert_eq!(Value::Bool(true), run(&format!("{}xs == xs and xs == ys and n == n", source)).unwrap());
+}
+
+#[test]
+fn test_lambdas() {
+    assert_eq!(Value::Int(7), run("add = { @ a, b = 1 -> a + b }\nadd(2, 4) + add(0)").unwrap());
+    assert_eq!(Value::Int(12), run("scale = { @ x, factor = x -> x * factor }\nscale(2) + scale(2, 4)").unwrap());
+    assert_eq!("Expected 1 to 2 arguments, found 0", run("f = { @ a, b = 1 -> a }\nf()").unwrap_err().message);
+    assert_eq!("Expected 1 argument, found 2", run("f = { @ a -> a }\nf(1, 2)").unwrap_err().message);
+    assert_eq!("Expected 2 arguments, found 1", run("f = { @ a, b -> a }\nf(1)").unwrap_err().message);
+    // lambdas may be passed to the other lambdas
+    assert_eq!(Value::Int(9), run("twice = { @ f, x -> f(f(x)) }\ntwice({ @ x -> x * 3 }, 1)").unwrap());
+}
+
+#[test]
+fn test_lambda_captures() {
+    // immutable variables are captured by value, mutable ones by reference
+    let source = "make = { @ ->\n  mut count = 0\n  step = 2\n  { @ -> count += step }\n}\ncounter = make()\ncounter()\ncounter()";
+    assert_eq!(Value::Int(4), run(source).unwrap());
+    let source = "result = if true {\n  mut total = 0\n  add = { @ x -> total += x }\n  add(5)\n  add(6)\n  total\n}\nresult";
+    assert_eq!(Value::Int(11), run(source).unwrap());
+    // captured immutable variable can't be assigned
+    assert!(run("if true {\n  a = 1\n  f = { @ -> a = 2 }\n  f()\n}").is_err());
+    // globals are not captured, so the lambda sees the variables declared later
+    assert_eq!(Value::Int(3), run("f = { @ -> late }\nlate = 3\nf()").unwrap());
+}
+
+#[test]
+fn test_map_and_filter() {
+    assert_eq!("[0, 2, 4]", run("map(0..3){ @ x -> x * 2 }").unwrap().to_string());
+    assert_eq!("[1, 3]", run("filter(0..5, { @ x -> x - x / 2 * 2 == 1 })").unwrap().to_string());
+    assert_eq!("[\"a!\", \"b!\"]", run("map(\"ab\", { @ c -> c + \"!\" })").unwrap().to_string());
+    assert_eq!(Value::Int(6), run("mut sum = 0\nfor x in map(1..=3, { @ x -> x }) { sum += x }\nsum").unwrap());
+    assert_eq!("Filter predicate must return Bool, found Int", run("filter(0..5, { @ x -> x })").unwrap_err().message);
+}
+
+#[test]
+fn test_immutable_variables() {
+    assert_eq!(8, run("a = 1\na = 2").unwrap_err().pos);
+    assert!(run("mut a = 1\nmut a = 2").is_err());
+    assert_eq!(Value::Int(1), run("a = 1\nif true { mut a = 2 }\na").unwrap());
+    assert_eq!(Value::Int(3), run("mut a = 1\nif true { a = 3 }\na").unwrap());
+}
+
+#[test]
+fn test_match() {
+    let source = "describe = { @ x -> match x {\n  0 -> \"zero\"\n  n if n < 0 -> \"negative\"\n  1..=9 -> \"digit\"\n  _ -> \"big\"\n}}\ndescribe(-3) + describe(0) + describe(7) + describe(10)";
+    assert_eq!(Value::Str(String::from("negativezerodigitbig")), run(source).unwrap());
+    assert_eq!(Value::Int(5), run("match (2, (3,)) { (a, (b,)) -> a + b }").unwrap());
+    assert_eq!(Value::Int(6), run("mut sum = 0\nfor x in map(1..=3, { @ x -> (x, x > 1) }) { sum += match x { (n, true) -> n, _ -> 1 } }\nsum").unwrap());
+    assert_eq!("[2, 3]", run("match map(1..=3, { @ x -> x }) { [] -> None, [_, ..rest] -> rest }").unwrap().to_string());
+    // bindings of the arm are not visible outside of it
+    assert!(run("match 1 { n -> n }\nn").is_err());
+    assert_eq!("No match arm for value \"a\"", run("match \"a\" { 1 -> 1 }").unwrap_err().message);
+}
+
+#[test]
+fn test_functions() {
+    let source = "fn fact(n: Int) -> Int {\n  if n <= 1 { return 1 }\n  n * fact(n - 1)\n}\nfact(10)";
+    assert_eq!(Value::Int(3628800), run(source).unwrap());
+    // global functions are hoisted and may be mutually recursive
+    let source = "r = is_even(10)\nfn is_even(n) { if n == 0 { true } else { is_odd(n - 1) } }\nfn is_odd(n) { if n == 0 { false } else { is_even(n - 1) } }\nr";
+    assert_eq!(Value::Bool(true), run(source).unwrap());
+    // nested function captures the local variables and sees itself
+    let source = "fn outer(k) {\n  fn count(n) { if n == 0 { 0 } else { k + count(n - 1) } }\n  count(3)\n}\nouter(2)";
+    assert_eq!(Value::Int(6), run(source).unwrap());
+    // functions are values
+    assert_eq!("[0.5, 1.0]", run("fn half(x: Float) -> Float { x / 2 }\nmap(1..3, half)").unwrap().to_string());
+    assert_eq!(Value::None, run("fn f() { for i in 0.. { if i == 3 { return } } }\nf()").unwrap());
+    assert_eq!("Parameter 'x' must be Float, found String", run("fn half(x: Float) { x }\nhalf(\"a\")").unwrap_err().message);
+    assert_eq!("<fn f> must return Int, found None", run("fn f() -> Int { }\nf()").unwrap_err().message);
+    assert_eq!("'return' outside of the function", run("return 1").unwrap_err().message);
+}
+
+#[test]
+fn test_structs() {
+    let source = "struct Point { x: Float, mut y: Float }\np = Point(1, 2)\nq = p\nq.y += 0.5\np.x + p.y";
+    assert_eq!(Value::Float(3.5), run(source).unwrap());
+    let source = "struct Line { start, end }\nstruct Point { x, y }\nfn length(line: Line) -> Int { line.end.x - line.start.x }\nlength(Line(Point(1, 0), Point(4, 0)))";
+    assert_eq!(Value::Int(3), run(source).unwrap());
+    assert_eq!("[Pair(a: 0, b: \"x\"), Pair(a: 1, b: \"x\")]", run("struct Pair { a, b }\nmap(0..2, { @ i -> Pair(i, \"x\") })").unwrap().to_string());
+    assert_eq!(Value::Bool(true), run("struct P { a }\nP(1) == P(1) and P(1) != P(2)").unwrap());
+    assert_eq!("Field 'x' of P is immutable, declare it with 'mut'", run("struct P { x }\np = P(1)\np.x = 2").unwrap_err().message);
+    assert_eq!("Parameter 'p' must be P, found Int", run("struct P { x }\nfn f(p: P) { p }\nf(1)").unwrap_err().message);
+    assert_eq!("Int has no fields", run("a = 1\na.x").unwrap_err().message);
+}
+
+#[test]
+fn test_enums() {
+    let source = "enum Shape { Circle(r), Rect(w, h), Empty }\nfn area(s: Shape) -> Float {\n  match s {\n    Circle(r) -> 3.0 * r * r\n    Shape.Rect(w, h) -> w * h\n    Empty -> 0\n  }\n}\nmap((Circle(1), Shape.Rect(2, 3), Shape.Empty, Empty), area)";
+    assert_eq!("[3.0, 6.0, 0.0, 0.0]", run(source).unwrap().to_string());
+    assert_eq!("Rect(1, \"a\")", run("enum Shape { Rect(w, h) }\nShape.Rect(1, \"a\")").unwrap().to_string());
+    assert_eq!(Value::Bool(true), run("enum E { A(x), B }\nA(1) == E.A(1) and B == E.B and A(1) != A(2)").unwrap());
+    let source = "enum E { A(x), B }\nstruct P { x }\n(A(1) is A, A(1) is E, A(1) is E.B, B is E.B, P(1) is P, P(1) is E, 1 is Int, None is None, 1.5 is Any)";
+    assert_eq!("(true, true, false, true, true, false, true, true, true)", run(source).unwrap().to_string());
+    assert_eq!("E has no variant 'C'", run("enum E { A }\nE.C").unwrap_err().message);
+    assert_eq!("A expects 1 fields, found 0", run("enum E { A(x) }\nA()").unwrap_err().message);
+}
+
+#[test]
+fn test_collections() {
+    let source = "mut xs = [1, 2, 3,\n  4]\nm = {\"a\": 1, (1, 2): [5]}\nm[\"b\"] = xs[1..3]\nxs[0] += 10\nxs.push(7)\n(xs, m)";
+    assert_eq!("([11, 2, 3, 4, 7], {\"a\": 1, (1, 2): [5], \"b\": [2, 3]})", run(source).unwrap().to_string());
+    let source = "[1, 2, 3].map{ @ x -> x * 2 }.filter{ @ x -> x > 2 }.reduce(0){ @ sum, x -> sum + x }";
+    assert_eq!(Value::Int(10), run(source).unwrap());
+    let source = "m = {\"a\": 1}\n(len(m), \"ell\" in \"hello\", 2 in [1, 2], \"b\" in m, 3 in 0:3:9, \"hello\"[1..=3], {}[(1,)] = 2)";
+    assert_eq!("(1, true, true, false, true, \"ell\", 2)", run(source).unwrap().to_string());
+    assert_eq!(Value::Int(3), run("mut n = 0\nfor key in {1: 2, 2: 3} { n += key }\nn").unwrap());
+    assert_eq!("Index 2 is out of bounds for the List of length 2", run("[1, 2][2]").unwrap_err().message);
+    assert_eq!("Key \"x\" is not found in the map", run("{}[\"x\"]").unwrap_err().message);
+    assert_eq!("Int has no method 'size'", run("n = 1\nn.size()").unwrap_err().message);
+    assert_eq!("Argument 1 of 'push' must be List, found Tuple", run("(1, 2).push(3)").unwrap_err().message);
+}
+
+#[test]
+fn test_modules() {
+    let tree = |source: &str| parse(source);
+    let source = "pub enum Shape { Circle(r), Square(s) }\npub fn area(s: Shape) { scale * match s { Circle(r) -> 3 * r * r, Square(s) -> s * s } }\nscale = 2";
+    let shapes = Interpreter::new().run_module("geo.shapes", &tree(source)).unwrap();
+    let mut interpreter = Interpreter::new();
+    interpreter.add_module("geo.shapes", shapes.clone());
+    let source = "x = shapes.area(Circle(1)) + area(shapes.Shape.Square(2))\nimport geo.shapes\nfrom geo.shapes import area, Circle\nx";
+    assert_eq!(Value::Int(14), interpreter.run(&tree(source)).unwrap());
+    let mut interpreter = Interpreter::new();
+    interpreter.add_module("geo.shapes", shapes);
+    assert_eq!("'scale' is private in module geo.shapes, declare it with 'pub'", interpreter.run(&tree("import geo.shapes\nshapes.scale")).unwrap_err().message);
+    assert_eq!("Module 'geo' is not loaded", run("import geo").unwrap_err().message);
+}
+
+#[test]
+fn test_tests() {
+    let source = "mut runs = 0\ntest \"sees globals\" {\n  runs += 1\n  assertEq(double(runs), 2)\n}\ntest \"fails\" {\n  x = 1\n  assert(x > 1)\n}\nfn double(n) { n * 2 }\nruns";
+    let tree = parse(source);
+    let mut interpreter = Interpreter::new();
+    assert_eq!(Value::Int(0), interpreter.run(&tree).unwrap());
+    assert!(interpreter.run_test(&tree.children[1]).is_ok());
+    assert_eq!(Value::Int(1), interpreter.global("runs").unwrap());
+    let error = interpreter.run_test(&tree.children[2]).unwrap_err();
+    assert_eq!((102, "Assertion failed"), (error.pos, error.message.as_str()));
+    assert_eq!(None, interpreter.global("x"));
+}
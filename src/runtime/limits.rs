@@ -0,0 +1,195 @@
+use crate::runtime::Value;
+use crate::dto::stack::{ has_stack, stack_size };
+use std::fmt;
+use std::mem;
+use std::time::{ Duration, Instant };
+
+/// Limits of the script execution, `None` means no limit.
+///
+/// Limits protect the host of the [`Engine`] from the untrusted scripts: the script is stopped
+/// with the runtime error as soon as any of the limits is exceeded. The stack used by the recursion is limited even
+/// without the limits, see [`Limit::Stack`].
+///
+/// [`Engine`]: crate::Engine
+#[derive(Debug, Clone, Default)]
+pub struct Limits {
+    /// Number of the evaluated nodes of the syntax tree.
+    pub max_steps: Option<u64>,
+    /// Number of the nested function calls.
+    pub max_depth: Option<usize>,
+    /// Total size in bytes of the strings and collections created by the script, the memory is never returned to the budget.
+    pub max_memory: Option<usize>,
+    /// Wall-clock time of the execution.
+    pub timeout: Option<Duration>,
+    /// Names of the builtins which the script may call, all builtins are allowed if it is `None`.
+    pub allowed_builtins: Option<Vec<String>>,
+}
+
+/// Limit which stopped the script.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Limit {
+    Steps(u64),
+    Depth(usize),
+    Memory(usize),
+    Timeout(Duration),
+    Builtin(String),
+    /// The source is nested deeper than [`MAX_DEPTH`], it is checked before the execution.
+    ///
+    /// [`MAX_DEPTH`]: crate::dto::MAX_DEPTH
+    Nesting(usize),
+    /// The walk of the syntax tree has used the stack of its [`StackGuard`], it is checked even without the other limits.
+    ///
+    /// [`StackGuard`]: crate::dto::StackGuard
+    Stack(usize),
+}
+
+/// Resources used by the script since the start of its execution.
+#[derive(Debug)]
+pub struct Budget {
+    pub limits: Limits,
+    steps: u64,
+    depth: usize,
+    memory: usize,
+    started: Instant,
+    /// The first exceeded limit, it is kept until the next start.
+    pub exceeded: Option<Limit>,
+}
+
+/// The timeout is checked once per this number of steps, because reading the clock is slow.
+const CLOCK_INTERVAL: u64 = 256;
+
+impl Limits {
+
+    /// Limits without the file access, suitable for the untrusted formulas.
+    ///
+    /// The deep recursion may exceed the stack before `max_depth`, especially in the debug build.
+    pub fn sandbox() -> Limits {
+        Limits {
+            max_steps: Some(1_000_000),
+            max_depth: Some(200),
+            max_memory: Some(16 * 1024 * 1024),
+            timeout: Some(Duration::from_secs(1)),
+            allowed_builtins: Some(crate::runtime::builtins::pure_builtins().map(String::from).collect()),
+        }
+    }
+}
+
+impl Budget {
+
+    pub fn new(limits: Limits) -> Budget {
+        Budget {
+            limits,
+            steps: 0,
+            depth: 0,
+            memory: 0,
+            started: Instant::now(),
+            exceeded: None,
+        }
+    }
+
+    /// Resets the used resources before the new execution.
+    pub fn start(&mut self) {
+        *self = Budget::new(mem::take(&mut self.limits))
+    }
+
+    pub fn step(&mut self) -> Result<(), Limit> {
+        self.steps += 1;
+        if !has_stack() {
+            return self.exceed(Limit::Stack(stack_size()))
+        }
+        match (self.limits.max_steps, self.limits.timeout) {
+            (Some(max_steps), _) if self.steps > max_steps => self.exceed(Limit::Steps(max_steps)),
+            (_, Some(timeout)) if self.steps.is_multiple_of(CLOCK_INTERVAL) && self.started.elapsed() > timeout => self.exceed(Limit::Timeout(timeout)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Counts the function call, [`leave`] must be called after it.
+    ///
+    /// [`leave`]: Budget::leave
+    pub fn enter(&mut self) -> Result<(), Limit> {
+        match self.limits.max_depth {
+            Some(max_depth) if self.depth >= max_depth => self.exceed(Limit::Depth(max_depth)),
+            _ => {
+                self.depth += 1;
+                Ok(())
+            },
+        }
+    }
+
+    pub fn leave(&mut self) {
+        self.depth -= 1
+    }
+
+    /// Counts the memory of the created value, see [`size_of`].
+    pub fn allocate(&mut self, size: usize) -> Result<(), Limit> {
+        self.memory += size;
+        match self.limits.max_memory {
+            Some(max_memory) if self.memory > max_memory => self.exceed(Limit::Memory(max_memory)),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn check_builtin(&mut self, name: &str) -> Result<(), Limit> {
+        match &self.limits.allowed_builtins {
+            Some(allowed) if !allowed.iter().any(|allowed| allowed == name) => self.exceed(Limit::Builtin(name.to_string())),
+            _ => Ok(()),
+        }
+    }
+
+    #[inline]
+    fn exceed(&mut self, limit: Limit) -> Result<(), Limit> {
+        self.exceeded.get_or_insert_with(|| limit.clone());
+        Err(limit)
+    }
+}
+
+/// Approximate size of the value without the items which are shared with the other values.
+pub fn size_of(value: &Value) -> usize {
+    match value {
+        Value::Str(value) => value.len(),
+        Value::List(values) => values.borrow().len() * mem::size_of::<Value>(),
+        Value::Tuple(values) => values.len() * mem::size_of::<Value>(),
+        Value::Map(map) => map.borrow().len() * 2 * mem::size_of::<Value>(),
+        _ => 0,
+    }
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Limit::Steps(max_steps) => write!(f, "Step limit of {} is exceeded", max_steps),
+            Limit::Depth(max_depth) => write!(f, "Recursion depth limit of {} is exceeded", max_depth),
+            Limit::Memory(max_memory) => write!(f, "Memory limit of {} bytes is exceeded", max_memory),
+            Limit::Timeout(timeout) => write!(f, "Timeout of {} ms is exceeded", timeout.as_millis()),
+            Limit::Builtin(name) => write!(f, "Builtin '{}' is not allowed", name),
+            Limit::Nesting(max_depth) => write!(f, "Nesting depth limit of {} is exceeded", max_depth),
+            Limit::Stack(size) => write!(f, "Stack limit of {} bytes is exceeded", size),
+        }
+    }
+}
+
+#[test]
+fn test_budget() {
+    let mut budget = Budget::new(Limits { max_steps: Some(2), max_depth: Some(1), ..Limits::default() });
+    assert_eq!(Ok(()), budget.step());
+    assert_eq!(Ok(()), budget.enter());
+    assert_eq!(Err(Limit::Depth(1)), budget.enter());
+    budget.leave();
+    assert_eq!(Ok(()), budget.step());
+    assert_eq!(Err(Limit::Steps(2)), budget.step());
+    assert_eq!(Some(Limit::Depth(1)), budget.exceeded);
+    budget.start();
+    assert_eq!((Ok(()), None), (budget.step(), budget.exceeded.clone()));
+    assert_eq!("Memory limit of 1024 bytes is exceeded", Limit::Memory(1024).to_string());
+}
+
+#[test]
+fn test_allocations_and_builtins() {
+    let mut budget = Budget::new(Limits { max_memory: Some(4), allowed_builtins: Some(vec!(String::from("len"))), ..Limits::default() });
+    assert_eq!(Ok(()), budget.allocate(size_of(&Value::Str(String::from("abcd")))));
+    assert_eq!(Ok(()), budget.allocate(size_of(&Value::Int(1))));
+    assert_eq!(Err(Limit::Memory(4)), budget.allocate(size_of(&Value::Str(String::from("e")))));
+    assert_eq!(Ok(()), budget.check_builtin("len"));
+    assert_eq!(Err(Limit::Builtin(String::from("readFile"))), budget.check_builtin("readFile"));
+}
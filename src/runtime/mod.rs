@@ -0,0 +1,19 @@
+pub mod value;
+pub mod range;
+pub mod function;
+pub mod environment;
+pub mod operations;
+pub mod interpreter;
+pub mod builtins;
+pub mod patterns;
+pub mod types;
+pub mod structs;
+pub mod collections;
+pub mod module;
+pub mod convert;
+pub mod limits;
+pub use value::Value;
+pub use range::Range;
+pub use function::Function;
+pub use environment::Environment;
+pub use interpreter::Interpreter;
@@ -0,0 +1,80 @@
+use crate::dto::{ Node, NodeType, Operator, Token };
+use crate::runtime::Value;
+use crate::runtime::environment::Globals;
+use std::fmt;
+#[cfg(test)]
+use crate::parser::parse;
+
+/// Executed module: its global variables and the names declared with `pub`.
+///
+/// `import utils` binds the module to the name `utils`, so its public members are accessed as `utils.name`.
+#[derive(Debug)]
+pub struct Module {
+    pub name: String,
+    pub globals: Globals,
+    pub exports: Vec<String>,
+}
+
+impl Module {
+
+    /// Returns the public member of the module.
+    pub fn get(&self, name: &str) -> Result<Value, String> {
+        let slot = self.globals.borrow().get(name).cloned();
+        match slot {
+            Some(slot) if self.exports.iter().any(|export| export == name) => Ok(slot.borrow().value.clone()),
+            Some(_) => Err(format!("'{}' is private in module {}, declare it with 'pub'", name, self.name)),
+            None => Err(format!("Module {} has no member '{}'", self.name, name)),
+        }
+    }
+
+    /// Names declared with `pub` in the root block, variants of the public enum are public too.
+    pub fn public_names(root: &Node) -> Vec<&str> {
+        let mut names = vec!();
+        for statement in root.children.iter().filter(|statement| statement.node_type == NodeType::Public) {
+            let declaration = statement.declaration();
+            match declaration.node_type {
+                NodeType::Function | NodeType::Struct => names.extend(identifier(declaration)),
+                NodeType::Enum => {
+                    names.extend(identifier(declaration));
+                    names.extend(declaration.children.iter().filter_map(identifier));
+                },
+                _ => {
+                    let target = &declaration.children[0];
+                    names.extend(match target.operator() {
+                        Some(Operator::Mut) => identifier(&target.children[0]),
+                        _ => identifier(target),
+                    })
+                },
+            }
+        }
+        names
+    }
+}
+
+#[inline]
+fn identifier(node: &Node) -> Option<&str> {
+    match &node.data {
+        Some(Token::Identifier { name, .. }) => Some(name),
+        _ => None,
+    }
+}
+
+/// Modules are equal only to themselves.
+impl PartialEq for Module {
+    fn eq(&self, other: &Module) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl fmt::Display for Module {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<module {}>", self.name)
+    }
+}
+
+#[test]
+fn test_public_names() {
+    let source = "pub fn f() {}\nfn g() {}\npub enum E { A, B(x) }\npub mut counter = 0\nsecret = 1\npub struct P { x }";
+    let tree = parse(source);
+    assert_eq!(vec!("f", "E", "A", "B", "counter", "P"), Module::public_names(&tree));
+}
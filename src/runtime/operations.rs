@@ -0,0 +1,161 @@
+use crate::dto::Operator;
+use crate::runtime::Value;
+use crate::runtime::collections::contains;
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+
+/// Applies the binary operator to the already evaluated operands.
+///
+/// Int operands are promoted to Float when the other operand is Float,
+/// any value is converted to the string when it is added to the String.
+pub fn binary_operation(operator: Operator, lhs: &Value, rhs: &Value) -> Result<Value, String> {
+    match operator {
+        Operator::Plus => add(lhs, rhs),
+        Operator::Minus | Operator::Multiply | Operator::Divide | Operator::Power => arithmetic(operator, lhs, rhs),
+        Operator::Equal => Ok(Value::Bool(is_equal(lhs, rhs))),
+        Operator::NotEqual => Ok(Value::Bool(!is_equal(lhs, rhs))),
+        Operator::Less | Operator::LessOrEqual | Operator::Greater | Operator::GreaterOrEqual => {
+            let ordering = compare(lhs, rhs)?;
+            Ok(Value::Bool(match operator {
+                Operator::Less => ordering == Ordering::Less,
+                Operator::LessOrEqual => ordering != Ordering::Greater,
+                Operator::Greater => ordering == Ordering::Greater,
+                _ => ordering != Ordering::Less,
+            }))
+        },
+        Operator::In => contains(rhs, lhs).map(Value::Bool),
+        Operator::And | Operator::Or | Operator::Xor => match (lhs, rhs) {
+            (Value::Bool(a), Value::Bool(b)) => Ok(Value::Bool(match operator {
+                Operator::And => *a && *b,
+                Operator::Or => *a || *b,
+                _ => *a ^ *b,
+            })),
+            _ => Err(format!("Operator '{}' can't be applied to {} and {}", operator, lhs.type_name(), rhs.type_name())),
+        },
+        _ => Err(format!("Unknown operator '{}'", operator)),
+    }
+}
+
+/// Applies the prefix operator to the already evaluated operand.
+pub fn unary_operation(operator: Operator, operand: &Value) -> Result<Value, String> {
+    match (operator, operand) {
+        (Operator::UnaryMinus, Value::Int(value)) => value.checked_neg().map(Value::Int).ok_or_else(overflow),
+        (Operator::UnaryMinus, Value::Float(value)) => Ok(Value::Float(-value)),
+        (Operator::Not, Value::Bool(value)) => Ok(Value::Bool(!value)),
+        _ => Err(format!("Operator '{}' can't be applied to {}", display_operator(operator), operand.type_name())),
+    }
+}
+
+/// Values of the different types are never equal, except for the Int and Float with the same numeric value.
+pub fn is_equal(lhs: &Value, rhs: &Value) -> bool {
+    match (lhs, rhs) {
+        (Value::Int(a), Value::Float(b)) => (*a as f64) == *b,
+        (Value::Float(a), Value::Int(b)) => *a == (*b as f64),
+        _ => lhs == rhs,
+    }
+}
+
+pub fn compare(lhs: &Value, rhs: &Value) -> Result<Ordering, String> {
+    let ordering = match (lhs, rhs) {
+        (Value::Int(a), Value::Int(b)) => Some(a.cmp(b)),
+        (Value::Str(a), Value::Str(b)) => Some(a.cmp(b)),
+        (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+        _ => match (as_float(lhs), as_float(rhs)) {
+            (Some(a), Some(b)) => a.partial_cmp(&b),
+            _ => return Err(format!("Can't compare {} and {}", lhs.type_name(), rhs.type_name())),
+        },
+    };
+    ordering.ok_or_else(|| String::from("Can't compare NaN with a number"))
+}
+
+#[inline]
+fn as_float(value: &Value) -> Option<f64> {
+    match value {
+        Value::Int(value) => Some(*value as f64),
+        Value::Float(value) => Some(*value),
+        _ => None,
+    }
+}
+
+#[inline]
+fn overflow() -> String {
+    String::from("Integer overflow")
+}
+
+#[inline]
+fn display_operator(operator: Operator) -> Operator {
+    match operator {
+        Operator::UnaryMinus => Operator::Minus,
+        _ => operator,
+    }
+}
+
+fn add(lhs: &Value, rhs: &Value) -> Result<Value, String> {
+    match (lhs, rhs) {
+        (Value::Str(a), b) => Ok(Value::Str(format!("{}{}", a, b))),
+        (a, Value::Str(b)) => Ok(Value::Str(format!("{}{}", a, b))),
+        _ => arithmetic(Operator::Plus, lhs, rhs),
+    }
+}
+
+fn arithmetic(operator: Operator, lhs: &Value, rhs: &Value) -> Result<Value, String> {
+    match (lhs, rhs) {
+        (Value::Int(a), Value::Int(b)) => int_arithmetic(operator, *a, *b),
+        _ => match (as_float(lhs), as_float(rhs)) {
+            (Some(a), Some(b)) => Ok(Value::Float(match operator {
+                Operator::Plus => a + b,
+                Operator::Minus => a - b,
+                Operator::Multiply => a * b,
+                Operator::Divide => a / b,
+                _ => a.powf(b),
+            })),
+            _ => Err(format!("Operator '{}' can't be applied to {} and {}", operator, lhs.type_name(), rhs.type_name())),
+        },
+    }
+}
+
+fn int_arithmetic(operator: Operator, a: i64, b: i64) -> Result<Value, String> {
+    let result = match operator {
+        Operator::Plus => a.checked_add(b),
+        Operator::Minus => a.checked_sub(b),
+        Operator::Multiply => a.checked_mul(b),
+        Operator::Divide => match b {
+            0 => return Err(String::from("Division by zero")),
+            _ => a.checked_div(b),
+        },
+        // negative powers of integers are fractions
+        _ => match u32::try_from(b) {
+            Ok(exponent) => a.checked_pow(exponent),
+            Err(_) => return Ok(Value::Float((a as f64).powf(b as f64))),
+        },
+    };
+    result.map(Value::Int).ok_or_else(overflow)
+}
+
+#[test]
+fn test_arithmetic() {
+    assert_eq!(Ok(Value::Int(7)), binary_operation(Operator::Plus, &Value::Int(3), &Value::Int(4)));
+    assert_eq!(Ok(Value::Float(7.5)), binary_operation(Operator::Plus, &Value::Int(3), &Value::Float(4.5)));
+    assert_eq!(Ok(Value::Int(2)), binary_operation(Operator::Divide, &Value::Int(7), &Value::Int(3)));
+    assert_eq!(Ok(Value::Int(1024)), binary_operation(Operator::Power, &Value::Int(2), &Value::Int(10)));
+    assert_eq!(Ok(Value::Float(0.5)), binary_operation(Operator::Power, &Value::Int(2), &Value::Int(-1)));
+    assert_eq!(Ok(Value::Str(String::from("a: 1.5"))), binary_operation(Operator::Plus, &Value::Str(String::from("a: ")), &Value::Float(1.5)));
+    assert!(binary_operation(Operator::Divide, &Value::Int(1), &Value::Int(0)).is_err());
+    assert!(binary_operation(Operator::Multiply, &Value::Int(i64::MAX), &Value::Int(2)).is_err());
+    assert!(binary_operation(Operator::Minus, &Value::Str(String::from("a")), &Value::Int(1)).is_err());
+}
+
+#[test]
+fn test_comparison_and_logic() {
+    assert_eq!(Ok(Value::Bool(true)), binary_operation(Operator::Equal, &Value::Int(10), &Value::Float(10.0)));
+    assert_eq!(Ok(Value::Bool(true)), binary_operation(Operator::Equal, &Value::None, &Value::None));
+    assert_eq!(Ok(Value::Bool(false)), binary_operation(Operator::Equal, &Value::Bool(false), &Value::None));
+    assert_eq!(Ok(Value::Bool(true)), binary_operation(Operator::NotEqual, &Value::Bool(true), &Value::Int(1)));
+    assert_eq!(Ok(Value::Bool(true)), binary_operation(Operator::Less, &Value::Int(1), &Value::Float(1.5)));
+    assert_eq!(Ok(Value::Bool(true)), binary_operation(Operator::GreaterOrEqual, &Value::Str(String::from("b")), &Value::Str(String::from("a"))));
+    assert_eq!(Ok(Value::Bool(true)), binary_operation(Operator::Xor, &Value::Bool(true), &Value::Bool(false)));
+    assert!(binary_operation(Operator::Less, &Value::None, &Value::Int(1)).is_err());
+    assert!(binary_operation(Operator::And, &Value::Bool(true), &Value::Int(1)).is_err());
+    assert_eq!(Ok(Value::Bool(false)), unary_operation(Operator::Not, &Value::Bool(true)));
+    assert!(unary_operation(Operator::UnaryMinus, &Value::Bool(true)).is_err());
+}
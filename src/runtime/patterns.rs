@@ -0,0 +1,127 @@
+use crate::dto::{ Node, NodeType, Operator, Token };
+use crate::runtime::Value;
+use crate::runtime::operations::{ is_equal, compare };
+use std::cmp::Ordering;
+#[cfg(test)]
+use crate::parser::parse;
+
+/// Checks whether the value matches the pattern of the match arm.
+///
+/// Names bound by the pattern are appended to the `bindings`, they are meaningful only if the value matches.
+pub fn matches(pattern: &Node, value: &Value, bindings: &mut Vec<(String, Value)>) -> bool {
+    match pattern.node_type {
+        NodeType::Token => match &pattern.data {
+            Some(Token::Identifier { name, .. }) if name == "_" => true,
+            Some(Token::Identifier { name, .. }) => {
+                bindings.push((name.to_string(), value.clone()));
+                true
+            },
+            Some(token) => literal(token).map(|literal| is_equal(&literal, value)).unwrap_or(false),
+            None => false,
+        },
+        NodeType::Range => is_in_range(pattern, value),
+        NodeType::Tuple => match value {
+            Value::Tuple(values) => values.len() == pattern.children.len()
+                && pattern.children.iter().zip(values.iter()).all(|(pattern, value)| matches(pattern, value, bindings)),
+            _ => false,
+        },
+        NodeType::List => match value {
+            Value::List(values) => matches_list(&pattern.children, &values.borrow(), bindings),
+            _ => false,
+        },
+        // `Circle` without the fields matches any circle
+        NodeType::Variant => match value {
+            Value::Instance(instance) => {
+                let definition = &instance.definition;
+                let is_same_type = Some(definition.name.as_str()) == name(pattern)
+                    && pattern.condition.first().map(|qualifier| definition.enum_name.as_deref() == name(qualifier)).unwrap_or(true);
+                let values = instance.values.borrow();
+                is_same_type && (pattern.children.is_empty() || pattern.children.len() == values.len())
+                    && pattern.children.iter().zip(values.iter()).all(|(pattern, value)| matches(pattern, value, bindings))
+            },
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// Converts the literal token of the pattern to the value.
+pub fn literal(token: &Token) -> Option<Value> {
+    match token {
+        Token::IntConstant { value, .. } => Some(Value::Int(*value)),
+        Token::FloatConstant { value, .. } => Some(Value::Float(*value)),
+        Token::StringConstant { value, .. } => Some(Value::Str(value.to_string())),
+        Token::BoolConstant { value, .. } => Some(Value::Bool(*value)),
+        Token::NoneConstant { .. } => Some(Value::None),
+        _ => None,
+    }
+}
+
+#[inline]
+fn name(node: &Node) -> Option<&str> {
+    match &node.data {
+        Some(Token::Identifier { name, .. }) => Some(name),
+        _ => None,
+    }
+}
+
+/// Range pattern matches the values of the same type as the bounds, so `1..3` does not match `"a"`.
+fn is_in_range(pattern: &Node, value: &Value) -> bool {
+    let bound = |node: &Node| node.data.as_ref().and_then(literal).filter(|bound| *bound != Value::None);
+    let is_inclusive = pattern.operator() == Some(Operator::InclusiveRange);
+    let is_after_start = match bound(&pattern.children[0]) {
+        Some(start) => matches!(compare(value, &start), Ok(Ordering::Greater) | Ok(Ordering::Equal)),
+        None => true,
+    };
+    let is_before_end = match bound(&pattern.children[1]) {
+        Some(end) => match compare(value, &end) {
+            Ok(Ordering::Less) => true,
+            Ok(Ordering::Equal) => is_inclusive,
+            _ => false,
+        },
+        None => true,
+    };
+    is_after_start && is_before_end
+}
+
+fn matches_list(patterns: &[Node], values: &[Value], bindings: &mut Vec<(String, Value)>) -> bool {
+    match patterns.last() {
+        Some(rest) if rest.node_type == NodeType::Rest => {
+            let head = &patterns[..patterns.len() - 1];
+            if values.len() < head.len() || !head.iter().zip(values.iter()).all(|(pattern, value)| matches(pattern, value, bindings)) {
+                return false
+            }
+            if let Some(Token::Identifier { name, .. }) = rest.children.first().and_then(|node| node.data.as_ref()) {
+                bindings.push((name.to_string(), Value::list(values[head.len()..].to_vec())))
+            }
+            true
+        },
+        _ => patterns.len() == values.len()
+            && patterns.iter().zip(values.iter()).all(|(pattern, value)| matches(pattern, value, bindings)),
+    }
+}
+
+#[cfg(test)]
+fn pattern(source: &str) -> Node {
+    let source = format!("match x {{ {} -> 1 }}", source);
+    let tree = parse(&source);
+    tree.children[0].children[0].condition[0].clone()
+}
+
+#[test]
+fn test_patterns() {
+    let mut bindings = vec!();
+    assert!(matches(&pattern("-1"), &Value::Int(-1), &mut bindings));
+    assert!(matches(&pattern("1..=5"), &Value::Float(5.0), &mut bindings));
+    assert!(!matches(&pattern("1..5"), &Value::Int(5), &mut bindings));
+    assert!(!matches(&pattern("1.."), &Value::Str(String::from("a")), &mut bindings));
+    assert!(matches(&pattern("\"a\"..=\"z\""), &Value::Str(String::from("q")), &mut bindings));
+    assert!(matches(&pattern("(_, None)"), &Value::tuple(vec!(Value::Int(1), Value::None)), &mut bindings));
+    assert!(!matches(&pattern("(_, _)"), &Value::tuple(vec!(Value::Int(1))), &mut bindings));
+    assert!(bindings.is_empty());
+    let list = Value::list(vec!(Value::Int(1), Value::Int(2), Value::Int(3)));
+    assert!(matches(&pattern("[first, ..rest]"), &list, &mut bindings));
+    assert_eq!(vec!((String::from("first"), Value::Int(1)), (String::from("rest"), Value::list(vec!(Value::Int(2), Value::Int(3))))), bindings);
+    assert!(!matches(&pattern("[_, _]"), &list, &mut vec!()));
+    assert!(matches(&pattern("[_, _, _, ..]"), &list, &mut vec!()));
+}
@@ -0,0 +1,202 @@
+use crate::runtime::Value;
+use std::fmt;
+
+/// Lazy sequence of numbers. Values are computed one by one during the iteration,
+/// so even the open range `0..` takes no memory.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Range {
+    pub start: Option<Value>,
+    pub end: Option<Value>,
+    pub step: Option<Value>,
+    pub is_inclusive: bool,
+    pub is_step_range: bool,
+}
+
+/// Iterator over the values of the [`Range`].
+///
+/// Float values are computed as `start + index * step` to avoid the accumulation of rounding errors.
+#[derive(Debug)]
+pub enum RangeIter {
+    Int { next: i64, end: Option<i64>, step: i64, is_inclusive: bool, is_finished: bool },
+    Float { start: f64, step: f64, index: u64, count: Option<u64> },
+}
+
+impl Range {
+
+    /// Checks the bounds and creates the range. Bounds must be `Int`, `Float` or `None` for the omitted ones.
+    pub fn new(start: Value, end: Value, step: Value, is_inclusive: bool, is_step_range: bool) -> Result<Range, String> {
+        let bound = |value: Value| match value {
+            Value::None => Ok(None),
+            Value::Int(_) | Value::Float(_) => Ok(Some(value)),
+            _ => Err(format!("Range bounds must be numbers, found {}", value.type_name())),
+        };
+        let range = Range { start: bound(start)?, end: bound(end)?, step: bound(step)?, is_inclusive, is_step_range };
+        match range.step {
+            Some(Value::Int(0)) => Err(String::from("Range step can't be zero")),
+            Some(Value::Float(0.0)) => Err(String::from("Range step can't be zero")),
+            _ => Ok(range),
+        }
+    }
+
+    /// Creates the lazy iterator over the range values.
+    pub fn iter(&self) -> Result<RangeIter, String> {
+        match (&self.start, &self.end, &self.step) {
+            (None, _, _) => Err(String::from("Range without start can't be iterated")),
+            (Some(Value::Int(start)), None, None) => Ok(RangeIter::Int { next: *start, end: None, step: 1, is_inclusive: false, is_finished: false }),
+            (Some(Value::Int(start)), Some(Value::Int(end)), None) =>
+                Ok(RangeIter::Int { next: *start, end: Some(*end), step: 1, is_inclusive: self.is_inclusive, is_finished: false }),
+            (Some(Value::Int(start)), end @ None, Some(Value::Int(step)))
+            | (Some(Value::Int(start)), end @ Some(Value::Int(_)), Some(Value::Int(step))) => Ok(RangeIter::Int {
+                next: *start,
+                end: end.as_ref().and_then(|end| match end { Value::Int(end) => Some(*end), _ => None }),
+                step: *step,
+                is_inclusive: self.is_inclusive,
+                is_finished: false,
+            }),
+            (Some(start), end, step) => {
+                let start = as_float(start).unwrap();
+                let step = step.as_ref().and_then(as_float).unwrap_or(1.0);
+                // small epsilon makes `0:0.1:1` include `1.0` despite the rounding errors
+                let count = end.as_ref().and_then(as_float).map(|end| {
+                    let steps = (end - start) / step;
+                    match self.is_inclusive {
+                        true => (steps + 1e-9).floor() + 1.0,
+                        false => (steps - 1e-9).ceil(),
+                    }.max(0.0) as u64
+                });
+                Ok(RangeIter::Float { start, step, index: 0, count })
+            },
+        }
+    }
+
+    /// Checks whether the number is between the bounds, values of the step range must also be reachable by the step:
+    /// `2.5 in 0..5` is `true`, while `3 in 0:2:10` is `false`.
+    pub fn contains(&self, value: &Value) -> bool {
+        let value = match as_float(value) {
+            Some(value) => value,
+            None => return false,
+        };
+        let start = self.start.as_ref().and_then(as_float);
+        let end = self.end.as_ref().and_then(as_float);
+        let step = self.step.as_ref().and_then(as_float);
+        let (low, high) = match step {
+            Some(step) if step < 0.0 => (end, start),
+            _ => (start, end),
+        };
+        let is_after_low = low.map(|low| value >= low).unwrap_or(true);
+        let is_before_high = match (high, self.is_inclusive) {
+            (Some(high), true) => value <= high,
+            (Some(high), false) => value < high,
+            (None, _) => true,
+        };
+        let is_reachable = match (self.is_step_range, start, step) {
+            (true, Some(start), Some(step)) => {
+                let steps = (value - start) / step;
+                (steps - steps.round()).abs() < 1e-9
+            },
+            _ => true,
+        };
+        is_after_low && is_before_high && is_reachable
+    }
+}
+
+impl Iterator for RangeIter {
+    type Item = Value;
+
+    fn next(&mut self) -> Option<Value> {
+        match self {
+            RangeIter::Int { next, end, step, is_inclusive, is_finished } => {
+                if *is_finished {
+                    return None
+                }
+                let value = *next;
+                let is_in_range = match (end, *step > 0, *is_inclusive) {
+                    (None, _, _) => true,
+                    (Some(end), true, true) => value <= *end,
+                    (Some(end), true, false) => value < *end,
+                    (Some(end), false, true) => value >= *end,
+                    (Some(end), false, false) => value > *end,
+                };
+                if !is_in_range {
+                    return None
+                }
+                match value.checked_add(*step) {
+                    Some(value) => *next = value,
+                    None => *is_finished = true,
+                };
+                Some(Value::Int(value))
+            },
+            RangeIter::Float { start, step, index, count } => {
+                if let Some(count) = count {
+                    if index >= count {
+                        return None
+                    }
+                }
+                let value = *start + (*index as f64) * *step;
+                *index += 1;
+                Some(Value::Float(value))
+            },
+        }
+    }
+}
+
+impl fmt::Display for Range {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bound = |value: &Option<Value>| value.as_ref().map(|value| value.to_string()).unwrap_or_default();
+        match (self.is_step_range, &self.step) {
+            (true, Some(step)) => write!(f, "{}:{}:{}", bound(&self.start), step, bound(&self.end)),
+            _ => write!(f, "{}{}{}", bound(&self.start), if self.is_inclusive { "..=" } else { ".." }, bound(&self.end)),
+        }
+    }
+}
+
+#[inline]
+fn as_float(value: &Value) -> Option<f64> {
+    match value {
+        Value::Int(value) => Some(*value as f64),
+        Value::Float(value) => Some(*value),
+        _ => None,
+    }
+}
+
+#[test]
+fn test_int_ranges() {
+    let values = |range: Range| range.iter().unwrap().collect::<Vec<Value>>();
+    let range = Range::new(Value::Int(1), Value::Int(4), Value::None, false, false).unwrap();
+    assert_eq!(vec!(Value::Int(1), Value::Int(2), Value::Int(3)), values(range));
+    let range = Range::new(Value::Int(1), Value::Int(4), Value::None, true, false).unwrap();
+    assert_eq!(4, values(range).len());
+    let range = Range::new(Value::Int(10), Value::Int(1), Value::Int(-3), true, true).unwrap();
+    assert_eq!(vec!(Value::Int(10), Value::Int(7), Value::Int(4), Value::Int(1)), values(range));
+    let range = Range::new(Value::Int(5), Value::Int(1), Value::None, false, false).unwrap();
+    assert!(values(range).is_empty());
+    // open range is lazy
+    let range = Range::new(Value::Int(0), Value::None, Value::None, false, false).unwrap();
+    assert_eq!(Some(Value::Int(1000)), range.iter().unwrap().nth(1000));
+    assert!(Range::new(Value::None, Value::Int(1), Value::None, false, false).unwrap().iter().is_err());
+    assert!(Range::new(Value::Int(0), Value::Int(1), Value::Int(0), true, true).is_err());
+}
+
+#[test]
+fn test_float_ranges() {
+    let range = Range::new(Value::Int(0), Value::Int(1), Value::Float(0.1), true, true).unwrap();
+    let values: Vec<Value> = range.iter().unwrap().collect();
+    assert_eq!(11, values.len());
+    assert_eq!(Value::Float(1.0), values[10]);
+    assert_eq!("0:0.1:1", range.to_string());
+    let range = Range::new(Value::Float(0.5), Value::Float(2.0), Value::None, false, false).unwrap();
+    assert_eq!(vec!(Value::Float(0.5), Value::Float(1.5)), range.iter().unwrap().collect::<Vec<Value>>());
+    assert_eq!("0.5..2.0", range.to_string());
+}
+
+#[test]
+fn test_contains() {
+    let range = Range::new(Value::Int(0), Value::Int(5), Value::None, false, false).unwrap();
+    assert!(range.contains(&Value::Float(2.5)));
+    assert!(!range.contains(&Value::Int(5)));
+    assert!(!range.contains(&Value::Str(String::from("1"))));
+    let range = Range::new(Value::Int(10), Value::Int(0), Value::Int(-2), true, true).unwrap();
+    assert!(range.contains(&Value::Int(0)));
+    assert!(!range.contains(&Value::Int(3)));
+    assert!(Range::new(Value::None, Value::Int(0), Value::None, true, false).unwrap().contains(&Value::Int(-100)));
+}
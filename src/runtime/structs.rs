@@ -0,0 +1,202 @@
+use crate::runtime::Value;
+use crate::runtime::types::conform;
+use crate::runtime::value::visit_once;
+use std::cell::RefCell;
+use std::fmt;
+use std::ptr;
+use std::rc::Rc;
+
+/// User-defined record type declared with `struct Name { field, mut field: Type }`.
+///
+/// The struct itself is a value: calling it creates the instance from the positional arguments.
+/// Variants of the enums are structs too, they know the name of their enum.
+#[derive(Debug)]
+pub struct Struct {
+    pub name: String,
+    pub fields: Vec<Field>,
+    pub enum_name: Option<String>,
+}
+
+/// Tagged union declared with `enum Name { Variant(field, field), Variant }`.
+#[derive(Debug)]
+pub struct Enum {
+    pub name: String,
+    pub variants: Vec<Rc<Struct>>,
+}
+
+#[derive(Debug)]
+pub struct Field {
+    pub name: String,
+    pub type_name: Option<String>,
+    pub is_mutable: bool,
+}
+
+/// Value of the struct type. Instances are shared like lists, so the change of the mutable field
+/// is visible through every variable which refers to the instance.
+#[derive(Debug)]
+pub struct Instance {
+    pub definition: Rc<Struct>,
+    pub values: RefCell<Vec<Value>>,
+}
+
+impl Struct {
+
+    /// Creates the instance, arguments are checked against the types of the fields.
+    pub fn instantiate(definition: &Rc<Struct>, arguments: Vec<Value>) -> Result<Value, String> {
+        if arguments.len() != definition.fields.len() {
+            return Err(format!("{} expects {} fields, found {}", definition.name, definition.fields.len(), arguments.len()))
+        }
+        let mut values = Vec::with_capacity(arguments.len());
+        for (field, value) in definition.fields.iter().zip(arguments) {
+            values.push(field.check(&definition.name, value)?);
+        }
+        Ok(Value::Instance(Rc::new(Instance { definition: definition.clone(), values: RefCell::new(values) })))
+    }
+
+    fn field_index(&self, name: &str) -> Result<usize, String> {
+        self.fields.iter().position(|field| field.name == name).ok_or_else(|| format!("{} has no field '{}'", self.name, name))
+    }
+}
+
+impl Enum {
+
+    /// Variant without fields is the value itself, other variants are called to create the value.
+    pub fn variant(&self, name: &str) -> Result<Value, String> {
+        match self.variants.iter().find(|variant| variant.name == name) {
+            Some(variant) if variant.fields.is_empty() => Struct::instantiate(variant, vec!()),
+            Some(variant) => Ok(Value::Struct(variant.clone())),
+            None => Err(format!("{} has no variant '{}'", self.name, name)),
+        }
+    }
+}
+
+impl Field {
+
+    fn check(&self, struct_name: &str, value: Value) -> Result<Value, String> {
+        match &self.type_name {
+            Some(type_name) => conform(type_name, value)
+                .map_err(|value| format!("Field '{}' of {} must be {}, found {}", self.name, struct_name, type_name, value.type_name())),
+            None => Ok(value),
+        }
+    }
+}
+
+impl Instance {
+
+    pub fn get(&self, name: &str) -> Result<Value, String> {
+        let index = self.definition.field_index(name)?;
+        Ok(self.values.borrow()[index].clone())
+    }
+
+    /// Changes the field, only fields declared with `mut` can be changed.
+    pub fn set(&self, name: &str, value: Value) -> Result<Value, String> {
+        let index = self.definition.field_index(name)?;
+        let field = &self.definition.fields[index];
+        if !field.is_mutable {
+            return Err(format!("Field '{}' of {} is immutable, declare it with 'mut'", name, self.definition.name))
+        }
+        let value = field.check(&self.definition.name, value)?;
+        self.values.borrow_mut()[index] = value.clone();
+        Ok(value)
+    }
+}
+
+/// Struct types are equal only to themselves.
+impl PartialEq for Struct {
+    fn eq(&self, other: &Struct) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+/// Instances are equal if they have the same type and equal fields.
+impl PartialEq for Instance {
+    fn eq(&self, other: &Instance) -> bool {
+        ptr::eq(self, other) || self.definition == other.definition
+            && visit_once((self as *const Instance as *const (), other as *const Instance as *const ()), || self.values == other.values).unwrap_or(true)
+    }
+}
+
+/// Enums are equal only to themselves.
+impl PartialEq for Enum {
+    fn eq(&self, other: &Enum) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl fmt::Display for Struct {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.enum_name {
+            Some(enum_name) => write!(f, "<variant {}.{}>", enum_name, self.name),
+            None => write!(f, "<struct {}>", self.name),
+        }
+    }
+}
+
+impl fmt::Display for Enum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<enum {}>", self.name)
+    }
+}
+
+/// Struct instances are printed with the names of the fields: `Point(x: 1, y: 2)`,
+/// while variants are printed like their constructors: `Rect(1, 2)` or `Empty`.
+/// The instance which contains itself is printed as `Point(...)` inside of itself.
+impl fmt::Display for Instance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let values = visit_once((self as *const Instance as *const (), ptr::null()), || {
+            self.values.borrow().iter().map(Value::repr).collect::<Vec<String>>()
+        });
+        let values = match values {
+            Some(values) => values,
+            None => return write!(f, "{}(...)", self.definition.name),
+        };
+        if self.definition.enum_name.is_some() {
+            return match values.is_empty() {
+                true => write!(f, "{}", self.definition.name),
+                false => write!(f, "{}({})", self.definition.name, values.join(", ")),
+            }
+        }
+        let fields: Vec<String> = self.definition.fields.iter().zip(values)
+            .map(|(field, value)| format!("{}: {}", field.name, value))
+            .collect();
+        write!(f, "{}({})", self.definition.name, fields.join(", "))
+    }
+}
+
+#[cfg(test)]
+fn point() -> Rc<Struct> {
+    let field = |name: &str, is_mutable: bool| Field { name: name.to_string(), type_name: Some(String::from("Float")), is_mutable };
+    Rc::new(Struct { name: String::from("Point"), fields: vec!(field("x", false), field("y", true)), enum_name: None })
+}
+
+#[test]
+fn test_instances() {
+    let point = point();
+    let instance = Struct::instantiate(&point, vec!(Value::Int(1), Value::Float(2.5))).unwrap();
+    assert_eq!("Point(x: 1.0, y: 2.5)", instance.to_string());
+    assert_eq!(instance, Struct::instantiate(&point, vec!(Value::Float(1.0), Value::Float(2.5))).unwrap());
+    assert_ne!(instance, Struct::instantiate(&self::point(), vec!(Value::Float(1.0), Value::Float(2.5))).unwrap());
+    if let Value::Instance(instance) = &instance {
+        assert_eq!(Ok(Value::Float(3.0)), instance.set("y", Value::Int(3)));
+        assert_eq!(Ok(Value::Float(3.0)), instance.get("y"));
+        assert_eq!(Err(String::from("Field 'x' of Point is immutable, declare it with 'mut'")), instance.set("x", Value::Int(0)));
+        assert_eq!(Err(String::from("Point has no field 'z'")), instance.get("z"));
+    }
+    assert_eq!(Err(String::from("Point expects 2 fields, found 1")), Struct::instantiate(&point, vec!(Value::Int(1))));
+    assert!(Struct::instantiate(&point, vec!(Value::None, Value::Int(1))).is_err());
+}
+
+#[test]
+fn test_variants() {
+    let variant = |name: &str, fields: Vec<Field>| Rc::new(Struct { name: name.to_string(), fields, enum_name: Some(String::from("Shape")) });
+    let circle = variant("Circle", vec!(Field { name: String::from("r"), type_name: None, is_mutable: false }));
+    let shape = Enum { name: String::from("Shape"), variants: vec!(circle, variant("Empty", vec!())) };
+    assert_eq!("Empty", shape.variant("Empty").unwrap().to_string());
+    let constructor = match shape.variant("Circle").unwrap() {
+        Value::Struct(constructor) => constructor,
+        value => panic!("Unexpected {}", value),
+    };
+    assert_eq!("<variant Shape.Circle>", constructor.to_string());
+    assert_eq!("Circle(1.5)", Struct::instantiate(&constructor, vec!(Value::Float(1.5))).unwrap().to_string());
+    assert_eq!(Err(String::from("Shape has no variant 'Square'")), shape.variant("Square"));
+}
@@ -0,0 +1,35 @@
+use crate::runtime::Value;
+
+/// Names which can be used in the type annotations besides the names of the structs. `Any` accepts values of all types,
+/// `Number` accepts both `Int` and `Float`.
+pub const TYPE_NAMES: &[&str] = &["Any", "Number", "None", "Bool", "Int", "Float", "String", "Range", "List", "Tuple", "Map", "Function", "Struct", "Enum", "Module"];
+
+/// Checks the value against the type annotation and returns the value converted to this type.
+///
+/// The only conversion is `Int` to `Float`, so `fn half(x: Float)` may be called as `half(1)`.
+pub fn conform(type_name: &str, value: Value) -> Result<Value, Value> {
+    match (type_name, value) {
+        ("Float", Value::Int(value)) => Ok(Value::Float(value as f64)),
+        (type_name, value) if is_instance_of(&value, type_name) => Ok(value),
+        (_, value) => Err(value),
+    }
+}
+
+/// Checks the type of the value, the variant of the enum is an instance of its enum as well.
+pub fn is_instance_of(value: &Value, type_name: &str) -> bool {
+    match value {
+        _ if type_name == "Any" || value.type_name() == type_name => true,
+        Value::Int(_) | Value::Float(_) => type_name == "Number",
+        Value::Instance(instance) => instance.definition.enum_name.as_deref() == Some(type_name),
+        _ => false,
+    }
+}
+
+#[test]
+fn test_conform() {
+    assert_eq!(Ok(Value::Float(2.0)), conform("Float", Value::Int(2)));
+    assert_eq!(Ok(Value::None), conform("None", Value::None));
+    assert_eq!(Ok(Value::Str(String::from("a"))), conform("Any", Value::Str(String::from("a"))));
+    assert_eq!(Err(Value::Float(0.5)), conform("Int", Value::Float(0.5)));
+    assert_eq!(Ok(Value::Int(1)), conform("Number", Value::Int(1)));
+}
@@ -0,0 +1,201 @@
+use crate::runtime::{ Range, Function };
+use crate::runtime::function::NativeFunction;
+use crate::runtime::collections::Map;
+use crate::runtime::module::Module;
+use crate::runtime::structs::{ Struct, Enum, Instance };
+use std::cell::RefCell;
+use std::fmt;
+use std::ptr;
+use std::rc::Rc;
+
+/// Any value which can be produced by the Avo program at runtime.
+///
+/// Lists, maps and instances are shared and mutable, so they may contain themselves, e.g. after `xs.push(xs)`.
+#[derive(Debug, Clone)]
+pub enum Value {
+    None,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Range(Rc<Range>),
+    List(Rc<RefCell<Vec<Value>>>),
+    Tuple(Rc<Vec<Value>>),
+    Map(Rc<RefCell<Map>>),
+    Function(Rc<Function>),
+    Native(Rc<NativeFunction>),
+    Struct(Rc<Struct>),
+    Instance(Rc<Instance>),
+    Enum(Rc<Enum>),
+    Module(Rc<Module>),
+}
+
+impl Value {
+
+    /// Name of the value type for the error messages and type annotations.
+    /// Instances of the structs have the name of their struct.
+    pub fn type_name(&self) -> &str {
+        match self {
+            Value::None => "None",
+            Value::Bool(_) => "Bool",
+            Value::Int(_) => "Int",
+            Value::Float(_) => "Float",
+            Value::Str(_) => "String",
+            Value::Range(_) => "Range",
+            Value::List(_) => "List",
+            Value::Tuple(_) => "Tuple",
+            Value::Map(_) => "Map",
+            Value::Function(_) | Value::Native(_) => "Function",
+            Value::Struct(_) => "Struct",
+            Value::Instance(instance) => &instance.definition.name,
+            Value::Enum(_) => "Enum",
+            Value::Module(_) => "Module",
+        }
+    }
+
+    pub fn list(values: Vec<Value>) -> Value {
+        Value::List(Rc::new(RefCell::new(values)))
+    }
+
+    pub fn tuple(values: Vec<Value>) -> Value {
+        Value::Tuple(Rc::new(values))
+    }
+
+    pub fn map(map: Map) -> Value {
+        Value::Map(Rc::new(RefCell::new(map)))
+    }
+
+    /// Creates an iterator over the items of ranges, lists, tuples and strings, or over the keys of maps.
+    ///
+    /// Lists and maps are iterated over the copy of their items, so the loop body may change them.
+    pub fn iter(&self) -> Result<Box<dyn Iterator<Item = Value>>, String> {
+        match self {
+            Value::Range(range) => Ok(Box::new(range.iter()?)),
+            Value::List(values) => Ok(Box::new(values.borrow().clone().into_iter())),
+            Value::Tuple(values) => Ok(Box::new(values.as_ref().clone().into_iter())),
+            Value::Map(map) => Ok(Box::new(map.borrow().keys().cloned().collect::<Vec<Value>>().into_iter())),
+            Value::Str(value) => Ok(Box::new(value.chars().map(|c| Value::Str(c.to_string())).collect::<Vec<Value>>().into_iter())),
+            _ => Err(format!("{} is not iterable", self.type_name())),
+        }
+    }
+
+    /// Same as `to_string`, but strings are quoted. Used for the items of collections.
+    pub fn repr(&self) -> String {
+        match self {
+            Value::Str(value) => format!("{:?}", value),
+            _ => self.to_string(),
+        }
+    }
+}
+
+thread_local! {
+    /// Pairs of the containers which are compared at the moment, the printed ones are paired with the null pointer.
+    static VISITED: RefCell<Vec<(*const (), *const ())>> = const { RefCell::new(vec!()) };
+}
+
+/// Calls `visit` unless the same containers are already visited up the stack, then the value contains itself and it is `None`.
+pub fn visit_once<T>(containers: (*const (), *const ()), visit: impl FnOnce() -> T) -> Option<T> {
+    if VISITED.with(|visited| visited.borrow().contains(&containers)) {
+        return None
+    }
+    VISITED.with(|visited| visited.borrow_mut().push(containers));
+    let result = visit();
+    VISITED.with(|visited| visited.borrow_mut().pop());
+    Some(result)
+}
+
+/// The list which is already compared up the stack is equal there, so the lists which contain themselves are comparable.
+impl PartialEq for Value {
+    fn eq(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::None, Value::None) => true,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Int(a), Value::Int(b)) => a == b,
+            (Value::Float(a), Value::Float(b)) => a == b,
+            (Value::Str(a), Value::Str(b)) => a == b,
+            (Value::Range(a), Value::Range(b)) => a == b,
+            (Value::List(a), Value::List(b)) => Rc::ptr_eq(a, b)
+                || visit_once((a.as_ptr() as *const (), b.as_ptr() as *const ()), || *a.borrow() == *b.borrow()).unwrap_or(true),
+            (Value::Tuple(a), Value::Tuple(b)) => a == b,
+            (Value::Map(a), Value::Map(b)) => a == b,
+            (Value::Function(a), Value::Function(b)) => a == b,
+            (Value::Native(a), Value::Native(b)) => a == b,
+            (Value::Struct(a), Value::Struct(b)) => a == b,
+            (Value::Instance(a), Value::Instance(b)) => a == b,
+            (Value::Enum(a), Value::Enum(b)) => a == b,
+            (Value::Module(a), Value::Module(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::None => write!(f, "None"),
+            Value::Bool(value) => write!(f, "{}", value),
+            Value::Int(value) => write!(f, "{}", value),
+            // `{:?}` keeps the fractional part of the round floats: `1.0` instead of `1`
+            Value::Float(value) => write!(f, "{:?}", value),
+            Value::Str(value) => write!(f, "{}", value),
+            Value::Range(value) => write!(f, "{}", value),
+            // the list which contains itself is printed as `[...]` inside of itself
+            Value::List(values) => match visit_once((values.as_ptr() as *const (), ptr::null()), || values.borrow().iter().map(Value::repr).collect::<Vec<String>>()) {
+                Some(items) => write!(f, "[{}]", items.join(", ")),
+                None => write!(f, "[...]"),
+            },
+            // tuple with the single item keeps the trailing comma: `(1,)`
+            Value::Tuple(values) => match values.as_slice() {
+                [value] => write!(f, "({},)", value.repr()),
+                _ => write!(f, "({})", values.iter().map(Value::repr).collect::<Vec<String>>().join(", ")),
+            },
+            Value::Map(map) => write!(f, "{}", map.borrow()),
+            Value::Function(function) => write!(f, "{}", function),
+            Value::Native(function) => write!(f, "{}", function),
+            Value::Struct(definition) => write!(f, "{}", definition),
+            Value::Instance(instance) => write!(f, "{}", instance),
+            Value::Enum(definition) => write!(f, "{}", definition),
+            Value::Module(module) => write!(f, "{}", module),
+        }
+    }
+}
+
+#[test]
+fn test_display() {
+    assert_eq!("None", Value::None.to_string());
+    assert_eq!("false", Value::Bool(false).to_string());
+    assert_eq!("-12", Value::Int(-12).to_string());
+    assert_eq!("1.0", Value::Float(1.0).to_string());
+    assert_eq!("0.042", Value::Float(0.042).to_string());
+    assert_eq!("text", Value::Str(String::from("text")).to_string());
+    assert_eq!("[1, \"a\", [None]]", Value::list(vec!(Value::Int(1), Value::Str(String::from("a")), Value::list(vec!(Value::None)))).to_string());
+    assert_eq!("(1, \"a\")", Value::tuple(vec!(Value::Int(1), Value::Str(String::from("a")))).to_string());
+    assert_eq!("(1,)", Value::tuple(vec!(Value::Int(1))).to_string());
+    assert_eq!("()", Value::tuple(vec!()).to_string());
+}
+
+#[test]
+fn test_values_which_contain_themselves() {
+    let list = Value::list(vec!(Value::Int(1)));
+    let other = Value::list(vec!(Value::Int(1)));
+    for value in [&list, &other] {
+        if let Value::List(values) = value {
+            values.borrow_mut().push(value.clone());
+        }
+    }
+    assert_eq!("[1, [...]]", list.to_string());
+    assert_eq!(list, list);
+    assert_eq!(list, other);
+    let map = Value::map(crate::runtime::collections::Map::new());
+    if let Value::Map(entries) = &map {
+        entries.borrow_mut().insert(Value::Str(String::from("self")), map.clone()).unwrap();
+        entries.borrow_mut().insert(Value::Str(String::from("list")), list.clone()).unwrap();
+    }
+    assert_eq!("{\"self\": {...}, \"list\": [1, [...]]}", map.to_string());
+    assert_eq!(map, map);
+    // the items are still compared
+    if let Value::List(values) = &other {
+        values.borrow_mut()[0] = Value::Int(2);
+    }
+    assert_ne!(list, other);
+}
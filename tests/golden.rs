@@ -0,0 +1,101 @@
+//! Golden tests of the compiler.
+//!
+//! Every `tests/cases/*.avo` program is compared with the files next to it:
+//! `.tokens` and `.ast` are the S-expression dumps of the tokens and the syntax tree,
+//! `.stderr` is the errors formatted the same way as `avoc` prints them to the standard error, `.stdout` is the output of the program.
+//! The missing file is the same as the empty one, e.g. the program without the errors has no `.stderr`.
+//!
+//! `cargo test --test golden -- --bless` writes the files from the current output instead of the comparison,
+//! the other arguments keep only the cases whose names contain them.
+use avoc::{ Engine, Error };
+use avoc::internals::{ error_info, dump_tokens, dump_tree, Format, TokenReader, TreeBuilder };
+use std::path::{ Path, PathBuf };
+
+const EXTENSIONS: [&str; 4] = ["tokens", "ast", "stderr", "stdout"];
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let is_bless = args.iter().any(|arg| arg == "--bless");
+    let filters: Vec<&String> = args.iter().filter(|arg| !arg.starts_with('-')).collect();
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("cases");
+    let mut cases: Vec<PathBuf> = std::fs::read_dir(&directory).unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension() == Some("avo".as_ref()))
+        .filter(|path| filters.is_empty() || filters.iter().any(|filter| file_name(path).contains(filter.as_str())))
+        .collect();
+    cases.sort();
+    let mut failed = vec!();
+    for case in &cases {
+        let source = std::fs::read_to_string(case).unwrap();
+        let outputs = run(&file_name(case), &source);
+        let mut is_ok = true;
+        for (extension, actual) in EXTENSIONS.iter().zip(&outputs) {
+            let golden = case.with_extension(extension);
+            if is_bless {
+                bless(&golden, actual);
+                continue
+            }
+            let expected = std::fs::read_to_string(&golden).unwrap_or_default();
+            if &expected != actual {
+                println!("--- {} ---\n{}", golden.file_name().unwrap().to_string_lossy(), diff(&expected, actual));
+                is_ok = false;
+            }
+        }
+        println!("case {} ... {}", file_name(case), if is_bless { "blessed" } else if is_ok { "ok" } else { "FAILED" });
+        if !is_ok {
+            failed.push(file_name(case));
+        }
+    }
+    println!("\ngolden result: {} cases, {} failed", cases.len(), failed.len());
+    if !failed.is_empty() {
+        println!("failed cases: {}\nrun `cargo test --test golden -- --bless` to accept the new output", failed.join(", "));
+        std::process::exit(1)
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().unwrap().to_string_lossy().to_string()
+}
+
+/// Returns the tokens, the syntax tree, the errors and the output of the program in the order of [`EXTENSIONS`].
+fn run(file_name: &str, source: &str) -> Vec<String> {
+    let tokens = TokenReader::new().parse_with_comments(source);
+    let tree = TokenReader::new().parse(source).and_then(|tokens| TreeBuilder::new().build_tree(&tokens));
+    let mut engine = Engine::new();
+    engine.capture_output();
+    let errors = match engine.eval(source) {
+        Ok(_) => vec!(),
+        Err(Error::Syntax(errors)) => errors.into_iter().map(|e| (e.pos, e.message)).collect(),
+        Err(Error::Runtime(e)) => vec!((e.pos, e.message)),
+        Err(Error::Limit { limit, pos }) => vec!((pos, limit.to_string())),
+        Err(Error::Conversion(message)) => vec!((0, message)),
+    };
+    vec!(
+        tokens.map(|tokens| dump_tokens(&tokens, source, Format::Sexpr)).unwrap_or_default(),
+        tree.map(|tree| dump_tree(&tree, source, Format::Sexpr)).unwrap_or_default(),
+        errors.into_iter().map(|(pos, message)| error_info(file_name, source, pos, message)).collect(),
+        engine.take_output(),
+    )
+}
+
+/// Writes the golden file, the empty output removes it.
+fn bless(golden: &Path, actual: &str) {
+    match actual.is_empty() {
+        true if golden.exists() => std::fs::remove_file(golden).unwrap(),
+        true => {},
+        false => std::fs::write(golden, actual).unwrap(),
+    }
+}
+
+/// Lines of the expected and the actual output from the first different line to the last one.
+fn diff(expected: &str, actual: &str) -> String {
+    let expected: Vec<&str> = expected.lines().collect();
+    let actual: Vec<&str> = actual.lines().collect();
+    let common_prefix = expected.iter().zip(&actual).take_while(|(lhs, rhs)| lhs == rhs).count();
+    let common_suffix = expected[common_prefix..].iter().rev().zip(actual[common_prefix..].iter().rev())
+        .take_while(|(lhs, rhs)| lhs == rhs).count();
+    let mut lines = vec!(format!("@@ line {} @@", common_prefix + 1));
+    lines.extend(expected[common_prefix..expected.len() - common_suffix].iter().map(|line| format!("-{}", line)));
+    lines.extend(actual[common_prefix..actual.len() - common_suffix].iter().map(|line| format!("+{}", line)));
+    lines.join("\n")
+}
@@ -0,0 +1,248 @@
+//! Property tests of the lexer and the parser on the generated sources.
+//!
+//! The sources are random, but the generator has the fixed seed, so the failures are reproducible.
+//! Set `AVO_PROPERTY_CASES` to check more sources than by default, e.g. after the changes of the parser.
+use avoc::Limit;
+use avoc::internals::{ Node, Token, MAX_DEPTH, RpnTreeBuilder, TextEdit, TokenReader, TreeBuilder };
+use std::panic;
+
+/// Pieces of the valid programs, the generated sources are their random sequences.
+const LEXEMES: &[&str] = &[
+    "a", "b", "_", "name_1", "f(", "g{", "if", "else", "loop", "for", "in", "break", "continue", "match", "fn",
+    "return", "struct", "enum", "is", "mut", "import", "from", "pub", "and", "or", "xor", "not", "test", "\"s\"",
+    "true", "false", "None", "0", "12", "1.5", "50%", "2.5%", "+", "-", "*", "/", "^", "=", "==", "!=", "<", "<=",
+    ">", ">=", "+=", "-=", "->", "!", "(", ")", "[", "]", "{", "}", ",", ".", "..", "..=", ":", "@", "\\", "\"\\\"\"",
+    "// line", "/* block */", "\n", "\n\n",
+];
+
+/// Symbols of the arbitrary sources, including the ones which are not allowed and the multibyte ones.
+const SYMBOLS: &[char] = &[
+    'a', 'z', '_', '0', '9', '.', '%', '"', '\\', '/', '*', '(', ')', '{', '}', '[', ']', '=', '-', '>', '<', '!',
+    '+', '^', ',', ':', '@', ' ', '\n', '\t', '#', '$', 'é', 'ж', '€', '😀', '\u{a0}',
+];
+
+/// Xorshift generator, the tests don't need the better randomness.
+struct Random(u64);
+
+impl Random {
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+}
+
+fn cases() -> usize {
+    std::env::var("AVO_PROPERTY_CASES").ok().and_then(|cases| cases.parse().ok()).unwrap_or(2000)
+}
+
+fn random_symbols(random: &mut Random) -> String {
+    (0..random.below(40)).map(|_| SYMBOLS[random.below(SYMBOLS.len())]).collect()
+}
+
+fn random_lexemes(random: &mut Random) -> String {
+    let mut source = String::new();
+    for _ in 0..random.below(30) {
+        source.push_str(LEXEMES[random.below(LEXEMES.len())]);
+        source.push_str([" ", " ", "", "\n"][random.below(4)]);
+    }
+    source
+}
+
+/// Runs the check and reports the source which made it panic.
+fn check(source: &str, check: impl FnOnce(&str) + panic::UnwindSafe) {
+    if panic::catch_unwind(|| check(source)).is_err() {
+        panic!("panicked on the source {:?}", source)
+    }
+}
+
+/// Lexer returns either the tokens or the error, the tokens start at the char boundaries in the increasing order.
+/// The tokens read from the bytes of the source are the same.
+fn check_lexer(source: &str) {
+    let tokens = TokenReader::new().parse_with_comments(source).map_err(|e| (e.pos, e.message));
+    let read_tokens: Result<Vec<Token>, _> = TokenReader::new().read_tokens(source.as_bytes()).collect();
+    assert_eq!(tokens, read_tokens.map_err(|e| (e.pos, e.message)));
+    if let Ok(tokens) = tokens {
+        let positions: Vec<usize> = tokens.iter().map(Token::pos).collect();
+        assert!(positions.windows(2).all(|pair| pair[0] < pair[1]), "positions {:?}", positions);
+        assert!(positions.iter().all(|pos| source.is_char_boundary(*pos) && *pos < source.len()), "positions {:?}", positions);
+    }
+}
+
+/// Parser returns either the tree or the error for any tokens.
+fn check_parser(source: &str) {
+    if let Ok(tokens) = TokenReader::new().parse(source) {
+        let _ = TreeBuilder::new().build_tree(&tokens);
+        let _ = build_expression(&tokens);
+    }
+}
+
+/// Feeds the tokens to the [`RpnTreeBuilder`] directly, the new lines separate the expressions.
+fn build_expression(tokens: &[Token]) -> Result<Node, avoc::SyntaxError> {
+    let mut builder = RpnTreeBuilder::new();
+    for token in tokens {
+        match token {
+            Token::NewLine { pos } => builder.notify_met_separator(*pos)?,
+            token => builder.push_token(token.clone())?,
+        }
+    }
+    builder.notify_met_separator(0)?;
+    builder.build_tree(0)
+}
+
+/// Writes the tokens back to the source: the tokens are separated by the spaces,
+/// except for the function names which must be followed by the parenthesis or the brace.
+fn format(tokens: &[Token]) -> String {
+    let mut source = String::new();
+    for (index, token) in tokens.iter().enumerate() {
+        let text = match token {
+            Token::Operator { payload, .. } => payload.to_string(),
+            Token::Identifier { name, .. } | Token::Function { name, .. } => name.to_string(),
+            Token::IntConstant { value, .. } => value.to_string(),
+            Token::FloatConstant { value, .. } if value.fract() == 0.0 => format!("{}.0", value),
+            Token::FloatConstant { value, .. } => value.to_string(),
+            Token::StringConstant { value, .. } => format!("\"{}\"", value),
+            Token::BoolConstant { value, .. } => value.to_string(),
+            Token::NoneConstant { .. } => String::from("None"),
+            Token::NewLine { .. } => String::from("\n"),
+            Token::Comment { text, .. } => text.to_string(),
+        };
+        let is_separated = match (token, tokens.get(index + 1)) {
+            // the lexer reads the new lines separated by the spaces as the different tokens
+            (Token::NewLine { .. }, Some(Token::NewLine { .. })) => true,
+            (_, None) | (Token::Function { .. }, _) | (Token::NewLine { .. }, _) | (_, Some(Token::NewLine { .. })) => false,
+            _ => true,
+        };
+        source.push_str(&text);
+        if is_separated {
+            source.push(' ')
+        }
+    }
+    source
+}
+
+/// Tokens without the positions, which change after the formatting.
+fn kinds(tokens: &[Token]) -> Vec<String> {
+    tokens.iter().map(|token| {
+        let text = format!("{:?}", token);
+        text[..text.rfind("pos:").unwrap_or(text.len())].to_string()
+    }).collect()
+}
+
+#[test]
+fn test_lexer_never_panics() {
+    let mut random = Random(0x5eed);
+    for _ in 0..cases() {
+        check(&random_symbols(&mut random), check_lexer);
+        check(&random_lexemes(&mut random), check_lexer);
+    }
+}
+
+#[test]
+fn test_parser_never_panics() {
+    let mut random = Random(0xa110);
+    for _ in 0..cases() {
+        check(&random_lexemes(&mut random), check_parser);
+        check(&random_symbols(&mut random), check_parser);
+    }
+}
+
+#[test]
+fn test_formatted_tokens_are_stable() {
+    let mut random = Random(0xf0f0);
+    for _ in 0..cases() {
+        let source = random_lexemes(&mut random);
+        let tokens = match TokenReader::new().parse_with_comments(&source) {
+            Ok(tokens) => tokens,
+            Err(_) => continue,
+        };
+        let formatted = format(&tokens);
+        let reread = TokenReader::new().parse_with_comments(&formatted)
+            .unwrap_or_else(|e| panic!("{:?} is formatted as {:?}, which is not read: {:?}", source, formatted, e));
+        assert_eq!(kinds(&tokens), kinds(&reread), "{:?} is formatted as {:?}", source, formatted);
+        assert_eq!(formatted, format(&reread), "{:?}", source);
+    }
+}
+
+/// Openings and closings of the constructions which nest the expressions and the blocks,
+/// the operators nest the tree without nesting the constructions.
+const NESTINGS: &[(&str, &str)] = &[
+    ("(", ")"), ("[", "]"), ("-", ""), ("not ", ""), ("a + ", ""), ("f(", ")"), ("{\"k\": ", "}"),
+    ("if a {\n", "\n}"), ("{ @ x -> ", " }"), ("match a { _ -> ", " }"),
+];
+
+/// Source with the random constructions nested into each other more than ten thousand times.
+fn random_nesting(random: &mut Random) -> String {
+    let nestings: Vec<_> = (0..10_000 + random.below(10_000)).map(|_| NESTINGS[random.below(NESTINGS.len())]).collect();
+    let mut source: String = nestings.iter().map(|(open, _)| *open).collect();
+    source.push('1');
+    source.extend(nestings.iter().rev().map(|(_, close)| *close));
+    source
+}
+
+#[test]
+fn test_deep_nesting_is_syntax_error() {
+    let mut random = Random(0xdee9);
+    for _ in 0..cases() / 100 {
+        let source = random_nesting(&mut random);
+        let tokens = TokenReader::new().parse(&source).unwrap();
+        let error = TreeBuilder::new().build_tree(&tokens).unwrap_err();
+        assert!(matches!(error.limit, Some(Limit::Nesting(MAX_DEPTH)) | Some(Limit::Stack(_))), "{:?}", &source[..100]);
+        check(&source, check_parser);
+    }
+}
+
+/// Replaces the random range of the source with the random lexemes or symbols, the range starts and ends at the char boundaries.
+fn random_edit(random: &mut Random, source: &str) -> TextEdit {
+    let boundaries: Vec<usize> = (0..=source.len()).filter(|offset| source.is_char_boundary(*offset)).collect();
+    let start = boundaries[random.below(boundaries.len())];
+    let end = boundaries.iter().copied().filter(|offset| *offset >= start).nth(random.below(4)).unwrap_or(start);
+    let text = match random.below(3) {
+        0 => random_symbols(random).chars().take(random.below(4)).collect(),
+        1 => LEXEMES[random.below(LEXEMES.len())].to_string(),
+        _ => String::new(),
+    };
+    TextEdit { start, end, text }
+}
+
+#[test]
+fn test_relexed_tokens_are_the_same_as_read_again() {
+    let mut random = Random(0xed17);
+    for _ in 0..cases() {
+        let source = random_lexemes(&mut random);
+        let tokens = match TokenReader::new().parse_with_comments(&source) {
+            Ok(tokens) => tokens,
+            Err(_) => continue,
+        };
+        for _ in 0..4 {
+            let edit = random_edit(&mut random, &source);
+            let new_source = format!("{}{}{}", &source[..edit.start], edit.text, &source[edit.end..]);
+            let expected = TokenReader::new().parse_with_comments(&new_source);
+            let actual = TokenReader::new().relex(&tokens, &new_source, &edit);
+            assert_eq!(
+                expected.map_err(|e| (e.pos, e.message)),
+                actual.map_err(|e| (e.pos, e.message)),
+                "{:?} is edited by {:?}", source, edit,
+            );
+        }
+    }
+}
+
+#[test]
+fn test_edge_cases() {
+    for source in ["", "\"", "\"abc", "\"a\\", "é", "aé = 1", "\"ж\" + \"😀\"", "1..2", "1.", "1%%", "/*", "//", "99999999999999999999",
+        "a[", "f(", "f(,)", "match a {", "match a { 1 ->", "{ @ ->", "1..", "..", "x.1", "a =", "-", "not", "fn f(", "struct S {", "enum E { A(",
+        "test \"a\"", "for x in", "if", "a is", "(1,", "{\"k\":", "pub", "from a import", "[..]", "match a { (..x, ..y) -> 1 }"] {
+        check(source, check_lexer);
+        check(source, check_parser);
+    }
+    let long_source = "a = 1\n".repeat(1000);
+    assert_eq!(4000, TokenReader::new().parse(&long_source).unwrap().len());
+    assert_eq!("This string has no closing quote", TokenReader::new().parse("x = \"abc").unwrap_err().message);
+}